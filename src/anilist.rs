@@ -0,0 +1,381 @@
+//! AniList GraphQL API 集成 (`anilist` feature)
+//! <https://docs.anilist.co/>
+//!
+//! 作为 Bangumi 之外的元数据来源 ([`crate::metadata`])，欧美发行的作品覆盖更全。
+//! 条目统一转换为 [`AnimeInfo`]，英文名、罗马音、MAL ID 等放在 `extra`。
+
+use crate::config::CONFIG;
+use crate::http_client::HTTP_CLIENT;
+use crate::metadata::{AnimeInfo, CalendarDay};
+use chrono::{Datelike, FixedOffset, TimeZone};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// 条目查询字段
+const MEDIA_FIELDS: &str = "id idMal title { romaji english native } synonyms \
+    description(asHtml: false) startDate { year month day } coverImage { large } \
+    siteUrl averageScore popularity format episodes genres";
+
+/// 每日放送最多请求的页数 (每页 50 条)
+const CALENDAR_MAX_PAGES: u32 = 6;
+
+/// AniList 条目 (type: ANIME)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Media {
+    pub id: i64,
+    pub id_mal: Option<i64>,
+    #[serde(default)]
+    pub title: MediaTitle,
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub start_date: FuzzyDate,
+    pub cover_image: Option<CoverImage>,
+    #[serde(default)]
+    pub site_url: String,
+    /// 平均分 (0-100)
+    pub average_score: Option<i32>,
+    pub popularity: Option<i64>,
+    /// TV / MOVIE / OVA 等
+    pub format: Option<String>,
+    pub episodes: Option<i32>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MediaTitle {
+    pub romaji: Option<String>,
+    pub english: Option<String>,
+    pub native: Option<String>,
+}
+
+/// 可能不完整的日期 (只有年份或年月)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FuzzyDate {
+    pub year: Option<i32>,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverImage {
+    pub large: Option<String>,
+}
+
+/// 放送时间表中的一集
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiringSchedule {
+    /// 放送时间 (Unix 时间戳)
+    pub airing_at: i64,
+    pub episode: i32,
+    pub media: Media,
+}
+
+impl FuzzyDate {
+    /// `YYYY-MM-DD` (不完整时为 `YYYY-MM` / `YYYY`，没有年份时为空)
+    fn format(&self) -> String {
+        match (self.year, self.month, self.day) {
+            (Some(y), Some(m), Some(d)) => format!("{:04}-{:02}-{:02}", y, m, d),
+            (Some(y), Some(m), None) => format!("{:04}-{:02}", y, m),
+            (Some(y), _, _) => format!("{:04}", y),
+            _ => String::new(),
+        }
+    }
+}
+
+impl From<Media> for AnimeInfo {
+    fn from(m: Media) -> Self {
+        let name = m
+            .title
+            .native
+            .clone()
+            .or_else(|| m.title.romaji.clone())
+            .unwrap_or_default();
+        let name_cn = m
+            .synonyms
+            .iter()
+            .find(|s| is_chinese_title(s))
+            .cloned()
+            .unwrap_or_default();
+        let extra = json!({
+            "id_mal": m.id_mal,
+            "romaji": m.title.romaji,
+            "english": m.title.english,
+            "synonyms": m.synonyms,
+            "format": m.format,
+            "episodes": m.episodes,
+            "genres": m.genres,
+            "popularity": m.popularity,
+        });
+        Self {
+            id: m.id,
+            name,
+            name_cn,
+            summary: strip_tags(m.description.as_deref().unwrap_or_default()),
+            summary_truncated: false,
+            air_date: m.start_date.format(),
+            image: m.cover_image.and_then(|c| c.large).unwrap_or_default(),
+            url: m.site_url,
+            // 与 Bangumi 评分统一为 10 分制
+            score: m
+                .average_score
+                .filter(|s| *s > 0)
+                .map(|s| f64::from(s) / 10.0),
+            rank: None,
+            extra: Some(extra),
+        }
+    }
+}
+
+/// 是否为中文别名 (含汉字且不含假名、谚文)
+fn is_chinese_title(title: &str) -> bool {
+    let has_han = title
+        .chars()
+        .any(|c| ('\u{4e00}'..='\u{9fff}').contains(&c));
+    let has_kana_or_hangul = title
+        .chars()
+        .any(|c| ('\u{3040}'..='\u{30ff}').contains(&c) || ('\u{ac00}'..='\u{d7af}').contains(&c));
+    has_han && !has_kana_or_hangul
+}
+
+/// 去除简介中残留的 HTML 标签 (`<br>`、`<i>` 等)
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+/// AniList API 客户端
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: String,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// 按服务配置创建 (ANILIST_API_BASE)
+    pub fn from_config() -> Self {
+        Self::new(CONFIG.anilist_api_base.as_str())
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// 执行 GraphQL 查询，返回 `data` (响应包含 `errors` 时返回第一条错误)
+    async fn query<T: DeserializeOwned>(&self, query: &str, variables: Value) -> anyhow::Result<T> {
+        let response = HTTP_CLIENT
+            .post(&self.base_url)
+            .header("Accept", "application/json")
+            .json(&json!({"query": query, "variables": variables}))
+            .send()
+            .await?;
+
+        let status = response.status();
+        let mut body: Value = response.json().await?;
+        if let Some(message) = body["errors"][0]["message"].as_str() {
+            anyhow::bail!("AniList API 返回错误: {} - {}", status, message);
+        }
+        if !status.is_success() {
+            anyhow::bail!("AniList API 返回错误: {}", status);
+        }
+        Ok(serde_json::from_value(body["data"].take())?)
+    }
+
+    /// 搜索动画 (按匹配度排序，最多 20 个)
+    pub async fn search(&self, keyword: &str) -> anyhow::Result<Vec<Media>> {
+        #[derive(Deserialize)]
+        struct Data {
+            #[serde(rename = "Page")]
+            page: Page,
+        }
+        #[derive(Deserialize)]
+        struct Page {
+            media: Vec<Media>,
+        }
+
+        let query = format!(
+            "query ($search: String) {{ Page(perPage: 20) {{ \
+             media(search: $search, type: ANIME, sort: SEARCH_MATCH) {{ {} }} }} }}",
+            MEDIA_FIELDS
+        );
+        let data: Data = self.query(&query, json!({"search": keyword})).await?;
+        Ok(data.page.media)
+    }
+
+    /// 条目详情
+    pub async fn media(&self, id: i64) -> anyhow::Result<Media> {
+        #[derive(Deserialize)]
+        struct Data {
+            #[serde(rename = "Media")]
+            media: Media,
+        }
+
+        let query = format!(
+            "query ($id: Int) {{ Media(id: $id, type: ANIME) {{ {} }} }}",
+            MEDIA_FIELDS
+        );
+        let data: Data = self.query(&query, json!({"id": id})).await?;
+        Ok(data.media)
+    }
+
+    /// `[from, to)` 时间段内的放送时间表 (Unix 时间戳，按时间排序)
+    pub async fn airing_schedule(&self, from: i64, to: i64) -> anyhow::Result<Vec<AiringSchedule>> {
+        #[derive(Deserialize)]
+        struct Data {
+            #[serde(rename = "Page")]
+            page: Page,
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Page {
+            page_info: PageInfo,
+            airing_schedules: Vec<AiringSchedule>,
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct PageInfo {
+            has_next_page: bool,
+        }
+
+        let query = format!(
+            "query ($page: Int, $from: Int, $to: Int) {{ Page(page: $page, perPage: 50) {{ \
+             pageInfo {{ hasNextPage }} \
+             airingSchedules(airingAt_greater: $from, airingAt_lesser: $to, sort: TIME) {{ \
+             airingAt episode media {{ {} }} }} }} }}",
+            MEDIA_FIELDS
+        );
+        let mut schedules = Vec::new();
+        for page in 1..=CALENDAR_MAX_PAGES {
+            let data: Data = self
+                .query(&query, json!({"page": page, "from": from - 1, "to": to}))
+                .await?;
+            schedules.extend(data.page.airing_schedules);
+            if !data.page.page_info.has_next_page {
+                break;
+            }
+        }
+        Ok(schedules)
+    }
+
+    /// 每日放送: 从今天 (日本时间) 起 7 天的放送表，按星期分组，同一天的同一部只保留一次
+    pub async fn calendar(&self) -> anyhow::Result<Vec<CalendarDay>> {
+        let jst = FixedOffset::east_opt(9 * 3600).expect("valid offset");
+        let today = chrono::Utc::now().with_timezone(&jst).date_naive();
+        let start = jst
+            .from_local_datetime(&today.and_hms_opt(0, 0, 0).expect("valid time"))
+            .single()
+            .map(|t| t.timestamp())
+            .unwrap_or_default();
+        let schedules = self.airing_schedule(start, start + 7 * 86400).await?;
+        Ok(group_by_weekday(schedules, jst))
+    }
+}
+
+/// 按放送当天的星期分组 (周一在前)
+fn group_by_weekday(schedules: Vec<AiringSchedule>, tz: FixedOffset) -> Vec<CalendarDay> {
+    let mut days: BTreeMap<u8, Vec<Media>> = BTreeMap::new();
+    for schedule in schedules {
+        let Some(time) = tz.timestamp_opt(schedule.airing_at, 0).single() else {
+            continue;
+        };
+        let weekday = time.weekday().number_from_monday() as u8;
+        let items = days.entry(weekday).or_default();
+        if !items.iter().any(|m| m.id == schedule.media.id) {
+            items.push(schedule.media);
+        }
+    }
+    days.into_iter()
+        .map(|(weekday, items)| CalendarDay {
+            weekday,
+            items: items.into_iter().map(AnimeInfo::from).collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn media(id: i64) -> Media {
+        serde_json::from_value(json!({
+            "id": id,
+            "idMal": 52991,
+            "title": {"romaji": "Sousou no Frieren", "english": "Frieren: Beyond Journey's End", "native": "葬送のフリーレン"},
+            "synonyms": ["Frieren at the Funeral", "葬送的芙莉莲"],
+            "description": "The adventure is over.<br><br>\n<i>Elf</i> mage Frieren...",
+            "startDate": {"year": 2023, "month": 9, "day": 29},
+            "coverImage": {"large": "https://img.anili.st/frieren.jpg"},
+            "siteUrl": "https://anilist.co/anime/154587",
+            "averageScore": 91,
+            "format": "TV",
+            "episodes": 28,
+            "genres": ["Adventure", "Drama"]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_media_to_anime_info() {
+        let info = AnimeInfo::from(media(154587));
+        assert_eq!(info.name, "葬送のフリーレン");
+        assert_eq!(info.name_cn, "葬送的芙莉莲");
+        assert_eq!(info.summary, "The adventure is over.\nElf mage Frieren...");
+        assert_eq!(info.air_date, "2023-09-29");
+        assert_eq!(info.score, Some(9.1));
+        let extra = info.extra.unwrap();
+        assert_eq!(extra["id_mal"], 52991);
+        assert_eq!(extra["english"], "Frieren: Beyond Journey's End");
+        assert_eq!(extra["episodes"], 28);
+    }
+
+    #[test]
+    fn test_calendar_groups_by_weekday_in_jst() {
+        let jst = FixedOffset::east_opt(9 * 3600).unwrap();
+        // 2024-01-05 23:30 JST (周五) 与 2024-01-06 00:30 JST (周六，UTC 仍为周五)
+        let friday = jst
+            .with_ymd_and_hms(2024, 1, 5, 23, 30, 0)
+            .unwrap()
+            .timestamp();
+        let schedules = vec![
+            AiringSchedule {
+                airing_at: friday,
+                episode: 1,
+                media: media(1),
+            },
+            AiringSchedule {
+                airing_at: friday + 3600,
+                episode: 1,
+                media: media(2),
+            },
+            AiringSchedule {
+                airing_at: friday + 3600,
+                episode: 2,
+                media: media(2),
+            },
+        ];
+        let days = group_by_weekday(schedules, jst);
+        assert_eq!(days.len(), 2);
+        assert_eq!((days[0].weekday, days[0].items.len()), (5, 1));
+        assert_eq!((days[1].weekday, days[1].items.len()), (6, 1));
+    }
+}
@@ -1,15 +1,23 @@
 //! Bangumi API 集成
-//! https://bangumi.github.io/api/
-//! User Agent 规范: https://github.com/bangumi/api/blob/master/docs-raw/user%20agent.md
+//! <https://bangumi.github.io/api/>
+//! User Agent 规范: <https://github.com/bangumi/api/blob/master/docs-raw/user%20agent.md>
 //! 
-//! 注意：这些类型和函数目前未使用（通过 /bgm/* 通用代理访问 Bangumi API）
-//! 保留以便将来可能的直接集成使用
+//! 注意：大部分类型和函数目前未使用（通过 /bgm/* 通用代理访问 Bangumi API）
+//! 保留以便将来可能的直接集成使用；`/bangumi/search/{keyword}/stream` 使用搜索与条目详情
 
 #![allow(dead_code)]
 
+use crate::config::CONFIG;
+use crate::cache::CompressedCache;
 use crate::http_client::HTTP_CLIENT;
+use crate::storage;
+use futures::stream::{self, Stream, StreamExt};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tracing::warn;
 
 const BANGUMI_API: &str = "https://api.bgm.tv";
@@ -37,11 +45,7 @@ pub fn get_effective_token(user_token: Option<&str>) -> Option<&str> {
 
 /// 获取服务端配置的默认 token (从环境变量 BANGUMI_ACCESS_TOKEN)
 fn get_server_token() -> Option<&'static str> {
-    use once_cell::sync::Lazy;
-    static SERVER_TOKEN: Lazy<Option<String>> = Lazy::new(|| {
-        std::env::var("BANGUMI_ACCESS_TOKEN").ok().filter(|s| !s.is_empty())
-    });
-    SERVER_TOKEN.as_deref()
+    CONFIG.bangumi_access_token.as_deref()
 }
 
 // ============================================================================
@@ -60,6 +64,8 @@ pub struct BangumiSearchResult {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BangumiSubject {
     pub id: i64,
+    /// 旧版 API 返回，v0 API 没有此字段
+    #[serde(default)]
     pub url: String,
     #[serde(rename = "type")]
     pub subject_type: i32,
@@ -452,6 +458,38 @@ pub struct Episode {
     pub duration_seconds: Option<i32>,
     #[serde(default)]
     pub subject_id: Option<i64>,
+    /// 上游新增的其他字段 (原样透传，避免静默丢失)
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// 精简章节 (追番列表常用字段，不含简介等长字段)
+#[derive(Debug, Clone, Serialize)]
+pub struct EpisodeSummary {
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub episode_type: i32,
+    pub name: String,
+    pub name_cn: String,
+    pub sort: f64,
+    pub ep: Option<f64>,
+    pub airdate: String,
+    pub comment: i32,
+}
+
+impl From<&Episode> for EpisodeSummary {
+    fn from(episode: &Episode) -> Self {
+        Self {
+            id: episode.id,
+            episode_type: episode.episode_type,
+            name: episode.name.clone(),
+            name_cn: episode.name_cn.clone(),
+            sort: episode.sort,
+            ep: episode.ep,
+            airdate: episode.airdate.clone(),
+            comment: episode.comment,
+        }
+    }
 }
 
 /// 章节列表响应
@@ -595,19 +633,8 @@ pub struct SearchResultV0 {
 // 简化类型 (用于前端)
 // ============================================================================
 
-/// 简化的动漫信息 (用于前端显示)
-#[derive(Debug, Clone, Serialize)]
-pub struct AnimeInfo {
-    pub id: i64,
-    pub name: String,
-    pub name_cn: String,
-    pub summary: String,
-    pub air_date: String,
-    pub image: String,
-    pub url: String,
-    pub score: Option<f64>,
-    pub rank: Option<i32>,
-}
+/// 简化的动漫信息 (各元数据来源共用，见 [`crate::metadata`])
+pub use crate::metadata::AnimeInfo;
 
 impl From<BangumiSubject> for AnimeInfo {
     fn from(s: BangumiSubject) -> Self {
@@ -616,12 +643,14 @@ impl From<BangumiSubject> for AnimeInfo {
             name: s.name,
             name_cn: s.name_cn,
             summary: s.summary,
+            summary_truncated: false,
             air_date: s.air_date,
             image: s.images.map(|i| i.large).unwrap_or_default(),
             url: s.url,
             score: s.rating.as_ref().and_then(|r| if r.score > 0.0 { Some(r.score) } else { None }),
             // 优先使用顶层 rank，回退到 rating.rank
             rank: s.rank.or_else(|| s.rating.as_ref().and_then(|r| r.rank)),
+            extra: None,
         }
     }
 }
@@ -724,66 +753,145 @@ async fn delete_with_auth(url: &str, token: &str) -> anyhow::Result<()> {
 }
 
 // ============================================================================
-// 公开 API (无需认证)
+// 客户端
 // ============================================================================
 
-/// 搜索动漫 (type=2)
-/// 使用 responseGroup=large 获取完整信息（评分、排名等）
-pub async fn search_anime(keyword: &str) -> anyhow::Result<BangumiSearchResult> {
-    let url = format!(
-        "{}/search/subject/{}?type=2&responseGroup=large",
-        BANGUMI_API,
-        urlencoding::encode(keyword)
-    );
+/// Bangumi API 客户端 (可指定 API 地址与访问令牌)
+///
+/// ```no_run
+/// use anime_search::bangumi::Client;
+///
+/// # async fn demo() -> anyhow::Result<()> {
+/// let client = Client::new("https://api.bgm.tv").with_token("your-access-token");
+/// let subject = client.subject(400602).await?;
+/// println!("{}", subject.name);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: String,
+    token: Option<String>,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
 
-    let response = HTTP_CLIENT
-        .get(&url)
-        .header("User-Agent", USER_AGENT)
-        .send()
-        .await?;
+    /// 使用访问令牌 (以 `Authorization: Bearer` 发送)
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
 
-    if !response.status().is_success() {
-        anyhow::bail!("Bangumi API 返回错误: {}", response.status());
+    /// 按服务配置创建 (BANGUMI_API_BASE + BANGUMI_ACCESS_TOKEN)
+    pub fn from_config() -> Self {
+        let client = Self::new(CONFIG.bangumi_api_base.as_str());
+        match get_server_token() {
+            Some(token) => client.with_token(token),
+            None => client,
+        }
     }
 
-    let result: BangumiSearchResult = response.json().await?;
-    Ok(result)
-}
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
 
-/// 获取条目详情
-pub async fn get_subject(id: i64) -> anyhow::Result<BangumiSubject> {
-    let url = format!("{}/subject/{}", BANGUMI_API, id);
+    /// GET 请求并解析 JSON
+    async fn get_json<T: for<'de> Deserialize<'de>>(&self, path: &str) -> anyhow::Result<T> {
+        let mut req = HTTP_CLIENT
+            .get(format!("{}{}", self.base_url, path))
+            .header("User-Agent", USER_AGENT);
+        if let Some(token) = &self.token {
+            req = req.header("Authorization", format!("Bearer {}", token));
+        }
 
-    let response = HTTP_CLIENT
-        .get(&url)
-        .header("User-Agent", USER_AGENT)
-        .send()
-        .await?;
+        let response = req.send().await?;
 
-    if !response.status().is_success() {
-        anyhow::bail!("Bangumi API 返回错误: {}", response.status());
+        if !response.status().is_success() {
+            anyhow::bail!("Bangumi API 返回错误: {}", response.status());
+        }
+
+        Ok(response.json().await?)
     }
 
-    let subject: BangumiSubject = response.json().await?;
-    Ok(subject)
+    /// 搜索动漫 (type=2，responseGroup=large)
+    pub async fn search(&self, keyword: &str) -> anyhow::Result<BangumiSearchResult> {
+        self.get_json(&format!(
+            "/search/subject/{}?type=2&responseGroup=large",
+            urlencoding::encode(keyword)
+        ))
+        .await
+    }
+
+    /// 获取条目详情 (不经过缓存)
+    pub async fn subject(&self, id: i64) -> anyhow::Result<BangumiSubject> {
+        self.get_json(&format!("/subject/{}", id)).await
+    }
+
+    /// 获取每日放送
+    pub async fn calendar(&self) -> anyhow::Result<Vec<CalendarItem>> {
+        self.get_json("/calendar").await
+    }
 }
 
-/// 获取每日放送
-pub async fn get_calendar() -> anyhow::Result<Vec<CalendarItem>> {
-    let url = format!("{}/calendar", BANGUMI_API);
+// ============================================================================
+// 公开 API (无需认证)
+// ============================================================================
 
-    let response = HTTP_CLIENT
-        .get(&url)
-        .header("User-Agent", USER_AGENT)
-        .send()
-        .await?;
+/// 搜索动漫 (type=2)
+/// 使用 responseGroup=large 获取完整信息（评分、排名等）
+pub async fn search_anime(keyword: &str) -> anyhow::Result<BangumiSearchResult> {
+    Client::from_config().search(keyword).await
+}
 
-    if !response.status().is_success() {
-        anyhow::bail!("Bangumi API 返回错误: {}", response.status());
+/// 条目详情缓存 (CACHE_BANGUMI_CAPACITY / CACHE_BANGUMI_TTL_SECS，CACHE_COMPRESS 时压缩保存)
+static SUBJECT_CACHE: Lazy<CompressedCache<i64, BangumiSubject>> = Lazy::new(|| {
+    CompressedCache::new(
+        "bangumi_subject",
+        CONFIG.cache_bangumi_capacity,
+        Duration::from_secs(CONFIG.cache_bangumi_ttl_secs),
+        CONFIG.cache_compress,
+    )
+    .follow_ttl(|| Duration::from_secs(CONFIG.cache_bangumi_ttl_secs))
+});
+
+/// 获取条目详情 (进程内缓存 -> 全局存储 -> 请求 API)
+pub async fn get_subject(id: i64) -> anyhow::Result<BangumiSubject> {
+    if let Some(subject) = SUBJECT_CACHE.get(&id) {
+        return Ok(subject);
+    }
+    let key = id.to_string();
+    if let Some(subject) =
+        storage::load_json::<BangumiSubject>(storage::ns::BANGUMI_SUBJECT, key.clone()).await
+    {
+        SUBJECT_CACHE.insert(id, subject.clone());
+        return Ok(subject);
     }
 
-    let calendar: Vec<CalendarItem> = response.json().await?;
-    Ok(calendar)
+    let subject = fetch_subject(id).await?;
+    storage::save_json(
+        storage::ns::BANGUMI_SUBJECT,
+        key,
+        &subject,
+        Some(Duration::from_secs(CONFIG.cache_bangumi_ttl_secs)),
+    );
+    SUBJECT_CACHE.insert(id, subject.clone());
+    Ok(subject)
+}
+
+/// 请求条目详情 (不经过缓存)
+async fn fetch_subject(id: i64) -> anyhow::Result<BangumiSubject> {
+    Client::from_config().subject(id).await
+}
+
+/// 获取每日放送
+pub async fn get_calendar() -> anyhow::Result<Vec<CalendarItem>> {
+    Client::from_config().calendar().await
 }
 
 /// 搜索并返回简化信息
@@ -797,6 +905,86 @@ pub async fn search_anime_simple(keyword: &str) -> Vec<AnimeInfo> {
     }
 }
 
+// ============================================================================
+// 流式搜索 + 详情补全
+// ============================================================================
+
+/// 详情补全的并发数
+const ENRICH_CONCURRENCY: usize = 4;
+/// 最多补全详情的条目数
+const ENRICH_LIMIT: usize = 10;
+
+/// Bangumi 流式搜索事件 (每行一个 JSON，按字段区分事件类型)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+// 事件构造后立即序列化，不值得为条目详情装箱
+#[allow(clippy::large_enum_variant)]
+pub enum BangumiStreamEvent {
+    /// `{"total": n, "hits": [...]}` 搜索命中 (简化信息)
+    Hits { total: usize, hits: Vec<AnimeInfo> },
+    /// `{"id": 1, "subject": {...}}` 条目详情
+    Subject { id: i64, subject: BangumiSubject },
+    /// `{"id": 1, "error": "..."}` 条目详情获取失败
+    SubjectError { id: i64, error: String },
+    /// `{"error": "..."}` 搜索失败 (随后为完成信号)
+    Error { error: String },
+    /// `{"done": true}` 完成信号
+    Done { done: bool },
+}
+
+/// 流式搜索: 先返回搜索命中，再并发获取每个命中的条目详情并逐条返回 (事件见 [`BangumiStreamEvent`])
+pub fn search_with_details_stream(keyword: String) -> impl Stream<Item = String> {
+    let (tx, rx) = mpsc::channel::<String>(32);
+
+    tokio::spawn(async move {
+        let subjects = match search_anime(&keyword).await {
+            Ok(result) => result.list,
+            Err(e) => {
+                warn!("Bangumi 搜索失败: {}", e);
+                let error = BangumiStreamEvent::Error { error: e.to_string() };
+                let _ = tx.send(format_line(&error)).await;
+                let _ = tx.send(format_line(&BangumiStreamEvent::Done { done: true })).await;
+                return;
+            }
+        };
+
+        let ids: Vec<i64> = subjects.iter().map(|s| s.id).take(ENRICH_LIMIT).collect();
+        let hits: Vec<AnimeInfo> = subjects.into_iter().map(AnimeInfo::from).collect();
+        let hits_event = BangumiStreamEvent::Hits {
+            total: hits.len(),
+            hits,
+        };
+        if tx.send(format_line(&hits_event)).await.is_err() {
+            return;
+        }
+
+        let mut details = stream::iter(ids)
+            .map(|id| async move { (id, get_subject(id).await) })
+            .buffer_unordered(ENRICH_CONCURRENCY);
+
+        while let Some((id, result)) = details.next().await {
+            let event = match result {
+                Ok(subject) => BangumiStreamEvent::Subject { id, subject },
+                Err(e) => BangumiStreamEvent::SubjectError {
+                    id,
+                    error: e.to_string(),
+                },
+            };
+            if tx.send(format_line(&event)).await.is_err() {
+                return;
+            }
+        }
+
+        let _ = tx.send(format_line(&BangumiStreamEvent::Done { done: true })).await;
+    });
+
+    ReceiverStream::new(rx)
+}
+
+fn format_line(event: &BangumiStreamEvent) -> String {
+    format!("{}\n", serde_json::to_string(event).unwrap_or_default())
+}
+
 // ============================================================================
 // v0 API (公开/可选认证)
 // ============================================================================
@@ -1258,3 +1446,48 @@ pub async fn uncollect_index(index_id: i64, token: &str) -> anyhow::Result<()> {
     let url = format!("{}/v0/indices/{}/collect", BANGUMI_API, index_id);
     delete_with_auth(&url, token).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_episode_list_keeps_extended_and_unknown_fields() {
+        let payload = r#"{
+            "data": [{
+                "airdate": "2023-09-29",
+                "name": "冒険の終わり",
+                "name_cn": "冒险的结束",
+                "duration": "00:24:40",
+                "desc": "勇者一行打倒魔王，回到了王都。",
+                "ep": 1,
+                "sort": 1,
+                "id": 1227087,
+                "subject_id": 400602,
+                "comment": 145,
+                "type": 0,
+                "disc": 0,
+                "duration_seconds": 1480,
+                "lock": false
+            }],
+            "total": 28,
+            "limit": 100,
+            "offset": 0
+        }"#;
+        let list: EpisodeList = serde_json::from_str(payload).unwrap();
+        let episode = &list.data[0];
+        assert_eq!(episode.airdate, "2023-09-29");
+        assert_eq!((episode.comment, episode.sort), (145, 1.0));
+        assert!(episode.desc.starts_with("勇者一行"));
+        assert_eq!(episode.extra["lock"], false);
+
+        // 完整模式原样透传 (含未知字段)，精简模式只保留常用字段
+        let full = serde_json::to_value(episode).unwrap();
+        assert_eq!(full["lock"], false);
+        assert_eq!(full["duration_seconds"], 1480);
+        let lean = serde_json::to_value(EpisodeSummary::from(episode)).unwrap();
+        assert_eq!(lean["comment"], 145);
+        assert_eq!(lean["type"], 0);
+        assert!(lean.get("desc").is_none());
+    }
+}
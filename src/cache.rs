@@ -0,0 +1,531 @@
+//! 进程内缓存
+//! 统一的 TTL 缓存 (moka)：容量上限、过期时间、按权重估算内存，并统计命中/未命中/淘汰，
+//! 所有缓存在创建时注册，由 `/admin/caches` 与 `/metrics` 统一输出。
+//! [`CompressedCache`] 可将缓存值序列化并 gzip 压缩后保存 (`CACHE_COMPRESS`)，以 CPU 换内存。
+//! 有效期可在运行时修改 (配置重载时由 [`refresh_ttls`] 按配置更新)，只影响之后写入的条目。
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use moka::notification::RemovalCause;
+use moka::sync::Cache;
+use moka::Expiry;
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::hash::Hash;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 命中/未命中/淘汰计数
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    /// 超出容量被淘汰
+    evictions: AtomicU64,
+    /// 过期被移除
+    expirations: AtomicU64,
+}
+
+/// 单个缓存的统计
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheReport {
+    pub name: &'static str,
+    /// 当前条目数 (近似值)
+    pub entries: u64,
+    /// 当前总权重 (设置 weigher 时为估算字节数，否则等于条目数)
+    pub weighted_size: u64,
+    /// 容量上限 (与 weighted_size 同单位)
+    pub capacity: u64,
+    pub ttl_seconds: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub expirations: u64,
+    /// 命中率 (无请求时为 0)
+    pub hit_rate: f64,
+    /// 压缩条目的原始字节数 (未启用压缩时为 0)
+    pub raw_bytes: u64,
+    /// 压缩条目实际占用的字节数 (未启用压缩时为 0)
+    pub stored_bytes: u64,
+}
+
+type ReportFn = Box<dyn Fn() -> CacheReport + Send + Sync>;
+
+type RefreshFn = Box<dyn Fn() + Send + Sync>;
+
+/// 计算单个值的 (原始字节数, 实际字节数)
+type PayloadSizeFn<V> = fn(&V) -> (u64, u64);
+
+/// 已注册的缓存
+static REGISTRY: Lazy<Mutex<Vec<ReportFn>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// 按配置更新有效期的缓存
+static TTL_FOLLOWERS: Lazy<Mutex<Vec<RefreshFn>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// 可在运行时修改的有效期 (毫秒)，条目写入 (含覆盖) 时按当前值计算过期时间
+#[derive(Clone)]
+struct SharedTtl(Arc<AtomicU64>);
+
+impl SharedTtl {
+    fn new(ttl: Duration) -> Self {
+        Self(Arc::new(AtomicU64::new(ttl.as_millis() as u64)))
+    }
+
+    fn get(&self) -> Duration {
+        Duration::from_millis(self.0.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, ttl: Duration) {
+        self.0.store(ttl.as_millis() as u64, Ordering::Relaxed);
+    }
+}
+
+impl<K, V> Expiry<K, V> for SharedTtl {
+    fn expire_after_create(&self, _key: &K, _value: &V, _created_at: Instant) -> Option<Duration> {
+        Some(self.get())
+    }
+
+    fn expire_after_update(
+        &self,
+        _key: &K,
+        _value: &V,
+        _updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(self.get())
+    }
+}
+
+/// TTL 缓存 (克隆后共享同一份数据)
+pub struct TtlCache<K, V> {
+    name: &'static str,
+    capacity: u64,
+    ttl: SharedTtl,
+    inner: Cache<K, V>,
+    counters: Arc<Counters>,
+    /// 统计压缩前后的字节数，仅压缩缓存设置
+    payload_size: Option<PayloadSizeFn<V>>,
+}
+
+impl<K, V> Clone for TtlCache<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            capacity: self.capacity,
+            ttl: self.ttl.clone(),
+            inner: self.inner.clone(),
+            counters: self.counters.clone(),
+            payload_size: self.payload_size,
+        }
+    }
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    /// 创建并注册缓存 (`capacity` 为最大条目数)
+    pub fn new(name: &'static str, capacity: u64, ttl: Duration) -> Self {
+        Self::build(name, capacity, ttl, None, None)
+    }
+
+    /// 创建并注册按权重计算容量的缓存 (`capacity` 为总权重上限，权重通常为估算字节数)
+    pub fn with_weigher(
+        name: &'static str,
+        capacity: u64,
+        ttl: Duration,
+        weigher: impl Fn(&K, &V) -> u32 + Send + Sync + 'static,
+    ) -> Self {
+        Self::build(name, capacity, ttl, Some(Box::new(weigher)), None)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn build(
+        name: &'static str,
+        capacity: u64,
+        ttl: Duration,
+        weigher: Option<Box<dyn Fn(&K, &V) -> u32 + Send + Sync>>,
+        payload_size: Option<PayloadSizeFn<V>>,
+    ) -> Self {
+        let counters = Arc::new(Counters::default());
+        let listener_counters = counters.clone();
+        let ttl = SharedTtl::new(ttl);
+        let mut builder = Cache::builder()
+            .max_capacity(capacity)
+            .expire_after(ttl.clone())
+            .eviction_listener(move |_key, _value, cause| match cause {
+                RemovalCause::Size => {
+                    listener_counters.evictions.fetch_add(1, Ordering::Relaxed);
+                }
+                RemovalCause::Expired => {
+                    listener_counters
+                        .expirations
+                        .fetch_add(1, Ordering::Relaxed);
+                }
+                RemovalCause::Explicit | RemovalCause::Replaced => {}
+            });
+        if let Some(weigher) = weigher {
+            builder = builder.weigher(move |k, v| weigher(k, v));
+        }
+        let cache = Self {
+            name,
+            capacity,
+            ttl,
+            inner: builder.build(),
+            counters,
+            payload_size,
+        };
+        let registered = cache.clone();
+        REGISTRY
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Box::new(move || registered.report()));
+        cache
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let value = self.inner.get(key);
+        let counter = if value.is_some() {
+            &self.counters.hits
+        } else {
+            &self.counters.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        self.inner.insert(key, value);
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.inner.remove(key)
+    }
+
+    pub fn clear(&self) {
+        self.inner.invalidate_all();
+    }
+
+    /// 修改有效期 (已有条目保持原过期时间)
+    pub fn set_ttl(&self, ttl: Duration) {
+        self.ttl.set(ttl);
+    }
+
+    /// 按配置更新有效期: 立即应用一次，之后每次 [`refresh_ttls`] 重新读取
+    pub fn follow_ttl(self, source: fn() -> Duration) -> Self {
+        self.set_ttl(source());
+        let ttl = self.ttl.clone();
+        TTL_FOLLOWERS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Box::new(move || ttl.set(source())));
+        self
+    }
+
+    /// 当前统计 (先处理挂起的淘汰任务，使条目数与计数更准确)
+    pub fn report(&self) -> CacheReport {
+        self.inner.run_pending_tasks();
+        let hits = self.counters.hits.load(Ordering::Relaxed);
+        let misses = self.counters.misses.load(Ordering::Relaxed);
+        let (raw_bytes, stored_bytes) = match self.payload_size {
+            Some(size) => self.inner.iter().fold((0, 0), |(raw, stored), (_, value)| {
+                let (r, s) = size(&value);
+                (raw + r, stored + s)
+            }),
+            None => (0, 0),
+        };
+        CacheReport {
+            name: self.name,
+            entries: self.inner.entry_count(),
+            weighted_size: self.inner.weighted_size(),
+            capacity: self.capacity,
+            ttl_seconds: self.ttl.get().as_secs(),
+            hits,
+            misses,
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            expirations: self.counters.expirations.load(Ordering::Relaxed),
+            hit_rate: if hits + misses == 0 {
+                0.0
+            } else {
+                hits as f64 / (hits + misses) as f64
+            },
+            raw_bytes,
+            stored_bytes,
+        }
+    }
+}
+
+/// 压缩缓存中保存的值
+#[derive(Clone)]
+enum Stored<V> {
+    Plain(V),
+    /// gzip 压缩的 JSON
+    Packed { bytes: Arc<[u8]>, raw_len: u64 },
+}
+
+impl<V> Stored<V> {
+    fn sizes(&self) -> (u64, u64) {
+        match self {
+            Stored::Plain(_) => (0, 0),
+            Stored::Packed { bytes, raw_len } => (*raw_len, bytes.len() as u64),
+        }
+    }
+}
+
+/// 可选压缩的 TTL 缓存 (`compress` 为 false 时与 [`TtlCache`] 相同)
+///
+/// 压缩时写入前序列化为 JSON 并 gzip，读取时解压反序列化；无法解码的条目视为未命中并移除
+pub struct CompressedCache<K, V> {
+    inner: TtlCache<K, Stored<V>>,
+    compress: bool,
+}
+
+impl<K, V> CompressedCache<K, V>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// 创建并注册缓存 (`capacity` 为最大条目数)
+    pub fn new(name: &'static str, capacity: u64, ttl: Duration, compress: bool) -> Self {
+        let payload_size = compress.then_some(Stored::<V>::sizes as PayloadSizeFn<Stored<V>>);
+        Self {
+            inner: TtlCache::build(name, capacity, ttl, None, payload_size),
+            compress,
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        match self.inner.get(key)? {
+            Stored::Plain(value) => Some(value),
+            Stored::Packed { bytes, .. } => {
+                let value = unpack(&bytes);
+                if value.is_none() {
+                    self.inner.remove(key);
+                }
+                value
+            }
+        }
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let stored = if self.compress {
+            match pack(&value) {
+                Some((bytes, raw_len)) => Stored::Packed { bytes: bytes.into(), raw_len },
+                None => Stored::Plain(value),
+            }
+        } else {
+            Stored::Plain(value)
+        };
+        self.inner.insert(key, stored);
+    }
+
+    pub fn remove(&self, key: &K) {
+        self.inner.remove(key);
+    }
+
+    pub fn clear(&self) {
+        self.inner.clear();
+    }
+
+    /// 见 [`TtlCache::follow_ttl`]
+    pub fn follow_ttl(self, source: fn() -> Duration) -> Self {
+        Self {
+            inner: self.inner.follow_ttl(source),
+            compress: self.compress,
+        }
+    }
+
+    pub fn report(&self) -> CacheReport {
+        self.inner.report()
+    }
+}
+
+/// 序列化并压缩，返回 (压缩数据, 原始字节数)
+fn pack<V: Serialize>(value: &V) -> Option<(Vec<u8>, u64)> {
+    let raw = serde_json::to_vec(value).ok()?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(&raw).ok()?;
+    Some((encoder.finish().ok()?, raw.len() as u64))
+}
+
+fn unpack<V: DeserializeOwned>(bytes: &[u8]) -> Option<V> {
+    let mut raw = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut raw).ok()?;
+    serde_json::from_slice(&raw).ok()
+}
+
+/// 按当前配置更新各缓存的有效期 (配置重载后调用)
+pub fn refresh_ttls() {
+    for refresh in TTL_FOLLOWERS.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        refresh();
+    }
+}
+
+/// 所有已注册缓存的统计 (按名称排序)
+pub fn reports() -> Vec<CacheReport> {
+    let mut reports: Vec<CacheReport> = REGISTRY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|report| report())
+        .collect();
+    reports.sort_by_key(|r| r.name);
+    reports
+}
+
+/// 指标名、类型、说明、取值
+type Metric = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&CacheReport) -> u64,
+);
+
+/// Prometheus 格式的缓存指标
+pub fn render_metrics(reports: &[CacheReport]) -> String {
+    let metrics: [Metric; 9] = [
+        (
+            "cache_hits_total",
+            "counter",
+            "Cache lookups that found an entry",
+            |r| r.hits,
+        ),
+        (
+            "cache_misses_total",
+            "counter",
+            "Cache lookups that found no entry",
+            |r| r.misses,
+        ),
+        (
+            "cache_evictions_total",
+            "counter",
+            "Entries evicted because the cache was full",
+            |r| r.evictions,
+        ),
+        (
+            "cache_expirations_total",
+            "counter",
+            "Entries removed after their TTL",
+            |r| r.expirations,
+        ),
+        (
+            "cache_entries",
+            "gauge",
+            "Approximate number of cached entries",
+            |r| r.entries,
+        ),
+        (
+            "cache_weighted_size",
+            "gauge",
+            "Approximate total weight of cached entries",
+            |r| r.weighted_size,
+        ),
+        (
+            "cache_raw_bytes",
+            "gauge",
+            "Uncompressed size of compressed entries",
+            |r| r.raw_bytes,
+        ),
+        (
+            "cache_stored_bytes",
+            "gauge",
+            "Stored size of compressed entries",
+            |r| r.stored_bytes,
+        ),
+        (
+            "cache_compression_saved_bytes",
+            "gauge",
+            "Bytes saved by compressing cached entries",
+            |r| r.raw_bytes.saturating_sub(r.stored_bytes),
+        ),
+    ];
+    let mut out = String::new();
+    for (name, kind, help, value) in metrics {
+        out.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} {}\n",
+            name, help, name, kind
+        ));
+        for report in reports {
+            out.push_str(&format!(
+                "{}{{cache=\"{}\"}} {}\n",
+                name,
+                report.name,
+                value(report)
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_counts_hits_misses_and_evictions() {
+        let cache: TtlCache<u32, String> = TtlCache::new("test_counts", 2, Duration::from_secs(60));
+        assert_eq!(cache.get(&1), None);
+        cache.insert(1, "a".to_string());
+        assert_eq!(cache.get(&1).as_deref(), Some("a"));
+        for i in 2..10 {
+            cache.insert(i, "b".to_string());
+        }
+
+        let report = cache.report();
+        assert_eq!((report.hits, report.misses), (1, 1));
+        assert!(report.entries <= 2);
+        assert!(report.evictions > 0);
+        assert!(reports().iter().any(|r| r.name == "test_counts"));
+        assert!(render_metrics(&[report]).contains("cache_hits_total{cache=\"test_counts\"} 1"));
+    }
+
+    #[test]
+    fn test_cache_expires_and_weighs_entries() {
+        let cache: TtlCache<u32, String> =
+            TtlCache::with_weigher("test_weigher", 100, Duration::from_millis(30), |_, v: &String| {
+                v.len() as u32
+            });
+        cache.insert(1, "x".repeat(40));
+        assert_eq!(cache.report().weighted_size, 40);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.get(&1), None);
+    }
+
+    #[test]
+    fn test_set_ttl_applies_to_new_entries() {
+        let cache: TtlCache<u32, String> = TtlCache::new("test_set_ttl", 10, Duration::from_secs(60));
+        cache.insert(1, "a".to_string());
+        cache.set_ttl(Duration::from_millis(30));
+        cache.insert(2, "b".to_string());
+        assert_eq!(cache.report().ttl_seconds, 0);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.get(&1).as_deref(), Some("a"));
+        assert_eq!(cache.get(&2), None);
+    }
+
+    #[test]
+    fn test_compressed_cache_round_trip_and_savings() {
+        let value: Vec<String> = (0..50).map(|i| format!("葬送的芙莉莲 第{}集", i)).collect();
+
+        let cache: CompressedCache<u32, Vec<String>> =
+            CompressedCache::new("test_compressed", 10, Duration::from_secs(60), true);
+        cache.insert(1, value.clone());
+        assert_eq!(cache.get(&1), Some(value.clone()));
+        let report = cache.report();
+        assert_eq!(report.raw_bytes, serde_json::to_vec(&value).unwrap().len() as u64);
+        assert!(report.stored_bytes > 0 && report.stored_bytes < report.raw_bytes);
+        assert!(render_metrics(&[report])
+            .contains("cache_compression_saved_bytes{cache=\"test_compressed\"}"));
+
+        let plain: CompressedCache<u32, Vec<String>> =
+            CompressedCache::new("test_uncompressed", 10, Duration::from_secs(60), false);
+        plain.insert(1, value.clone());
+        assert_eq!(plain.get(&1), Some(value));
+        assert_eq!(plain.report().raw_bytes, 0);
+    }
+}
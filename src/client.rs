@@ -0,0 +1,329 @@
+//! 本服务的 HTTP 客户端 (`client` feature)
+//!
+//! 请求与响应类型与服务端共用 ([`StreamEvent`]、[`RuleInfo`]、[`UpdateResponse`] 等)，
+//! 服务端调整格式时客户端随之编译失败而不是静默解析出错。
+//!
+//! ```no_run
+//! use anime_search::client::{ApiClient, SearchParams};
+//! use anime_search::StreamEvent;
+//! use futures::StreamExt;
+//!
+//! # async fn demo() -> Result<(), anime_search::client::ClientError> {
+//! let client = ApiClient::new("http://localhost:3000");
+//! let mut events = Box::pin(client.stream_search(&SearchParams::new("葬送的芙莉莲")).await?);
+//! while let Some(event) = events.next().await {
+//!     if let StreamEvent::Result { result, .. } = event? {
+//!         println!("{}: {} 个结果", result.name, result.items.len());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::types::{RuleInfo, StreamEvent, UpdateResponse};
+use futures::stream::Stream;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+#[cfg(feature = "bangumi")]
+use crate::bangumi::{BangumiStreamEvent, BangumiSubject};
+
+/// 客户端错误
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("请求失败: {0}")]
+    Request(#[from] reqwest::Error),
+    /// 非 2xx 响应 (`message` 为响应中的 `error` 字段，没有时为响应体)
+    #[error("响应异常状态码 {status}: {message}")]
+    Status { status: u16, message: String },
+    #[error("响应解析失败: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+/// 流式搜索参数 (对应 `POST /api` 的表单字段)
+#[derive(Debug, Clone, Default)]
+pub struct SearchParams {
+    pub keyword: String,
+    /// 指定规则 (为空时使用全部规则)
+    pub rules: Vec<String>,
+    /// 规则分组
+    pub group: Option<String>,
+    /// 结果名称字形 (`hans` / `hant`)
+    pub script: Option<String>,
+    /// 每个规则只返回第一个有效结果
+    pub first_only: bool,
+    /// 通过 Bangumi 标注规范名称
+    pub enrich: bool,
+    /// 每个规则最多返回的结果数 (同时替换规则 searchURL 中的 `@limit`)
+    pub limit: Option<usize>,
+    /// 替换规则 searchURL 中的 `@offset`
+    pub offset: Option<usize>,
+}
+
+impl SearchParams {
+    pub fn new(keyword: impl Into<String>) -> Self {
+        Self {
+            keyword: keyword.into(),
+            ..Default::default()
+        }
+    }
+
+    /// 表单字段 (未设置的可选字段不发送)
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![("anime", self.keyword.clone())];
+        if !self.rules.is_empty() {
+            fields.push(("rules", self.rules.join(",")));
+        }
+        if let Some(group) = &self.group {
+            fields.push(("group", group.clone()));
+        }
+        if let Some(script) = &self.script {
+            fields.push(("script", script.clone()));
+        }
+        if self.first_only {
+            fields.push(("first_only", "1".to_string()));
+        }
+        if self.enrich {
+            fields.push(("enrich", "1".to_string()));
+        }
+        if let Some(limit) = self.limit {
+            fields.push(("limit", limit.to_string()));
+        }
+        if let Some(offset) = self.offset {
+            fields.push(("offset", offset.to_string()));
+        }
+        fields
+    }
+}
+
+/// 本服务的 API 客户端
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    base_url: String,
+    http: reqwest::Client,
+    admin_key: Option<String>,
+}
+
+impl ApiClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            admin_key: None,
+        }
+    }
+
+    /// 使用自定义的 reqwest 客户端 (超时、代理等)
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// 管理密钥 (以 `X-Admin-Key` 发送)
+    pub fn with_admin_key(mut self, key: impl Into<String>) -> Self {
+        self.admin_key = Some(key.into());
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, format!("{}{}", self.base_url, path));
+        match &self.admin_key {
+            Some(key) => request.header("X-Admin-Key", key),
+            None => request,
+        }
+    }
+
+    /// GET 请求并解析 JSON
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, ClientError> {
+        let response = check_status(self.request(reqwest::Method::GET, path).send().await?).await?;
+        Ok(serde_json::from_slice(&response.bytes().await?)?)
+    }
+
+    /// 流式搜索 (`POST /api`)，按行解析为 [`StreamEvent`]
+    pub async fn stream_search(
+        &self,
+        params: &SearchParams,
+    ) -> Result<impl Stream<Item = Result<StreamEvent, ClientError>>, ClientError> {
+        let boundary = multipart_boundary();
+        let response = self
+            .request(reqwest::Method::POST, "/api")
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(multipart_body(&boundary, &params.fields()))
+            .send()
+            .await?;
+        Ok(event_stream(check_status(response).await?))
+    }
+
+    /// 规则列表 (`GET /rules`)
+    pub async fn rules(&self) -> Result<Vec<RuleInfo>, ClientError> {
+        self.get_json("/rules").await
+    }
+
+    /// 从规则仓库更新规则 (`GET /update`)
+    pub async fn update(&self) -> Result<UpdateResponse, ClientError> {
+        self.get_json("/update").await
+    }
+
+    /// Bangumi 条目详情 (经 `/bgm/v0/subjects/{id}` 代理，`token` 为用户的 Bangumi 令牌)
+    #[cfg(feature = "bangumi")]
+    pub async fn bangumi_subject(
+        &self,
+        id: i64,
+        token: Option<&str>,
+    ) -> Result<BangumiSubject, ClientError> {
+        let mut request = self.request(reqwest::Method::GET, &format!("/bgm/v0/subjects/{}", id));
+        if let Some(token) = token {
+            request = request.header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = check_status(request.send().await?).await?;
+        Ok(serde_json::from_slice(&response.bytes().await?)?)
+    }
+
+    /// Bangumi 流式搜索 (`GET /bangumi/search/{keyword}/stream`)
+    #[cfg(feature = "bangumi")]
+    pub async fn bangumi_search(
+        &self,
+        keyword: &str,
+    ) -> Result<impl Stream<Item = Result<BangumiStreamEvent, ClientError>>, ClientError> {
+        let path = format!("/bangumi/search/{}/stream", urlencoding::encode(keyword));
+        let response = self.request(reqwest::Method::GET, &path).send().await?;
+        Ok(event_stream(check_status(response).await?))
+    }
+}
+
+/// 非 2xx 响应转换为 [`ClientError::Status`]
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, ClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| v["error"].as_str().map(str::to_string))
+        .unwrap_or(body);
+    Err(ClientError::Status {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+/// 逐块读取响应体并按行解析事件
+fn event_stream<T: DeserializeOwned>(
+    mut response: reqwest::Response,
+) -> impl Stream<Item = Result<T, ClientError>> {
+    async_stream::stream! {
+        let mut decoder = LineDecoder::default();
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    for line in decoder.push(&chunk) {
+                        yield serde_json::from_str(&line).map_err(ClientError::from);
+                    }
+                }
+                Ok(None) => {
+                    for line in decoder.finish() {
+                        yield serde_json::from_str(&line).map_err(ClientError::from);
+                    }
+                    break;
+                }
+                Err(e) => {
+                    yield Err(ClientError::Request(e));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// 流式响应的行解析: 兼容 NDJSON (每行一个 JSON) 与 SSE (`data:` 行)，
+/// 跳过空行、SSE 注释及 `event:`/`id:`/`retry:` 字段
+#[derive(Debug, Default)]
+pub struct LineDecoder {
+    buffer: Vec<u8>,
+}
+
+impl LineDecoder {
+    /// 追加一块数据，返回其中完整的事件数据行
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            if let Some(data) = event_data(&line) {
+                lines.push(data);
+            }
+        }
+        lines
+    }
+
+    /// 响应结束: 返回末尾没有换行的最后一行 (如有)
+    pub fn finish(&mut self) -> Vec<String> {
+        let rest = std::mem::take(&mut self.buffer);
+        event_data(&rest).into_iter().collect()
+    }
+}
+
+/// 单行中的事件数据 (不含换行与 `data:` 前缀)
+fn event_data(line: &[u8]) -> Option<String> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim_end_matches(['\r', '\n']);
+    if line.trim().is_empty() || line.starts_with(':') {
+        return None;
+    }
+    if let Some(data) = line.strip_prefix("data:") {
+        let data = data.strip_prefix(' ').unwrap_or(data);
+        return (!data.trim().is_empty()).then(|| data.to_string());
+    }
+    if ["event:", "id:", "retry:"].iter().any(|f| line.starts_with(f)) {
+        return None;
+    }
+    Some(line.to_string())
+}
+
+/// multipart 分隔符 (随时间变化，避免与字段内容冲突)
+fn multipart_boundary() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("anime-search-client-{:x}", nanos)
+}
+
+/// multipart/form-data 请求体 (字段均为文本)
+fn multipart_body(boundary: &str, fields: &[(&str, String)]) -> String {
+    let mut body = String::new();
+    for (name, value) in fields {
+        body.push_str(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            boundary, name, value
+        ));
+    }
+    body.push_str(&format!("--{}--\r\n", boundary));
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_decoder_handles_ndjson_and_sse() {
+        let mut decoder = LineDecoder::default();
+        assert!(decoder.push(b"{\"total\":").is_empty());
+        assert_eq!(decoder.push(b" 2}\n\n{\"done\""), vec!["{\"total\": 2}"]);
+        assert_eq!(decoder.finish(), vec!["{\"done\""]);
+
+        let mut decoder = LineDecoder::default();
+        let sse = ": keep-alive\r\nevent: result\r\nid: 1\r\ndata: {\"done\":true}\r\n\r\n";
+        assert_eq!(decoder.push(sse.as_bytes()), vec!["{\"done\":true}"]);
+        assert!(decoder.finish().is_empty());
+    }
+}
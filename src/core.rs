@@ -1,43 +1,457 @@
 //! 核心搜索逻辑
 //! 处理并发搜索和 SSE 流式响应
 
-use crate::engine::search_with_rule;
-use crate::types::{Rule, StreamEvent, StreamProgress, StreamResult};
-use futures::stream::Stream;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
-use tokio::sync::mpsc;
+use crate::config::CONFIG;
+use crate::engine::search_cached;
+use crate::events::{EventSchema, EventV2, Frame, VersionedEvent};
+use crate::rules::RuleSet;
+use crate::history::{self, RuleOutcome};
+use crate::http_client;
+use crate::{rule_stats, script};
+use crate::shutdown::SearchGuard;
+use crate::unified::title_key;
+use crate::types::{
+    known_color_hex, CanonicalTitle, ErrorKind, PlatformSearchResult, Rule, SearchOptions, StreamEvent, StreamProgress, StreamResult,
+    TopRating,
+};
+use crate::metadata::{AnimeInfo, MetadataProvider};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use futures::stream::{Stream, StreamExt};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Semaphore};
 use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info};
 
+/// 单次搜索并发数的硬上限 (管理员覆盖也不能超过)
+pub const MAX_SEARCH_CONCURRENCY: usize = 64;
+
+/// 本次搜索的并发数 (请求覆盖值优先，截断到 1..=MAX_SEARCH_CONCURRENCY)
+fn concurrency_limit(options: &SearchOptions) -> usize {
+    options
+        .concurrency
+        .unwrap_or(CONFIG.search_concurrency)
+        .clamp(1, MAX_SEARCH_CONCURRENCY)
+}
+
+/// 预算耗尽时规则返回的错误
+pub const BUDGET_EXHAUSTED: &str = "Search time budget exhausted";
+
+/// 超过搜索截止时间而被中止的规则的错误消息
+pub const DEADLINE_EXCEEDED: &str = "timeout";
+
+/// 一次搜索中各规则共享的出站时间预算
+///
+/// 每个规则结束后把实际耗时计入预算；剩余不足总预算的 1/10 时不再开始新的规则，
+/// 已开始的规则最多运行开始时的剩余预算。
+#[derive(Debug)]
+pub struct TimeBudget {
+    total: Duration,
+    spent_micros: AtomicU64,
+}
+
+impl TimeBudget {
+    pub fn new(total: Duration) -> Self {
+        Self {
+            total,
+            spent_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// 按 SEARCH_TIME_BUDGET_SECONDS 创建 (为 0 时不限制)
+    pub fn from_config() -> Option<Arc<Self>> {
+        (CONFIG.search_time_budget_seconds > 0)
+            .then(|| Arc::new(Self::new(Duration::from_secs(CONFIG.search_time_budget_seconds))))
+    }
+
+    /// 剩余预算
+    pub fn remaining(&self) -> Duration {
+        let spent = Duration::from_micros(self.spent_micros.load(Ordering::Relaxed));
+        self.total.saturating_sub(spent)
+    }
+
+    /// 剩余预算是否已不足以开始新的规则
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining() <= self.total / 10
+    }
+
+    /// 计入一个规则的耗时
+    pub fn charge(&self, elapsed: Duration) {
+        self.spent_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// 在预算内执行单个规则，返回结果、耗时与是否因预算耗尽而跳过；
+/// 预算与搜索截止时间中较早者传给 HTTP 层，超时后剩余时间不够再请求一次时不再重试
+async fn run_rule_within(
+    rule: &Rule,
+    keyword: &str,
+    options: &SearchOptions,
+    budget: Option<&TimeBudget>,
+    deadline: Option<Instant>,
+) -> (PlatformSearchResult, Duration, bool) {
+    let started = Instant::now();
+    let Some(budget) = budget else {
+        let result = http_client::with_deadline(deadline, run_rule(rule, keyword, options)).await;
+        return (result, started.elapsed(), false);
+    };
+    if budget.is_exhausted() {
+        debug!("规则 {} 因时间预算耗尽跳过", rule.name);
+        return (budget_exhausted(), Duration::ZERO, true);
+    }
+    let remaining = budget.remaining();
+    let deadline = Some(deadline.map_or(started + remaining, |d| d.min(started + remaining)));
+    let search = http_client::with_deadline(deadline, run_rule(rule, keyword, options));
+    let result = tokio::time::timeout(remaining, search)
+        .await
+        .unwrap_or_else(|_| budget_exhausted());
+    let elapsed = started.elapsed();
+    budget.charge(elapsed);
+    (result, elapsed, false)
+}
+
+fn budget_exhausted() -> PlatformSearchResult {
+    PlatformSearchResult::with_error(ErrorKind::Timeout, BUDGET_EXHAUSTED.to_string())
+}
+
+/// 查询规范名称的超时时间 (超时视为没有匹配)
+const CANONICAL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 各规则共享的规范名称查询 (未请求 enrich 时立即得到 None)
+type CanonicalLookup = Shared<BoxFuture<'static, Option<CanonicalTitle>>>;
+
+fn canonical_lookup(keyword: &str, options: &SearchOptions) -> CanonicalLookup {
+    let keyword = keyword.to_string();
+    let enrich = options.enrich;
+    let provider = options.metadata_provider.clone();
+    async move {
+        if !enrich {
+            return None;
+        }
+        tokio::time::timeout(CANONICAL_TIMEOUT, lookup_canonical(&keyword, provider.as_deref()))
+            .await
+            .ok()
+            .flatten()
+    }
+    .boxed()
+    .shared()
+}
+
+/// 在元数据来源中查找名称 (原名或中文名) 与关键词规范化后完全相同的动画条目
+async fn lookup_canonical(keyword: &str, provider: Option<&str>) -> Option<CanonicalTitle> {
+    let provider = match crate::metadata::provider(provider) {
+        Ok(provider) => provider,
+        Err(e) => {
+            debug!("规范名称查询跳过: {}", e);
+            return None;
+        }
+    };
+    let hits = match provider.search(keyword).await {
+        Ok(hits) => hits,
+        Err(e) => {
+            debug!("规范名称查询失败 {}: {}", keyword, e);
+            return None;
+        }
+    };
+    pick_canonical(
+        keyword,
+        hits.into_iter().map(|hit| CanonicalTitle {
+            subject_id: hit.id,
+            name: hit.name,
+            name_cn: hit.name_cn,
+            provider: provider.name().to_string(),
+        }),
+    )
+}
+
+/// 取第一个与关键词完全匹配的条目 (只接受规范化后相同的名称，避免把续作、剧场版等标成同一部)
+fn pick_canonical(
+    keyword: &str,
+    candidates: impl IntoIterator<Item = CanonicalTitle>,
+) -> Option<CanonicalTitle> {
+    candidates
+        .into_iter()
+        .find(|candidate| canonical_matches(candidate, keyword))
+}
+
+/// 标题是否与规范名称 (原名或中文名) 规范化后完全相同
+fn canonical_matches(canonical: &CanonicalTitle, title: &str) -> bool {
+    let key = title_key(title);
+    !key.is_empty() && (key == title_key(&canonical.name) || key == title_key(&canonical.name_cn))
+}
+
+/// 平台第一个结果 (名称或别名) 与规范名称匹配时标注到结果上
+fn annotate_canonical(result: &mut StreamResult, canonical: Option<&CanonicalTitle>) {
+    result.canonical = canonical
+        .filter(|canonical| {
+            result.items.first().is_some_and(|item| {
+                std::iter::once(&item.name)
+                    .chain(&item.alt_name)
+                    .any(|title| canonical_matches(canonical, title))
+            })
+        })
+        .cloned();
+}
+
+/// 每次搜索最多查询评分的不同标题数 (同名标题只查询一次)
+const TOP_RATING_LOOKUPS: usize = 8;
+
+/// 评分查询: 先按标题搜索元数据来源，再取匹配条目的详情 (Bangumi 使用条目缓存)
+type RatingLookup = Shared<BoxFuture<'static, Option<AnimeInfo>>>;
+
+/// 一次搜索中各规则第一个结果的评分查询 (未请求 `top_rating` 或来源不可用时不查询)
+#[derive(Clone)]
+struct RatingLookups {
+    provider: Option<&'static dyn MetadataProvider>,
+    pending: Arc<Mutex<HashMap<String, RatingLookup>>>,
+}
+
+impl RatingLookups {
+    fn new(options: &SearchOptions) -> Self {
+        let provider = options
+            .top_rating
+            .then(|| crate::metadata::provider(options.metadata_provider.as_deref()).ok())
+            .flatten();
+        Self {
+            provider,
+            pending: Arc::default(),
+        }
+    }
+
+    /// 标题的评分查询 (同名标题共用一次查询，超出 [`TOP_RATING_LOOKUPS`] 的新标题不再查询)；
+    /// 按名称搜索，名称或别名与条目名称相同均视为匹配
+    fn lookup(
+        &self,
+        rule: &str,
+        title: &str,
+        alt_title: Option<&str>,
+        url: String,
+    ) -> Option<BoxFuture<'static, Option<TopRating>>> {
+        let provider = self.provider?;
+        let key = title_key(title);
+        if key.is_empty() {
+            return None;
+        }
+        let lookup = {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            match pending.get(&key) {
+                Some(lookup) => lookup.clone(),
+                None if pending.len() >= TOP_RATING_LOOKUPS => return None,
+                None => {
+                    let keys = std::iter::once(key.clone())
+                        .chain(alt_title.map(title_key).filter(|alt| !alt.is_empty()))
+                        .collect();
+                    let lookup = lookup_rating(provider, keys, title.to_string()).boxed().shared();
+                    pending.insert(key, lookup.clone());
+                    lookup
+                }
+            }
+        };
+        let rule = rule.to_string();
+        Some(
+            async move {
+                let info = lookup.await?;
+                Some(TopRating {
+                    rule,
+                    url,
+                    subject_id: info.id,
+                    provider: provider.name().to_string(),
+                    score: info.score,
+                    rank: info.rank,
+                    image: Some(info.image).filter(|image| !image.is_empty()),
+                })
+            }
+            .boxed(),
+        )
+    }
+}
+
+/// 在元数据来源中查找名称 (原名或中文名) 规范化后与 `keys` 之一完全相同的条目并获取详情
+/// (超时、查询失败或没有匹配时为 None)
+async fn lookup_rating(
+    provider: &'static dyn MetadataProvider,
+    keys: Vec<String>,
+    title: String,
+) -> Option<AnimeInfo> {
+    let find = async {
+        let hits = provider.search(&title).await.ok()?;
+        let hit = hits.into_iter().find(|hit| {
+            keys.contains(&title_key(&hit.name)) || keys.contains(&title_key(&hit.name_cn))
+        })?;
+        provider.subject(hit.id).await.ok()
+    };
+    let info = tokio::time::timeout(CANONICAL_TIMEOUT, find).await.ok().flatten();
+    if info.is_none() {
+        debug!("评分查询没有匹配: {}", title);
+    }
+    info
+}
+
+/// 搜索引擎: 规则集 + 默认搜索选项
+///
+/// ```no_run
+/// use anime_search::{Engine, RuleSet};
+///
+/// # async fn demo() {
+/// let engine = Engine::new(RuleSet::load("rules"));
+/// let rule = engine.rules().get("AGE").expect("rule not found");
+/// let result = engine.search_rule(&rule, "葬送的芙莉莲").await;
+/// println!("{} 个结果", result.count);
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Engine {
+    rules: RuleSet,
+    options: SearchOptions,
+}
+
+impl Engine {
+    pub fn new(rules: RuleSet) -> Self {
+        Self {
+            rules,
+            options: SearchOptions::default(),
+        }
+    }
+
+    /// 设置默认搜索选项 (简繁转换、并发数、仅首个结果)
+    pub fn with_options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    /// 使用单个规则搜索
+    pub async fn search_rule(&self, rule: &Rule, keyword: &str) -> PlatformSearchResult {
+        run_rule(rule, keyword, &self.options).await
+    }
+
+    /// 使用全部规则流式搜索，每项为一行 JSON 编码的 [`StreamEvent`]
+    pub fn search_stream(&self, keyword: impl Into<String>) -> impl Stream<Item = String> {
+        search_stream_with_rules(keyword.into(), self.rules.to_vec(), self.options.clone())
+    }
+
+    /// 使用全部规则搜索，全部完成后一次性返回 (按规则顺序)
+    pub async fn search_all(&self, keyword: impl Into<String>) -> Vec<StreamResult> {
+        search_all(keyword.into(), self.rules.to_vec(), self.options.clone()).await
+    }
+}
+
+/// 规范化搜索关键词: 去除控制字符与首尾空白，校验非空与最大长度 (字符数)
+pub fn normalize_keyword(raw: &str, max_len: usize) -> Result<String, String> {
+    let keyword: String = raw.chars().filter(|c| !c.is_control()).collect();
+    let keyword = keyword.trim();
+
+    if keyword.is_empty() {
+        return Err("Anime name is required".to_string());
+    }
+
+    let len = keyword.chars().count();
+    if len > max_len {
+        return Err(format!(
+            "Anime name is too long ({} characters, max {})",
+            len, max_len
+        ));
+    }
+
+    Ok(keyword.to_string())
+}
+
 /// 使用指定规则执行流式搜索
 pub fn search_stream_with_rules(
     keyword: String,
     rules: Vec<Arc<Rule>>,
+    options: SearchOptions,
 ) -> impl Stream<Item = String> {
-    let (tx, rx) = mpsc::channel::<String>(100);
+    let (tx, rx) = mpsc::channel::<SearchEvent>(100);
+    // 在接收端编码与分帧，SSE 的 id 按实际发送顺序递增
+    let framing = options.framing;
+    let schema = options.event_schema;
+    // 在返回流之前计数，保证停机排空能等到这次搜索
+    let guard = SearchGuard::acquire();
+
+    // 流被 drop (客户端断开) 时取消本次搜索，正在进行与排队中的规则请求随之中止
+    let cancel = CancellationToken::new();
+    let cancel_on_drop = cancel.clone().drop_guard();
 
     tokio::spawn(async move {
-        execute_parallel_search(keyword, rules, tx).await;
+        let _guard = guard;
+        let budget = TimeBudget::from_config();
+        execute_parallel_search(keyword, rules, options, budget, cancel, tx).await;
     });
 
-    ReceiverStream::new(rx)
+    ReceiverStream::new(rx).enumerate().map(move |(i, event)| {
+        let _ = &cancel_on_drop;
+        framing.write(i as u64 + 1, &event.encode(schema))
+    })
+}
+
+/// 搜索管线发出的事件: 流式搜索按事件格式编码为帧，非流式搜索 ([`search_all`]) 只收集结果
+enum SearchEvent {
+    Stream(StreamEvent),
+    /// 规则完成但没有结果 (流式搜索只发送进度，非流式搜索返回该规则的空结果)
+    Empty {
+        progress: StreamProgress,
+        result: StreamResult,
+    },
+    /// v2 汇总 (只在 v2 格式下发出)
+    Summary(EventV2),
+}
+
+impl SearchEvent {
+    fn encode(self, schema: EventSchema) -> Frame {
+        match self {
+            SearchEvent::Stream(event) => schema.encode(event),
+            SearchEvent::Empty { progress, .. } => schema.encode(StreamEvent::Progress { progress }),
+            SearchEvent::Summary(summary) => VersionedEvent::new(summary).to_frame(),
+        }
+    }
 }
 
-/// 并行执行搜索
+/// 并行执行搜索 (设置了时间预算时，预算耗尽后其余规则不再请求；`cancel` 取消后中止全部规则，不再发送事件)
 async fn execute_parallel_search(
     keyword: String,
     rules: Vec<Arc<Rule>>,
-    tx: mpsc::Sender<String>,
+    options: SearchOptions,
+    budget: Option<Arc<TimeBudget>>,
+    cancel: CancellationToken,
+    tx: mpsc::Sender<SearchEvent>,
 ) {
     let total = rules.len();
+    let started = Instant::now();
     let completed = Arc::new(AtomicUsize::new(0));
+    // 已发送的结果条数 (持锁计数与发送，保证事件中的进度与累计数按发送顺序递增)
+    let running_total = Arc::new(tokio::sync::Mutex::new(0usize));
+    let semaphore = Arc::new(Semaphore::new(concurrency_limit(&options)));
 
     info!("开始搜索: {}, 共 {} 个规则", keyword, total);
 
+    let schema = options.event_schema;
+    let canonical = canonical_lookup(&keyword, &options);
+    let ratings = RatingLookups::new(&options);
+    // 截止时间: 到时仍未完成的规则中止请求，以超时结果返回，随后照常发送完成信号
+    let deadline = search_deadline(&options).map(|d| tokio::time::Instant::from_std(started) + d);
+
+    // 所选规则全部已自动停用 (熔断) 时，结束前发送 unavailable 事件；
+    // AUTO_DISABLE_ALL_ACTION=skip 时不再请求这些规则
+    let names: Vec<String> = rules.iter().map(|r| r.name.clone()).collect();
+    let all_disabled = total > 0 && names.iter().all(|name| rule_stats::is_auto_disabled(name));
+    let rules = if all_disabled && CONFIG.auto_disable_all_action == "skip" {
+        info!("所选规则均已自动停用，跳过请求: {}", names.join(", "));
+        Vec::new()
+    } else {
+        rules
+    };
+
     // 发送初始事件
     let init_event = StreamEvent::Init { total };
-    if tx.send(format_event(&init_event)).await.is_err() {
+    if tx.send(SearchEvent::Stream(init_event)).await.is_err() {
         return;
     }
 
@@ -48,58 +462,893 @@ async fn execute_parallel_search(
         let keyword = keyword.clone();
         let tx = tx.clone();
         let completed = completed.clone();
+        let running_total = running_total.clone();
+        let options = options.clone();
+        let semaphore = semaphore.clone();
+        let budget = budget.clone();
+        let canonical = canonical.clone();
+        let ratings = ratings.clone();
+        let cancel = cancel.clone();
 
         let handle = tokio::spawn(async move {
-            let result = search_with_rule(&rule, &keyword).await;
-            let current = completed.fetch_add(1, Ordering::SeqCst) + 1;
-
-            let progress = StreamProgress {
-                completed: current,
-                total,
+            // 取消时丢弃等待槽位或请求中的 future，进行中的 HTTP 请求随之中止
+            let search = async {
+                let _permit = semaphore.acquire().await;
+                let deadline = deadline.map(tokio::time::Instant::into_std);
+                run_rule_within(&rule, &keyword, &options, budget.as_deref(), deadline).await
+            };
+            let expired = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
             };
+            let (result, elapsed, skipped, timed_out) = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => return None,
+                (result, elapsed, skipped) = search => (result, elapsed, skipped, false),
+                _ = expired => {
+                    debug!("规则 {} 超过搜索截止时间，已中止", rule.name);
+                    let result =
+                        PlatformSearchResult::with_error(ErrorKind::Timeout, DEADLINE_EXCEEDED.to_string());
+                    (result, started.elapsed(), false, true)
+                }
+            };
+            let outcome = rule_outcome(&rule, &result, elapsed);
 
             debug!("规则 {} 搜索完成: {} 个结果", rule.name, result.count);
 
-            // 只有有结果或有错误时才发送结果
-            let event = if result.count > 0 || result.error.is_some() {
-                let stream_result = StreamResult {
-                    name: rule.name.clone(),
-                    color: if result.error.is_some() {
-                        "red".to_string()
-                    } else {
-                        rule.color.clone()
-                    },
-                    tags: rule.tags.clone(),
-                    items: result.items,
-                    error: result.error,
-                };
-                StreamEvent::Result {
-                    progress,
-                    result: stream_result,
-                }
+            // 只有有结果或有错误时才发送结果 (没有结果时只发送进度)
+            let has_result = result.count > 0 || result.error.is_some();
+            let mut result = to_stream_result(&rule, result);
+            if has_result {
+                annotate_canonical(&mut result, before_deadline(deadline, canonical).await.flatten().as_ref());
+            }
+
+            // 持锁计数并发送: 并发上限下规则成批完成时，进度与累计数仍按发送顺序递增
+            let mut running_total = running_total.lock().await;
+            let progress = StreamProgress {
+                completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                total,
+            };
+            // 合计上限: 截断到剩余名额，截断后没有结果也没有错误时只发送进度
+            if let Some(total_limit) = options.total_limit {
+                result.items.truncate(total_limit.saturating_sub(*running_total));
+            }
+            let top = result
+                .items
+                .first()
+                .map(|item| (item.name.clone(), item.alt_name.clone(), item.url.clone()));
+            let event = if !has_result || (result.items.is_empty() && result.error.is_none()) {
+                SearchEvent::Empty { progress, result }
             } else {
-                StreamEvent::Progress { progress }
+                *running_total += result.items.len();
+                SearchEvent::Stream(StreamEvent::Result {
+                    progress,
+                    result,
+                    running_total: *running_total,
+                })
             };
+            let _ = tx.send(event).await;
+            drop(running_total);
 
-            let _ = tx.send(format_event(&event)).await;
+            // 评分在结果之后单独发送，不阻塞结果
+            let lookup = top.and_then(|(name, alt_name, url)| {
+                ratings.lookup(&rule.name, &name, alt_name.as_deref(), url)
+            });
+            if let Some(lookup) = lookup {
+                if let Some(Some(rating)) = before_deadline(deadline, lookup).await {
+                    let _ = tx.send(SearchEvent::Stream(StreamEvent::Rating { rating })).await;
+                }
+            }
+            Some((outcome, skipped, timed_out))
         });
 
         handles.push(handle);
     }
 
     // 等待所有搜索完成
+    let mut outcomes = Vec::with_capacity(total);
+    let mut skipped = Vec::new();
+    let mut timed_out = Vec::new();
     for handle in handles {
-        let _ = handle.await;
+        if let Ok(Some((outcome, was_skipped, was_timed_out))) = handle.await {
+            if was_skipped {
+                skipped.push(outcome.rule.clone());
+            }
+            if was_timed_out {
+                timed_out.push(outcome.rule.clone());
+            }
+            outcomes.push(outcome);
+        }
+    }
+    if cancel.is_cancelled() {
+        info!("客户端已断开，取消搜索: {}", keyword);
+        return;
+    }
+    let elapsed = started.elapsed();
+    if !skipped.is_empty() {
+        info!("时间预算耗尽，跳过 {} 个规则: {}", skipped.len(), skipped.join(", "));
+    }
+    if !timed_out.is_empty() {
+        info!("超过搜索截止时间，中止 {} 个规则: {}", timed_out.len(), timed_out.join(", "));
     }
 
+    // 没有规则成功时才报告不可用 (探测恢复后的成功照常返回)
+    let all_unavailable = all_disabled && outcomes.iter().all(|o| !o.success);
+    if all_unavailable {
+        let cooldowns = rule_stats::cooldowns(names.iter().map(String::as_str));
+        let event = StreamEvent::Unavailable {
+            all_rules_unavailable: true,
+            retry_after_secs: cooldowns.iter().filter_map(|c| c.retry_after_secs).min(),
+            cooldowns,
+        };
+        let _ = tx.send(SearchEvent::Stream(event)).await;
+    }
+
+    // v2 在完成信号前发送汇总
+    if schema == EventSchema::V2 {
+        let succeeded = outcomes.iter().filter(|o| o.success).count();
+        let summary = EventV2::Summary {
+            total,
+            succeeded,
+            failed: outcomes.len() - succeeded,
+            items: outcomes.iter().map(|o| o.items).sum(),
+            elapsed_ms: elapsed.as_millis() as u64,
+            skipped,
+            canonical: before_deadline(deadline, canonical).await.flatten().map(Into::into),
+            all_rules_unavailable: all_unavailable,
+        };
+        let _ = tx.send(SearchEvent::Summary(summary)).await;
+    }
+    crate::stats::record_search(&keyword, outcomes.iter().map(|o| o.rule.as_str()));
+    history::record_search(&keyword, outcomes, elapsed);
+
     // 发送完成信号
-    let done_event = StreamEvent::Done { done: true };
-    let _ = tx.send(format_event(&done_event)).await;
+    let done_event = StreamEvent::Done { done: true, timed_out };
+    let _ = tx.send(SearchEvent::Stream(done_event)).await;
 
     info!("搜索完成: {}", keyword);
 }
 
-/// 格式化 SSE 事件
-fn format_event(event: &StreamEvent) -> String {
-    format!("{}\n", serde_json::to_string(event).unwrap_or_default())
+/// 在截止时间前等待 `future` (规范名称、评分等附加查询)，超过截止时间返回 None；没有截止时间时一直等待
+async fn before_deadline<F: std::future::Future>(
+    deadline: Option<tokio::time::Instant>,
+    future: F,
+) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+/// 本次搜索的截止时间 (请求覆盖优先，否则为 SEARCH_DEADLINE_SECONDS，0 为不限制)
+fn search_deadline(options: &SearchOptions) -> Option<Duration> {
+    options.deadline.or_else(|| {
+        (CONFIG.search_deadline_seconds > 0).then(|| Duration::from_secs(CONFIG.search_deadline_seconds))
+    })
+}
+
+/// 执行单个规则的搜索并应用搜索选项 (结果缓存见 [`search_cached`]，命中时不计入规则统计)
+async fn run_rule(rule: &Rule, keyword: &str, options: &SearchOptions) -> PlatformSearchResult {
+    let mut result = match search_cached(rule, keyword, options).await {
+        (result, true) => result,
+        (result, false) => {
+            rule_stats::record(&rule.name, result.error.as_deref());
+            mark_circuit_open(result, rule_stats::is_auto_disabled(&rule.name))
+        }
+    };
+    if let Some(target) = options.script {
+        script::convert_items(&mut result.items, target);
+    }
+    result
+}
+
+/// 已自动停用 (熔断) 的规则再次失败时，错误归为 CircuitOpen
+fn mark_circuit_open(mut result: PlatformSearchResult, open: bool) -> PlatformSearchResult {
+    if open && result.error.is_some() {
+        result.error_kind = Some(ErrorKind::CircuitOpen);
+    }
+    result
+}
+
+/// 单个规则的搜索结果摘要 (写入搜索历史)
+fn rule_outcome(rule: &Rule, result: &PlatformSearchResult, elapsed: Duration) -> RuleOutcome {
+    RuleOutcome {
+        rule: rule.name.clone(),
+        success: result.error.is_none(),
+        items: result.count.max(0) as usize,
+        elapsed_ms: elapsed.as_millis() as u64,
+    }
+}
+
+/// 将平台结果转换为流中的结果 (出错时颜色标红)
+fn to_stream_result(rule: &Rule, result: PlatformSearchResult) -> StreamResult {
+    let failed = result.error.is_some();
+    StreamResult {
+        name: rule.name.clone(),
+        color: if failed { "red".to_string() } else { rule.color.clone() },
+        color_hex: if failed {
+            known_color_hex("red").unwrap_or_default().to_string()
+        } else {
+            rule.color_hex()
+        },
+        icon: rule.icon_url(),
+        tags: rule.tags.clone(),
+        items: result.items,
+        error: result.error,
+        error_kind: result.error_kind,
+        next_page_url: result.next_page_url,
+        canonical: None,
+        timing: result.timing,
+    }
+}
+
+/// 执行非流式搜索，等待所有规则完成后一次性返回 (按规则顺序)
+///
+/// 与流式搜索走同一条管线 (截止时间、时间预算、自动停用的规则等处理相同)，收集其中的结果；
+/// 没有结果的规则返回空结果。返回的 future 被丢弃 (客户端断开) 时取消搜索
+pub async fn search_all(
+    keyword: String,
+    rules: Vec<Arc<Rule>>,
+    options: SearchOptions,
+) -> Vec<StreamResult> {
+    let _guard = SearchGuard::acquire();
+    let order: HashMap<String, usize> = rules.iter().enumerate().map(|(i, rule)| (rule.name.clone(), i)).collect();
+    let (tx, mut rx) = mpsc::channel::<SearchEvent>(100);
+    let cancel = CancellationToken::new();
+    let _cancel_on_drop = cancel.clone().drop_guard();
+    let search = execute_parallel_search(keyword, rules, options, TimeBudget::from_config(), cancel, tx);
+    let collect = async {
+        let mut results = Vec::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                SearchEvent::Stream(StreamEvent::Result { result, .. }) | SearchEvent::Empty { result, .. } => {
+                    results.push(result)
+                }
+                _ => {}
+            }
+        }
+        results
+    };
+    let ((), mut results) = tokio::join!(search, collect);
+    results.sort_by_key(|result| order.get(&result.name).copied().unwrap_or(usize::MAX));
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrency_override_is_clamped() {
+        let options = SearchOptions {
+            concurrency: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(concurrency_limit(&options), MAX_SEARCH_CONCURRENCY);
+        let options = SearchOptions {
+            concurrency: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(concurrency_limit(&options), 1);
+    }
+
+    #[test]
+    fn test_normalize_keyword_length_boundary() {
+        let exact = "芙".repeat(100);
+        assert_eq!(normalize_keyword(&exact, 100), Ok(exact.clone()));
+        let over = "芙".repeat(101);
+        assert!(normalize_keyword(&over, 100).unwrap_err().contains("too long"));
+    }
+
+    #[test]
+    fn test_normalize_keyword_strips_control_chars() {
+        assert_eq!(
+            normalize_keyword("  葬送\u{0}的\r\n芙莉莲\t ", 100),
+            Ok("葬送的芙莉莲".to_string())
+        );
+        assert!(normalize_keyword("\u{7}\u{1b} ", 100).is_err());
+    }
+
+    fn frieren() -> CanonicalTitle {
+        CanonicalTitle {
+            subject_id: 400602,
+            name: "葬送のフリーレン".to_string(),
+            name_cn: "葬送的芙莉莲".to_string(),
+            provider: "bangumi".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_canonical_match_is_exact_after_normalization() {
+        let canonical = frieren();
+        for title in ["葬送的芙莉莲", " 葬送的芙莉莲！", "葬送のフリーレン", "葬送の フリーレン"] {
+            assert!(canonical_matches(&canonical, title), "{}", title);
+        }
+        for title in ["葬送的芙莉莲 第二季", "芙莉莲", "葬送的芙莉莲 剧场版", "葬送的芙莉莲2", "", "！"] {
+            assert!(!canonical_matches(&canonical, title), "{}", title);
+        }
+
+        // 关键词只是条目名称的一部分时不选取
+        let sequel = CanonicalTitle {
+            subject_id: 500000,
+            name: "葬送のフリーレン 第2期".to_string(),
+            name_cn: "葬送的芙莉莲 第二季".to_string(),
+            provider: "bangumi".to_string(),
+        };
+        assert_eq!(pick_canonical("芙莉莲", [sequel.clone(), frieren()]), None);
+        assert_eq!(
+            pick_canonical("葬送的芙莉莲", [sequel, frieren()]).map(|c| c.subject_id),
+            Some(400602)
+        );
+    }
+
+    #[test]
+    fn test_canonical_annotates_only_matching_first_item() {
+        use crate::types::SearchResultItem;
+
+        let item = |name: &str| SearchResultItem {
+            name: name.to_string(),
+            alt_name: None,
+            url: "https://example.com/1".to_string(),
+            tags: None,
+            latest: None,
+            cover: None,
+            episodes: None,
+            raw_html: None,
+            source_id: None,
+            rule: String::new(),
+            rule_color: None,
+        };
+        let rule = Rule {
+            name: "AGE".to_string(),
+            ..Default::default()
+        };
+        let canonical = frieren();
+
+        let mut matched = to_stream_result(
+            &rule,
+            PlatformSearchResult::with_items(vec![item("葬送的芙莉莲"), item("其他")]),
+        );
+        annotate_canonical(&mut matched, Some(&canonical));
+        assert_eq!(matched.canonical, Some(canonical.clone()));
+
+        let mut near_miss = to_stream_result(
+            &rule,
+            PlatformSearchResult::with_items(vec![item("葬送的芙莉莲 第二季"), item("葬送的芙莉莲")]),
+        );
+        annotate_canonical(&mut near_miss, Some(&canonical));
+        assert_eq!(near_miss.canonical, None);
+
+        // 名称不同但别名与条目原名相同
+        let mut by_alt = to_stream_result(
+            &rule,
+            PlatformSearchResult::with_items(vec![SearchResultItem {
+                alt_name: Some("葬送のフリーレン".to_string()),
+                ..item("Frieren")
+            }]),
+        );
+        annotate_canonical(&mut by_alt, Some(&canonical));
+        assert_eq!(by_alt.canonical, Some(canonical.clone()));
+    }
+
+    #[test]
+    fn test_failures_of_open_circuit_are_circuit_open() {
+        let failed = || {
+            PlatformSearchResult::with_error(ErrorKind::BadStatus, "响应异常状态码: 502".into())
+        };
+        let result = mark_circuit_open(failed(), true);
+        assert_eq!(result.error_kind, Some(ErrorKind::CircuitOpen));
+        assert_eq!(result.error.as_deref(), Some("响应异常状态码: 502"));
+        assert_eq!(mark_circuit_open(failed(), false).error_kind, Some(ErrorKind::BadStatus));
+        let ok = mark_circuit_open(PlatformSearchResult::with_items(vec![]), true);
+        assert_eq!(ok.error_kind, None);
+
+        let rule = Rule::default();
+        let stream = to_stream_result(&rule, failed());
+        assert_eq!(stream.error_kind, Some(ErrorKind::BadStatus));
+        let json = serde_json::to_value(&stream).unwrap();
+        assert_eq!(json["error_kind"], "bad_status");
+    }
+
+    #[tokio::test]
+    async fn test_dropping_stream_cancels_pending_requests() {
+        use futures::StreamExt;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_millis(300))
+                    .set_body_string(r#"<div class="item"><a href="/v/1">芙莉莲</a></div>"#),
+            )
+            .mount(&server)
+            .await;
+        let rules: Vec<Arc<Rule>> = ["取消a", "取消b", "取消c"]
+            .iter()
+            .map(|name| {
+                Arc::new(Rule {
+                    name: name.to_string(),
+                    base_url: server.uri(),
+                    search_url: format!("{}/search?wd=@keyword", server.uri()),
+                    search_list: "//div[@class='item']".to_string(),
+                    search_name: "//a".to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        // 并发 1: 第一个规则请求中，其余规则排队
+        let options = SearchOptions {
+            concurrency: Some(1),
+            ..Default::default()
+        };
+
+        let mut stream = Box::pin(search_stream_with_rules("取消测试".to_string(), rules, options));
+        let init: serde_json::Value = serde_json::from_str(&stream.next().await.unwrap()).unwrap();
+        assert_eq!(init["total"], 3);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(stream);
+
+        // 未取消时排队的两个规则会在第一个请求结束后依次请求
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_search_can_be_rerun_with_coalescing() {
+        use futures::StreamExt;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        assert!(CONFIG.coalesce_requests);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_millis(200))
+                    .set_body_string(r#"<div class="item"><a href="/v/1">芙莉莲</a></div>"#),
+            )
+            .mount(&server)
+            .await;
+        let rules = vec![Arc::new(Rule {
+            name: "重跑".to_string(),
+            base_url: server.uri(),
+            search_url: format!("{}/search?wd=@keyword", server.uri()),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//a".to_string(),
+            ..Default::default()
+        })];
+        let search = || search_stream_with_rules("重跑测试".to_string(), rules.clone(), SearchOptions::default());
+
+        // 请求发出后取消: 合并中的请求随最后一个等待者移除
+        let mut stream = Box::pin(search());
+        stream.next().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(stream);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+        assert!(!crate::http_client::is_in_flight(&server.uri()));
+
+        // 重新搜索发出新的请求并正常完成
+        let events: Vec<serde_json::Value> = search()
+            .map(|frame| serde_json::from_str(&frame).unwrap())
+            .collect()
+            .await;
+        let result = events.iter().find_map(|e| e.get("result")).unwrap();
+        assert_eq!(result["items"][0]["name"], "芙莉莲");
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_deadline_times_out_unfinished_rules() {
+        use futures::StreamExt;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let body = r#"<div class="item"><a href="/v/1">芙莉莲</a></div>"#;
+        let fast = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&fast)
+            .await;
+        let slow = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_secs(5))
+                    .set_body_string(body),
+            )
+            .mount(&slow)
+            .await;
+        let rule = |name: &str, server: &MockServer| {
+            Arc::new(Rule {
+                name: name.to_string(),
+                base_url: server.uri(),
+                search_url: format!("{}/search?wd=@keyword", server.uri()),
+                search_list: "//div[@class='item']".to_string(),
+                search_name: "//a".to_string(),
+                ..Default::default()
+            })
+        };
+        let rules = vec![rule("截止快", &fast), rule("截止慢", &slow)];
+        let options = SearchOptions {
+            deadline: Some(Duration::from_millis(300)),
+            ..Default::default()
+        };
+
+        let started = Instant::now();
+        let lines: Vec<serde_json::Value> = search_stream_with_rules("截止测试".to_string(), rules, options)
+            .map(|line| serde_json::from_str(&line).unwrap())
+            .collect()
+            .await;
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        let result = |name: &str| {
+            lines
+                .iter()
+                .find(|line| line["result"]["name"] == name)
+                .map(|line| line["result"].clone())
+                .unwrap()
+        };
+        assert_eq!(result("截止快")["items"].as_array().unwrap().len(), 1);
+        assert_eq!(result("截止慢")["error"], DEADLINE_EXCEEDED);
+
+        let done = lines.last().unwrap();
+        assert_eq!(done["done"], true);
+        assert_eq!(done["timed_out"], serde_json::json!(["截止慢"]));
+    }
+
+    #[tokio::test]
+    async fn test_tiny_budget_skips_remaining_rules() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_millis(200))
+                    .set_body_string(r#"<div class="item"><a href="/v/1">芙莉莲</a></div>"#),
+            )
+            .mount(&server)
+            .await;
+        let rules: Vec<Arc<Rule>> = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                Arc::new(Rule {
+                    name: name.to_string(),
+                    base_url: server.uri(),
+                    search_url: format!("{}/search?wd=@keyword", server.uri()),
+                    search_list: "//div[@class='item']".to_string(),
+                    search_name: "//a".to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let options = SearchOptions {
+            concurrency: Some(1),
+            event_schema: EventSchema::V2,
+            ..Default::default()
+        };
+        let budget = Arc::new(TimeBudget::new(Duration::from_millis(20)));
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let cancel = CancellationToken::new();
+        execute_parallel_search("预算测试".to_string(), rules, options, Some(budget), cancel, tx)
+            .await;
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            let frame = event.encode(EventSchema::V2);
+            events.push(serde_json::from_str::<serde_json::Value>(&frame.data).unwrap());
+        }
+
+        let results: Vec<_> = events.iter().filter(|e| e["event"] == "result").collect();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|e| e["result"]["error"] == BUDGET_EXHAUSTED));
+        let summary = events.iter().find(|e| e["event"] == "summary").unwrap();
+        assert_eq!(summary["skipped"], serde_json::json!(["b", "c"]));
+        assert_eq!(summary["failed"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_lookups_stop_waiting_at_deadline() {
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(100);
+        let started = Instant::now();
+        // 卡住的查询 (规范名称 / 评分) 不会让完成信号晚于截止时间
+        assert_eq!(before_deadline(Some(deadline), std::future::pending::<u32>()).await, None);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(before_deadline(Some(deadline), async { 1 }).await, Some(1));
+        assert_eq!(before_deadline(None, async { 2 }).await, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_search_all_collects_pipeline_results_in_rule_order() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/slow"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_secs(3))
+                    .set_body_string(r#"<div class="item"><a href="/v/1">芙莉莲</a></div>"#),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/empty"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html></html>"))
+            .mount(&server)
+            .await;
+        let rule = |name: &str, site: &str| {
+            Arc::new(Rule {
+                name: name.to_string(),
+                base_url: server.uri(),
+                search_url: format!("{}/{}?wd=@keyword", server.uri(), site),
+                search_list: "//div[@class='item']".to_string(),
+                search_name: "//a".to_string(),
+                ..Default::default()
+            })
+        };
+        let options = SearchOptions {
+            deadline: Some(Duration::from_millis(300)),
+            skip_episodes: true,
+            ..Default::default()
+        };
+
+        // 与流式搜索相同受截止时间约束；没有结果的规则返回空结果，按规则顺序排列
+        let started = Instant::now();
+        let results = search_all("非流式测试".to_string(), vec![rule("慢", "slow"), rule("空", "empty")], options).await;
+        assert!(started.elapsed() < Duration::from_secs(2));
+        let summary: Vec<_> = results
+            .iter()
+            .map(|r| (r.name.as_str(), r.error.as_deref(), r.items.len()))
+            .collect();
+        assert_eq!(summary, [("慢", Some(DEADLINE_EXCEEDED), 0), ("空", None, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_budget_and_deadline_earlier_one_wins() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_secs(3))
+                    .set_body_string(r#"<div class="item"><a href="/v/1">芙莉莲</a></div>"#),
+            )
+            .mount(&server)
+            .await;
+        let rule = Arc::new(Rule {
+            name: "先到".to_string(),
+            base_url: server.uri(),
+            search_url: format!("{}/search?wd=@keyword", server.uri()),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//a".to_string(),
+            ..Default::default()
+        });
+        let run = |budget: u64, deadline: u64| {
+            let rule = rule.clone();
+            async move {
+                let options = SearchOptions {
+                    deadline: Some(Duration::from_millis(deadline)),
+                    ..Default::default()
+                };
+                let budget = Arc::new(TimeBudget::new(Duration::from_millis(budget)));
+                let (tx, mut rx) = mpsc::channel(16);
+                let cancel = CancellationToken::new();
+                execute_parallel_search("先到测试".to_string(), vec![rule], options, Some(budget), cancel, tx)
+                    .await;
+                let mut error = None;
+                while let Some(event) = rx.recv().await {
+                    let frame = event.encode(EventSchema::V1);
+                    let event: serde_json::Value = serde_json::from_str(&frame.data).unwrap();
+                    if let Some(e) = event["result"]["error"].as_str() {
+                        error = Some(e.to_string());
+                    }
+                }
+                error
+            }
+        };
+
+        assert_eq!(run(200, 1000).await.as_deref(), Some(BUDGET_EXHAUSTED));
+        assert_eq!(run(1000, 200).await.as_deref(), Some(DEADLINE_EXCEEDED));
+    }
+
+    #[tokio::test]
+    async fn test_mock_rule_flows_through_stream() {
+        use crate::types::SearchResultItem;
+        use futures::StreamExt;
+
+        let item = |name: &str| SearchResultItem {
+            name: name.to_string(),
+            alt_name: None,
+            url: format!("https://mock.invalid/{}", name),
+            tags: None,
+            latest: Some("更新至第12集".to_string()),
+            cover: None,
+            episodes: None,
+            raw_html: None,
+            source_id: None,
+            rule: String::new(),
+            rule_color: None,
+        };
+        let rule = Arc::new(Rule {
+            name: "模拟源".to_string(),
+            color: "green".to_string(),
+            mock_results: Some(vec![item("葬送的芙莉莲"), item("芙莉莲 SP")]),
+            ..Default::default()
+        });
+
+        let lines: Vec<String> =
+            search_stream_with_rules("芙莉莲".to_string(), vec![rule], SearchOptions::default())
+                .collect()
+                .await;
+        let events: Vec<serde_json::Value> =
+            lines.iter().map(|l| serde_json::from_str(l).unwrap()).collect();
+
+        let result = events.iter().find_map(|e| e.get("result")).unwrap();
+        assert_eq!(result["name"], "模拟源");
+        assert_eq!(result["color"], "green");
+        assert_eq!(result["color_hex"], "#4caf50");
+        assert!(result.get("error").is_none());
+        let names: Vec<_> = result["items"].as_array().unwrap().iter().map(|i| i["name"].clone()).collect();
+        assert_eq!(names, ["葬送的芙莉莲", "芙莉莲 SP"]);
+        assert_eq!(result["items"][0]["latest"], "更新至第12集");
+        assert!(events.iter().any(|e| e.get("done") == Some(&serde_json::json!(true))), "{:?}", events);
+    }
+
+    #[tokio::test]
+    async fn test_running_total_accumulates_across_rules() {
+        use crate::types::SearchResultItem;
+        use futures::StreamExt;
+
+        let rule = |name: &str, count: usize| {
+            let items = (0..count)
+                .map(|i| SearchResultItem {
+                    name: format!("{} {}", name, i),
+                    alt_name: None,
+                    url: format!("https://mock.invalid/{}/{}", name, i),
+                    tags: None,
+                    latest: None,
+                    cover: None,
+                    episodes: None,
+                    raw_html: None,
+                    source_id: None,
+                    rule: String::new(),
+                    rule_color: None,
+                })
+                .collect();
+            Arc::new(Rule {
+                name: name.to_string(),
+                mock_results: Some(items),
+                ..Default::default()
+            })
+        };
+        // 无结果的规则只发送进度，出错的规则 (searchURL 为空) 计 0 条
+        let failing = Arc::new(Rule {
+            name: "累计出错".to_string(),
+            ..Default::default()
+        });
+        let rules = vec![rule("累计甲", 2), rule("累计乙", 3), rule("累计丙", 0), failing];
+
+        let lines: Vec<String> =
+            search_stream_with_rules("芙莉莲".to_string(), rules, SearchOptions::default())
+                .collect()
+                .await;
+        let mut previous = 0;
+        let mut results = 0;
+        for line in &lines {
+            let event: serde_json::Value = serde_json::from_str(line).unwrap();
+            let Some(result) = event.get("result") else {
+                continue;
+            };
+            let running_total = event["running_total"].as_u64().unwrap() as usize;
+            assert_eq!(running_total, previous + result["items"].as_array().unwrap().len(), "{}", line);
+            previous = running_total;
+            results += 1;
+        }
+        assert_eq!((results, previous), (3, 5));
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_keeps_progress_ordered() {
+        use crate::types::MockSource;
+        use futures::StreamExt;
+
+        // 6 个各耗时 100ms 的模拟源，并发 2 时分三批完成
+        let rules: Vec<_> = (0..6)
+            .map(|i| {
+                Arc::new(Rule {
+                    name: format!("并发{}", i),
+                    rule_type: "mock".to_string(),
+                    mock: Some(MockSource {
+                        latency_ms: 100,
+                        items: 1,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let options = SearchOptions {
+            concurrency: Some(2),
+            ..Default::default()
+        };
+
+        let started = Instant::now();
+        let lines: Vec<String> = search_stream_with_rules("芙莉莲".to_string(), rules, options)
+            .collect()
+            .await;
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        let events: Vec<serde_json::Value> =
+            lines.iter().map(|l| serde_json::from_str(l).unwrap()).collect();
+        let progress: Vec<_> = events
+            .iter()
+            .filter_map(|e| e.get("progress"))
+            .map(|p| (p["completed"].as_u64().unwrap(), p["total"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(progress, (1..=6).map(|i| (i, 6)).collect::<Vec<_>>());
+        assert_eq!(events.last().unwrap()["done"], true);
+    }
+
+    #[tokio::test]
+    async fn test_all_rules_on_cooldown_emits_unavailable() {
+        use futures::StreamExt;
+
+        // searchURL 为空的规则不发送请求即失败
+        let rules: Vec<_> = ["熔断甲", "熔断乙"]
+            .iter()
+            .map(|name| {
+                rule_stats::force_disable(name, "连续失败 10 次");
+                Arc::new(Rule {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let options = SearchOptions {
+            event_schema: EventSchema::V2,
+            ..Default::default()
+        };
+        let lines: Vec<String> = search_stream_with_rules("芙莉莲".to_string(), rules, options)
+            .collect()
+            .await;
+        let events: Vec<serde_json::Value> =
+            lines.iter().map(|l| serde_json::from_str(l).unwrap()).collect();
+
+        let results: Vec<_> = events.iter().filter(|e| e["event"] == "result").collect();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|e| e["result"]["errorKind"] == "circuit_open"), "{:?}", results);
+        let unavailable = events.iter().find(|e| e["event"] == "unavailable").unwrap();
+        let cooldowns: Vec<_> = unavailable["cooldowns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["rule"].as_str().unwrap())
+            .collect();
+        assert_eq!(cooldowns, ["熔断甲", "熔断乙"]);
+        assert_eq!(unavailable["cooldowns"][0]["reason"], "连续失败 10 次");
+        let summary = events.iter().find(|e| e["event"] == "summary").unwrap();
+        assert_eq!(summary["allRulesUnavailable"], true);
+
+        // 有未停用的规则时不报告
+        let rule = Arc::new(Rule {
+            name: "未熔断".to_string(),
+            ..Default::default()
+        });
+        let lines: Vec<String> =
+            search_stream_with_rules("芙莉莲".to_string(), vec![rule], SearchOptions::default())
+                .collect()
+                .await;
+        assert!(lines.iter().all(|l| !l.contains("all_rules_unavailable")), "{:?}", lines);
+    }
 }
@@ -0,0 +1,417 @@
+//! 弹幕 (弹弹play 开放平台)
+//! <https://api.dandanplay.net/swagger/index.html>
+//!
+//! 请求按开放平台要求签名: `X-Signature = base64(sha256(AppId + Timestamp + Path + AppSecret))`，
+//! AppId 与 AppSecret 来自 DANDANPLAY_APP_ID / DANDANPLAY_APP_SECRET。
+//! 番剧搜索、剧集列表与弹幕均按 CACHE_DANMAKU_TTL_SECS 缓存。
+
+use crate::cache::CompressedCache;
+use crate::config::CONFIG;
+use crate::http_client::HTTP_CLIENT;
+use base64::Engine as _;
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// 缓存容量 (每类条目数)
+const CACHE_CAPACITY: u64 = 500;
+
+/// 番剧 (`/api/v2/search/anime`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuAnime {
+    pub anime_id: i64,
+    pub anime_title: String,
+    #[serde(default, rename = "type")]
+    pub anime_type: String,
+    #[serde(default)]
+    pub type_description: String,
+    #[serde(default)]
+    pub image_url: Option<String>,
+    #[serde(default)]
+    pub start_date: Option<String>,
+    #[serde(default)]
+    pub episode_count: Option<i32>,
+    #[serde(default)]
+    pub rating: Option<f64>,
+}
+
+/// 剧集
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuEpisode {
+    pub episode_id: i64,
+    pub episode_title: String,
+}
+
+/// 番剧及其剧集 (`/api/v2/search/episodes`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DanmakuEpisodeGroup {
+    pub anime_id: i64,
+    pub anime_title: String,
+    #[serde(default)]
+    pub episodes: Vec<DanmakuEpisode>,
+}
+
+/// 单条弹幕 (`p` 为 "出现时间,模式,颜色,用户ID"，`m` 为内容)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanmakuComment {
+    pub cid: i64,
+    pub p: String,
+    pub m: String,
+}
+
+/// 弹幕列表 (`/api/v2/comment/{episodeId}`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanmakuComments {
+    pub count: usize,
+    #[serde(default)]
+    pub comments: Vec<DanmakuComment>,
+}
+
+/// 按关键词与集数匹配到的剧集
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanmakuMatch {
+    pub anime_id: i64,
+    pub anime_title: String,
+    pub episode_id: i64,
+    pub episode_title: String,
+}
+
+/// 开放平台的通用响应字段
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    #[serde(default = "default_success")]
+    success: bool,
+    #[serde(default)]
+    error_message: Option<String>,
+}
+
+fn default_success() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchAnimeResponse {
+    #[serde(default)]
+    animes: Vec<DanmakuAnime>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchEpisodesResponse {
+    #[serde(default)]
+    animes: Vec<DanmakuEpisodeGroup>,
+}
+
+/// 请求签名: base64(sha256(AppId + Timestamp + Path + AppSecret))
+pub fn signature(app_id: &str, timestamp: i64, path: &str, app_secret: &str) -> String {
+    let digest = Sha256::digest(format!("{}{}{}{}", app_id, timestamp, path, app_secret));
+    base64::engine::general_purpose::STANDARD.encode(digest)
+}
+
+/// 弹弹play 开放平台客户端
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: String,
+    app_id: String,
+    app_secret: String,
+}
+
+impl Client {
+    pub fn new(
+        base_url: impl Into<String>,
+        app_id: impl Into<String>,
+        app_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            app_id: app_id.into(),
+            app_secret: app_secret.into(),
+        }
+    }
+
+    /// 按服务配置创建，未配置 AppId/AppSecret 时为 None
+    pub fn from_config() -> Option<Self> {
+        Some(Self::new(
+            CONFIG.dandanplay_api_base.as_str(),
+            CONFIG.dandanplay_app_id.clone()?,
+            CONFIG.dandanplay_app_secret.clone()?,
+        ))
+    }
+
+    /// 签名后的 GET 请求 (`path` 不含查询参数，签名只覆盖路径)
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> anyhow::Result<T> {
+        let mut url = url::Url::parse(&format!("{}{}", self.base_url, path))?;
+        url.query_pairs_mut().extend_pairs(query);
+        let timestamp = chrono::Utc::now().timestamp();
+        let response = HTTP_CLIENT
+            .get(url)
+            .header("Accept", "application/json")
+            .header("X-AppId", &self.app_id)
+            .header("X-Timestamp", timestamp.to_string())
+            .header(
+                "X-Signature",
+                signature(&self.app_id, timestamp, path, &self.app_secret),
+            )
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("弹弹play API 返回错误: {}", response.status());
+        }
+        let body = response.text().await?;
+        let envelope: Envelope = serde_json::from_str(&body)?;
+        if !envelope.success {
+            anyhow::bail!(
+                "弹弹play API 返回错误: {}",
+                envelope.error_message.unwrap_or_default()
+            );
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// 搜索番剧
+    pub async fn search_anime(&self, keyword: &str) -> anyhow::Result<Vec<DanmakuAnime>> {
+        let response: SearchAnimeResponse = self
+            .get_json("/api/v2/search/anime", &[("keyword", keyword.to_string())])
+            .await?;
+        Ok(response.animes)
+    }
+
+    /// 搜索番剧及其剧集列表
+    pub async fn search_episodes(&self, keyword: &str) -> anyhow::Result<Vec<DanmakuEpisodeGroup>> {
+        let response: SearchEpisodesResponse = self
+            .get_json("/api/v2/search/episodes", &[("anime", keyword.to_string())])
+            .await?;
+        Ok(response.animes)
+    }
+
+    /// 剧集弹幕 (含第三方关联弹幕)
+    pub async fn comments(&self, episode_id: i64) -> anyhow::Result<DanmakuComments> {
+        self.get_json(
+            &format!("/api/v2/comment/{}", episode_id),
+            &[("withRelated", "true".to_string())],
+        )
+        .await
+    }
+}
+
+// ============================================================================
+// 缓存
+// ============================================================================
+
+static ANIME_CACHE: Lazy<CompressedCache<String, Vec<DanmakuAnime>>> =
+    Lazy::new(|| cache("danmaku_anime"));
+
+static EPISODES_CACHE: Lazy<CompressedCache<String, Vec<DanmakuEpisodeGroup>>> =
+    Lazy::new(|| cache("danmaku_episodes"));
+
+static COMMENTS_CACHE: Lazy<CompressedCache<i64, DanmakuComments>> =
+    Lazy::new(|| cache("danmaku_comments"));
+
+fn cache<K, V>(name: &'static str) -> CompressedCache<K, V>
+where
+    K: std::hash::Hash + Eq + Send + Sync + 'static,
+    V: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    CompressedCache::new(
+        name,
+        CACHE_CAPACITY,
+        Duration::from_secs(CONFIG.cache_danmaku_ttl_secs),
+        CONFIG.cache_compress,
+    )
+    .follow_ttl(|| Duration::from_secs(CONFIG.cache_danmaku_ttl_secs))
+}
+
+/// 搜索番剧 (缓存)
+pub async fn search_anime(client: &Client, keyword: &str) -> anyhow::Result<Vec<DanmakuAnime>> {
+    if let Some(animes) = ANIME_CACHE.get(&keyword.to_string()) {
+        return Ok(animes);
+    }
+    let animes = client.search_anime(keyword).await?;
+    ANIME_CACHE.insert(keyword.to_string(), animes.clone());
+    Ok(animes)
+}
+
+/// 剧集弹幕 (缓存)
+pub async fn comments(client: &Client, episode_id: i64) -> anyhow::Result<DanmakuComments> {
+    if let Some(comments) = COMMENTS_CACHE.get(&episode_id) {
+        return Ok(comments);
+    }
+    let comments = client.comments(episode_id).await?;
+    COMMENTS_CACHE.insert(episode_id, comments.clone());
+    Ok(comments)
+}
+
+/// 按关键词与集数匹配剧集 (剧集列表缓存)，没有匹配时为 None
+pub async fn match_episode(
+    client: &Client,
+    keyword: &str,
+    episode: u32,
+) -> anyhow::Result<Option<DanmakuMatch>> {
+    let groups = match EPISODES_CACHE.get(&keyword.to_string()) {
+        Some(groups) => groups,
+        None => {
+            let groups = client.search_episodes(keyword).await?;
+            EPISODES_CACHE.insert(keyword.to_string(), groups.clone());
+            groups
+        }
+    };
+    Ok(best_match(&groups, keyword, episode))
+}
+
+// ============================================================================
+// 匹配
+// ============================================================================
+
+/// 标题比较用的形式: 小写，只保留字母与数字 (含中日文字符)
+fn title_key(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 剧集标题中的集数: 优先取 "第N话/話/集"，否则取开头的数字 (如 "03 标题")
+fn episode_number(title: &str) -> Option<u32> {
+    let digits = |s: &str| -> Option<u32> {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        s[..end].parse().ok()
+    };
+    if let Some(pos) = title.find('第') {
+        let rest = &title[pos + '第'.len_utf8()..];
+        if let Some(n) = digits(rest.trim_start()) {
+            return Some(n);
+        }
+    }
+    digits(title.trim_start())
+}
+
+/// 选择番剧 (标题相同 > 标题包含关键词 > 第一个)，再按集数选择剧集；
+/// 剧集标题均无集数时按顺序取第 N 个
+fn best_match(groups: &[DanmakuEpisodeGroup], keyword: &str, episode: u32) -> Option<DanmakuMatch> {
+    let key = title_key(keyword);
+    let group = groups
+        .iter()
+        .find(|g| title_key(&g.anime_title) == key)
+        .or_else(|| groups.iter().find(|g| title_key(&g.anime_title).contains(&key)))
+        .or_else(|| groups.first())?;
+
+    let numbered = group
+        .episodes
+        .iter()
+        .any(|e| episode_number(&e.episode_title).is_some());
+    let found = if numbered {
+        group
+            .episodes
+            .iter()
+            .find(|e| episode_number(&e.episode_title) == Some(episode))
+    } else {
+        group.episodes.get((episode as usize).checked_sub(1)?)
+    }?;
+
+    Some(DanmakuMatch {
+        anime_id: group.anime_id,
+        anime_title: group.anime_title.clone(),
+        episode_id: found.episode_id,
+        episode_title: found.episode_title.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(id: i64, title: &str, episodes: &[(i64, &str)]) -> DanmakuEpisodeGroup {
+        DanmakuEpisodeGroup {
+            anime_id: id,
+            anime_title: title.to_string(),
+            episodes: episodes
+                .iter()
+                .map(|(id, title)| DanmakuEpisode {
+                    episode_id: *id,
+                    episode_title: title.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_signature_matches_reference() {
+        // base64(sha256("id1700000000/api/v2/comment/1secret"))
+        assert_eq!(
+            signature("id", 1700000000, "/api/v2/comment/1", "secret"),
+            "KEH//nSULlg/PCca2m9dyr1pHYspMg/EkXRa9Vevdx0="
+        );
+    }
+
+    #[test]
+    fn test_best_match_prefers_exact_title_and_episode_number() {
+        let groups = vec![
+            group(1, "葬送的芙莉莲 特别篇", &[(101, "第1话 特别篇")]),
+            group(2, "葬送的芙莉莲", &[(201, "第1话 冒险的结束"), (203, "第3話 杀人魔法"), (228, "第28话")]),
+        ];
+        let found = best_match(&groups, "葬送的 芙莉莲", 3).unwrap();
+        assert_eq!((found.anime_id, found.episode_id), (2, 203));
+        assert!(best_match(&groups, "葬送的芙莉莲", 4).is_none());
+
+        // 没有集数的标题按顺序取
+        let groups = vec![group(3, "Frieren", &[(301, "Intro"), (302, "Journey")])];
+        assert_eq!(best_match(&groups, "frieren", 2).unwrap().episode_id, 302);
+        assert!(best_match(&groups, "frieren", 0).is_none());
+        assert!(best_match(&[], "frieren", 1).is_none());
+        assert_eq!(episode_number("03 旅立ち"), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_client_signs_requests() {
+        use wiremock::matchers::{header, header_exists, method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/search/anime"))
+            .and(query_param("keyword", "芙莉莲"))
+            .and(header("X-AppId", "app"))
+            .and(header_exists("X-Timestamp"))
+            .and(header_exists("X-Signature"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "success": true,
+                "animes": [{"animeId": 1, "animeTitle": "葬送的芙莉莲", "type": "tvseries", "episodeCount": 28}],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/comment/203"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "success": false,
+                "errorMessage": "episode not found",
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "app", "secret");
+        let animes = client.search_anime("芙莉莲").await.unwrap();
+        assert_eq!(animes[0].anime_title, "葬送的芙莉莲");
+        assert_eq!(animes[0].episode_count, Some(28));
+
+        let err = client.comments(203).await.unwrap_err();
+        assert!(err.to_string().contains("episode not found"));
+
+        let request = &server.received_requests().await.unwrap()[0];
+        let timestamp: i64 = request.headers["X-Timestamp"].to_str().unwrap().parse().unwrap();
+        assert_eq!(
+            request.headers["X-Signature"].to_str().unwrap(),
+            signature("app", timestamp, "/api/v2/search/anime", "secret")
+        );
+    }
+}
@@ -1,79 +1,895 @@
 //! 规则驱动的搜索引擎
-//! 完全兼容 Kazumi 规则格式: https://github.com/Predidit/Kazumi
+//! 完全兼容 Kazumi 规则格式: <https://github.com/Predidit/Kazumi>
 //! 使用纯 Rust 库 (scraper) 进行 HTML 解析，通过 XPath→CSS 转换支持规则
 
-use crate::http_client::{get_text, post_form_text};
-use crate::types::{Episode, EpisodeRoad, PlatformSearchResult, Rule, SearchResultItem};
+use crate::cache::{CacheReport, CompressedCache};
+use crate::config::CONFIG;
+use crate::http_client::{
+    get_page, get_text, get_text_with_retry, post_form_text_with_retry, HttpClientError, RequestTrace,
+};
+use crate::types::{
+    Episode, EpisodeRoad, ErrorKind, MockSource, PlatformSearchResult, Rule, SearchOptions,
+    SearchResultItem, Timing,
+};
+use crate::storage::{self, ns};
 use crate::xpath_to_css::{xpath_to_css, PositionFilter};
+use futures::stream::{self, Stream, StreamExt};
+use regex::Regex;
 use scraper::{Html, Selector, ElementRef};
-use tracing::{debug, warn};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// 集数名中的第一个数字 (集数模板的默认提取正则)
+static RE_EPISODE_NUMBER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\d+(?:\.\d+)?").unwrap());
+
+/// 调试用原始 HTML 的最大长度 (字节)
+const MAX_RAW_HTML_LEN: usize = 4096;
+
+/// 像集数的链接文字 (如 "第12集", "12", "EP12", "SP", "OVA", "剧场版")
+static RE_EPISODE_NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(第\s*\d+(\.\d+)?\s*[集话話期]|\d{1,4}(\.\d+)?\s*[集话話]?|(ep|e)\s*\d+|sp\d*|ova\d*|oad\d*|剧场版|劇場版|正片|全集|hd|tc|完结)$").unwrap()
+});
+
+/// 保留的搜索页最大字节数 (超出时不保留)
+const MAX_REMEMBERED_PAGE_LEN: usize = 512 * 1024;
+
+/// 各规则最近一次请求成功的搜索页 (供解析基准复用，不落盘)
+static LAST_SEARCH_PAGES: LazyLock<Mutex<HashMap<String, Arc<str>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 规则最近一次请求成功的搜索页
+pub fn last_search_page(rule: &str) -> Option<Arc<str>> {
+    LAST_SEARCH_PAGES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(rule)
+        .cloned()
+}
+
+fn remember_search_page(rule: &str, html: &str) {
+    if html.len() <= MAX_REMEMBERED_PAGE_LEN {
+        LAST_SEARCH_PAGES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(rule.to_string(), Arc::from(html));
+    }
+}
+
+/// 单个规则搜索结果的缓存键 (规则版本变化后自然失效)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+struct ResultKey {
+    rule: String,
+    version: String,
+    keyword: String,
+    first_only: bool,
+    include_raw: bool,
+    skip_episodes: bool,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+impl ResultKey {
+    fn new(rule: &Rule, keyword: &str, options: &SearchOptions) -> Self {
+        Self {
+            rule: rule.name.clone(),
+            version: rule.version.clone(),
+            keyword: keyword.to_string(),
+            first_only: options.first_only,
+            include_raw: options.include_raw,
+            skip_episodes: options.skip_episodes,
+            limit: options.limit,
+            offset: options.offset,
+        }
+    }
+
+    /// 持久化存储中的键 (各字段的 JSON 编码)
+    fn storage_key(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// 搜索结果缓存 (CACHE_SEARCH_CAPACITY / CACHE_SEARCH_TTL_SECS)
+///
+/// 只缓存成功的结果，缓存简繁转换前的原文，导出等接口可复用刚执行过的搜索；
+/// CACHE_COMPRESS 时压缩保存 (含 include_raw 的原始 HTML)。
+/// 存储为 SQLite / Redis 时同时写入存储 (重启或多实例间复用)，进程内未命中时再查存储
+static RESULT_CACHE: LazyLock<CompressedCache<ResultKey, PlatformSearchResult>> = LazyLock::new(|| {
+    CompressedCache::new(
+        "search_result",
+        CONFIG.cache_search_capacity,
+        Duration::from_secs(CONFIG.cache_search_ttl_secs),
+        CONFIG.cache_compress,
+    )
+    .follow_ttl(|| Duration::from_secs(CONFIG.cache_search_ttl_secs))
+});
+
+/// 搜索结果缓存 (有效期为 0 时不缓存，重载配置后随之启用/停用)
+fn result_cache() -> Option<&'static CompressedCache<ResultKey, PlatformSearchResult>> {
+    (CONFIG.cache_search_ttl_secs > 0).then(|| &*RESULT_CACHE)
+}
+
+/// 搜索结果缓存的统计 (未启用时为 None)
+pub fn result_cache_report() -> Option<CacheReport> {
+    result_cache().map(CompressedCache::report)
+}
+
+/// 清空搜索结果缓存 (含存储中的副本)，返回进程内清除的条目数 (未启用时为 None)
+pub fn clear_result_cache() -> Option<u64> {
+    let cache = result_cache()?;
+    let entries = cache.report().entries;
+    cache.clear();
+    if storage::is_persistent() {
+        storage::spawn_write(|store| store.clear(ns::SEARCH_RESULT));
+    }
+    Some(entries)
+}
 
 /// 使用规则搜索动漫 (自动获取集数信息)
 pub async fn search_with_rule(rule: &Rule, keyword: &str) -> PlatformSearchResult {
-    match execute_search(rule, keyword).await {
-        Ok(items) => PlatformSearchResult::with_items(items),
+    search_with_options(rule, keyword, &SearchOptions::default()).await
+}
+
+/// 使用规则搜索动漫，`first_only` 时只解析第一个有效结果并只获取其集数
+/// (优先使用结果缓存，见 [`search_cached`])
+pub async fn search_with_options(
+    rule: &Rule,
+    keyword: &str,
+    options: &SearchOptions,
+) -> PlatformSearchResult {
+    search_cached(rule, keyword, options).await.0
+}
+
+/// 优先使用结果缓存搜索，返回 (结果, 是否命中缓存)
+///
+/// 成功的结果写入缓存 (模拟源每次都按参数重新生成)；命中时没有请求源站，耗时与请求次数为 0
+pub(crate) async fn search_cached(
+    rule: &Rule,
+    keyword: &str,
+    options: &SearchOptions,
+) -> (PlatformSearchResult, bool) {
+    let key = ResultKey::new(rule, keyword, options);
+    let cache = result_cache().filter(|_| !rule.is_mock());
+    let mut cached = cache.and_then(|cache| cache.get(&key));
+    let persist = cache.is_some() && storage::is_persistent();
+    if cached.is_none() && persist {
+        cached = storage::load_json(ns::SEARCH_RESULT, key.storage_key()).await;
+        if let (Some(cache), Some(result)) = (cache, &cached) {
+            cache.insert(key.clone(), result.clone());
+        }
+    }
+    if let Some(result) = cached {
+        debug!("规则 {} 命中结果缓存: {}", rule.name, keyword);
+        let result = PlatformSearchResult {
+            elapsed_ms: 0,
+            http_status: None,
+            attempts: 0,
+            timing: None,
+            ..result
+        };
+        return (result, true);
+    }
+
+    let result = search_uncached(rule, keyword, options).await;
+    if let Some(cache) = cache.filter(|_| result.error.is_none()) {
+        if persist {
+            let ttl = Duration::from_secs(CONFIG.cache_search_ttl_secs);
+            storage::save_json(ns::SEARCH_RESULT, key.storage_key(), &result, Some(ttl));
+        }
+        cache.insert(key, result.clone());
+    }
+    (result, false)
+}
+
+/// 请求源站搜索，不读取结果缓存 (自动停用规则的探测等需要实际请求的场景)
+pub async fn search_uncached(
+    rule: &Rule,
+    keyword: &str,
+    options: &SearchOptions,
+) -> PlatformSearchResult {
+    let started = Instant::now();
+    let mut trace = RequestTrace::default();
+    let result = match execute_search(rule, keyword, options, &mut trace).await {
+        Ok((items, next_page_url)) => PlatformSearchResult {
+            next_page_url,
+            ..PlatformSearchResult::with_items(items)
+        },
         Err(e) => {
             warn!("规则 {} 搜索失败: {}", rule.name, e);
-            PlatformSearchResult::with_error(e.to_string())
+            PlatformSearchResult::with_error(error_kind(&e), e.to_string())
+        }
+    };
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    PlatformSearchResult {
+        elapsed_ms,
+        http_status: trace.status,
+        attempts: trace.attempts,
+        timing: options.debug_timing.then_some(Timing {
+            ttfb_ms: trace.ttfb.as_millis() as u64,
+            body_ms: trace.body.as_millis() as u64,
+            total_ms: elapsed_ms,
+        }),
+        ..result
+    }
+}
+
+/// 可归类的引擎失败 (HTTP 层的失败见 [`HttpClientError`])
+#[derive(Debug, thiserror::Error)]
+pub enum EngineError {
+    /// 页面请求成功，但没有找到需要的内容
+    #[error("{0}")]
+    ParseEmpty(&'static str),
+    /// RSS 规则的响应不是有效的 RSS (按 parse_empty 归类)
+    #[error("{0}")]
+    InvalidFeed(String),
+}
+
+/// 搜索失败的类别 (按底层 HTTP 错误或引擎错误区分)
+pub fn error_kind(error: &anyhow::Error) -> ErrorKind {
+    if let Some(EngineError::ParseEmpty(_) | EngineError::InvalidFeed(_)) =
+        error.downcast_ref::<EngineError>()
+    {
+        return ErrorKind::ParseEmpty;
+    }
+    match error.downcast_ref::<HttpClientError>() {
+        Some(HttpClientError::Timeout) => ErrorKind::Timeout,
+        Some(HttpClientError::Forbidden) => ErrorKind::Blocked,
+        Some(HttpClientError::RateLimited(_)) => ErrorKind::RateLimited,
+        Some(HttpClientError::BadStatus(_)) => ErrorKind::BadStatus,
+        _ => ErrorKind::Other,
+    }
+}
+
+/// 请求搜索页并解析结果，`trace` 记录搜索页请求的尝试次数与状态码
+async fn execute_search(
+    rule: &Rule,
+    keyword: &str,
+    options: &SearchOptions,
+    trace: &mut RequestTrace,
+) -> anyhow::Result<(Vec<SearchResultItem>, Option<String>)> {
+    if rule.is_mock() {
+        return Ok((mock_search(rule, keyword, options).await?, None));
+    }
+
+    // 模拟源: 直接返回预置条目 (仍按 first_only / limit 截断)
+    if let Some(mock) = &rule.mock_results {
+        let limit = if options.first_only { 1 } else { mock.len() };
+        let limit = options.limit.map_or(limit, |l| l.min(limit));
+        return Ok((mock.iter().take(limit).cloned().collect(), None));
+    }
+
+    let (search_url, html) = fetch_search_page(rule, keyword, options, 1, trace).await?;
+    remember_search_page(&rule.name, &html);
+
+    // 解析 HTML 并提取结果 (站点忽略 @limit 时在本地截断，不请求多余的详情页)
+    let mut items = parse_search_results_with(rule, &html, options)?;
+    let exhausted = walk_pages(rule, keyword, options, &mut items, trace).await;
+    if let Some(limit) = options.limit {
+        items.truncate(limit);
+    }
+    // RSS 规则没有下一页与集数
+    if rule.is_rss() {
+        debug!("规则 {} 找到 {} 个 RSS 条目", rule.name, items.len());
+        return Ok((items, None));
+    }
+    // 翻页到头 (没有新结果) 时不再返回下一页
+    let next_page_url = if exhausted {
+        None
+    } else {
+        parse_next_page(rule, &html, &search_url)?
+    };
+    
+    debug!("规则 {} 找到 {} 个结果", rule.name, items.len());
+
+    // 如果规则有章节选择器 (或启用了兜底解析)，获取每个结果的章节信息
+    if !options.skip_episodes && (has_chapter_selectors(rule) || rule.episode_fallback) {
+        for item in items.iter_mut() {
+            match fetch_episodes(rule, &item.url).await {
+                Ok(episodes) => {
+                    if !episodes.is_empty() {
+                        item.episodes = Some(episodes);
+                    }
+                }
+                Err(e) => {
+                    debug!("获取章节失败 {}: {}", item.url, e);
+                }
+            }
         }
     }
+
+    Ok((items, next_page_url))
 }
 
-async fn execute_search(rule: &Rule, keyword: &str) -> anyhow::Result<Vec<SearchResultItem>> {
+/// `"type": "mock"` 规则的搜索: 按参数等待与随机失败，生成 `关键词 序号` 结果 (按 first_only / limit / offset 截取)
+async fn mock_search(
+    rule: &Rule,
+    keyword: &str,
+    options: &SearchOptions,
+) -> anyhow::Result<Vec<SearchResultItem>> {
+    let mock = rule.mock.clone().unwrap_or_default();
+    let jitter = (mock_random() * (mock.jitter_ms + 1) as f64) as u64;
+    tokio::time::sleep(Duration::from_millis(mock.latency_ms + jitter)).await;
+    if mock_random() < mock.failure_rate {
+        return Err(HttpClientError::BadStatus(503).into());
+    }
+
+    let count = if options.first_only { mock.items.min(1) } else { mock.items };
+    let count = options.limit.map_or(count, |l| l.min(count));
+    let offset = options.offset.unwrap_or(0);
+    Ok((offset + 1..=offset + count)
+        .map(|n| {
+            let url = format!("mock://{}/{}", rule.name, n);
+            let episodes = (!options.skip_episodes && mock.episodes > 0)
+                .then(|| vec![mock_road(&url, &mock)]);
+            SearchResultItem {
+                name: format!("{} {}", keyword, n),
+                alt_name: None,
+                url,
+                tags: None,
+                latest: None,
+                cover: None,
+                episodes,
+                raw_html: None,
+                source_id: None,
+                rule: String::new(),
+                rule_color: None,
+            }
+        })
+        .collect())
+}
+
+/// 模拟源详情页 `detail_url` 的唯一播放源
+fn mock_road(detail_url: &str, mock: &MockSource) -> EpisodeRoad {
+    let episodes = (1..=mock.episodes)
+        .map(|n| Episode {
+            name: format!("第{}集", n),
+            url: format!("{}/{}", detail_url, n),
+            display_name: None,
+        })
+        .collect();
+    EpisodeRoad::new(detail_url, 0, Some("线路1".to_string()), episodes)
+}
+
+/// searchURL 含 `@page` 且 `maxPages` > 1 时继续请求第 2 页起的结果并追加到 `items`。
+/// 某页没有新链接 (如站点对超出范围的页码重复返回第一页) 或没有结果时停止并返回 true；
+/// 后续页请求失败时保留已有结果
+async fn walk_pages(
+    rule: &Rule,
+    keyword: &str,
+    options: &SearchOptions,
+    items: &mut Vec<SearchResultItem>,
+    trace: &mut RequestTrace,
+) -> bool {
+    if rule.max_pages <= 1 || options.first_only || !rule.search_url.contains("@page") {
+        return false;
+    }
+    let mut seen: HashSet<String> = items.iter().map(|item| dedup_key(&item.url)).collect();
+    for page in 2..=rule.max_pages {
+        if options.limit.is_some_and(|limit| items.len() >= limit) {
+            return false;
+        }
+        let page_items = fetch_search_page(rule, keyword, options, page, trace)
+            .await
+            .and_then(|(_, html)| parse_search_results_with(rule, &html, options));
+        let page_items = match page_items {
+            Ok(page_items) => page_items,
+            Err(e) => {
+                debug!("规则 {} 第 {} 页请求失败，停止翻页: {}", rule.name, page, e);
+                return false;
+            }
+        };
+        let before = items.len();
+        items.extend(page_items.into_iter().filter(|item| seen.insert(dedup_key(&item.url))));
+        if items.len() == before {
+            debug!("规则 {} 第 {} 页没有新结果，停止翻页", rule.name, page);
+            return true;
+        }
+    }
+    false
+}
+
+/// 模拟源使用的 [0, 1) 伪随机数 (每次调用使用新的随机哈希密钥，不需要密码学强度)
+fn mock_random() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    let bits = std::collections::hash_map::RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// 请求未指定 limit 时替换 `@limit` 的值
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// 搜索 URL (替换 `@keyword`，以及支持分页参数的站点的 `@limit` / `@offset` / `@page`)
+fn build_search_url(rule: &Rule, keyword: &str, options: &SearchOptions, page: usize) -> String {
+    rule.search_url
+        .replace("@keyword", &urlencoding::encode(keyword))
+        .replace("@page", &page.to_string())
+        .replace(
+            "@limit",
+            &options.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).to_string(),
+        )
+        .replace("@offset", &options.offset.unwrap_or(0).to_string())
+}
+
+/// 请求搜索页 (按规则处理 token 与 POST)，返回 (搜索 URL, 页面 HTML)
+async fn fetch_search_page(
+    rule: &Rule,
+    keyword: &str,
+    options: &SearchOptions,
+    page: usize,
+    trace: &mut RequestTrace,
+) -> anyhow::Result<(String, String)> {
     // 构建搜索 URL
-    let search_url = rule.search_url.replace("@keyword", &urlencoding::encode(keyword));
+    let search_url = build_search_url(rule, keyword, options, page);
     debug!("搜索 URL: {}", search_url);
 
+    // 需要搜索 token 时先请求首页提取 (同时带上首页设置的 Cookie)
+    let (token, cookie) = if rule.token_xpath.is_empty() {
+        (None, None)
+    } else {
+        let (page, cookie) = get_page(&rule.base_url, Some(&rule.base_url), rule.request_timeout()).await?;
+        let token = parse_search_token(rule, &page)?;
+        debug!("规则 {} 搜索 token: {}", rule.name, token);
+        (Some(token), cookie)
+    };
+    let token_field = if rule.token_field.is_empty() {
+        "token"
+    } else {
+        rule.token_field.as_str()
+    };
+
     // 发送请求
     let html = if rule.use_post {
         // POST 请求
-        let uri = url::Url::parse(&search_url)?;
-        let query_params: std::collections::HashMap<String, String> = uri
+        // 保持查询参数的原始顺序 (部分站点校验表单字段顺序)
+        let mut uri = url::Url::parse(&search_url)?;
+        let mut form: Vec<(String, String)> = uri
             .query_pairs()
             .map(|(k, v)| (k.to_string(), v.to_string()))
             .collect();
-        let base_url = format!("{}://{}{}", uri.scheme(), uri.host_str().unwrap_or(""), uri.path());
-        post_form_text(&base_url, &query_params, Some(&rule.base_url)).await?
+        if let Some(token) = token {
+            match form.iter_mut().find(|(k, _)| k == token_field) {
+                Some((_, value)) => *value = token,
+                None => form.push((token_field.to_string(), token)),
+            }
+        }
+        uri.set_query(None);
+        post_form_text_with_retry(
+            uri.as_str(),
+            &form,
+            Some(&rule.base_url),
+            cookie.as_deref(),
+            rule.request_timeout(),
+            trace,
+        )
+        .await?
     } else {
         // GET 请求
-        get_text(&search_url, Some(&rule.base_url)).await?
+        let search_url = match token {
+            Some(token) => {
+                let mut uri = url::Url::parse(&search_url)?;
+                uri.query_pairs_mut().append_pair(token_field, &token);
+                uri.to_string()
+            }
+            None => search_url.clone(),
+        };
+        get_text_with_retry(
+            &search_url,
+            Some(&rule.base_url),
+            cookie.as_deref(),
+            rule.request_timeout(),
+            trace,
+        )
+        .await?
     };
 
-    // 解析 HTML 并提取结果
-    let mut items = parse_search_results(rule, &html)?;
-    
-    debug!("规则 {} 找到 {} 个结果", rule.name, items.len());
+    Ok((search_url, html))
+}
 
-    // 如果规则有章节选择器，获取每个结果的章节信息
-    if !rule.chapter_roads.is_empty() && !rule.chapter_result.is_empty() {
-        for item in items.iter_mut() {
-            match fetch_episodes(rule, &item.url).await {
-                Ok(episodes) => {
-                    if !episodes.is_empty() {
-                        item.episodes = Some(episodes);
+/// 获取动漫详情页的章节列表 (规则没有章节选择器时为空)
+///
+/// 配置 `chapterRoadLinks` 时各播放源页面单独请求，任一播放源失败时跳过，全部失败时返回第一个错误
+pub async fn fetch_episodes(rule: &Rule, detail_url: &str) -> anyhow::Result<Vec<EpisodeRoad>> {
+    let mut roads = Vec::new();
+    let mut first_error = None;
+    let resolved = resolve_roads(rule, detail_url);
+    futures::pin_mut!(resolved);
+    while let Some(result) = resolved.next().await {
+        match result {
+            Ok(road) => roads.push(road),
+            Err(e) => {
+                debug!("规则 {} 播放源获取失败 {}: {}", rule.name, detail_url, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    if let (true, Some(e)) = (roads.is_empty(), first_error) {
+        return Err(e);
+    }
+    if !rule.chapter_road_links.is_empty() {
+        // 单独请求的播放源按完成顺序返回，恢复为页面顺序
+        roads.sort_by_key(|road| road.index);
+        apply_road_preference(rule, &mut roads)?;
+    }
+    Ok(roads)
+}
+
+/// 逐个解析详情页的播放源，先完成的先返回
+///
+/// 播放源都在详情页中时随页面一起解析完成；配置 `chapterRoadLinks` 时各播放源页面并发请求，
+/// 与详情页请求一样按 EPISODE_HOST_CONCURRENCY 限制同一站点的并发数
+fn resolve_roads<'a>(
+    rule: &'a Rule,
+    detail_url: &'a str,
+) -> impl Stream<Item = anyhow::Result<EpisodeRoad>> + 'a {
+    async_stream::stream! {
+        if rule.is_mock() {
+            let mock = rule.mock.clone().unwrap_or_default();
+            if mock.episodes > 0 {
+                yield Ok(mock_road(detail_url, &mock));
+            }
+            return;
+        }
+        if !has_chapter_selectors(rule) && !rule.episode_fallback {
+            return;
+        }
+
+        // 获取详情页 HTML (同一站点的详情页请求按 EPISODE_HOST_CONCURRENCY 排队)
+        let html = match fetch_episode_page(rule, detail_url).await {
+            Ok(html) => html,
+            Err(e) => {
+                yield Err(e);
+                return;
+            }
+        };
+
+        if rule.chapter_road_links.is_empty() {
+            match parse_episodes_with_fallback(rule, &html, detail_url) {
+                Ok(roads) => {
+                    for road in roads {
+                        yield Ok(road);
                     }
                 }
-                Err(e) => {
-                    debug!("获取章节失败 {}: {}", item.url, e);
+                Err(e) => yield Err(e),
+            }
+            return;
+        }
+
+        let links = match parse_road_links(rule, &html, detail_url) {
+            Ok(links) => links,
+            Err(e) => {
+                yield Err(e);
+                return;
+            }
+        };
+        let mut pending: stream::FuturesUnordered<_> = links
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, url))| fetch_linked_road(rule, detail_url, index, name, url))
+            .collect();
+        while let Some(result) = pending.next().await {
+            match result {
+                // 没有集数的播放源丢弃 (不影响其余播放源的序号)
+                Ok(Some(road)) => yield Ok(road),
+                Ok(None) => {}
+                Err(e) => yield Err(e),
+            }
+        }
+    }
+}
+
+/// 请求详情页或播放源页面 (同一站点按 EPISODE_HOST_CONCURRENCY 排队)
+async fn fetch_episode_page(rule: &Rule, url: &str) -> anyhow::Result<String> {
+    let _permit = crate::limiter::acquire_episode_host(url).await;
+    Ok(get_text(url, Some(&rule.base_url), rule.request_timeout()).await?)
+}
+
+/// 请求单独的播放源页面，取页面中第一个有集数的播放源作为详情页中第 `index` 个播放源
+async fn fetch_linked_road(
+    rule: &Rule,
+    detail_url: &str,
+    index: usize,
+    name: Option<String>,
+    url: String,
+) -> anyhow::Result<Option<EpisodeRoad>> {
+    let html = fetch_episode_page(rule, &url).await?;
+    let episodes = parse_episodes_with_fallback(rule, &html, &url)?
+        .into_iter()
+        .map(|road| road.episodes)
+        .find(|episodes| !episodes.is_empty());
+    Ok(episodes.map(|episodes| EpisodeRoad::new(detail_url, index, name, episodes)))
+}
+
+/// 提取详情页中各播放源页面的链接与名称 (默认取 `href`，以 `/@属性名` 结尾时取该属性)，
+/// 链接按详情页地址补全，只保留 http(s) 地址
+fn parse_road_links(
+    rule: &Rule,
+    html: &str,
+    detail_url: &str,
+) -> anyhow::Result<Vec<(Option<String>, String)>> {
+    let (xpath, attr) = match rule.chapter_road_links.rsplit_once("/@") {
+        Some((xpath, attr)) if !attr.contains(['/', '[']) => (xpath, attr),
+        _ => (rule.chapter_road_links.as_str(), "href"),
+    };
+    let css = xpath_to_css(xpath).map_err(|e| anyhow::anyhow!("播放源链接 XPath 转换失败: {}", e))?;
+    let selector = Selector::parse(&css.selector)
+        .map_err(|e| anyhow::anyhow!("无效的播放源链接 CSS 选择器: {:?}", e))?;
+    let base = url::Url::parse(detail_url)?;
+
+    let document = Html::parse_document(html);
+    let links: Vec<(Option<String>, String)> = document
+        .select(&selector)
+        .enumerate()
+        .filter(|(i, _)| apply_position_filter(*i, &css.position_filter))
+        .filter_map(|(_, element)| {
+            let href = element.value().attr(attr)?.trim();
+            let url = base.join(href).ok().filter(|u| matches!(u.scheme(), "http" | "https"))?;
+            let name = normalize_whitespace(&get_element_text(&element));
+            Some(((!name.is_empty()).then_some(name), url.to_string()))
+        })
+        .collect();
+    debug!("找到 {} 个播放源链接", links.len());
+    Ok(links)
+}
+
+/// 逐个播放源返回的集数事件 (每行一个 JSON)
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum RoadStreamEvent {
+    /// `{"road": {...}}` 一个播放源
+    Road { road: EpisodeRoad },
+    /// `{"error": "..."}` 获取失败 (随后为完成信号)
+    Error { error: String },
+    /// `{"done": true, "roads": n}` 完成信号，附带已返回的播放源数
+    Done { done: bool, roads: usize },
+}
+
+/// 获取详情页的章节列表，每个播放源解析完成即返回事件，最后为完成信号 (事件见 [`RoadStreamEvent`])
+///
+/// 同一详情页中的播放源随页面一起解析；配置 `chapterRoadLinks` 时各播放源页面并发请求，先完成的先返回，
+/// 单个播放源失败时返回错误事件并继续。请求与 [`fetch_episodes`] 相同，受 EPISODE_HOST_CONCURRENCY 限制。
+/// `road_id` 只返回该播放源，完成信号中的 `roads` 为实际返回的播放源数
+pub fn fetch_episodes_stream(
+    rule: Arc<Rule>,
+    detail_url: String,
+    road_id: Option<String>,
+) -> impl Stream<Item = RoadStreamEvent> {
+    async_stream::stream! {
+        let mut emitted = 0;
+        let resolved = resolve_roads(&rule, &detail_url);
+        futures::pin_mut!(resolved);
+        while let Some(result) = resolved.next().await {
+            match result {
+                Ok(road) if road_id.as_ref().is_none_or(|id| road.id == *id) => {
+                    emitted += 1;
+                    yield RoadStreamEvent::Road { road };
                 }
+                Ok(_) => {}
+                Err(e) => yield RoadStreamEvent::Error { error: e.to_string() },
             }
         }
+        yield RoadStreamEvent::Done { done: true, roads: emitted };
     }
+}
 
-    Ok(items)
+/// 从页面提取搜索 token (`tokenXpath` 以 `/@属性名` 结尾时取该属性)
+pub fn parse_search_token(rule: &Rule, html: &str) -> anyhow::Result<String> {
+    let (xpath, attr) = match rule.token_xpath.rsplit_once("/@") {
+        Some((xpath, attr)) if !attr.contains(['/', '[']) => (xpath, Some(attr)),
+        _ => (rule.token_xpath.as_str(), None),
+    };
+
+    let css = xpath_to_css(xpath).map_err(|e| anyhow::anyhow!("token XPath 转换失败: {}", e))?;
+    let selector = Selector::parse(&css.selector)
+        .map_err(|e| anyhow::anyhow!("无效的 token CSS 选择器: {:?}", e))?;
+
+    let document = Html::parse_document(html);
+    let element = document
+        .select(&selector)
+        .next()
+        .ok_or(EngineError::ParseEmpty("页面中未找到搜索 token"))?;
+
+    let token = match attr {
+        Some(attr) => element.value().attr(attr).map(|v| v.to_string()),
+        None => element
+            .value()
+            .attr("value")
+            .or_else(|| element.value().attr("content"))
+            .map(|v| v.to_string())
+            .or_else(|| Some(get_element_text(&element))),
+    }
+    .map(|v| v.trim().to_string())
+    .filter(|v| !v.is_empty());
+
+    token.ok_or_else(|| EngineError::ParseEmpty("搜索 token 为空").into())
+}
+
+/// 提取搜索结果页的下一页链接 (未配置 `searchNextPage` 或页面中没有时为 None)
+///
+/// 链接按搜索页地址补全 (支持 `?page=2` 这类相对地址)，指回当前页时视为没有下一页
+pub fn parse_next_page(rule: &Rule, html: &str, page_url: &str) -> anyhow::Result<Option<String>> {
+    if rule.search_next_page.is_empty() {
+        return Ok(None);
+    }
+    let (xpath, attr) = match rule.search_next_page.rsplit_once("/@") {
+        Some((xpath, attr)) if !attr.contains(['/', '[']) => (xpath, attr),
+        _ => (rule.search_next_page.as_str(), "href"),
+    };
+    let css =
+        xpath_to_css(xpath).map_err(|e| anyhow::anyhow!("下一页 XPath 转换失败: {}", e))?;
+    let selector = Selector::parse(&css.selector)
+        .map_err(|e| anyhow::anyhow!("无效的下一页 CSS 选择器: {:?}", e))?;
+
+    let document = Html::parse_document(html);
+    let href = document
+        .select(&selector)
+        .filter_map(|e| e.value().attr(attr))
+        .map(str::trim)
+        .find(|href| !href.is_empty() && *href != "#" && !href.starts_with("javascript:"));
+    let Some(href) = href else {
+        return Ok(None);
+    };
+
+    let url = match url::Url::parse(page_url).and_then(|base| base.join(href)) {
+        Ok(url) => url.to_string(),
+        Err(_) => normalize_url(href, &rule.base_url),
+    };
+    Ok((url != page_url).then_some(url))
+}
+
+/// 规则是否配置了章节选择器
+fn has_chapter_selectors(rule: &Rule) -> bool {
+    !rule.chapter_roads.is_empty() && !rule.chapter_result.is_empty()
+}
+
+/// 解析章节列表，选择器无结果时按规则配置兜底扫描链接
+fn parse_episodes_with_fallback(
+    rule: &Rule,
+    html: &str,
+    detail_url: &str,
+) -> anyhow::Result<Vec<EpisodeRoad>> {
+    let mut roads = parse_episode_roads(rule, html, detail_url)?;
+    apply_episode_names(rule, &mut roads)?;
+    apply_road_preference(rule, &mut roads)?;
+    Ok(roads)
+}
+
+/// 按规则的优先播放源正则调整顺序: 名称匹配的播放源在前，同组内保持页面顺序
+/// (序号与 id 仍按页面位置，没有名称的播放源视为不匹配)
+fn apply_road_preference(rule: &Rule, roads: &mut [EpisodeRoad]) -> anyhow::Result<()> {
+    if rule.preferred_road_pattern.is_empty() {
+        return Ok(());
+    }
+    let pattern = Regex::new(&rule.preferred_road_pattern)
+        .map_err(|e| anyhow::anyhow!("无效的优先播放源正则: {}", e))?;
+    roads.sort_by_key(|road| !road.name.as_deref().is_some_and(|name| pattern.is_match(name)));
+    Ok(())
+}
+
+/// 按规则的集数模板生成显示名 (未配置模板与正则时不处理)
+fn apply_episode_names(rule: &Rule, roads: &mut [EpisodeRoad]) -> anyhow::Result<()> {
+    if rule.episode_name_template.is_empty() && rule.episode_number_regex.is_empty() {
+        return Ok(());
+    }
+    let template = if rule.episode_name_template.is_empty() {
+        "第{n}集"
+    } else {
+        rule.episode_name_template.as_str()
+    };
+    let number_regex = if rule.episode_number_regex.is_empty() {
+        RE_EPISODE_NUMBER.clone()
+    } else {
+        Regex::new(&rule.episode_number_regex)
+            .map_err(|e| anyhow::anyhow!("无效的集数正则: {}", e))?
+    };
+
+    for episode in roads.iter_mut().flat_map(|road| road.episodes.iter_mut()) {
+        episode.display_name = extract_episode_number(&number_regex, &episode.name)
+            .map(|n| template.replace("{n}", &n).replace("{name}", &episode.name));
+    }
+    Ok(())
+}
+
+/// 提取集数 (优先第一个捕获组)，去掉前导零: "HD第01集" -> "1"，"12.5" -> "12.5"
+fn extract_episode_number(regex: &Regex, name: &str) -> Option<String> {
+    let captures = regex.captures(name)?;
+    let number = captures.get(1).or_else(|| captures.get(0))?.as_str().trim();
+    if number.is_empty() {
+        return None;
+    }
+    let trimmed = number.trim_start_matches('0');
+    Some(if trimmed.is_empty() || trimmed.starts_with('.') {
+        format!("0{}", trimmed)
+    } else {
+        trimmed.to_string()
+    })
+}
+
+/// 解析章节列表 (选择器 + 兜底)
+fn parse_episode_roads(
+    rule: &Rule,
+    html: &str,
+    detail_url: &str,
+) -> anyhow::Result<Vec<EpisodeRoad>> {
+    let parsed = if has_chapter_selectors(rule) {
+        parse_episodes(rule, html, detail_url)
+    } else {
+        Ok(vec![])
+    };
+
+    if !rule.episode_fallback {
+        return parsed;
+    }
+
+    match parsed {
+        Ok(roads) if !roads.is_empty() => Ok(roads),
+        other => {
+            if let Err(e) = other {
+                debug!("章节选择器解析失败 {}: {}", detail_url, e);
+            }
+            let roads = parse_episodes_fallback(rule, html, detail_url)?;
+            info!(
+                "规则 {} 章节选择器无结果，兜底解析出 {} 集: {}",
+                rule.name,
+                roads.iter().map(|r| r.episodes.len()).sum::<usize>(),
+                detail_url
+            );
+            Ok(roads)
+        }
+    }
 }
 
-/// 获取动漫详情页的章节列表
-async fn fetch_episodes(rule: &Rule, detail_url: &str) -> anyhow::Result<Vec<EpisodeRoad>> {
-    if rule.chapter_roads.is_empty() || rule.chapter_result.is_empty() {
+/// 兜底解析: 扫描页面中 href 匹配规则正则、文字像集数的链接，合并为单个播放源
+fn parse_episodes_fallback(
+    rule: &Rule,
+    html: &str,
+    detail_url: &str,
+) -> anyhow::Result<Vec<EpisodeRoad>> {
+    let href_pattern = if rule.episode_href_pattern.is_empty() {
+        None
+    } else {
+        Some(
+            Regex::new(&rule.episode_href_pattern)
+                .map_err(|e| anyhow::anyhow!("无效的章节链接正则: {}", e))?,
+        )
+    };
+
+    let document = Html::parse_document(html);
+    let a_selector = Selector::parse("a[href]").expect("valid selector");
+    let url_base = extract_base_url(detail_url, &rule.base_url);
+
+    let mut episodes: Vec<Episode> = Vec::new();
+    for a in document.select(&a_selector) {
+        let href = a.value().attr("href").unwrap_or_default();
+        let name = normalize_whitespace(&get_element_text(&a));
+
+        if href.is_empty() || !RE_EPISODE_NAME.is_match(&name) {
+            continue;
+        }
+        if let Some(pattern) = &href_pattern {
+            if !pattern.is_match(href) {
+                continue;
+            }
+        }
+
+        let url = normalize_url(href, &url_base);
+        if episodes.iter().any(|e| e.url == url) {
+            continue;
+        }
+        episodes.push(Episode {
+            name,
+            url,
+            display_name: None,
+        });
+    }
+
+    if episodes.is_empty() {
         return Ok(vec![]);
     }
 
-    // 获取详情页 HTML
-    let html = get_text(detail_url, Some(&rule.base_url)).await?;
-    
-    // 解析章节
-    parse_episodes(rule, &html, detail_url)
+    Ok(vec![EpisodeRoad::new(detail_url, 0, None, episodes)])
 }
 
 /// 解析章节列表
@@ -120,18 +936,17 @@ fn parse_episodes(rule: &Rule, html: &str, base_url: &str) -> anyhow::Result<Vec
             }
 
             let url = normalize_url(&href, &url_base);
-            episodes.push(Episode { name, url });
+            episodes.push(Episode {
+                name,
+                url,
+                display_name: None,
+            });
         }
 
+        // 序号按页面中的位置计算，丢弃空播放源不影响其余播放源的 id
         if !episodes.is_empty() {
-            roads.push(EpisodeRoad {
-                name: if road_elements.len() > 1 {
-                    Some(format!("线路{}", index + 1))
-                } else {
-                    None
-                },
-                episodes,
-            });
+            let name = (road_elements.len() > 1).then(|| format!("线路{}", index + 1));
+            roads.push(EpisodeRoad::new(base_url, index, name, episodes));
         }
     }
 
@@ -139,7 +954,24 @@ fn parse_episodes(rule: &Rule, html: &str, base_url: &str) -> anyhow::Result<Vec
 }
 
 /// 解析搜索结果 (兼容 Kazumi 规则)
-fn parse_search_results(rule: &Rule, html: &str) -> anyhow::Result<Vec<SearchResultItem>> {
+pub fn parse_search_results(rule: &Rule, html: &str) -> anyhow::Result<Vec<SearchResultItem>> {
+    parse_search_results_with(rule, html, &SearchOptions::default())
+}
+
+/// 按搜索选项解析搜索结果 (`first_only` 时得到第一个有效结果后停止解析，
+/// `include_raw` 时附带列表节点的 HTML)
+pub fn parse_search_results_with(
+    rule: &Rule,
+    html: &str,
+    options: &SearchOptions,
+) -> anyhow::Result<Vec<SearchResultItem>> {
+    if rule.is_rss() {
+        let mut items = crate::rss::parse_items(rule, html, options)?;
+        apply_source_ids(rule, &mut items)?;
+        return Ok(if rule.dedup_items { dedup_items(items) } else { items });
+    }
+
+    let limit = options.first_only.then_some(1);
     let mut items = Vec::new();
     let document = Html::parse_document(html);
 
@@ -165,6 +997,41 @@ fn parse_search_results(rule: &Rule, html: &str) -> anyhow::Result<Vec<SearchRes
         .map_err(|e| anyhow::anyhow!("无效的名称 CSS 选择器: {:?}", e))?;
     let result_selector = Selector::parse(&result_css.selector)
         .map_err(|e| anyhow::anyhow!("无效的结果 CSS 选择器: {:?}", e))?;
+    let update_selector = if rule.search_update.is_empty() {
+        None
+    } else {
+        let update_css = xpath_to_css(&rule.search_update)
+            .map_err(|e| anyhow::anyhow!("更新信息 XPath 转换失败: {}", e))?;
+        debug!("更新信息 CSS: {}", update_css.selector);
+        Some(
+            Selector::parse(&update_css.selector)
+                .map_err(|e| anyhow::anyhow!("无效的更新信息 CSS 选择器: {:?}", e))?,
+        )
+    };
+
+    let cover_selector = if rule.search_cover.is_empty() {
+        None
+    } else {
+        let (xpath, attr) = split_attr(&rule.search_cover);
+        let cover_css = xpath_to_css(xpath)
+            .map_err(|e| anyhow::anyhow!("封面 XPath 转换失败: {}", e))?;
+        debug!("封面 CSS: {}", cover_css.selector);
+        let selector = Selector::parse(&cover_css.selector)
+            .map_err(|e| anyhow::anyhow!("无效的封面 CSS 选择器: {:?}", e))?;
+        Some((selector, attr))
+    };
+
+    let alt_name_selector = if rule.search_alt_name.is_empty() {
+        None
+    } else {
+        let alt_name_css = xpath_to_css(&rule.search_alt_name)
+            .map_err(|e| anyhow::anyhow!("别名 XPath 转换失败: {}", e))?;
+        debug!("别名 CSS: {}", alt_name_css.selector);
+        Some(
+            Selector::parse(&alt_name_css.selector)
+                .map_err(|e| anyhow::anyhow!("无效的别名 CSS 选择器: {:?}", e))?,
+        )
+    };
 
     // 查询列表元素
     let list_elements: Vec<ElementRef> = document.select(&list_selector)
@@ -176,6 +1043,10 @@ fn parse_search_results(rule: &Rule, html: &str) -> anyhow::Result<Vec<SearchRes
     debug!("找到 {} 个列表节点", list_elements.len());
 
     for element in list_elements {
+        if limit.is_some_and(|limit| items.len() >= limit) {
+            break;
+        }
+
         // 在列表项内查找名称
         let name = element.select(&name_selector)
             .next()
@@ -207,17 +1078,212 @@ fn parse_search_results(rule: &Rule, html: &str) -> anyhow::Result<Vec<SearchRes
         // 构建完整 URL
         let url = normalize_url(&href, &rule.base_url);
 
+        // 更新信息 (合并空白，为空时不返回)
+        let latest = update_selector.as_ref().and_then(|selector| {
+            element
+                .select(selector)
+                .next()
+                .map(|e| normalize_whitespace(&get_element_text(&e)))
+                .filter(|s| !s.is_empty())
+        });
+
+        // 别名 (合并空白，为空或与名称相同时不返回)
+        let alt_name = alt_name_selector.as_ref().and_then(|selector| {
+            element
+                .select(selector)
+                .next()
+                .map(|e| normalize_whitespace(&get_element_text(&e)))
+                .filter(|s| !s.is_empty() && *s != normalize_whitespace(&name))
+        });
+
+        let cover = cover_selector.as_ref().and_then(|(selector, attr)| {
+            element
+                .select(selector)
+                .find_map(|e| pick_cover(&e, *attr))
+                .map(|src| normalize_url(&src, &rule.base_url))
+        });
+
+        let raw_html = options
+            .include_raw
+            .then(|| truncate_html(element.html(), MAX_RAW_HTML_LEN));
+
         items.push(SearchResultItem {
             name,
+            alt_name,
             url,
             tags: None,
+            latest,
+            cover,
             episodes: None,
+            raw_html,
+            source_id: None,
+            rule: rule.name.clone(),
+            rule_color: (!rule.color.is_empty()).then(|| rule.color.clone()),
         });
     }
 
+    apply_source_ids(rule, &mut items)?;
+    if rule.dedup_items {
+        items = dedup_items(items);
+    }
+
     Ok(items)
 }
 
+/// 按规则的 ID 正则从结果链接提取站点侧 ID (有捕获组时取第一个捕获组，不匹配的结果不设置)
+fn apply_source_ids(rule: &Rule, items: &mut [SearchResultItem]) -> anyhow::Result<()> {
+    if rule.id_regex.is_empty() {
+        return Ok(());
+    }
+    let pattern = Regex::new(&rule.id_regex).map_err(|e| anyhow::anyhow!("无效的 ID 正则: {}", e))?;
+    for item in items {
+        item.source_id = pattern
+            .captures(&item.url)
+            .and_then(|caps| caps.get(1).or_else(|| caps.get(0)))
+            .map(|m| m.as_str().to_string())
+            .filter(|id| !id.is_empty());
+    }
+    Ok(())
+}
+
+/// 合并链接相同的结果 (按站点侧 ID，没有 ID 时按规范化 URL；保留第一个的位置与名称，合并标签并补全更新信息)
+fn dedup_items(items: Vec<SearchResultItem>) -> Vec<SearchResultItem> {
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut deduped: Vec<SearchResultItem> = Vec::with_capacity(items.len());
+    for item in items {
+        let key = match &item.source_id {
+            Some(id) => format!("id:{}", id),
+            None => dedup_key(&item.url),
+        };
+        let Some(&i) = index.get(&key) else {
+            index.insert(key, deduped.len());
+            deduped.push(item);
+            continue;
+        };
+        let kept = &mut deduped[i];
+        if let Some(tags) = item.tags {
+            let merged = kept.tags.get_or_insert_with(Vec::new);
+            for tag in tags {
+                if !merged.contains(&tag) {
+                    merged.push(tag);
+                }
+            }
+        }
+        if kept.latest.is_none() {
+            kept.latest = item.latest;
+        }
+        if kept.cover.is_none() {
+            kept.cover = item.cover;
+        }
+        if kept.alt_name.is_none() {
+            kept.alt_name = item.alt_name;
+        }
+    }
+    deduped
+}
+
+/// 拆分 XPath 末尾的 `/@属性名`
+fn split_attr(xpath: &str) -> (&str, Option<&str>) {
+    match xpath.rsplit_once("/@") {
+        Some((xpath, attr)) if !attr.contains(['/', '[']) => (xpath, Some(attr)),
+        _ => (xpath, None),
+    }
+}
+
+/// 封面候选属性 (懒加载属性优先，src 多为占位图)
+const COVER_ATTRS: &[&str] = &["data-src", "data-original", "srcset", "src"];
+
+/// 从匹配的节点 (或其中第一个 img) 取封面地址: 依次尝试指定属性与 [`COVER_ATTRS`]，
+/// srcset 取分辨率最高的一项，返回第一个像真实图片的地址
+fn pick_cover(element: &ElementRef, attr: Option<&str>) -> Option<String> {
+    let img_selector = Selector::parse("img").ok()?;
+    let candidates = std::iter::once(*element).chain(element.select(&img_selector));
+    for node in candidates {
+        for name in attr.into_iter().chain(COVER_ATTRS.iter().copied()) {
+            let Some(value) = node.value().attr(name).map(str::trim) else {
+                continue;
+            };
+            let url = if name.ends_with("srcset") {
+                best_srcset_entry(value)
+            } else {
+                Some(value)
+            };
+            if let Some(url) = url.filter(|url| looks_like_image(url)) {
+                return Some(url.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// srcset 中分辨率最高的地址 (按 `w`/`x` 描述符，省略时为 1x)
+fn best_srcset_entry(srcset: &str) -> Option<&str> {
+    srcset
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split_whitespace();
+            let url = parts.next()?;
+            let size = parts
+                .next()
+                .and_then(|d| d.trim_end_matches(['w', 'x']).parse::<f64>().ok())
+                .unwrap_or(1.0);
+            Some((url, size))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(url, _)| url)
+}
+
+/// 是否像真实图片地址: 不是 data URI 或占位图，且有图片扩展名或来自图片 CDN
+fn looks_like_image(url: &str) -> bool {
+    const EXTENSIONS: &[&str] = &[".jpg", ".jpeg", ".png", ".webp", ".gif", ".avif", ".bmp"];
+    const PLACEHOLDERS: &[&str] = &["loading", "placeholder", "lazy", "blank", "default"];
+    let lower = url.to_ascii_lowercase();
+    if lower.is_empty() || lower.starts_with("data:") || lower.starts_with("javascript:") {
+        return false;
+    }
+    let path = lower.split(['?', '#']).next().unwrap_or_default();
+    let file = path.rsplit('/').next().unwrap_or_default();
+    if PLACEHOLDERS.iter().any(|p| file.contains(p)) {
+        return false;
+    }
+    if EXTENSIONS.iter().any(|ext| path.ends_with(ext)) {
+        return true;
+    }
+    // 协议相对地址 (//cdn.example.com/...) 按 https 解析
+    let absolute = if url.starts_with("//") { format!("https:{}", url) } else { url.to_string() };
+    let host = url::Url::parse(&absolute)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_ascii_lowercase))
+        .unwrap_or_default();
+    host.contains("cdn") || host.starts_with("img") || host.starts_with("image") || host.starts_with("pic")
+}
+
+/// 去重用的 URL: 忽略片段、主机名大小写与路径末尾的 `/`
+pub(crate) fn dedup_key(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            let path = parsed.path().trim_end_matches('/').to_string();
+            parsed.set_path(&path);
+            parsed.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+/// 截断 HTML 到不超过 `max_len` 字节 (按字符边界，截断时追加省略号)
+fn truncate_html(mut html: String, max_len: usize) -> String {
+    if html.len() > max_len {
+        let mut end = max_len;
+        while !html.is_char_boundary(end) {
+            end -= 1;
+        }
+        html.truncate(end);
+        html.push('…');
+    }
+    html
+}
+
 /// 应用位置过滤器
 fn apply_position_filter(index: usize, filter: &Option<PositionFilter>) -> bool {
     match filter {
@@ -231,8 +1297,13 @@ fn get_element_text(element: &ElementRef) -> String {
     element.text().collect::<Vec<_>>().join(" ").trim().to_string()
 }
 
+/// 合并连续空白为单个空格
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 /// 规范化 URL
-fn normalize_url(href: &str, base_url: &str) -> String {
+pub(crate) fn normalize_url(href: &str, base_url: &str) -> String {
     if href.starts_with("http://") || href.starts_with("https://") {
         href.to_string()
     } else if href.starts_with("//") {
@@ -253,6 +1324,151 @@ fn extract_base_url(detail_url: &str, rule_base_url: &str) -> String {
     }
 }
 
+/// 试运行中单个 XPath 阶段的匹配情况
+#[derive(Debug, Clone, Serialize)]
+pub struct StageCount {
+    /// 规则字段名 (searchList / searchName / chapterRoads ...)
+    pub stage: &'static str,
+    pub xpath: String,
+    /// 匹配的节点数 (列表内的阶段为匹配到节点的列表项数)
+    pub matched: usize,
+}
+
+/// 规则试运行结果 (`POST /debug/dry-run` 与 `rule check --keyword` 共用)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DryRunReport {
+    pub rule: String,
+    pub keyword: String,
+    pub search_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    pub stages: Vec<StageCount>,
+    pub items: Vec<SearchResultItem>,
+    /// 统计章节阶段所用的详情页 (第一个结果)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 转换单个阶段的 XPath
+fn stage_selector(xpath: &str) -> anyhow::Result<(Selector, Option<PositionFilter>)> {
+    let css =
+        xpath_to_css(xpath).map_err(|e| anyhow::anyhow!("XPath 转换失败 ({}): {}", xpath, e))?;
+    let selector = Selector::parse(&css.selector)
+        .map_err(|e| anyhow::anyhow!("无效的 CSS 选择器 ({}): {:?}", css.selector, e))?;
+    Ok((selector, css.position_filter))
+}
+
+/// 按位置过滤后的匹配节点
+fn select_filtered<'a>(
+    document: &'a Html,
+    selector: &Selector,
+    filter: &Option<PositionFilter>,
+) -> Vec<ElementRef<'a>> {
+    document
+        .select(selector)
+        .enumerate()
+        .filter(|(i, _)| apply_position_filter(*i, filter))
+        .map(|(_, e)| e)
+        .collect()
+}
+
+/// 搜索页各阶段的匹配数: searchList 为列表节点数，其余为匹配到节点的列表项数
+pub fn search_stage_counts(rule: &Rule, html: &str) -> anyhow::Result<Vec<StageCount>> {
+    if rule.is_rss() {
+        return Ok(vec![StageCount {
+            stage: "rssItems",
+            xpath: "/rss/channel/item".to_string(),
+            matched: crate::rss::count_items(html)?,
+        }]);
+    }
+    let document = Html::parse_document(html);
+    let (list_selector, list_filter) = stage_selector(&rule.search_list)?;
+    let list = select_filtered(&document, &list_selector, &list_filter);
+
+    let mut stages = vec![StageCount {
+        stage: "searchList",
+        xpath: rule.search_list.clone(),
+        matched: list.len(),
+    }];
+    for (stage, xpath) in [
+        ("searchName", &rule.search_name),
+        ("searchResult", &rule.search_result),
+        ("searchUpdate", &rule.search_update),
+        ("searchCover", &rule.search_cover),
+        ("searchAltName", &rule.search_alt_name),
+    ] {
+        if xpath.is_empty() {
+            continue;
+        }
+        let (selector, _) = stage_selector(split_attr(xpath).0)?;
+        stages.push(StageCount {
+            stage,
+            xpath: xpath.clone(),
+            matched: list.iter().filter(|e| e.select(&selector).next().is_some()).count(),
+        });
+    }
+    Ok(stages)
+}
+
+/// 详情页各阶段的匹配数: chapterRoads 为播放源节点数，chapterResult 为所有播放源内的章节节点总数
+pub fn chapter_stage_counts(rule: &Rule, html: &str) -> anyhow::Result<Vec<StageCount>> {
+    let document = Html::parse_document(html);
+    let (roads_selector, roads_filter) = stage_selector(&rule.chapter_roads)?;
+    let (result_selector, _) = stage_selector(&rule.chapter_result)?;
+    let roads = select_filtered(&document, &roads_selector, &roads_filter);
+    Ok(vec![
+        StageCount {
+            stage: "chapterRoads",
+            xpath: rule.chapter_roads.clone(),
+            matched: roads.len(),
+        },
+        StageCount {
+            stage: "chapterResult",
+            xpath: rule.chapter_result.clone(),
+            matched: roads.iter().map(|r| r.select(&result_selector).count()).sum(),
+        },
+    ])
+}
+
+/// 试运行规则: 请求搜索页，统计各 XPath 阶段的匹配数并解析结果；
+/// 有章节选择器时再请求第一个结果的详情页统计章节阶段
+pub async fn dry_run(rule: &Rule, keyword: &str) -> DryRunReport {
+    let mut report = DryRunReport {
+        rule: rule.name.clone(),
+        keyword: keyword.to_string(),
+        search_url: build_search_url(rule, keyword, &SearchOptions::default(), 1),
+        ..Default::default()
+    };
+    if let Err(e) = dry_run_into(rule, keyword, &mut report).await {
+        report.error = Some(e.to_string());
+    }
+    report
+}
+
+async fn dry_run_into(rule: &Rule, keyword: &str, report: &mut DryRunReport) -> anyhow::Result<()> {
+    let mut trace = RequestTrace::default();
+    let page = fetch_search_page(rule, keyword, &SearchOptions::default(), 1, &mut trace).await;
+    report.http_status = trace.status;
+    let (_, html) = page?;
+
+    report.stages = search_stage_counts(rule, &html)?;
+    report.items = parse_search_results(rule, &html)?;
+
+    let Some(first) = report.items.first() else {
+        return Ok(());
+    };
+    if !has_chapter_selectors(rule) {
+        return Ok(());
+    }
+    let detail_url = first.url.clone();
+    let detail = get_text(&detail_url, Some(&rule.base_url), rule.request_timeout()).await?;
+    report.detail_url = Some(detail_url);
+    report.stages.extend(chapter_stage_counts(rule, &detail)?);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,6 +1512,393 @@ mod tests {
         assert_eq!(items.len(), 2);
     }
 
+    #[test]
+    fn test_parse_search_update() {
+        let html = r#"
+        <html>
+        <body>
+            <div class="item">
+                <h3><a href="/video/1">动漫1</a></h3>
+                <span class="update">更新至
+                    第12集</span>
+            </div>
+            <div class="item">
+                <h3><a href="/video/2">动漫2</a></h3>
+            </div>
+        </body>
+        </html>
+        "#;
+
+        let rule = Rule {
+            base_url: "https://example.com".to_string(),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//h3/a".to_string(),
+            search_result: "//h3/a".to_string(),
+            search_update: "//span[@class='update']".to_string(),
+            ..Default::default()
+        };
+
+        let items = parse_search_results(&rule, html).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].latest.as_deref(), Some("更新至 第12集"));
+        assert_eq!(items[1].latest, None);
+    }
+
+    #[test]
+    fn test_preferred_road_leads() {
+        let html = include_str!("../tests/fixtures/multi_road_detail.html");
+        let detail_url = "https://example.com/detail/1";
+        let mut rule = Rule {
+            base_url: "https://example.com".to_string(),
+            chapter_roads: "//ul[@class='playlist']".to_string(),
+            chapter_result: "//li/a".to_string(),
+            preferred_road_pattern: "线路[23]$".to_string(),
+            ..Default::default()
+        };
+
+        let roads = parse_episodes_with_fallback(&rule, html, detail_url).unwrap();
+        let names: Vec<_> = roads.iter().map(|r| r.name.as_deref().unwrap()).collect();
+        assert_eq!(names, ["线路2", "线路3", "线路1"]);
+        // 只调整顺序: 序号与 id 仍对应页面中的位置
+        assert_eq!(roads[0].index, 1);
+        assert_eq!(roads[0].id, crate::types::road_id(detail_url, 1));
+        assert_eq!(roads[0].episodes[0].url, "https://example.com/play/2-1.html");
+
+        rule.preferred_road_pattern = "备用".to_string();
+        let roads = parse_episodes_with_fallback(&rule, html, detail_url).unwrap();
+        assert_eq!(roads.iter().map(|r| r.index).collect::<Vec<_>>(), [0, 1, 2]);
+
+        rule.preferred_road_pattern = "线路(".to_string();
+        assert!(parse_episodes_with_fallback(&rule, html, detail_url).is_err());
+    }
+
+    #[test]
+    fn test_source_id_extracted_and_used_for_dedup() {
+        let html = include_str!("../tests/fixtures/source_id_search.html");
+        let mut rule = Rule {
+            base_url: "https://example.com".to_string(),
+            search_list: "//li[@class='item']".to_string(),
+            search_name: "//h3/a".to_string(),
+            id_regex: r"detail/(?:id/)?(\d+)".to_string(),
+            ..Default::default()
+        };
+
+        // 两种地址格式的同一部作品按 ID 合并，没有 ID 的专题页按链接保留
+        let items = parse_search_results(&rule, html).unwrap();
+        let ids: Vec<_> = items.iter().map(|i| i.source_id.as_deref()).collect();
+        assert_eq!(ids, [Some("12345"), Some("678"), None]);
+        assert_eq!(items[0].name, "葬送的芙莉莲");
+        assert_eq!(items[0].url, "https://example.com/voddetail/12345.html");
+
+        rule.dedup_items = false;
+        assert_eq!(parse_search_results(&rule, html).unwrap().len(), 4);
+
+        rule.id_regex = "detail/(".to_string();
+        assert!(parse_search_results(&rule, html).is_err());
+    }
+
+    #[test]
+    fn test_search_alt_name_dropped_when_same_as_name() {
+        let html = include_str!("../tests/fixtures/dual_title_search.html");
+        let rule = Rule {
+            base_url: "https://example.com".to_string(),
+            search_list: "//li[@class='item']".to_string(),
+            search_name: "//h3/a".to_string(),
+            search_alt_name: "//p[@class='alias']".to_string(),
+            ..Default::default()
+        };
+
+        let items = parse_search_results(&rule, html).unwrap();
+        let names: Vec<_> = items
+            .iter()
+            .map(|i| (i.name.as_str(), i.alt_name.as_deref()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("葬送的芙莉莲", Some("葬送のフリーレン")),
+                ("迷宫饭", None),
+                ("药屋少女的呢喃", None),
+                ("间谍过家家", Some("SPY×FAMILY")),
+            ]
+        );
+
+        let stages = search_stage_counts(&rule, html).unwrap();
+        let alt = stages.iter().find(|s| s.stage == "searchAltName").unwrap();
+        assert_eq!(alt.matched, 4);
+    }
+
+    #[test]
+    fn test_search_cover_skips_lazy_placeholders() {
+        let html = include_str!("../tests/fixtures/lazy_cover_search.html");
+        let mut rule = Rule {
+            base_url: "https://example.com".to_string(),
+            search_list: "//li[@class='item']".to_string(),
+            search_name: "//h3/a".to_string(),
+            search_cover: "//a[@class='cover']".to_string(),
+            ..Default::default()
+        };
+
+        let items = parse_search_results(&rule, html).unwrap();
+        let covers: Vec<_> = items.iter().map(|i| i.cover.as_deref()).collect();
+        assert_eq!(
+            covers,
+            [
+                Some("https://img.example-cdn.com/cover/frieren.jpg"),
+                Some("https://example.com/upload/dungeon-960.webp"),
+                None,
+                Some("https://pic.example.net/i/8f3a2c"),
+            ]
+        );
+
+        // 指定的属性不像图片 (alt 文本) 时回退到候选属性
+        rule.search_cover = "//img/@alt".to_string();
+        let items = parse_search_results(&rule, html).unwrap();
+        assert_eq!(
+            items[0].cover.as_deref(),
+            Some("https://img.example-cdn.com/cover/frieren.jpg")
+        );
+        assert_eq!(best_srcset_entry("/a.jpg, /b.jpg 2x"), Some("/b.jpg"));
+    }
+
+    #[test]
+    fn test_duplicate_hrefs_are_merged() {
+        // 同一部番剧出现在多个标签分组下
+        let html = r#"
+        <div class="item"><a href="/video/1">葬送的芙莉莲</a></div>
+        <div class="item"><a href="/video/2">迷宫饭</a><span>更新至第24集</span></div>
+        <div class="item"><a href="https://example.com/video/1/#play">葬送的芙莉莲 (新番)</a></div>
+        <div class="item"><a href="/video/2">迷宫饭</a></div>
+        <div class="item"><a href="/video/3">药屋少女的呢喃</a></div>
+        "#;
+        let mut rule = Rule {
+            base_url: "https://example.com".to_string(),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//a".to_string(),
+            search_update: "//span".to_string(),
+            ..Default::default()
+        };
+
+        let items = parse_search_results(&rule, html).unwrap();
+        let names: Vec<_> = items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["葬送的芙莉莲", "迷宫饭", "药屋少女的呢喃"]);
+        assert_eq!(items[1].latest.as_deref(), Some("更新至第24集"));
+
+        rule.dedup_items = false;
+        assert_eq!(parse_search_results(&rule, html).unwrap().len(), 5);
+
+        let tagged = |url: &str, tags: &[&str], latest: Option<&str>| SearchResultItem {
+            name: "葬送的芙莉莲".to_string(),
+            alt_name: None,
+            url: url.to_string(),
+            tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+            latest: latest.map(str::to_string),
+            cover: None,
+            episodes: None,
+            raw_html: None,
+            source_id: None,
+            rule: String::new(),
+            rule_color: None,
+        };
+        let merged = dedup_items(vec![
+            tagged("https://example.com/video/1", &["TV"], None),
+            tagged("https://EXAMPLE.com/video/1/", &["新番", "TV"], Some("第28集")),
+            tagged("https://example.com/video/10", &["剧场版"], None),
+        ]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].tags.as_deref(), Some(&["TV".to_string(), "新番".to_string()][..]));
+        assert_eq!(merged[0].latest.as_deref(), Some("第28集"));
+        assert_eq!(merged[1].url, "https://example.com/video/10");
+    }
+
+    #[test]
+    fn test_parse_next_page_from_pager() {
+        let html = r#"
+        <div class="item"><h3><a href="/video/1">动漫1</a></h3></div>
+        <div class="pager">
+            <a href="?wd=frieren&page=1" class="current">1</a>
+            <a href="?wd=frieren&page=2">2</a>
+            <a href="?wd=frieren&page=2" class="next">下一页</a>
+        </div>
+        "#;
+        let page_url = "https://example.com/search/index.php?wd=frieren";
+        let mut rule = Rule {
+            base_url: "https://example.com".to_string(),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//h3/a".to_string(),
+            search_next_page: "//div[@class='pager']/a[@class='next']".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            parse_next_page(&rule, html, page_url).unwrap().as_deref(),
+            Some("https://example.com/search/index.php?wd=frieren&page=2")
+        );
+
+        // 最后一页: 下一页按钮指回当前页或没有链接
+        let last = r#"<div class="pager"><a class="next" href="javascript:;">下一页</a></div>"#;
+        assert_eq!(parse_next_page(&rule, last, page_url).unwrap(), None);
+        assert_eq!(
+            parse_next_page(&rule, html, "https://example.com/search/index.php?wd=frieren&page=2")
+                .unwrap(),
+            None
+        );
+
+        rule.search_next_page = "//link[@rel='next']/@data-url".to_string();
+        let head = r#"<link rel="next" data-url="/search/frieren/2">"#;
+        assert_eq!(
+            parse_next_page(&rule, head, page_url).unwrap().as_deref(),
+            Some("https://example.com/search/frieren/2")
+        );
+
+        rule.search_next_page.clear();
+        assert_eq!(parse_next_page(&rule, html, page_url).unwrap(), None);
+    }
+
+    #[test]
+    fn test_include_raw_attaches_list_node_html() {
+        let html = r#"<div class="item"><h3><a href="/video/1">动漫1</a></h3></div>"#;
+        let rule = Rule {
+            base_url: "https://example.com".to_string(),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//h3/a".to_string(),
+            ..Default::default()
+        };
+
+        let items = parse_search_results(&rule, html).unwrap();
+        assert_eq!(items[0].raw_html, None);
+
+        let options = SearchOptions {
+            include_raw: true,
+            ..Default::default()
+        };
+        let items = parse_search_results_with(&rule, html, &options).unwrap();
+        assert_eq!(
+            items[0].raw_html.as_deref(),
+            Some(r#"<div class="item"><h3><a href="/video/1">动漫1</a></h3></div>"#)
+        );
+
+        let truncated = truncate_html("芙莉莲".to_string(), 4);
+        assert_eq!(truncated, "芙…");
+    }
+
+    #[test]
+    fn test_episode_fallback_recovers_episodes() {
+        let html = r#"
+        <html>
+        <body>
+            <div class="new-layout">
+                <a href="/play/1-1.html">第01集</a>
+                <a href="/play/1-2.html">第02集</a>
+                <a href="/play/1-2.html">第02集</a>
+                <a href="/play/1-3.html">SP</a>
+                <a href="/video/2.html">12</a>
+                <a href="/about.html">关于我们</a>
+            </div>
+        </body>
+        </html>
+        "#;
+
+        let rule = Rule {
+            base_url: "https://example.com".to_string(),
+            chapter_roads: "//div[@class='old-layout']".to_string(),
+            chapter_result: "//ul/li/a".to_string(),
+            episode_fallback: true,
+            episode_href_pattern: r"/play/\d+-\d+\.html".to_string(),
+            ..Default::default()
+        };
+
+        let detail_url = "https://example.com/video/1.html";
+        assert!(parse_episodes(&rule, html, detail_url).unwrap().is_empty());
+
+        let roads = parse_episodes_with_fallback(&rule, html, detail_url).unwrap();
+        assert_eq!(roads.len(), 1);
+        let names: Vec<_> = roads[0].episodes.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["第01集", "第02集", "SP"]);
+        assert_eq!(roads[0].episodes[0].url, "https://example.com/play/1-1.html");
+
+        let disabled = Rule {
+            episode_fallback: false,
+            ..rule
+        };
+        assert!(parse_episodes_with_fallback(&disabled, html, detail_url)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_road_ids_are_stable_when_empty_roads_are_dropped() {
+        let html = r#"
+        <div class="road"><a href="/play/1-1">01</a></div>
+        <div class="road"></div>
+        <div class="road"><a href="/play/3-1">01</a><a href="/play/3-2">02</a></div>
+        "#;
+        let rule = Rule {
+            base_url: "https://example.com".to_string(),
+            chapter_roads: "//div[@class='road']".to_string(),
+            chapter_result: "//a".to_string(),
+            ..Default::default()
+        };
+        let detail_url = "https://example.com/video/1.html";
+
+        let roads = parse_episodes(&rule, html, detail_url).unwrap();
+        let indexes: Vec<_> = roads.iter().map(|r| r.index).collect();
+        assert_eq!(indexes, vec![0, 2]);
+        assert_eq!(roads[1].name.as_deref(), Some("线路3"));
+        assert_eq!(roads[1].id, crate::types::road_id(detail_url, 2));
+        // 同一详情页重复解析得到相同 id，不同详情页不同
+        assert_eq!(parse_episodes(&rule, html, detail_url).unwrap()[1].id, roads[1].id);
+        assert_ne!(crate::types::road_id("https://example.com/video/2.html", 2), roads[1].id);
+    }
+
+    #[test]
+    fn test_episode_name_template_normalizes_varied_formats() {
+        let html = r#"
+        <ul class="roads">
+            <li><a href="/play/1">HD第01集</a></li>
+            <li><a href="/play/2">正片 02</a></li>
+            <li><a href="/play/3">EP03</a></li>
+            <li><a href="/play/4">第 12.5 话</a></li>
+            <li><a href="/play/5">SP</a></li>
+        </ul>
+        "#;
+        let rule = Rule {
+            base_url: "https://example.com".to_string(),
+            chapter_roads: "//ul[@class='roads']".to_string(),
+            chapter_result: "//li/a".to_string(),
+            episode_name_template: "第{n}集".to_string(),
+            ..Default::default()
+        };
+        let detail_url = "https://example.com/video/1";
+
+        let roads = parse_episodes_with_fallback(&rule, html, detail_url).unwrap();
+        let episodes = &roads[0].episodes;
+        let display: Vec<_> = episodes.iter().map(|e| e.display_name.as_deref()).collect();
+        assert_eq!(
+            display,
+            vec![Some("第1集"), Some("第2集"), Some("第3集"), Some("第12.5集"), None]
+        );
+        assert_eq!(episodes[0].name, "HD第01集");
+
+        // 自定义正则: 只取 "第N集" 中的数字
+        let rule = Rule {
+            episode_number_regex: r"第\s*(\d+)".to_string(),
+            episode_name_template: "EP{n} ({name})".to_string(),
+            ..rule
+        };
+        let roads = parse_episodes_with_fallback(&rule, html, detail_url).unwrap();
+        assert_eq!(roads[0].episodes[0].display_name.as_deref(), Some("EP1 (HD第01集)"));
+        assert_eq!(roads[0].episodes[1].display_name, None);
+
+        let plain = Rule {
+            episode_number_regex: String::new(),
+            episode_name_template: String::new(),
+            ..rule
+        };
+        let roads = parse_episodes_with_fallback(&plain, html, detail_url).unwrap();
+        assert!(roads[0].episodes.iter().all(|e| e.display_name.is_none()));
+    }
+
     #[test]
     fn test_get_element_text() {
         let html = r#"<div><span>Hello</span> <span>World</span></div>"#;
@@ -306,4 +1909,453 @@ mod tests {
         assert!(text.contains("Hello"));
         assert!(text.contains("World"));
     }
+
+    #[tokio::test]
+    async fn test_search_token_two_step_flow() {
+        use wiremock::matchers::{body_string_contains, header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Set-Cookie", "session=s1; Path=/; HttpOnly")
+                    .set_body_string(
+                        r#"<form><input type="hidden" name="_token" value="abc123"></form>"#,
+                    ),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/search"))
+            .and(header("Cookie", "session=s1"))
+            .and(body_string_contains("_token=abc123"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<div class="item"><a href="/video/1">葬送的芙莉莲</a></div>"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let rule = Rule {
+            name: "token".to_string(),
+            base_url: format!("{}/", server.uri()),
+            search_url: format!("{}/search?wd=@keyword", server.uri()),
+            use_post: true,
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//a".to_string(),
+            token_xpath: "//input[@name='_token']/@value".to_string(),
+            token_field: "_token".to_string(),
+            ..Default::default()
+        };
+
+        let mut trace = RequestTrace::default();
+        let (items, _) = execute_search(&rule, "芙莉莲", &SearchOptions::default(), &mut trace)
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].url, format!("{}/video/1", server.uri()));
+        // 只记录搜索页请求，不含获取 token 的首页请求
+        assert_eq!((trace.status, trace.attempts), (Some(200), 1));
+    }
+
+    #[tokio::test]
+    async fn test_paging_stops_when_page_repeats() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        // (关键词, 页码, 结果 id, 预期请求次数)
+        let pages: [(&str, &str, &[u32], u64); 7] = [
+            ("dup", "1", &[1, 2], 2),
+            ("dup", "2", &[1, 2], 1),
+            ("dup", "3", &[3], 0),
+            ("more", "1", &[1, 2], 1),
+            ("more", "2", &[2, 3], 1),
+            ("more", "3", &[3], 1),
+            ("more", "4", &[4], 0),
+        ];
+        for (keyword, page, ids, expected) in pages {
+            let body: String = ids
+                .iter()
+                .map(|id| format!(r#"<div class="item"><a href="/video/{0}">结果{0}</a></div>"#, id))
+                .collect();
+            Mock::given(method("GET"))
+                .and(path("/search"))
+                .and(query_param("wd", keyword))
+                .and(query_param("p", page))
+                .respond_with(ResponseTemplate::new(200).set_body_string(body))
+                .expect(expected)
+                .mount(&server)
+                .await;
+        }
+
+        let mut rule = Rule {
+            name: "paging".to_string(),
+            base_url: format!("{}/", server.uri()),
+            search_url: format!("{}/search?wd=@keyword&p=@page", server.uri()),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//a".to_string(),
+            max_pages: 5,
+            ..Default::default()
+        };
+        let search = |rule: &Rule, keyword: &'static str| {
+            let rule = rule.clone();
+            async move {
+                let mut trace = RequestTrace::default();
+                let (items, next) = execute_search(&rule, keyword, &SearchOptions::default(), &mut trace)
+                    .await
+                    .unwrap();
+                (items.into_iter().map(|i| i.name).collect::<Vec<_>>(), next)
+            }
+        };
+
+        // 第 2 页重复第 1 页: 停止翻页，不再请求第 3 页，也不返回下一页
+        assert_eq!(search(&rule, "dup").await, (vec!["结果1".to_string(), "结果2".to_string()], None));
+        // 部分重复的页只追加新结果，没有新结果的页结束翻页
+        assert_eq!(search(&rule, "more").await.0, ["结果1", "结果2", "结果3"]);
+
+        // 不含 @page 时 maxPages 不生效
+        rule.search_url = format!("{}/search?wd=@keyword&p=1", server.uri());
+        assert_eq!(search(&rule, "dup").await.0.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_post_form_keeps_query_pair_order() {
+        use wiremock::matchers::{body_string, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/search"))
+            .and(body_string("z=1&m=2&wd=frieren&a=3"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<div class="item"><a href="/video/1">Frieren</a></div>"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let rule = Rule {
+            name: "ordered".to_string(),
+            base_url: format!("{}/", server.uri()),
+            search_url: format!("{}/search?z=1&m=2&wd=@keyword&a=3", server.uri()),
+            use_post: true,
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//a".to_string(),
+            ..Default::default()
+        };
+
+        let mut trace = RequestTrace::default();
+        let (items, _) = execute_search(&rule, "frieren", &SearchOptions::default(), &mut trace)
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_result_carries_status_and_error_kind() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        let rule = Rule {
+            name: "missing".to_string(),
+            base_url: server.uri(),
+            search_url: format!("{}/search?wd=@keyword", server.uri()),
+            search_list: "//div".to_string(),
+            search_name: "//a".to_string(),
+            ..Default::default()
+        };
+
+        let result = search_uncached(&rule, "芙莉莲", &SearchOptions::default()).await;
+        assert_eq!(result.count, -1);
+        assert_eq!(result.http_status, Some(404));
+        assert_eq!(result.attempts, 1);
+        assert_eq!(result.error_kind, Some(ErrorKind::BadStatus));
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["error_kind"], "bad_status");
+        assert!(json["elapsed_ms"].is_u64());
+
+    }
+
+    #[test]
+    fn test_error_kind_mapping() {
+        let kind = |e: HttpClientError| error_kind(&anyhow::Error::from(e));
+        assert_eq!(kind(HttpClientError::Timeout), ErrorKind::Timeout);
+        assert_eq!(kind(HttpClientError::BadStatus(404)), ErrorKind::BadStatus);
+        assert_eq!(kind(HttpClientError::BadStatus(502)), ErrorKind::BadStatus);
+        assert_eq!(kind(HttpClientError::from_status(403, None)), ErrorKind::Blocked);
+        assert_eq!(kind(HttpClientError::from_status(429, Some("30"))), ErrorKind::RateLimited);
+        assert_eq!(kind(HttpClientError::from_status(500, None)), ErrorKind::BadStatus);
+        assert_eq!(
+            kind(HttpClientError::RequestFailed("connection reset".to_string())),
+            ErrorKind::Other
+        );
+        assert_eq!(error_kind(&anyhow::anyhow!("列表 XPath 转换失败")), ErrorKind::Other);
+
+        // 页面中没有 token 时归为 ParseEmpty，消息保持不变
+        let rule = Rule {
+            token_xpath: "//input[@name='_token']/@value".to_string(),
+            ..Default::default()
+        };
+        let missing = parse_search_token(&rule, "<form></form>").unwrap_err();
+        assert_eq!(error_kind(&missing), ErrorKind::ParseEmpty);
+        assert_eq!(missing.to_string(), "页面中未找到搜索 token");
+        let empty = parse_search_token(&rule, r#"<input name="_token" value=" ">"#).unwrap_err();
+        assert_eq!(error_kind(&empty), ErrorKind::ParseEmpty);
+    }
+
+    #[tokio::test]
+    async fn test_first_only_returns_one_item_per_rule() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"
+                <div class="item"><a href="/video/0"></a></div>
+                <div class="item"><a href="/video/1">动漫1</a></div>
+                <div class="item"><a href="/video/2">动漫2</a></div>
+                "#,
+            ))
+            .mount(&server)
+            .await;
+        // 只有第一个有效结果会请求详情页
+        Mock::given(method("GET"))
+            .and(path("/video/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<ul class="roads"><li><a href="/play/1-1">第1集</a></li></ul>"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/video/2"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let rule = Rule {
+            name: "first".to_string(),
+            base_url: server.uri(),
+            search_url: format!("{}/search?wd=@keyword", server.uri()),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//a".to_string(),
+            chapter_roads: "//ul[@class='roads']".to_string(),
+            chapter_result: "//li/a".to_string(),
+            ..Default::default()
+        };
+
+        let options = SearchOptions {
+            first_only: true,
+            ..Default::default()
+        };
+        let result = search_uncached(&rule, "动漫", &options).await;
+        assert_eq!(result.count, 1);
+        assert_eq!(result.items[0].name, "动漫1");
+        assert!(result.items[0].episodes.is_some());
+    }
+
+    #[test]
+    fn test_search_url_limit_and_offset_placeholders() {
+        let rule = Rule {
+            search_url: "https://example.com/s?q=@keyword&size=@limit&from=@offset".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            build_search_url(&rule, "芙莉莲 2", &SearchOptions::default(), 1),
+            format!(
+                "https://example.com/s?q=%E8%8A%99%E8%8E%89%E8%8E%B2%202&size={}&from=0",
+                DEFAULT_SEARCH_LIMIT
+            )
+        );
+        let options = SearchOptions {
+            limit: Some(5),
+            offset: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(
+            build_search_url(&rule, "x", &options, 1),
+            "https://example.com/s?q=x&size=5&from=10"
+        );
+        // 没有占位符的地址不受影响
+        let plain = Rule {
+            search_url: "https://example.com/s?q=@keyword".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            build_search_url(&plain, "x", &options, 1),
+            "https://example.com/s?q=x"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_counts_each_stage() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<div class="item"><h3><a href="/v/1">芙莉莲</a></h3></div>
+                <div class="item"><h3><a href="/v/2">芙莉莲 剧场版</a></h3></div>
+                <div class="item"><span>广告</span></div>"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<ul class="road"><li><a href="/p/1">01</a></li><li><a href="/p/2">02</a></li></ul>
+                <ul class="road"><li><a href="/p/3">01</a></li></ul>"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let rule = Rule {
+            name: "dry".to_string(),
+            base_url: format!("{}/", server.uri()),
+            search_url: format!("{}/search?wd=@keyword", server.uri()),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//h3/a".to_string(),
+            chapter_roads: "//ul[@class='road']".to_string(),
+            chapter_result: "//li/a".to_string(),
+            ..Default::default()
+        };
+
+        let report = dry_run(&rule, "芙莉莲").await;
+        assert_eq!(report.error, None);
+        assert_eq!(report.http_status, Some(200));
+        let counts: Vec<(&str, usize)> = report.stages.iter().map(|s| (s.stage, s.matched)).collect();
+        assert_eq!(
+            counts,
+            vec![("searchList", 3), ("searchName", 2), ("chapterRoads", 2), ("chapterResult", 3)]
+        );
+        assert_eq!(report.items.len(), 2);
+        assert_eq!(report.detail_url, Some(format!("{}/v/1", server.uri())));
+    }
+
+    #[tokio::test]
+    async fn test_mock_rule_generates_results_without_requests() {
+        let mut rule = Rule {
+            name: "mock-engine".to_string(),
+            rule_type: "mock".to_string(),
+            mock: Some(MockSource {
+                items: 4,
+                episodes: 3,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let options = SearchOptions {
+            limit: Some(2),
+            offset: Some(10),
+            ..Default::default()
+        };
+        let result = search_uncached(&rule, "芙莉莲", &options).await;
+        assert_eq!(result.error, None);
+        let names: Vec<&str> = result.items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["芙莉莲 11", "芙莉莲 12"]);
+        let road = &result.items[0].episodes.as_ref().unwrap()[0];
+        assert_eq!(road.episodes.len(), 3);
+        assert_eq!(road.episodes[2].url, "mock://mock-engine/11/3");
+        let roads = fetch_episodes(&rule, &result.items[0].url).await.unwrap();
+        assert_eq!(roads[0].id, road.id);
+
+        rule.mock.as_mut().unwrap().failure_rate = 1.0;
+        let result = search_uncached(&rule, "芙莉莲", &options).await;
+        assert_eq!(result.error_kind, Some(ErrorKind::BadStatus));
+        assert_eq!(result.http_status, None);
+    }
+
+    #[tokio::test]
+    async fn test_engine_search_uses_result_cache() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        if result_cache().is_none() {
+            return;
+        }
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/cached/search"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(r#"<div class="item"><a href="/v/1">葬送的芙莉莲</a></div>"#),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let rule = Rule {
+            name: "engine-cached".to_string(),
+            base_url: format!("{}/", server.uri()),
+            search_url: format!("{}/cached/search?wd=@keyword", server.uri()),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//a".to_string(),
+            ..Default::default()
+        };
+        let options = SearchOptions {
+            skip_episodes: true,
+            ..Default::default()
+        };
+
+        let (first, hit) = search_cached(&rule, "芙莉莲", &options).await;
+        assert!(!hit);
+        assert!(first.attempts > 0);
+        // 库接口与服务端共用同一缓存，命中时不再请求源站
+        let second = search_with_options(&rule, "芙莉莲", &options).await;
+        assert_eq!((second.count, second.attempts), (1, 0));
+        assert_eq!(second.items[0].name, first.items[0].name);
+    }
+
+    #[tokio::test]
+    async fn test_debug_timing_reports_breakdown() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/timing/search"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"<div class="item"><a href="/v/1">葬送的芙莉莲</a></div>"#)
+                    .set_delay(std::time::Duration::from_millis(80)),
+            )
+            .mount(&server)
+            .await;
+        let rule = Rule {
+            name: "timing".to_string(),
+            base_url: format!("{}/", server.uri()),
+            search_url: format!("{}/timing/search?wd=@keyword", server.uri()),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//a".to_string(),
+            ..Default::default()
+        };
+
+        let result = search_uncached(&rule, "芙莉莲", &SearchOptions::default()).await;
+        assert_eq!(result.count, 1);
+        assert_eq!(result.timing, None);
+
+        let options = SearchOptions {
+            debug_timing: true,
+            ..Default::default()
+        };
+        let result = search_uncached(&rule, "芙莉莲", &options).await;
+        let timing = result.timing.unwrap();
+        assert!(timing.ttfb_ms >= 80, "{:?}", timing);
+        assert!(timing.total_ms >= timing.ttfb_ms + timing.body_ms, "{:?}", timing);
+        let json = serde_json::to_value(&result).unwrap();
+        assert!(json["timing"]["body_ms"].is_u64());
+    }
 }
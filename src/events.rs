@@ -0,0 +1,716 @@
+//! 流式事件 v2
+//! v1 事件 ([`StreamEvent`]) 按字段区分类型，字段命名混用 snake_case 与 camelCase；
+//! v2 统一为 camelCase，每个事件带 `"v": 2` 与 `"event"` 类型字段，新增字段不影响按类型解析。
+//! 通过 `?schema=2` 或 `Accept: ...; profile="events/v2"` 协商，默认仍输出 v1。
+//!
+//! 两种格式都可按 SSE (`event:` / `id:` / `data:` 帧，HTTP 默认) 或逐行 JSON
+//! (`?framing=ndjson`，库接口默认) 分帧，见 [`Framing`]。
+
+use crate::types::{
+    CanonicalTitle, Episode, EpisodeRoad, ErrorKind, RuleCooldown, SearchResultItem, StreamEvent,
+    StreamResult,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// v2 事件的版本号
+pub const VERSION: u8 = 2;
+
+/// Accept 头中选择 v2 的 profile 参数值
+pub const V2_PROFILE: &str = "events/v2";
+
+/// 输出的事件格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventSchema {
+    /// 兼容格式 (默认)
+    #[default]
+    V1,
+    /// 版本化、统一 camelCase 的格式
+    V2,
+}
+
+impl EventSchema {
+    /// 按查询参数 `schema` 与 Accept 头协商 (查询参数优先，不支持的版本返回错误)
+    pub fn negotiate(schema: Option<&str>, accept: Option<&str>) -> Result<Self, &'static str> {
+        match schema.map(str::trim).filter(|s| !s.is_empty()) {
+            Some("1") => return Ok(EventSchema::V1),
+            Some("2") => return Ok(EventSchema::V2),
+            Some(_) => return Err("Unsupported schema version, expected 1 or 2"),
+            None => {}
+        }
+        let wants_v2 = accept.is_some_and(|accept| {
+            accept.split(',').any(|range| {
+                range.split(';').skip(1).any(|param| {
+                    param
+                        .trim()
+                        .strip_prefix("profile=")
+                        .is_some_and(|v| v.trim_matches('"') == V2_PROFILE)
+                })
+            })
+        });
+        Ok(if wants_v2 {
+            EventSchema::V2
+        } else {
+            EventSchema::V1
+        })
+    }
+
+    /// 按格式序列化为待分帧的事件
+    pub fn encode(self, event: StreamEvent) -> Frame {
+        match self {
+            EventSchema::V1 => Frame::new(event.name(), &event),
+            EventSchema::V2 => VersionedEvent::new(event.into()).to_frame(),
+        }
+    }
+
+    /// 按格式序列化为一行 JSON
+    pub fn format(self, event: StreamEvent) -> String {
+        Framing::Ndjson.write(0, &self.encode(event))
+    }
+}
+
+/// 待分帧的事件: SSE 事件名与单行 JSON 数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub event: &'static str,
+    pub data: String,
+}
+
+impl Frame {
+    fn new(event: &'static str, value: &impl Serialize) -> Self {
+        Self {
+            event,
+            data: serde_json::to_string(value).unwrap_or_default(),
+        }
+    }
+}
+
+/// 流式响应的分帧方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// 每行一个 JSON (库接口默认，HTTP 通过 `framing=ndjson` 选择)
+    #[default]
+    Ndjson,
+    /// 标准 SSE 帧: `event:`、递增的 `id:` 与 `data:`，空行结束 (HTTP 默认)
+    Sse,
+}
+
+impl Framing {
+    /// 按请求参数 `framing` 选择，未指定时为 SSE
+    pub fn from_param(framing: Option<&str>) -> Result<Self, &'static str> {
+        match framing.map(str::trim).filter(|s| !s.is_empty()) {
+            None | Some("sse") => Ok(Framing::Sse),
+            Some("ndjson") => Ok(Framing::Ndjson),
+            Some(_) => Err("Unsupported framing, expected sse or ndjson"),
+        }
+    }
+
+    /// 响应的 Content-Type
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Framing::Ndjson => "application/x-ndjson; charset=utf-8",
+            Framing::Sse => "text/event-stream; charset=utf-8",
+        }
+    }
+
+    /// 按分帧方式输出一个事件 (`id` 仅 SSE 使用)
+    pub fn write(self, id: u64, frame: &Frame) -> String {
+        match self {
+            Framing::Ndjson => format!("{}\n", frame.data),
+            Framing::Sse => format!("event: {}\nid: {}\ndata: {}\n\n", frame.event, id, frame.data),
+        }
+    }
+}
+
+/// 带版本号的 v2 事件 (`{"v": 2, "event": "...", ...}`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(example = VersionedEvent::new(EventV2::Init { total: 2 }))]
+pub struct VersionedEvent {
+    /// 事件格式版本，固定为 2
+    #[schemars(range(min = 2, max = 2))]
+    pub v: u8,
+    #[serde(flatten)]
+    pub event: EventV2,
+}
+
+impl VersionedEvent {
+    pub fn new(event: EventV2) -> Self {
+        Self { v: VERSION, event }
+    }
+
+    /// 序列化为待分帧的事件 (事件名与 `event` 字段一致)
+    pub fn to_frame(&self) -> Frame {
+        Frame::new(self.event.name(), self)
+    }
+}
+
+/// v2 事件 (`event` 字段区分类型)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(
+    tag = "event",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum EventV2 {
+    /// 开始搜索，包含规则总数
+    Init { total: usize },
+    /// 一个规则完成但没有结果
+    Progress { completed: usize, total: usize },
+    /// 一个规则完成，附带结果或错误
+    Result {
+        completed: usize,
+        total: usize,
+        /// 截至本事件 (含) 所有规则已发送的结果条数
+        #[serde(default)]
+        running_total: usize,
+        result: SourceResult,
+    },
+    /// 单个结果的集数 (与结果分开返回时使用)
+    Episodes {
+        rule: String,
+        url: String,
+        roads: Vec<Road>,
+    },
+    /// 关联的 Bangumi 条目
+    Bangumi { subject_id: i64, subject: Value },
+    /// 平台第一个结果对应条目的评分 (请求 `top_rating=1` 且找到同名条目时)
+    Rating {
+        rule: String,
+        url: String,
+        subject_id: i64,
+        provider: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        score: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rank: Option<i32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        image: Option<String>,
+    },
+    /// 搜索汇总 (在 done 之前发送)
+    Summary {
+        total: usize,
+        succeeded: usize,
+        failed: usize,
+        items: usize,
+        elapsed_ms: u64,
+        /// 因时间预算耗尽而未请求的规则
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        skipped: Vec<String>,
+        /// 关键词匹配到的规范名称 (请求 `enrich=1` 时)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        canonical: Option<Canonical>,
+        /// 所选规则全部处于自动停用状态且没有规则成功 (详见 unavailable 事件)
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        all_rules_unavailable: bool,
+    },
+    /// 所选规则全部处于自动停用状态 (在 summary 之前发送)
+    Unavailable {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after_secs: Option<u64>,
+        cooldowns: Vec<Cooldown>,
+    },
+    /// 保活
+    Ping,
+    /// 完成信号 (`timedOut` 为超过搜索截止时间而被中止的规则)
+    Done {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        timed_out: Vec<String>,
+    },
+}
+
+impl EventV2 {
+    /// 事件类型名 (与 `event` 字段一致)
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventV2::Init { .. } => "init",
+            EventV2::Progress { .. } => "progress",
+            EventV2::Result { .. } => "result",
+            EventV2::Episodes { .. } => "episodes",
+            EventV2::Bangumi { .. } => "bangumi",
+            EventV2::Rating { .. } => "rating",
+            EventV2::Summary { .. } => "summary",
+            EventV2::Unavailable { .. } => "unavailable",
+            EventV2::Ping => "ping",
+            EventV2::Done { .. } => "done",
+        }
+    }
+}
+
+impl From<StreamEvent> for EventV2 {
+    fn from(event: StreamEvent) -> Self {
+        match event {
+            StreamEvent::Init { total } => EventV2::Init { total },
+            StreamEvent::Progress { progress } => EventV2::Progress {
+                completed: progress.completed,
+                total: progress.total,
+            },
+            StreamEvent::Result {
+                progress,
+                result,
+                running_total,
+            } => EventV2::Result {
+                completed: progress.completed,
+                total: progress.total,
+                running_total,
+                result: result.into(),
+            },
+            StreamEvent::Rating { rating } => EventV2::Rating {
+                rule: rating.rule,
+                url: rating.url,
+                subject_id: rating.subject_id,
+                provider: rating.provider,
+                score: rating.score,
+                rank: rating.rank,
+                image: rating.image,
+            },
+            StreamEvent::Unavailable {
+                retry_after_secs,
+                cooldowns,
+                ..
+            } => EventV2::Unavailable {
+                retry_after_secs,
+                cooldowns: cooldowns.into_iter().map(Into::into).collect(),
+            },
+            StreamEvent::Done { timed_out, .. } => EventV2::Done { timed_out },
+        }
+    }
+}
+
+/// 单个规则的结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceResult {
+    /// 规则名
+    pub rule: String,
+    pub color: String,
+    /// 平台颜色的色值
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub color_hex: String,
+    /// 平台图标地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    pub tags: Vec<String>,
+    pub items: Vec<Item>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 错误类别 (`timeout` / `bad_status` / `blocked` / `rate_limited` / `parse_empty` / `circuit_open` / `other`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+    /// 来源搜索结果页的下一页链接
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_page_url: Option<String>,
+    /// 规范名称 (第一个结果与元数据条目名称匹配时)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<Canonical>,
+    /// 耗时分解 (管理员请求 `debug_timing=1` 时)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
+}
+
+impl From<StreamResult> for SourceResult {
+    fn from(result: StreamResult) -> Self {
+        Self {
+            rule: result.name,
+            color: result.color,
+            color_hex: result.color_hex,
+            icon: result.icon,
+            tags: result.tags,
+            items: result.items.into_iter().map(Item::from).collect(),
+            error: result.error,
+            error_kind: result.error_kind,
+            next_page_url: result.next_page_url,
+            canonical: result.canonical.map(Canonical::from),
+            timing: result.timing.map(Timing::from),
+        }
+    }
+}
+
+/// 单个规则的耗时分解 (毫秒)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Timing {
+    /// 搜索页请求发出到收到响应头 (含 DNS 与建连)
+    pub ttfb_ms: u64,
+    /// 读取搜索页响应体
+    pub body_ms: u64,
+    /// 规则总耗时
+    pub total_ms: u64,
+}
+
+impl From<crate::types::Timing> for Timing {
+    fn from(timing: crate::types::Timing) -> Self {
+        Self {
+            ttfb_ms: timing.ttfb_ms,
+            body_ms: timing.body_ms,
+            total_ms: timing.total_ms,
+        }
+    }
+}
+
+/// 元数据条目的规范名称
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Canonical {
+    pub subject_id: i64,
+    pub name: String,
+    pub name_cn: String,
+    /// 元数据来源 (`bangumi` / `anilist`)
+    #[serde(default)]
+    pub provider: String,
+}
+
+impl From<CanonicalTitle> for Canonical {
+    fn from(title: CanonicalTitle) -> Self {
+        Self {
+            subject_id: title.subject_id,
+            name: title.name,
+            name_cn: title.name_cn,
+            provider: title.provider,
+        }
+    }
+}
+
+/// 已自动停用的规则及预计恢复时间
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Cooldown {
+    pub rule: String,
+    pub since: String,
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+impl From<RuleCooldown> for Cooldown {
+    fn from(cooldown: RuleCooldown) -> Self {
+        Self {
+            rule: cooldown.rule,
+            since: cooldown.since,
+            reason: cooldown.reason,
+            reset_at: cooldown.reset_at,
+            retry_after_secs: cooldown.retry_after_secs,
+        }
+    }
+}
+
+/// 单个搜索结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Item {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt_name: Option<String>,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub episodes: Option<Vec<Road>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_html: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<String>,
+}
+
+impl From<SearchResultItem> for Item {
+    fn from(item: SearchResultItem) -> Self {
+        Self {
+            name: item.name,
+            alt_name: item.alt_name,
+            url: item.url,
+            tags: item.tags.unwrap_or_default(),
+            latest: item.latest,
+            cover: item.cover,
+            episodes: item
+                .episodes
+                .map(|roads| roads.into_iter().map(Road::from).collect()),
+            raw_html: item.raw_html,
+            source_id: item.source_id,
+        }
+    }
+}
+
+/// 播放源
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Road {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 在详情页中的序号 (从 0 开始，丢弃空播放源不影响其余序号)
+    pub index: usize,
+    /// 稳定 id (详情页链接 + 序号的哈希)，可用于 `/episodes?road_id=` 与 `/export/m3u?road_id=`
+    pub id: String,
+    pub episodes: Vec<EpisodeItem>,
+}
+
+impl From<EpisodeRoad> for Road {
+    fn from(road: EpisodeRoad) -> Self {
+        Self {
+            name: road.name,
+            index: road.index,
+            id: road.id,
+            episodes: road.episodes.into_iter().map(EpisodeItem::from).collect(),
+        }
+    }
+}
+
+/// 单集
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeItem {
+    pub name: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+}
+
+impl From<Episode> for EpisodeItem {
+    fn from(episode: Episode) -> Self {
+        Self {
+            name: episode.name,
+            url: episode.url,
+            display_name: episode.display_name,
+        }
+    }
+}
+
+/// v2 事件的 JSON Schema (由 [`VersionedEvent`] 派生)
+pub fn event_schema() -> schemars::Schema {
+    schemars::schema_for!(VersionedEvent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::StreamProgress;
+
+    fn round_trip(event: EventV2) -> Value {
+        let event = VersionedEvent::new(event);
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["v"], 2);
+        let back: VersionedEvent = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(back, event);
+        json
+    }
+
+    #[test]
+    fn test_v2_events_round_trip_with_camel_case() {
+        let result = StreamEvent::Result {
+            running_total: 1,
+            progress: StreamProgress {
+                completed: 1,
+                total: 2,
+            },
+            result: StreamResult {
+                name: "AGE".to_string(),
+                color: "orange".to_string(),
+                color_hex: String::new(),
+                icon: None,
+                tags: vec![],
+                items: vec![SearchResultItem {
+                    name: "葬送的芙莉莲".to_string(),
+                    alt_name: None,
+                    url: "https://example.com/1".to_string(),
+                    tags: None,
+                    latest: None,
+                    cover: None,
+                    episodes: Some(vec![EpisodeRoad::new(
+                        "https://example.com/1",
+                        1,
+                        None,
+                        vec![Episode {
+                            name: "01".to_string(),
+                            url: "https://example.com/1/1".to_string(),
+                            display_name: Some("第1集".to_string()),
+                        }],
+                    )]),
+                    raw_html: Some("<li></li>".to_string()),
+                    source_id: None,
+                    rule: "AGE".to_string(),
+                    rule_color: Some("orange".to_string()),
+                }],
+                error: None,
+                error_kind: None,
+                next_page_url: None,
+                canonical: None,
+                timing: None,
+            },
+        };
+        let json = round_trip(result.into());
+        assert_eq!(json["event"], "result");
+        assert_eq!(json["result"]["rule"], "AGE");
+        assert_eq!(json["result"]["items"][0]["rawHtml"], "<li></li>");
+        assert_eq!(
+            json["result"]["items"][0]["episodes"][0]["episodes"][0]["displayName"],
+            "第1集"
+        );
+
+        let json = round_trip(EventV2::Summary {
+            total: 2,
+            succeeded: 1,
+            failed: 1,
+            items: 1,
+            elapsed_ms: 120,
+            skipped: vec!["NT".to_string()],
+            canonical: Some(Canonical {
+                subject_id: 400602,
+                name: "葬送のフリーレン".to_string(),
+                name_cn: "葬送的芙莉莲".to_string(),
+                provider: "bangumi".to_string(),
+            }),
+            all_rules_unavailable: false,
+        });
+        assert_eq!(json["elapsedMs"], 120);
+        assert!(json.get("allRulesUnavailable").is_none());
+        assert_eq!(json["skipped"][0], "NT");
+        assert_eq!(json["canonical"]["nameCn"], "葬送的芙莉莲");
+        let json = round_trip(EventV2::Bangumi {
+            subject_id: 400602,
+            subject: serde_json::json!({"name": "葬送のフリーレン"}),
+        });
+        assert_eq!(json["subjectId"], 400602);
+        for event in [
+            EventV2::Init { total: 2 },
+            EventV2::Ping,
+            EventV2::Done { timed_out: vec![] },
+        ] {
+            round_trip(event);
+        }
+        assert_eq!(
+            EventSchema::V2.format(StreamEvent::Done {
+                done: true,
+                timed_out: vec![]
+            }),
+            "{\"v\":2,\"event\":\"done\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_sse_frames_for_each_event() {
+        let progress = StreamProgress {
+            completed: 1,
+            total: 2,
+        };
+        let result = StreamResult {
+            name: "AGE".to_string(),
+            color: "orange".to_string(),
+            color_hex: String::new(),
+            icon: None,
+            tags: vec![],
+            items: vec![],
+            error: Some("超时".to_string()),
+            error_kind: Some(ErrorKind::Timeout),
+            next_page_url: None,
+            canonical: None,
+            timing: None,
+        };
+        let rating = crate::types::TopRating {
+            rule: "AGE".to_string(),
+            url: "https://example.com/1".to_string(),
+            subject_id: 400602,
+            provider: "bangumi".to_string(),
+            score: Some(9.1),
+            rank: None,
+            image: None,
+        };
+        let cases = [
+            (StreamEvent::Init { total: 2 }, "init", "{\"total\":2}"),
+            (
+                StreamEvent::Progress {
+                    progress: progress.clone(),
+                },
+                "progress",
+                "{\"progress\":{\"completed\":1,\"total\":2}}",
+            ),
+            (
+                StreamEvent::Result {
+                    progress,
+                    result,
+                    running_total: 0,
+                },
+                "result",
+                "{\"progress\":{\"completed\":1,\"total\":2},\"result\":{\"name\":\"AGE\",\"color\":\"orange\",\"tags\":[],\"items\":[],\"error\":\"超时\",\"error_kind\":\"timeout\"},\"running_total\":0}",
+            ),
+            (
+                StreamEvent::Rating { rating },
+                "rating",
+                "{\"rating\":{\"rule\":\"AGE\",\"url\":\"https://example.com/1\",\"subject_id\":400602,\"provider\":\"bangumi\",\"score\":9.1}}",
+            ),
+            (
+                StreamEvent::Unavailable {
+                    all_rules_unavailable: true,
+                    retry_after_secs: None,
+                    cooldowns: vec![],
+                },
+                "unavailable",
+                "{\"all_rules_unavailable\":true,\"cooldowns\":[]}",
+            ),
+            (
+                StreamEvent::Done {
+                    done: true,
+                    timed_out: vec![],
+                },
+                "done",
+                "{\"done\":true}",
+            ),
+            (
+                StreamEvent::Done {
+                    done: true,
+                    timed_out: vec!["AGE".to_string()],
+                },
+                "done",
+                "{\"done\":true,\"timed_out\":[\"AGE\"]}",
+            ),
+        ];
+        for (id, (event, name, data)) in cases.into_iter().enumerate() {
+            let frame = EventSchema::V1.encode(event);
+            assert_eq!(frame.event, name);
+            assert_eq!(
+                Framing::Sse.write(id as u64 + 1, &frame),
+                format!("event: {}\nid: {}\ndata: {}\n\n", name, id + 1, data)
+            );
+            assert_eq!(Framing::Ndjson.write(id as u64 + 1, &frame), format!("{}\n", data));
+        }
+
+        let frame = EventSchema::V2.encode(StreamEvent::Done {
+            done: true,
+            timed_out: vec!["AGE".to_string()],
+        });
+        assert_eq!(
+            Framing::Sse.write(7, &frame),
+            "event: done\nid: 7\ndata: {\"v\":2,\"event\":\"done\",\"timedOut\":[\"AGE\"]}\n\n"
+        );
+        assert_eq!(Framing::from_param(None), Ok(Framing::Sse));
+        assert_eq!(Framing::from_param(Some("ndjson")), Ok(Framing::Ndjson));
+        assert!(Framing::from_param(Some("xml")).is_err());
+    }
+
+    #[test]
+    fn test_schema_negotiation() {
+        assert_eq!(EventSchema::negotiate(None, None), Ok(EventSchema::V1));
+        assert_eq!(EventSchema::negotiate(Some("2"), None), Ok(EventSchema::V2));
+        assert_eq!(
+            EventSchema::negotiate(None, Some("text/event-stream; profile=\"events/v2\"")),
+            Ok(EventSchema::V2)
+        );
+        assert_eq!(
+            EventSchema::negotiate(Some("1"), Some("text/event-stream; profile=events/v2")),
+            Ok(EventSchema::V1)
+        );
+        assert!(EventSchema::negotiate(Some("3"), None).is_err());
+
+        let schema = serde_json::to_value(event_schema()).unwrap();
+        let text = schema.to_string();
+        for name in [
+            "init", "progress", "result", "episodes", "bangumi", "summary", "ping", "done",
+        ] {
+            assert!(text.contains(&format!("\"{}\"", name)), "{}", name);
+        }
+    }
+}
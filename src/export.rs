@@ -0,0 +1,325 @@
+//! 搜索结果导出
+//! 将聚合搜索结果展平为表格 (CSV/TSV)，方便在 Excel 等工具中整理；
+//! 或打包为可下载的归档文件 (完整 JSON / 按结果展开的 CSV)；单个播放源可导出为 M3U 播放列表
+
+use crate::types::{EpisodeRoad, StreamResult};
+use serde_json::json;
+
+/// 归档导出的最大结果条数 (超出部分截断)
+pub const MAX_EXPORT_ITEMS: usize = 5000;
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Tsv,
+}
+
+impl ExportFormat {
+    /// 解析 `format` 参数 (缺省为 CSV)
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("") | Some("csv") => Some(ExportFormat::Csv),
+            Some("tsv") => Some(ExportFormat::Tsv),
+            _ => None,
+        }
+    }
+
+    fn delimiter(self) -> u8 {
+        match self {
+            ExportFormat::Csv => b',',
+            ExportFormat::Tsv => b'\t',
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Tsv => "text/tab-separated-values; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+        }
+    }
+}
+
+/// 将搜索结果写为 `platform,name,url,tags` 表格 (不含集数)
+///
+/// 条目自带标签时使用条目标签，否则使用平台标签，多个标签以 `|` 连接
+pub fn to_table(results: &[StreamResult], format: ExportFormat) -> anyhow::Result<Vec<u8>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(format.delimiter())
+        .from_writer(Vec::new());
+
+    writer.write_record(["platform", "name", "url", "tags"])?;
+    for result in results {
+        for item in &result.items {
+            let tags = item.tags.as_ref().unwrap_or(&result.tags).join("|");
+            writer.write_record([
+                item.rule_or(&result.name),
+                item.name.as_str(),
+                item.url.as_str(),
+                tags.as_str(),
+            ])?;
+        }
+    }
+
+    Ok(writer.into_inner()?)
+}
+
+/// 归档导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Json,
+    Csv,
+}
+
+impl ArchiveFormat {
+    /// 解析 `format` 参数 (缺省为 JSON)
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("") | Some("json") => Some(ArchiveFormat::Json),
+            Some("csv") => Some(ArchiveFormat::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ArchiveFormat::Json => "application/json",
+            ArchiveFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Json => "json",
+            ArchiveFormat::Csv => "csv",
+        }
+    }
+}
+
+/// 按顺序保留至多 `max_items` 条结果，返回是否发生截断
+pub fn cap_items(results: &mut [StreamResult], max_items: usize) -> bool {
+    let mut remaining = max_items;
+    let mut truncated = false;
+    for result in results.iter_mut() {
+        if result.items.len() > remaining {
+            result.items.truncate(remaining);
+            truncated = true;
+        }
+        remaining -= result.items.len();
+    }
+    truncated
+}
+
+/// 生成归档文件: JSON 为带元数据的完整结果，CSV 为 `rule,name,url,episode_count` 行
+pub fn to_archive(
+    keyword: &str,
+    results: &[StreamResult],
+    truncated: bool,
+    format: ArchiveFormat,
+) -> anyhow::Result<Vec<u8>> {
+    match format {
+        ArchiveFormat::Json => {
+            let total_items: usize = results.iter().map(|r| r.items.len()).sum();
+            Ok(serde_json::to_vec_pretty(&json!({
+                "keyword": keyword,
+                "exported_at": chrono::Utc::now().to_rfc3339(),
+                "rules": results.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(),
+                "total_items": total_items,
+                "truncated": truncated,
+                "results": results,
+            }))?)
+        }
+        ArchiveFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(["rule", "name", "url", "episode_count"])?;
+            for result in results {
+                for item in &result.items {
+                    let episode_count: usize = item
+                        .episodes
+                        .iter()
+                        .flatten()
+                        .map(|road| road.episodes.len())
+                        .sum();
+                    writer.write_record([
+                        item.rule_or(&result.name),
+                        item.name.as_str(),
+                        item.url.as_str(),
+                        episode_count.to_string().as_str(),
+                    ])?;
+                }
+            }
+            Ok(writer.into_inner()?)
+        }
+    }
+}
+
+/// M3U 播放列表的 Content-Type
+pub const M3U_CONTENT_TYPE: &str = "audio/x-mpegurl; charset=utf-8";
+
+/// 将播放源导出为扩展 M3U 播放列表 (每集一项，标题优先使用规范化显示名)
+pub fn to_m3u(title: &str, road: &EpisodeRoad) -> String {
+    // 标题中的换行会破坏行格式
+    let clean = |text: &str| text.replace(['\r', '\n'], " ");
+    let mut playlist = String::from("#EXTM3U\n");
+    playlist.push_str(&format!("#PLAYLIST:{}\n", clean(title)));
+    for episode in &road.episodes {
+        let name = episode.display_name.as_deref().unwrap_or(&episode.name);
+        playlist.push_str(&format!("#EXTINF:-1,{}\n{}\n", clean(name), clean(&episode.url)));
+    }
+    playlist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SearchResultItem;
+
+    #[test]
+    fn test_csv_escaping() {
+        let results = vec![StreamResult {
+            name: "AGE".to_string(),
+            color: "orange".to_string(),
+            color_hex: String::new(),
+            icon: None,
+            tags: vec!["在线".to_string()],
+            items: vec![SearchResultItem {
+                name: "Foo, \"Bar\"\nBaz".to_string(),
+                alt_name: None,
+                url: "https://example.com/1".to_string(),
+                tags: None,
+                latest: None,
+                cover: None,
+                episodes: None,
+                raw_html: None,
+                source_id: None,
+                rule: String::new(),
+                rule_color: None,
+            }],
+            error: None,
+            error_kind: None,
+            next_page_url: None,
+            canonical: None,
+            timing: None,
+        }];
+
+        let csv = String::from_utf8(to_table(&results, ExportFormat::Csv).unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "platform,name,url,tags\nAGE,\"Foo, \"\"Bar\"\"\nBaz\",https://example.com/1,在线\n"
+        );
+    }
+
+    #[test]
+    fn test_archive_caps_items_and_counts_episodes() {
+        use crate::types::{Episode, EpisodeRoad};
+
+        let item = |n: usize, rule: &str| SearchResultItem {
+            name: format!("动漫{}", n),
+            alt_name: None,
+            url: format!("https://example.com/{}", n),
+            tags: None,
+            latest: None,
+            cover: None,
+            episodes: Some(vec![EpisodeRoad::new(
+                &format!("https://example.com/{}", n),
+                0,
+                None,
+                (0..n)
+                    .map(|i| Episode {
+                        name: format!("第{}集", i + 1),
+                        url: format!("https://example.com/{}/{}", n, i),
+                        display_name: None,
+                    })
+                    .collect(),
+            )]),
+            raw_html: None,
+            source_id: None,
+            rule: rule.to_string(),
+            rule_color: Some("orange".to_string()),
+        };
+        let mut results = vec![
+            StreamResult {
+                name: "AGE".to_string(),
+                color: "orange".to_string(),
+                color_hex: String::new(),
+                icon: None,
+                tags: vec![],
+                items: vec![item(2, "AGE"), item(3, "AGE")],
+                error: None,
+                error_kind: None,
+                next_page_url: None,
+                canonical: None,
+                timing: None,
+            },
+            StreamResult {
+                name: "NT".to_string(),
+                color: "white".to_string(),
+                color_hex: String::new(),
+                icon: None,
+                tags: vec![],
+                items: vec![item(1, "NT")],
+                error: None,
+                error_kind: None,
+                next_page_url: None,
+                canonical: None,
+                timing: None,
+            },
+        ];
+
+        assert!(cap_items(&mut results, 2));
+        assert!(results[1].items.is_empty());
+
+        let csv = to_archive("芙莉莲", &results, true, ArchiveFormat::Csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "rule,name,url,episode_count\nAGE,动漫2,https://example.com/2,2\nAGE,动漫3,https://example.com/3,3\n"
+        );
+
+        let json = to_archive("芙莉莲", &results, true, ArchiveFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["total_items"], 2);
+        assert_eq!(value["truncated"], true);
+        assert_eq!(value["results"][0]["items"][1]["episodes"][0]["episodes"][2]["name"], "第3集");
+        // 按规则分组的结果中不重复输出来源规则
+        assert!(value["results"][0]["items"][0].get("rule").is_none());
+        assert!(value["results"][0]["items"][0].get("rule_color").is_none());
+        assert_eq!(
+            value["results"][0]["items"][0]["episodes"][0]["id"],
+            crate::types::road_id("https://example.com/2", 0)
+        );
+    }
+
+    #[test]
+    fn test_m3u_lists_episodes_in_order() {
+        use crate::types::Episode;
+
+        let road = EpisodeRoad::new(
+            "https://example.com/1",
+            1,
+            Some("线路2".to_string()),
+            vec![
+                Episode {
+                    name: "01".to_string(),
+                    url: "https://example.com/play/1".to_string(),
+                    display_name: Some("第1集".to_string()),
+                },
+                Episode {
+                    name: "SP\n特别篇".to_string(),
+                    url: "https://example.com/play/sp".to_string(),
+                    display_name: None,
+                },
+            ],
+        );
+        assert_eq!(
+            to_m3u("芙莉莲", &road),
+            "#EXTM3U\n#PLAYLIST:芙莉莲\n#EXTINF:-1,第1集\nhttps://example.com/play/1\n#EXTINF:-1,SP 特别篇\nhttps://example.com/play/sp\n"
+        );
+    }
+}
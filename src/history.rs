@@ -0,0 +1,538 @@
+//! 搜索历史 (可选)
+//! 设置 HISTORY_DB 后 (需 sqlite feature)，把每次完成的搜索与各规则的结果写入独立的 SQLite 数据库，
+//! 供 `/history/stats` 统计热门关键词与规则成功率趋势。写入在后台线程进行，不阻塞搜索，队列满时丢弃。
+//! 未设置 HISTORY_DB 但存储为 SQLite / Redis (DATABASE_PATH / REDIS_URL) 时，在存储中按日计数
+//! (关键词、各规则次数与成功数)，统计结果相同，只是不保留单次搜索的明细。
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// 单个规则在一次搜索中的结果
+#[derive(Debug, Clone)]
+pub struct RuleOutcome {
+    pub rule: String,
+    pub success: bool,
+    pub items: usize,
+    pub elapsed_ms: u64,
+}
+
+/// 关键词搜索次数
+#[derive(Debug, Clone, Serialize)]
+pub struct KeywordCount {
+    pub keyword: String,
+    pub searches: u64,
+}
+
+/// 单日的规则成功率
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyRate {
+    /// UTC 日期 (YYYY-MM-DD)
+    pub date: String,
+    pub attempts: u64,
+    pub successes: u64,
+    pub success_rate: f64,
+}
+
+/// 规则成功率 (统计区间合计与按日明细)
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleRate {
+    pub rule: String,
+    pub attempts: u64,
+    pub successes: u64,
+    pub success_rate: f64,
+    pub daily: Vec<DailyRate>,
+}
+
+/// 统计结果
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryStats {
+    /// 统计最近的天数
+    pub days: u32,
+    pub searches: u64,
+    pub top_keywords: Vec<KeywordCount>,
+    pub rules: Vec<RuleRate>,
+}
+
+/// 按配置打开历史数据库 (服务启动时调用)，返回是否启用
+pub fn init() -> anyhow::Result<bool> {
+    let Some(path) = crate::config::CONFIG.history_db.as_deref() else {
+        return Ok(kv::is_enabled());
+    };
+    #[cfg(feature = "sqlite")]
+    {
+        sqlite::init(path)?;
+        Ok(true)
+    }
+    #[cfg(not(feature = "sqlite"))]
+    {
+        let _ = path;
+        tracing::warn!("已设置 HISTORY_DB，但未启用 sqlite feature，不记录搜索历史");
+        Ok(false)
+    }
+}
+
+/// 是否使用独立的历史数据库 (HISTORY_DB)
+fn uses_history_db() -> bool {
+    #[cfg(feature = "sqlite")]
+    return sqlite::HISTORY.get().is_some();
+    #[cfg(not(feature = "sqlite"))]
+    false
+}
+
+/// 是否记录历史
+pub fn is_enabled() -> bool {
+    uses_history_db() || kv::is_enabled()
+}
+
+/// 记录一次完成的搜索 (未启用时忽略)
+pub fn record_search(keyword: &str, outcomes: Vec<RuleOutcome>, elapsed: Duration) {
+    if !uses_history_db() {
+        if kv::is_enabled() {
+            kv::record(keyword, outcomes);
+        }
+        return;
+    }
+    #[cfg(feature = "sqlite")]
+    sqlite::record(keyword, outcomes, elapsed);
+    #[cfg(not(feature = "sqlite"))]
+    let _ = elapsed;
+}
+
+/// 最近 `days` 天的统计 (未启用时返回 None)
+pub fn stats(days: u32, top: usize) -> anyhow::Result<Option<HistoryStats>> {
+    if !uses_history_db() {
+        return Ok(kv::is_enabled().then(|| kv::stats(days, top)));
+    }
+    #[cfg(feature = "sqlite")]
+    return sqlite::stats(days, top);
+    #[cfg(not(feature = "sqlite"))]
+    unreachable!()
+}
+
+fn rate(successes: u64, attempts: u64) -> f64 {
+    if attempts == 0 {
+        0.0
+    } else {
+        successes as f64 / attempts as f64
+    }
+}
+
+/// 存储中的按日计数
+///
+/// 键为 `searches:{日期}`、`keyword:{日期}:{关键词}`、`attempts:{日期}:{规则}`、
+/// `successes:{日期}:{规则}`，首次计数时设置保留期限
+mod kv {
+    use super::*;
+    use crate::storage::{self, ns, Store};
+    use std::collections::{BTreeMap, HashMap};
+
+    /// 计数保留时间 (覆盖 `/history/stats` 的最大统计天数)
+    const RETENTION: Duration = Duration::from_secs(366 * 86400);
+
+    pub(super) fn is_enabled() -> bool {
+        storage::is_persistent()
+    }
+
+    fn date(at: chrono::DateTime<chrono::Utc>) -> String {
+        at.format("%Y-%m-%d").to_string()
+    }
+
+    pub(super) fn record(keyword: &str, outcomes: Vec<RuleOutcome>) {
+        let today = date(chrono::Utc::now());
+        let keyword = keyword.to_string();
+        storage::spawn_write(move |store| write(store, &today, &keyword, &outcomes));
+    }
+
+    fn write(store: &dyn Store, date: &str, keyword: &str, outcomes: &[RuleOutcome]) {
+        let incr = |key: String| {
+            store.incr(ns::SEARCH_HISTORY, &key, RETENTION);
+        };
+        incr(format!("searches:{}", date));
+        incr(format!("keyword:{}:{}", date, keyword));
+        for outcome in outcomes {
+            incr(format!("attempts:{}:{}", date, outcome.rule));
+            if outcome.success {
+                incr(format!("successes:{}:{}", date, outcome.rule));
+            }
+        }
+    }
+
+    pub(super) fn stats(days: u32, top: usize) -> HistoryStats {
+        let since = chrono::Utc::now() - chrono::Duration::days(days.saturating_sub(1) as i64);
+        query_stats(storage::store(), days, &date(since), top)
+    }
+
+    /// 汇总 `since` (含) 之后各日的计数
+    fn query_stats(store: &dyn Store, days: u32, since: &str, top: usize) -> HistoryStats {
+        let mut searches = 0;
+        let mut keywords: HashMap<String, u64> = HashMap::new();
+        // 规则 -> 日期 -> (次数, 成功数)
+        let mut rules: BTreeMap<String, BTreeMap<String, (u64, u64)>> = BTreeMap::new();
+        for (key, value) in store.list(ns::SEARCH_HISTORY) {
+            let mut parts = key.splitn(3, ':');
+            let (Some(kind), Some(date)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            let count: u64 = value.parse().unwrap_or(0);
+            if date < since {
+                continue;
+            }
+            let name = parts.next().unwrap_or_default().to_string();
+            match kind {
+                "searches" => searches += count,
+                "keyword" => *keywords.entry(name).or_default() += count,
+                "attempts" => {
+                    rules.entry(name).or_default().entry(date.to_string()).or_default().0 += count
+                }
+                "successes" => {
+                    rules.entry(name).or_default().entry(date.to_string()).or_default().1 += count
+                }
+                _ => {}
+            }
+        }
+
+        let mut top_keywords: Vec<KeywordCount> = keywords
+            .into_iter()
+            .map(|(keyword, searches)| KeywordCount { keyword, searches })
+            .collect();
+        top_keywords.sort_by(|a, b| b.searches.cmp(&a.searches).then(a.keyword.cmp(&b.keyword)));
+        top_keywords.truncate(top);
+
+        let rules = rules
+            .into_iter()
+            .map(|(rule, daily)| {
+                let daily: Vec<DailyRate> = daily
+                    .into_iter()
+                    .map(|(date, (attempts, successes))| DailyRate {
+                        date,
+                        attempts,
+                        successes,
+                        success_rate: rate(successes, attempts),
+                    })
+                    .collect();
+                let attempts = daily.iter().map(|d| d.attempts).sum();
+                let successes = daily.iter().map(|d| d.successes).sum();
+                RuleRate {
+                    rule,
+                    attempts,
+                    successes,
+                    success_rate: rate(successes, attempts),
+                    daily,
+                }
+            })
+            .collect();
+
+        HistoryStats {
+            days,
+            searches,
+            top_keywords,
+            rules,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::storage::MemoryStore;
+
+        #[test]
+        fn test_store_history_counts_per_day() {
+            let store = MemoryStore::default();
+            let outcome = |rule: &str, success: bool| RuleOutcome {
+                rule: rule.to_string(),
+                success,
+                items: 0,
+                elapsed_ms: 0,
+            };
+            write(&store, "2026-01-01", "芙莉莲", &[outcome("AGE", true), outcome("NT", true)]);
+            write(&store, "2026-01-02", "芙莉莲", &[outcome("AGE", false)]);
+            write(&store, "2026-01-02", "进击的巨人", &[outcome("AGE", true)]);
+
+            let stats = query_stats(&store, 7, "2026-01-01", 1);
+            assert_eq!(stats.searches, 3);
+            assert_eq!(stats.top_keywords.len(), 1);
+            assert_eq!(
+                (stats.top_keywords[0].keyword.as_str(), stats.top_keywords[0].searches),
+                ("芙莉莲", 2)
+            );
+            let age = &stats.rules[0];
+            assert_eq!((age.rule.as_str(), age.attempts, age.successes), ("AGE", 3, 2));
+            assert_eq!(age.daily.len(), 2);
+            assert_eq!(age.daily[1].success_rate, 0.5);
+            assert_eq!(stats.rules[1].success_rate, 1.0);
+
+            // 只统计区间内的日期
+            assert_eq!(query_stats(&store, 1, "2026-01-02", 10).searches, 2);
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::*;
+    use once_cell::sync::OnceCell;
+    use rusqlite::{params, Connection};
+    use std::collections::BTreeMap;
+    use std::sync::mpsc::{self, SyncSender, TrySendError};
+    use std::sync::{Arc, Mutex};
+    use tracing::{info, warn};
+
+    /// 写入队列长度
+    const QUEUE_SIZE: usize = 1024;
+
+    /// 迁移脚本，按顺序执行，版本号记录在 `PRAGMA user_version`
+    const MIGRATIONS: &[&str] = &["CREATE TABLE searches (
+            id INTEGER PRIMARY KEY,
+            keyword TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            rules INTEGER NOT NULL,
+            items INTEGER NOT NULL,
+            elapsed_ms INTEGER NOT NULL
+        );
+        CREATE INDEX searches_created_at ON searches (created_at);
+        CREATE TABLE rule_outcomes (
+            search_id INTEGER NOT NULL REFERENCES searches (id),
+            rule TEXT NOT NULL,
+            success INTEGER NOT NULL,
+            items INTEGER NOT NULL,
+            elapsed_ms INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX rule_outcomes_created_at ON rule_outcomes (created_at);"];
+
+    /// 一次搜索的记录
+    pub(super) struct SearchRecord {
+        keyword: String,
+        created_at: i64,
+        elapsed_ms: u64,
+        outcomes: Vec<RuleOutcome>,
+    }
+
+    pub(super) struct History {
+        sender: SyncSender<SearchRecord>,
+        conn: Arc<Mutex<Connection>>,
+    }
+
+    pub(super) static HISTORY: OnceCell<History> = OnceCell::new();
+
+    pub(super) fn init(path: &str) -> anyhow::Result<()> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            if !dir.as_os_str().is_empty() {
+                std::fs::create_dir_all(dir)?;
+            }
+        }
+        let conn = Arc::new(Mutex::new(open(Connection::open(path)?)?));
+        let (sender, receiver) = mpsc::sync_channel::<SearchRecord>(QUEUE_SIZE);
+        let writer = conn.clone();
+        std::thread::Builder::new()
+            .name("history-writer".to_string())
+            .spawn(move || {
+                for record in receiver {
+                    let mut conn = writer.lock().unwrap_or_else(|e| e.into_inner());
+                    if let Err(e) = write(&mut conn, &record) {
+                        warn!("写入搜索历史失败: {}", e);
+                    }
+                }
+            })?;
+        if HISTORY.set(History { sender, conn }).is_err() {
+            anyhow::bail!("搜索历史已初始化");
+        }
+        info!("📈 搜索历史写入: {}", path);
+        Ok(())
+    }
+
+    fn open(mut conn: Connection) -> anyhow::Result<Connection> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(sql)?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+            tx.commit()?;
+        }
+        Ok(conn)
+    }
+
+    pub(super) fn record(keyword: &str, outcomes: Vec<RuleOutcome>, elapsed: Duration) {
+        let Some(history) = HISTORY.get() else {
+            return;
+        };
+        let record = SearchRecord {
+            keyword: keyword.to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+            elapsed_ms: elapsed.as_millis() as u64,
+            outcomes,
+        };
+        if let Err(TrySendError::Full(_)) = history.sender.try_send(record) {
+            warn!("搜索历史写入队列已满，丢弃记录");
+        }
+    }
+
+    fn write(conn: &mut Connection, record: &SearchRecord) -> rusqlite::Result<()> {
+        let items: usize = record.outcomes.iter().map(|o| o.items).sum();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO searches (keyword, created_at, rules, items, elapsed_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                record.keyword,
+                record.created_at,
+                record.outcomes.len() as i64,
+                items as i64,
+                record.elapsed_ms as i64
+            ],
+        )?;
+        let search_id = tx.last_insert_rowid();
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO rule_outcomes (search_id, rule, success, items, elapsed_ms, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for outcome in &record.outcomes {
+                insert.execute(params![
+                    search_id,
+                    outcome.rule,
+                    outcome.success,
+                    outcome.items as i64,
+                    outcome.elapsed_ms as i64,
+                    record.created_at
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    pub(super) fn stats(days: u32, top: usize) -> anyhow::Result<Option<HistoryStats>> {
+        let Some(history) = HISTORY.get() else {
+            return Ok(None);
+        };
+        let conn = history.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let since = chrono::Utc::now().timestamp() - days as i64 * 86400;
+        Ok(Some(query_stats(&conn, days, since, top)?))
+    }
+
+    fn query_stats(
+        conn: &Connection,
+        days: u32,
+        since: i64,
+        top: usize,
+    ) -> rusqlite::Result<HistoryStats> {
+        let searches: u64 = conn.query_row(
+            "SELECT COUNT(*) FROM searches WHERE created_at >= ?1",
+            [since],
+            |row| row.get(0),
+        )?;
+
+        let top_keywords = conn
+            .prepare(
+                "SELECT keyword, COUNT(*) AS n FROM searches WHERE created_at >= ?1
+                 GROUP BY keyword ORDER BY n DESC, keyword LIMIT ?2",
+            )?
+            .query_map(params![since, top as i64], |row| {
+                Ok(KeywordCount {
+                    keyword: row.get(0)?,
+                    searches: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut rules: BTreeMap<String, RuleRate> = BTreeMap::new();
+        let mut daily = conn.prepare(
+            "SELECT rule, date(created_at, 'unixepoch') AS day, COUNT(*), SUM(success)
+             FROM rule_outcomes WHERE created_at >= ?1
+             GROUP BY rule, day ORDER BY rule, day",
+        )?;
+        let rows = daily.query_map([since], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, u64>(2)?,
+                row.get::<_, u64>(3)?,
+            ))
+        })?;
+        for row in rows {
+            let (rule, date, attempts, successes) = row?;
+            let entry = rules.entry(rule.clone()).or_insert_with(|| RuleRate {
+                rule,
+                attempts: 0,
+                successes: 0,
+                success_rate: 0.0,
+                daily: Vec::new(),
+            });
+            entry.attempts += attempts;
+            entry.successes += successes;
+            entry.daily.push(DailyRate {
+                date,
+                attempts,
+                successes,
+                success_rate: rate(successes, attempts),
+            });
+        }
+        let rules = rules
+            .into_values()
+            .map(|mut r| {
+                r.success_rate = rate(r.successes, r.attempts);
+                r
+            })
+            .collect();
+
+        Ok(HistoryStats {
+            days,
+            searches,
+            top_keywords,
+            rules,
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_history_aggregates_keywords_and_rule_rates() {
+            let mut conn = open(Connection::open_in_memory().unwrap()).unwrap();
+            let outcome = |rule: &str, success: bool| RuleOutcome {
+                rule: rule.to_string(),
+                success,
+                items: if success { 3 } else { 0 },
+                elapsed_ms: 100,
+            };
+            let day = 86400;
+            for (keyword, created_at, age_ok) in [
+                ("芙莉莲", 10 * day, true),
+                ("芙莉莲", 11 * day, false),
+                ("进击的巨人", 11 * day + 60, true),
+            ] {
+                let record = SearchRecord {
+                    keyword: keyword.to_string(),
+                    created_at,
+                    elapsed_ms: 500,
+                    outcomes: vec![outcome("AGE", age_ok), outcome("NT", true)],
+                };
+                write(&mut conn, &record).unwrap();
+            }
+
+            let stats = query_stats(&conn, 7, 0, 1).unwrap();
+            assert_eq!(stats.searches, 3);
+            assert_eq!(stats.top_keywords.len(), 1);
+            assert_eq!(
+                (
+                    stats.top_keywords[0].keyword.as_str(),
+                    stats.top_keywords[0].searches
+                ),
+                ("芙莉莲", 2)
+            );
+            let age = &stats.rules[0];
+            assert_eq!(
+                (age.rule.as_str(), age.attempts, age.successes),
+                ("AGE", 3, 2)
+            );
+            assert_eq!(age.daily.len(), 2);
+            assert_eq!(age.daily[1].success_rate, 0.5);
+            assert_eq!(stats.rules[1].success_rate, 1.0);
+
+            // 只统计区间内的记录
+            assert_eq!(query_stats(&conn, 1, 11 * day, 10).unwrap().searches, 2);
+        }
+    }
+}
@@ -1,29 +1,198 @@
-use crate::config::CONFIG;
+use crate::config::{Reloadable, CONFIG};
+use futures::future::{BoxFuture, FutureExt, Shared};
 use once_cell::sync::Lazy;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
 use reqwest::{Client, Response};
 use std::collections::HashMap;
-use std::time::Duration;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 /// 创建 HTTP 客户端
 fn build_client(timeout_secs: u64) -> Client {
-    Client::builder()
+    client_builder(timeout_secs)
+        .build()
+        .expect("Failed to create HTTP client")
+}
+
+/// 只连接公网地址、不跟随重定向的客户端 (图片代理，由调用方逐跳校验重定向目标)
+fn build_public_client() -> Client {
+    client_builder(CONFIG.timeout_seconds)
+        .dns_resolver(PublicResolver)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Failed to create HTTP client")
+}
+
+/// 按当前配置的客户端设置 (超时、User-Agent、连接池、DNS 缓存、TLS 版本)
+fn client_builder(timeout_secs: u64) -> reqwest::ClientBuilder {
+    let mut builder = Client::builder()
         .timeout(Duration::from_secs(timeout_secs))
         .user_agent(&CONFIG.user_agent)
         .gzip(true)
         .brotli(true)
         .danger_accept_invalid_certs(true) // 某些站点证书有问题
-        .build()
-        .expect("Failed to create HTTP client")
+        // 空闲连接到期后关闭，新连接重新解析域名 (换 IP 的站点不会一直连旧地址)
+        .pool_idle_timeout(
+            (CONFIG.pool_idle_timeout_seconds > 0)
+                .then(|| Duration::from_secs(CONFIG.pool_idle_timeout_seconds)),
+        );
+
+    if CONFIG.pool_idle_timeout_seconds == 0 {
+        builder = builder.pool_max_idle_per_host(0);
+    }
+    if CONFIG.connect_timeout_seconds > 0 {
+        builder = builder.connect_timeout(Duration::from_secs(CONFIG.connect_timeout_seconds));
+    }
+    if CONFIG.dns_cache_seconds > 0 {
+        builder = builder.dns_resolver(CachingResolver {
+            ttl: Duration::from_secs(CONFIG.dns_cache_seconds),
+        });
+    }
+
+    // 最低 TLS 版本与证书校验相互独立: 即使跳过证书校验，低于该版本的握手也会被拒绝
+    if let Some(version) = CONFIG.min_tls_version.as_deref().and_then(parse_tls_version) {
+        builder = builder.min_tls_version(version);
+    }
+
+    builder
+}
+
+/// 解析 TLS 版本 ("1.0" / "1.1" / "1.2" / "1.3")
+fn parse_tls_version(value: &str) -> Option<reqwest::tls::Version> {
+    match value.trim() {
+        "1.0" => Some(reqwest::tls::Version::TLS_1_0),
+        "1.1" => Some(reqwest::tls::Version::TLS_1_1),
+        "1.2" => Some(reqwest::tls::Version::TLS_1_2),
+        "1.3" => Some(reqwest::tls::Version::TLS_1_3),
+        _ => None,
+    }
+}
+
+/// 域名 -> 解析时间与地址
+type DnsEntries = HashMap<String, (Instant, Vec<SocketAddr>)>;
+
+/// DNS 缓存
+static DNS_CACHE: Lazy<Mutex<DnsEntries>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 带有效期的 DNS 解析器 (DNS_CACHE_SECONDS > 0 时使用)
+///
+/// reqwest 默认每次新建连接都通过系统解析器 (getaddrinfo) 查询，自身不缓存；
+/// 本缓存用于减少频繁建连时的解析次数，过期后重新查询
+struct CachingResolver {
+    ttl: Duration,
+}
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_ascii_lowercase();
+        let ttl = self.ttl;
+        Box::pin(async move {
+            if let Some(addrs) = cached_addrs(&host, ttl, Instant::now()) {
+                return Ok(Box::new(addrs.into_iter()) as Addrs);
+            }
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .collect();
+            dns_cache().insert(host, (Instant::now(), addrs.clone()));
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// 只返回公网地址的 DNS 解析器 (解析到回环、内网、链路本地等地址时失败，防止借代理访问内网)
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .collect();
+            if addrs.is_empty() || addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+                return Err(Box::new(NonPublicAddress(host)) as Box<dyn std::error::Error + Send + Sync>);
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// 域名解析到了非公网地址 (PUBLIC_CLIENT 拒绝连接)
+#[derive(Debug, Error)]
+#[error("{0} resolves to a non-public address")]
+pub struct NonPublicAddress(pub String);
+
+/// 是否为公网地址 (排除回环、内网、链路本地、CGNAT、组播、未指定等地址)
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+fn dns_cache() -> std::sync::MutexGuard<'static, DnsEntries> {
+    DNS_CACHE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 未过期的缓存地址
+fn cached_addrs(host: &str, ttl: Duration, now: Instant) -> Option<Vec<SocketAddr>> {
+    dns_cache()
+        .get(host)
+        .filter(|(resolved, addrs)| now.duration_since(*resolved) < ttl && !addrs.is_empty())
+        .map(|(_, addrs)| addrs.clone())
+}
+
+/// 清理过期的 DNS 缓存，返回清理的条目数 (由后台任务定期调用)
+pub fn purge_dns_cache() -> usize {
+    let ttl = Duration::from_secs(CONFIG.dns_cache_seconds);
+    let now = Instant::now();
+    let mut cache = dns_cache();
+    let before = cache.len();
+    cache.retain(|_, (resolved, _)| now.duration_since(*resolved) < ttl);
+    before - cache.len()
 }
 
 /// 全局 HTTP 客户端
-pub static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| build_client(CONFIG.timeout_seconds));
+pub static HTTP_CLIENT: Reloadable<Client> = Reloadable::new(|| build_client(CONFIG.timeout_seconds));
 
 /// 用于重试的 HTTP 客户端 (更长超时)
-static RETRY_CLIENT: Lazy<Client> = Lazy::new(|| build_client(CONFIG.retry_timeout_seconds));
+static RETRY_CLIENT: Reloadable<Client> = Reloadable::new(|| build_client(CONFIG.retry_timeout_seconds));
 
-#[derive(Debug, Error)]
+/// 只连接公网地址、不跟随重定向的 HTTP 客户端 (图片代理)
+pub static PUBLIC_CLIENT: Reloadable<Client> = Reloadable::new(build_public_client);
+
+/// 按当前配置重建客户端 (配置重载后超时与 User-Agent 立即生效，进行中的请求不受影响)
+pub fn rebuild_clients() {
+    HTTP_CLIENT.replace(build_client(CONFIG.timeout_seconds));
+    RETRY_CLIENT.replace(build_client(CONFIG.retry_timeout_seconds));
+    PUBLIC_CLIENT.replace(build_public_client());
+}
+
+#[derive(Debug, Clone, Error)]
 pub enum HttpClientError {
     #[error("请求超时")]
     Timeout,
@@ -31,45 +200,219 @@ pub enum HttpClientError {
     RequestFailed(String),
     #[error("响应异常状态码: {0}")]
     BadStatus(u16),
+    /// 403，多为 IP/UA 被屏蔽
+    #[error("源站拒绝访问 (403)")]
+    Forbidden,
+    /// 429，附带 Retry-After 的秒数 (响应中有时)
+    #[error("源站限流 (429)")]
+    RateLimited(Option<u64>),
+    /// 连接被重置或在响应完成前关闭
+    #[error("连接中断: {0}")]
+    ConnectionReset(String),
 }
 
-/// 判断是否应该使用反代重试
-fn should_retry(error: &HttpClientError) -> bool {
+impl HttpClientError {
+    /// 按状态码归类非 2xx 响应 (`retry_after` 为响应的 Retry-After 头，仅 429 使用)
+    pub fn from_status(status: u16, retry_after: Option<&str>) -> Self {
+        match status {
+            403 => Self::Forbidden,
+            429 => Self::RateLimited(retry_after.and_then(parse_retry_after)),
+            status => Self::BadStatus(status),
+        }
+    }
+
+    /// 对应的响应状态码 (没有收到响应时为 None)
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::BadStatus(status) => Some(*status),
+            Self::Forbidden => Some(403),
+            Self::RateLimited(_) => Some(429),
+            Self::Timeout | Self::RequestFailed(_) | Self::ConnectionReset(_) => None,
+        }
+    }
+}
+
+/// 非 2xx 响应对应的错误
+fn status_error(response: &Response) -> HttpClientError {
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok());
+    HttpClientError::from_status(response.status().as_u16(), retry_after)
+}
+
+/// 发送请求失败对应的错误 (超时、连接中断或其他网络问题)
+fn send_error(e: reqwest::Error) -> HttpClientError {
+    if e.is_timeout() {
+        HttpClientError::Timeout
+    } else if is_connection_reset(&e) {
+        HttpClientError::ConnectionReset(e.to_string())
+    } else {
+        HttpClientError::RequestFailed(e.to_string())
+    }
+}
+
+/// 错误链中是否有连接被重置/中止的 IO 错误，或 hyper 的「响应完成前连接关闭」
+fn is_connection_reset(e: &reqwest::Error) -> bool {
+    use std::io::ErrorKind;
+    let mut source = std::error::Error::source(e);
+    while let Some(error) = source {
+        if let Some(io) = error.downcast_ref::<std::io::Error>() {
+            if matches!(
+                io.kind(),
+                ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::UnexpectedEof
+            ) {
+                return true;
+            }
+        }
+        // hyper 不导出该错误类型，只能按消息判断
+        if error.to_string().contains("connection closed before message completed") {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+/// 解析 Retry-After (秒数或 HTTP 日期)，返回距现在的秒数
+fn parse_retry_after(value: &str) -> Option<u64> {
+    let value = value.trim();
+    value.parse().ok().or_else(|| {
+        let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+        Some((at.timestamp() - chrono::Utc::now().timestamp()).max(0) as u64)
+    })
+}
+
+/// 判断失败的请求是否应该使用反代重试: 网络问题与 5xx 总是重试，
+/// 403/429 (多为反爬) 按 PROXY_RETRY_BLOCKED 决定
+fn should_retry(error: &HttpClientError, retry_blocked: bool) -> bool {
+    match error {
+        HttpClientError::Timeout
+        | HttpClientError::RequestFailed(_)
+        | HttpClientError::ConnectionReset(_) => true,
+        HttpClientError::Forbidden | HttpClientError::RateLimited(_) => retry_blocked,
+        HttpClientError::BadStatus(status) => (500..=599).contains(status),
+    }
+}
+
+/// 直连重试的首次退避时间 (之后每次翻倍: 200ms、400ms、800ms…)
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// 是否为值得稍后直连重试的临时故障: 超时、连接中断与 502/503/504
+/// (404 等其他状态码立即失败)
+fn is_transient(error: &HttpClientError) -> bool {
     matches!(
         error,
         HttpClientError::Timeout
-            | HttpClientError::RequestFailed(_)
+            | HttpClientError::ConnectionReset(_)
+            | HttpClientError::BadStatus(502..=504)
     )
 }
 
-/// 判断状态码是否应该重试
-fn should_retry_status(status: u16) -> bool {
-    // 403, 404, 500+ 等可能是反爬，尝试反代
-    matches!(status, 403 | 429 | 500..=599)
+tokio::task_local! {
+    /// 当前搜索的截止时间 (时间预算与 SEARCH_DEADLINE_SECONDS 中较早者)，决定超时后是否还值得重试
+    static DEADLINE: Option<Instant>;
+}
+
+/// 在截止时间内执行请求 (`deadline` 为 None 时不限制)
+pub async fn with_deadline<F: std::future::Future>(deadline: Option<Instant>, future: F) -> F::Output {
+    DEADLINE.scope(deadline, future).await
+}
+
+/// 距截止时间的剩余时间 (未设置截止时间时为 None)
+fn remaining_time() -> Option<Duration> {
+    DEADLINE
+        .try_with(|deadline| deadline.map(|d| d.saturating_duration_since(Instant::now())))
+        .ok()
+        .flatten()
+}
+
+/// 第 `attempt` 次失败后是否直连重试: 须为临时故障，且剩余时间容得下退避等待；
+/// 超时还须容得下完整的下一次请求 (`timeout` 为 None 时按全局超时计算)，否则交给反代或直接失败
+fn should_retry_direct(
+    error: &HttpClientError,
+    attempt: u32,
+    timeout: Option<Duration>,
+    remaining: Option<Duration>,
+) -> bool {
+    if !is_transient(error) {
+        return false;
+    }
+    let Some(remaining) = remaining else {
+        return true;
+    };
+    let mut needed = retry_backoff(attempt);
+    if matches!(error, HttpClientError::Timeout) {
+        needed += timeout.unwrap_or(Duration::from_secs(CONFIG.timeout_seconds));
+    }
+    remaining > needed
+}
+
+/// 第 `attempt` 次直连重试前的退避时间 (从 0 开始)
+fn retry_backoff(attempt: u32) -> Duration {
+    RETRY_BACKOFF * 2u32.saturating_pow(attempt.min(10))
+}
+
+/// 直连重试前等待退避时间
+async fn wait_before_retry(url: &str, error: &HttpClientError, attempt: u32) {
+    let backoff = retry_backoff(attempt);
+    tracing::debug!("{} 临时失败 ({})，{:?} 后重试", url, error, backoff);
+    tokio::time::sleep(backoff).await;
+}
+
+/// 一次逻辑请求的尝试记录 (直连与反代重试合计)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestTrace {
+    /// 最后一次收到响应的状态码 (没有收到响应时为 None)
+    pub status: Option<u16>,
+    /// 实际发出的请求次数
+    pub attempts: u8,
+    /// 发出请求到收到响应头的累计耗时 (含 DNS、建连与 TLS 握手，reqwest 不单独报告这些阶段)
+    pub ttfb: Duration,
+    /// 读取响应体的累计耗时
+    pub body: Duration,
 }
 
 /// GET 请求 (内部实现)
-async fn get_internal(client: &Client, url: &str, referer: Option<&str>) -> Result<Response, HttpClientError> {
+async fn get_internal(
+    client: &Client,
+    url: &str,
+    referer: Option<&str>,
+    cookie: Option<&str>,
+    timeout: Option<Duration>,
+    trace: &mut RequestTrace,
+) -> Result<Response, HttpClientError> {
     let mut req = client.get(url);
+
+    // 单个请求的超时优先于客户端的全局超时
+    if let Some(timeout) = timeout {
+        req = req.timeout(timeout);
+    }
     
     if let Some(ref_url) = referer {
         req = req.header("Referer", ref_url);
     }
+
+    if let Some(cookie) = cookie {
+        req = req.header("Cookie", cookie);
+    }
     
     req = req
         .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
         .header("Connection", "keep-alive");
 
-    let response = req.send().await.map_err(|e| {
-        if e.is_timeout() {
-            HttpClientError::Timeout
-        } else {
-            HttpClientError::RequestFailed(e.to_string())
-        }
-    })?;
+    trace.attempts = trace.attempts.saturating_add(1);
+    let sent = Instant::now();
+    let response = req.send().await.map_err(send_error);
+    trace.ttfb += sent.elapsed();
+    let response = response?;
+    trace.status = Some(response.status().as_u16());
 
     if !response.status().is_success() {
-        return Err(HttpClientError::BadStatus(response.status().as_u16()));
+        return Err(status_error(&response));
     }
 
     Ok(response)
@@ -77,20 +420,48 @@ async fn get_internal(client: &Client, url: &str, referer: Option<&str>) -> Resu
 
 /// GET 请求 (自动重试反代)
 pub async fn get(url: &str, referer: Option<&str>) -> Result<Response, HttpClientError> {
-    // 第一次尝试直连
-    match get_internal(&HTTP_CLIENT, url, referer).await {
+    get_with_cookie(url, referer, None).await
+}
+
+/// 携带 Cookie 的 GET 请求 (自动重试反代)
+pub async fn get_with_cookie(
+    url: &str,
+    referer: Option<&str>,
+    cookie: Option<&str>,
+) -> Result<Response, HttpClientError> {
+    get_with_trace(url, referer, cookie, None, 0, &mut RequestTrace::default()).await
+}
+
+/// 携带 Cookie 的 GET 请求，并把尝试次数与状态码记录到 `trace` (自动重试反代)；
+/// `timeout` 同时用于直连与反代重试，为 None 时使用各客户端的全局超时；
+/// 直连的临时故障先按指数退避重试 `retries` 次，仍失败再使用反代
+async fn get_with_trace(
+    url: &str,
+    referer: Option<&str>,
+    cookie: Option<&str>,
+    timeout: Option<Duration>,
+    retries: u32,
+    trace: &mut RequestTrace,
+) -> Result<Response, HttpClientError> {
+    // 先尝试直连
+    let mut attempt = 0;
+    let direct = loop {
+        match get_internal(&HTTP_CLIENT, url, referer, cookie, timeout, trace).await {
+            Err(e) if attempt < retries && should_retry_direct(&e, attempt, timeout, remaining_time()) => {
+                wait_before_retry(url, &e, attempt).await;
+                attempt += 1;
+            }
+            result => break result,
+        }
+    };
+    match direct {
         Ok(resp) => Ok(resp),
         Err(e) => {
             // 网络问题或反爬状态码，尝试反代
-            let should_use_proxy = match &e {
-                HttpClientError::BadStatus(status) => should_retry_status(*status),
-                _ => should_retry(&e),
-            };
-
-            if should_use_proxy {
+            if should_retry(&e, CONFIG.proxy_retry_blocked) {
                 let proxy_url = format!("{}{}", CONFIG.proxy_prefix, url);
                 tracing::debug!("使用反代重试: {}", url);
-                get_internal(&RETRY_CLIENT, &proxy_url, referer).await
+                get_internal(&RETRY_CLIENT, &proxy_url, referer, cookie, timeout, trace).await
             } else {
                 Err(e)
             }
@@ -98,13 +469,274 @@ pub async fn get(url: &str, referer: Option<&str>) -> Result<Response, HttpClien
     }
 }
 
-/// GET 请求并返回文本
-pub async fn get_text(url: &str, referer: Option<&str>) -> Result<String, HttpClientError> {
-    let response = get(url, referer).await?;
-    response
-        .text()
+/// 读取响应文本 (编码识别见 [`decode_body`])
+async fn read_text(response: Response) -> Result<String, HttpClientError> {
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let bytes = response
+        .bytes()
         .await
-        .map_err(|e| HttpClientError::RequestFailed(e.to_string()))
+        .map_err(|e| HttpClientError::RequestFailed(e.to_string()))?;
+    Ok(decode_body(&bytes, content_type.as_deref()))
+}
+
+/// 读取响应文本，并把读取耗时累计到 `trace`
+async fn read_text_traced(
+    response: Response,
+    trace: &mut RequestTrace,
+) -> Result<String, HttpClientError> {
+    let started = Instant::now();
+    let text = read_text(response).await;
+    trace.body += started.elapsed();
+    text
+}
+
+/// 解码响应体: BOM 优先，其次 Content-Type 的 charset，再次 HTML 开头的 `<meta charset>`，
+/// 都没有时按 UTF-8 解码 (非法字节替换为 U+FFFD)
+pub fn decode_body(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = encoding_rs::Encoding::for_bom(bytes)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| content_type.and_then(charset_param).and_then(lookup_encoding))
+        // 能读出 ASCII 的 meta 声明说明不是 UTF-16，按 HTML 规范改用 UTF-8
+        .or_else(|| {
+            sniff_meta_charset(bytes)
+                .and_then(lookup_encoding)
+                .map(|encoding| encoding.output_encoding())
+        })
+        .unwrap_or(encoding_rs::UTF_8);
+    // decode 会自行识别并去掉 BOM
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+fn lookup_encoding(label: &str) -> Option<&'static encoding_rs::Encoding> {
+    encoding_rs::Encoding::for_label(label.trim().as_bytes())
+}
+
+/// Content-Type 中的 charset 参数
+fn charset_param(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// 在 HTML 前 1024 字节中查找 `charset=` 声明 (`<meta charset>` 与 `http-equiv` 两种写法)
+fn sniff_meta_charset(bytes: &[u8]) -> Option<&str> {
+    let head = &bytes[..bytes.len().min(1024)];
+    let lower = head.to_ascii_lowercase();
+    let start = lower.windows(8).position(|w| w == b"charset=")? + 8;
+    let rest = &head[start..];
+    let rest = rest.strip_prefix(b"\"").or_else(|| rest.strip_prefix(b"'")).unwrap_or(rest);
+    let end = rest
+        .iter()
+        .position(|b| matches!(b, b'"' | b'\'' | b';' | b'>' | b'/') || b.is_ascii_whitespace())
+        .unwrap_or(rest.len());
+    std::str::from_utf8(&rest[..end]).ok().filter(|s| !s.is_empty())
+}
+
+/// 一次 GET 文本请求的结果与尝试记录
+type FetchResult = (Result<String, HttpClientError>, RequestTrace);
+
+/// 进行中的请求 (可被多个调用方同时等待)
+type Flight = Shared<BoxFuture<'static, FetchResult>>;
+
+/// 进行中的请求与等待它的调用方数
+struct FlightEntry {
+    id: u64,
+    flight: Flight,
+    waiters: usize,
+}
+
+/// (地址, Referer, Cookie, 超时) -> 进行中的请求；请求完成或等待者全部离开后移除，不缓存结果
+static IN_FLIGHT: Lazy<Mutex<HashMap<String, FlightEntry>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+static NEXT_FLIGHT: AtomicU64 = AtomicU64::new(0);
+
+fn in_flight() -> std::sync::MutexGuard<'static, HashMap<String, FlightEntry>> {
+    IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 等待合并请求的调用方: drop 时 (完成或被取消) 减少等待者计数，
+/// 最后一个等待者离开时移除尚未完成的请求，请求随最后一个句柄一起中止
+struct FlightWaiter {
+    key: String,
+    id: u64,
+}
+
+impl Drop for FlightWaiter {
+    fn drop(&mut self) {
+        let mut flights = in_flight();
+        let Some(entry) = flights.get_mut(&self.key).filter(|entry| entry.id == self.id) else {
+            return;
+        };
+        entry.waiters -= 1;
+        if entry.waiters == 0 {
+            let abandoned = flights.remove(&self.key);
+            // 在锁外中止请求
+            drop(flights);
+            drop(abandoned);
+        }
+    }
+}
+
+/// GET 文本请求 (单飞合并): 相同的请求正在进行时等待其结果 (含错误)，不再重复请求；
+/// 未启用 COALESCE_REQUESTS 时直接请求
+async fn get_text_coalesced(
+    url: &str,
+    referer: Option<&str>,
+    cookie: Option<&str>,
+    timeout: Option<Duration>,
+    retries: u32,
+) -> FetchResult {
+    let mut key = format!(
+        "{}\n{}\n{}",
+        url,
+        referer.unwrap_or_default(),
+        cookie.unwrap_or_default()
+    );
+    // 超时不同的请求不合并 (避免短超时的调用方等待长超时的请求)
+    if let Some(timeout) = timeout {
+        key.push_str(&format!("\n{}", timeout.as_millis()));
+    }
+    if retries > 0 {
+        key.push_str(&format!("\nretry:{}", retries));
+    }
+    let url = url.to_string();
+    let referer = referer.map(str::to_string);
+    let cookie = cookie.map(str::to_string);
+    let fetch = async move {
+        let mut trace = RequestTrace::default();
+        let result = match get_with_trace(
+            &url,
+            referer.as_deref(),
+            cookie.as_deref(),
+            timeout,
+            retries,
+            &mut trace,
+        )
+        .await
+        {
+            Ok(response) => read_text_traced(response, &mut trace).await,
+            Err(e) => Err(e),
+        };
+        (result, trace)
+    };
+    if !CONFIG.coalesce_requests {
+        return fetch.await;
+    }
+
+    let (flight, _waiter) = {
+        let mut flights = in_flight();
+        let entry = match flights.get_mut(&key) {
+            Some(entry) => entry,
+            None => {
+                let id = NEXT_FLIGHT.fetch_add(1, Ordering::Relaxed);
+                let cleanup_key = key.clone();
+                let flight = async move {
+                    let result = fetch.await;
+                    // 只移除自己 (完成前被替换的条目不受影响)
+                    let mut flights = in_flight();
+                    if flights.get(&cleanup_key).is_some_and(|entry| entry.id == id) {
+                        flights.remove(&cleanup_key);
+                    }
+                    result
+                }
+                .boxed()
+                .shared();
+                let entry = FlightEntry {
+                    id,
+                    flight,
+                    waiters: 0,
+                };
+                flights.entry(key.clone()).or_insert(entry)
+            }
+        };
+        entry.waiters += 1;
+        let waiter = FlightWaiter { key, id: entry.id };
+        (entry.flight.clone(), waiter)
+    };
+    flight.await
+}
+
+/// 是否有以 `url_prefix` 开头的地址的进行中合并请求
+#[cfg(all(test, feature = "scraper"))]
+pub(crate) fn is_in_flight(url_prefix: &str) -> bool {
+    in_flight().keys().any(|key| key.starts_with(url_prefix))
+}
+
+/// GET 请求并返回文本 (`timeout` 为 None 时使用全局超时)
+pub async fn get_text(
+    url: &str,
+    referer: Option<&str>,
+    timeout: Option<Duration>,
+) -> Result<String, HttpClientError> {
+    get_text_coalesced(url, referer, None, timeout, 0).await.0
+}
+
+/// 携带 Cookie 的 GET 请求并返回文本
+pub async fn get_text_with_cookie(
+    url: &str,
+    referer: Option<&str>,
+    cookie: Option<&str>,
+) -> Result<String, HttpClientError> {
+    get_text_coalesced(url, referer, cookie, None, 0).await.0
+}
+
+/// 携带 Cookie 的 GET 请求并返回文本，同时记录尝试次数与状态码
+/// (合并的请求记录的是实际发出请求的那一次)
+pub async fn get_text_traced(
+    url: &str,
+    referer: Option<&str>,
+    cookie: Option<&str>,
+    timeout: Option<Duration>,
+    trace: &mut RequestTrace,
+) -> Result<String, HttpClientError> {
+    let (result, fetched) = get_text_coalesced(url, referer, cookie, timeout, 0).await;
+    *trace = fetched;
+    result
+}
+
+/// 同 [`get_text_traced`]，直连遇到临时故障 (超时、502/503/504、连接中断) 时
+/// 按指数退避重试 HTTP_MAX_RETRIES 次；404 等其他错误立即返回
+pub async fn get_text_with_retry(
+    url: &str,
+    referer: Option<&str>,
+    cookie: Option<&str>,
+    timeout: Option<Duration>,
+    trace: &mut RequestTrace,
+) -> Result<String, HttpClientError> {
+    let (result, fetched) =
+        get_text_coalesced(url, referer, cookie, timeout, CONFIG.http_max_retries).await;
+    *trace = fetched;
+    result
+}
+
+/// GET 请求并返回文本与响应设置的 Cookie (`name=value; ...`，供同一流程的后续请求携带)
+pub async fn get_page(
+    url: &str,
+    referer: Option<&str>,
+    timeout: Option<Duration>,
+) -> Result<(String, Option<String>), HttpClientError> {
+    let response =
+        get_with_trace(url, referer, None, timeout, 0, &mut RequestTrace::default()).await?;
+    let cookies: Vec<String> = response
+        .headers()
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .filter_map(|v| v.split(';').next())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    let text = read_text(response).await?;
+    Ok((text, (!cookies.is_empty()).then(|| cookies.join("; "))))
 }
 
 /// GET 请求并返回 JSON
@@ -124,60 +756,118 @@ pub async fn get_json<T: serde::de::DeserializeOwned>(
 async fn post_form_internal(
     client: &Client,
     url: &str,
-    form: &HashMap<String, String>,
+    form: &[(String, String)],
     referer: Option<&str>,
+    cookie: Option<&str>,
+    timeout: Option<Duration>,
+    trace: &mut RequestTrace,
 ) -> Result<Response, HttpClientError> {
     let mut req = client.post(url).form(form);
 
+    if let Some(timeout) = timeout {
+        req = req.timeout(timeout);
+    }
+
     if let Some(ref_url) = referer {
         req = req.header("Referer", ref_url);
     }
 
+    if let Some(cookie) = cookie {
+        req = req.header("Cookie", cookie);
+    }
+
     req = req
         .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
         .header("Connection", "keep-alive");
 
-    let response = req.send().await.map_err(|e| {
-        if e.is_timeout() {
-            HttpClientError::Timeout
-        } else {
-            HttpClientError::RequestFailed(e.to_string())
-        }
-    })?;
+    trace.attempts = trace.attempts.saturating_add(1);
+    let sent = Instant::now();
+    let response = req.send().await.map_err(send_error);
+    trace.ttfb += sent.elapsed();
+    let response = response?;
+    trace.status = Some(response.status().as_u16());
 
     if !response.status().is_success() {
-        return Err(HttpClientError::BadStatus(response.status().as_u16()));
+        return Err(status_error(&response));
     }
 
     Ok(response)
 }
 
-/// POST 请求 (Form body) 并返回文本 (自动重试反代)
+/// POST 请求 (Form body) 并返回文本 (自动重试反代)，字段按给定顺序编码
 pub async fn post_form_text(
     url: &str,
-    form: &HashMap<String, String>,
+    form: &[(String, String)],
     referer: Option<&str>,
+    cookie: Option<&str>,
+    timeout: Option<Duration>,
 ) -> Result<String, HttpClientError> {
-    // 第一次尝试直连
-    match post_form_internal(&HTTP_CLIENT, url, form, referer).await {
-        Ok(resp) => resp
-            .text()
-            .await
-            .map_err(|e| HttpClientError::RequestFailed(e.to_string())),
+    post_form_text_traced(url, form, referer, cookie, timeout, &mut RequestTrace::default()).await
+}
+
+/// POST 请求 (Form body) 并返回文本，同时记录尝试次数与状态码 (自动重试反代)
+pub async fn post_form_text_traced(
+    url: &str,
+    form: &[(String, String)],
+    referer: Option<&str>,
+    cookie: Option<&str>,
+    timeout: Option<Duration>,
+    trace: &mut RequestTrace,
+) -> Result<String, HttpClientError> {
+    post_form_text_retrying(url, form, referer, cookie, timeout, 0, trace).await
+}
+
+/// 同 [`post_form_text_traced`]，直连的临时故障按指数退避重试 HTTP_MAX_RETRIES 次
+pub async fn post_form_text_with_retry(
+    url: &str,
+    form: &[(String, String)],
+    referer: Option<&str>,
+    cookie: Option<&str>,
+    timeout: Option<Duration>,
+    trace: &mut RequestTrace,
+) -> Result<String, HttpClientError> {
+    let retries = CONFIG.http_max_retries;
+    post_form_text_retrying(url, form, referer, cookie, timeout, retries, trace).await
+}
+
+/// POST 文本请求: 直连的临时故障先重试 `retries` 次，仍失败再使用反代
+async fn post_form_text_retrying(
+    url: &str,
+    form: &[(String, String)],
+    referer: Option<&str>,
+    cookie: Option<&str>,
+    timeout: Option<Duration>,
+    retries: u32,
+    trace: &mut RequestTrace,
+) -> Result<String, HttpClientError> {
+    let mut attempt = 0;
+    let direct = loop {
+        match post_form_internal(&HTTP_CLIENT, url, form, referer, cookie, timeout, trace).await {
+            Err(e) if attempt < retries && should_retry_direct(&e, attempt, timeout, remaining_time()) => {
+                wait_before_retry(url, &e, attempt).await;
+                attempt += 1;
+            }
+            result => break result,
+        }
+    };
+    match direct {
+        Ok(resp) => read_text_traced(resp, trace).await,
         Err(e) => {
             // 网络问题或反爬状态码，尝试反代
-            let should_use_proxy = match &e {
-                HttpClientError::BadStatus(status) => should_retry_status(*status),
-                _ => should_retry(&e),
-            };
-
-            if should_use_proxy {
+            if should_retry(&e, CONFIG.proxy_retry_blocked) {
                 let proxy_url = format!("{}{}", CONFIG.proxy_prefix, url);
                 tracing::debug!("使用反代重试 POST: {}", url);
-                let resp = post_form_internal(&RETRY_CLIENT, &proxy_url, form, referer).await?;
-                resp.text()
-                    .await
-                    .map_err(|e| HttpClientError::RequestFailed(e.to_string()))
+                let resp = post_form_internal(
+                    &RETRY_CLIENT,
+                    &proxy_url,
+                    form,
+                    referer,
+                    cookie,
+                    timeout,
+                    trace,
+                )
+                .await?;
+                read_text_traced(resp, trace).await
             } else {
                 Err(e)
             }
@@ -198,17 +888,270 @@ pub async fn post_json<T: serde::Serialize>(
         req = req.header("Referer", ref_url);
     }
 
-    let response = req.send().await.map_err(|e| {
-        if e.is_timeout() {
-            HttpClientError::Timeout
-        } else {
-            HttpClientError::RequestFailed(e.to_string())
-        }
-    })?;
+    let response = req.send().await.map_err(send_error)?;
 
     if !response.status().is_success() {
-        return Err(HttpClientError::BadStatus(response.status().as_u16()));
+        return Err(status_error(&response));
     }
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_utf16le_bom_without_charset() {
+        let mut body = vec![0xFF, 0xFE];
+        body.extend("<a>葬送的芙莉莲</a>".encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(decode_body(&body, Some("text/html")), "<a>葬送的芙莉莲</a>");
+        // BOM 优先于 (错误的) 声明
+        assert_eq!(
+            decode_body(&body, Some("text/html; charset=gbk")),
+            "<a>葬送的芙莉莲</a>"
+        );
+    }
+
+    #[test]
+    fn test_decode_header_and_meta_charset() {
+        let (gbk, _, _) = encoding_rs::GBK.encode("芙莉莲");
+        assert_eq!(decode_body(&gbk, Some("text/html; charset=\"GBK\"")), "芙莉莲");
+
+        let mut html = b"<html><head><meta charset=\"gb2312\"></head>".to_vec();
+        html.extend_from_slice(&gbk);
+        assert!(decode_body(&html, None).ends_with("芙莉莲"));
+
+        let utf8_bom = [&[0xEF, 0xBB, 0xBF][..], "芙莉莲".as_bytes()].concat();
+        assert_eq!(decode_body(&utf8_bom, None), "芙莉莲");
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_dns_cache_entries_expire() {
+        let addr: SocketAddr = "203.0.113.7:0".parse().unwrap();
+        let resolved = Instant::now();
+        dns_cache().insert("mirror.test".to_string(), (resolved, vec![addr]));
+
+        let ttl = Duration::from_secs(60);
+        assert_eq!(cached_addrs("mirror.test", ttl, resolved), Some(vec![addr]));
+        assert_eq!(cached_addrs("mirror.test", ttl, resolved + ttl), None);
+        assert_eq!(cached_addrs("other.test", ttl, resolved), None);
+    }
+
+    #[test]
+    fn test_blocked_statuses_are_distinct() {
+        assert!(matches!(HttpClientError::from_status(403, None), HttpClientError::Forbidden));
+        assert!(matches!(
+            HttpClientError::from_status(429, Some(" 120 ")),
+            HttpClientError::RateLimited(Some(120))
+        ));
+        assert!(matches!(
+            HttpClientError::from_status(429, Some("Wed, 21 Oct 2015 07:28:00 GMT")),
+            HttpClientError::RateLimited(Some(0))
+        ));
+        assert!(matches!(HttpClientError::from_status(429, None), HttpClientError::RateLimited(None)));
+        assert!(matches!(HttpClientError::from_status(404, Some("5")), HttpClientError::BadStatus(404)));
+        assert_eq!(HttpClientError::from_status(429, None).status(), Some(429));
+        assert_eq!(HttpClientError::Timeout.status(), None);
+
+        // 403/429 是否走反代由配置决定，其余按错误类型
+        let forbidden = HttpClientError::Forbidden;
+        assert!(should_retry(&forbidden, true));
+        assert!(!should_retry(&forbidden, false));
+        assert!(!should_retry(&HttpClientError::RateLimited(None), false));
+        assert!(should_retry(&HttpClientError::BadStatus(502), false));
+        assert!(!should_retry(&HttpClientError::BadStatus(404), true));
+        assert!(should_retry(&HttpClientError::Timeout, false));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_identical_gets_share_one_fetch() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("<a>葬送的芙莉莲</a>")
+                    .set_delay(Duration::from_millis(200)),
+            )
+            .mount(&server)
+            .await;
+        // 400 不走反代重试，便于统计请求次数
+        Mock::given(method("GET"))
+            .and(path("/broken"))
+            .respond_with(ResponseTemplate::new(400).set_delay(Duration::from_millis(200)))
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/search", server.uri());
+        let texts = futures::future::join_all((0..5).map(|_| get_text(&url, None, None))).await;
+        assert!(texts.iter().all(|t| t.as_deref().ok() == Some("<a>葬送的芙莉莲</a>")));
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+        assert!(!in_flight().contains_key(&format!("{}\n\n", url)));
+
+        // 等待者全部取消: 立即移除并中止请求，再次请求重新发出
+        let cancelled = tokio::time::timeout(Duration::from_millis(50), get_text(&url, None, None)).await;
+        assert!(cancelled.is_err());
+        assert!(!in_flight().contains_key(&format!("{}\n\n", url)));
+        assert_eq!(get_text(&url, None, None).await.unwrap(), "<a>葬送的芙莉莲</a>");
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+
+        // 错误同样共享，完成后再次请求会重新发出
+        let broken = format!("{}/broken", server.uri());
+        let results = futures::future::join_all((0..3).map(|_| async {
+            let mut trace = RequestTrace::default();
+            let result = get_text_traced(&broken, None, None, None, &mut trace).await;
+            (result, trace)
+        }))
+        .await;
+        for (result, trace) in &results {
+            assert!(matches!(result, Err(HttpClientError::BadStatus(400))));
+            assert_eq!(trace.status, Some(400));
+        }
+        assert_eq!(server.received_requests().await.unwrap().len(), 4);
+        get_text(&broken, None, None).await.unwrap_err();
+        assert_eq!(server.received_requests().await.unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_overrides_client_default() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/slow"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("ok")
+                    .set_delay(Duration::from_millis(300)),
+            )
+            .mount(&server)
+            .await;
+
+        // 直接调用单次请求，不走反代重试
+        let url = format!("{}/slow", server.uri());
+        let mut trace = RequestTrace::default();
+        let short = Some(Duration::from_millis(50));
+        let fast = get_internal(&HTTP_CLIENT, &url, None, None, short, &mut trace).await;
+        assert!(matches!(fast, Err(HttpClientError::Timeout)));
+        let long = Some(Duration::from_secs(5));
+        let slow = get_internal(&HTTP_CLIENT, &url, None, None, long, &mut trace).await;
+        assert_eq!(slow.unwrap().text().await.unwrap(), "ok");
+        assert_eq!(trace.attempts, 2);
+    }
+
+    #[test]
+    fn test_direct_retry_respects_remaining_time() {
+        let timeout = Some(Duration::from_secs(5));
+        let reset = HttpClientError::ConnectionReset("reset".into());
+        let timed_out = HttpClientError::Timeout;
+        // 不限制截止时间: 临时故障都重试
+        assert!(should_retry_direct(&timed_out, 0, timeout, None));
+        assert!(!should_retry_direct(&HttpClientError::BadStatus(404), 0, timeout, None));
+        // 剩余时间容不下下一次完整请求: 超时不再重试，连接中断与 502/503/504 仍重试
+        let remaining = Some(Duration::from_secs(3));
+        assert!(!should_retry_direct(&timed_out, 0, timeout, remaining));
+        assert!(should_retry_direct(&reset, 0, timeout, remaining));
+        assert!(should_retry_direct(&HttpClientError::BadStatus(503), 0, timeout, remaining));
+        assert!(should_retry_direct(&timed_out, 0, timeout, Some(Duration::from_secs(6))));
+        // 退避等待也要在截止时间之前
+        assert!(!should_retry_direct(&reset, 0, timeout, Some(Duration::from_millis(100))));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_is_scoped_to_the_search() {
+        assert_eq!(remaining_time(), None);
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let remaining = with_deadline(Some(deadline), async { remaining_time() }).await;
+        assert!(remaining.is_some_and(|r| r > Duration::from_secs(50)));
+        assert_eq!(with_deadline(None, async { remaining_time() }).await, None);
+    }
+
+    #[test]
+    fn test_transient_errors_and_backoff() {
+        assert!(is_transient(&HttpClientError::Timeout));
+        assert!(is_transient(&HttpClientError::ConnectionReset("reset".into())));
+        assert!(is_transient(&HttpClientError::BadStatus(503)));
+        assert!(!is_transient(&HttpClientError::BadStatus(404)));
+        assert!(!is_transient(&HttpClientError::BadStatus(500)));
+        assert!(!is_transient(&HttpClientError::RequestFailed("dns".into())));
+        let backoff: Vec<_> = (0..3).map(retry_backoff).collect();
+        assert_eq!(backoff, [200, 400, 800].map(Duration::from_millis));
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried_with_backoff() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(502))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("posted"))
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/flaky", server.uri());
+        let mut trace = RequestTrace::default();
+        let started = Instant::now();
+        let response = get_with_trace(&url, None, None, None, 2, &mut trace).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+        assert_eq!(trace.attempts, 3);
+        assert!(started.elapsed() >= Duration::from_millis(600));
+
+        let mut trace = RequestTrace::default();
+        let text = post_form_text_retrying(&url, &[], None, None, None, 1, &mut trace).await;
+        assert_eq!(text.unwrap(), "posted");
+        assert_eq!(trace.attempts, 2);
+
+        // 404 不重试
+        let missing = format!("{}/missing", server.uri());
+        let mut trace = RequestTrace::default();
+        let result = get_with_trace(&missing, None, None, None, 2, &mut trace).await;
+        assert!(matches!(result, Err(HttpClientError::BadStatus(404))));
+        assert_eq!(trace.attempts, 1);
+    }
+}
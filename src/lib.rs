@@ -0,0 +1,130 @@
+//! 在线动漫聚合搜索
+//!
+//! 规则驱动的多站点搜索引擎 (兼容 Kazumi 规则格式) 与 Bangumi API 客户端。
+//! 默认启用的 `server` feature 提供 HTTP 服务；仅作为库使用时可只启用需要的 feature，不引入 axum:
+//!
+//! ```toml
+//! anime-search-api = { version = "0.1", default-features = false, features = ["scraper", "bangumi"] }
+//! ```
+//!
+//! | Feature | 内容 |
+//! |---------|------|
+//! | `server` | HTTP 服务 |
+//! | `scraper` | 规则引擎、规则加载与更新 ([`Engine`], [`RuleSet`]) |
+//! | `bangumi` | Bangumi API 客户端 ([`bangumi::Client`]) |
+//! | `anilist` | AniList 元数据来源 (`anilist::Client`，与 Bangumi 同为 [`metadata::MetadataProvider`]，默认关闭) |
+//! | `frontend` | 内嵌前端页面 (`GET /`) |
+//! | `sqlite` | SQLite 持久化存储 ([`storage`]，默认关闭) |
+//! | `redis` | Redis 共享存储 ([`storage`]，多实例部署，优先于 SQLite，默认关闭) |
+//! | `danmaku` | 弹弹play 弹幕搜索与匹配 ([`danmaku`]，默认关闭) |
+//! | `client` | 本服务的 HTTP 客户端 (`client::ApiClient`，默认关闭) |
+//!
+//! 配置 (请求超时、User-Agent 等) 与服务端相同，从环境变量读取，见 [`config`]。
+//!
+//! # 使用单个规则搜索
+//!
+//! ```no_run
+//! use anime_search::{Engine, RuleSet};
+//!
+//! # async fn demo() {
+//! let engine = Engine::new(RuleSet::load("rules"));
+//! if let Some(rule) = engine.rules().get("AGE") {
+//!     let result = engine.search_rule(&rule, "葬送的芙莉莲").await;
+//!     for item in result.items {
+//!         println!("{} {}", item.name, item.url);
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! # 流式搜索多个规则
+//!
+//! 每项为一行 JSON 编码的 [`StreamEvent`]，与 `POST /api` 的响应相同:
+//!
+//! ```no_run
+//! use anime_search::{Engine, RuleSet};
+//! use futures::StreamExt;
+//!
+//! # async fn demo() {
+//! let engine = Engine::new(RuleSet::load("rules"));
+//! let mut events = Box::pin(engine.search_stream("葬送的芙莉莲"));
+//! while let Some(line) = events.next().await {
+//!     print!("{}", line);
+//! }
+//! # }
+//! ```
+//!
+//! # 获取 Bangumi 条目
+//!
+//! ```no_run
+//! use anime_search::bangumi::Client;
+//!
+//! # async fn demo() -> anyhow::Result<()> {
+//! let client = Client::new("https://api.bgm.tv");
+//! let subject = client.subject(400602).await?;
+//! println!("{} ({})", subject.name_cn, subject.name);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod cache;
+pub mod config;
+pub mod events;
+pub mod http_client;
+pub mod limiter;
+pub mod metadata;
+pub mod shutdown;
+pub mod stats;
+pub mod storage;
+pub mod types;
+
+#[cfg(feature = "anilist")]
+pub mod anilist;
+
+#[cfg(feature = "bangumi")]
+pub mod bangumi;
+
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(feature = "danmaku")]
+pub mod danmaku;
+
+#[cfg(feature = "scraper")]
+pub mod core;
+#[cfg(feature = "scraper")]
+pub mod engine;
+#[cfg(feature = "scraper")]
+pub mod export;
+#[cfg(feature = "scraper")]
+pub mod history;
+#[cfg(feature = "scraper")]
+pub mod rule_stats;
+#[cfg(feature = "scraper")]
+pub mod rss;
+#[cfg(feature = "scraper")]
+pub mod rules;
+#[cfg(feature = "scraper")]
+pub mod script;
+#[cfg(feature = "scraper")]
+pub mod unified;
+#[cfg(feature = "scraper")]
+pub mod updater;
+#[cfg(feature = "scraper")]
+pub mod xpath_to_css;
+
+#[cfg(feature = "server")]
+pub mod server;
+
+#[cfg(feature = "scraper")]
+pub use crate::core::Engine;
+#[cfg(feature = "scraper")]
+pub use crate::rules::RuleSet;
+#[cfg(feature = "scraper")]
+pub use crate::script::Script;
+#[cfg(feature = "scraper")]
+pub use crate::types::SearchOptions;
+pub use crate::types::{
+    Episode, EpisodeRoad, ErrorKind, PlatformSearchResult, Rule, RuleInfo, SearchResultItem,
+    StreamEvent, StreamProgress, StreamResult, UpdateDetail, UpdateResponse, UpdateResult,
+};
@@ -0,0 +1,152 @@
+//! 全局搜索并发限制
+//! 限制同时执行的搜索数 (MAX_CONCURRENT_SEARCHES)，超出时按配置短暂排队或直接拒绝 (429)；
+//! 另按站点限制同时请求的详情页数 (EPISODE_HOST_CONCURRENCY)，超出时排队
+
+use crate::config::CONFIG;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 搜索槽位 (MAX_CONCURRENT_SEARCHES 为 0 时不限制)
+static SEARCH_SLOTS: Lazy<Option<Arc<Semaphore>>> = Lazy::new(|| {
+    (CONFIG.max_concurrent_searches > 0)
+        .then(|| Arc::new(Semaphore::new(CONFIG.max_concurrent_searches)))
+});
+
+/// 当前占用槽位的搜索数
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// 超出上限时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowMode {
+    /// 立即拒绝
+    Reject,
+    /// 排队等待 (最长 SEARCH_QUEUE_TIMEOUT_MS)
+    Queue,
+}
+
+impl OverflowMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "reject" => Some(OverflowMode::Reject),
+            "queue" => Some(OverflowMode::Queue),
+            _ => None,
+        }
+    }
+}
+
+/// 搜索槽位，drop 时释放 (流式响应中随响应体一起 drop，客户端断开也会释放)
+pub struct SearchSlot {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for SearchSlot {
+    fn drop(&mut self) {
+        ACTIVE.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 槽位已满
+#[derive(Debug)]
+pub struct SearchBusy;
+
+/// 获取搜索槽位
+pub async fn acquire_search_slot() -> Result<SearchSlot, SearchBusy> {
+    let permit = match SEARCH_SLOTS.as_ref() {
+        None => None,
+        Some(semaphore) => {
+            let semaphore = semaphore.clone();
+            let permit = match CONFIG.search_overflow {
+                OverflowMode::Reject => semaphore.try_acquire_owned().ok(),
+                OverflowMode::Queue => {
+                    let wait = Duration::from_millis(CONFIG.search_queue_timeout_ms);
+                    tokio::time::timeout(wait, semaphore.acquire_owned())
+                        .await
+                        .ok()
+                        .and_then(|r| r.ok())
+                }
+            };
+            Some(permit.ok_or(SearchBusy)?)
+        }
+    };
+
+    ACTIVE.fetch_add(1, Ordering::SeqCst);
+    Ok(SearchSlot { _permit: permit })
+}
+
+/// 当前进行中的搜索数
+pub fn active_searches() -> usize {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/// 各站点的详情页请求槽位 (按小写主机名，首次请求时创建)
+static EPISODE_HOSTS: Lazy<Mutex<HashMap<String, Arc<Semaphore>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 获取详情页所在站点的请求槽位 (排队直到有空位，drop 时释放；链接无法解析主机名时不限制)
+pub async fn acquire_episode_host(url: &str) -> Option<OwnedSemaphorePermit> {
+    acquire_host(url, CONFIG.episode_host_concurrency).await
+}
+
+async fn acquire_host(url: &str, limit: usize) -> Option<OwnedSemaphorePermit> {
+    let host = url::Url::parse(url).ok()?.host_str()?.to_ascii_lowercase();
+    let semaphore = EPISODE_HOSTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(host)
+        .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+        .clone();
+    semaphore.acquire_owned().await.ok()
+}
+
+/// Prometheus 文本格式指标
+pub fn render_metrics() -> String {
+    format!(
+        "# HELP active_searches Number of searches currently holding a search slot\n\
+         # TYPE active_searches gauge\n\
+         active_searches {}\n\
+         # HELP max_concurrent_searches Configured limit of concurrent searches (0 = unlimited)\n\
+         # TYPE max_concurrent_searches gauge\n\
+         max_concurrent_searches {}\n",
+        active_searches(),
+        CONFIG.max_concurrent_searches
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_episode_host_limits_concurrent_fetches() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let (in_flight, peak) = (in_flight.clone(), peak.clone());
+                tokio::spawn(async move {
+                    let url = format!("https://Limited.Example.com/detail/{}", i);
+                    let _permit = acquire_host(&url, 2).await;
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+        // 其他站点不受影响
+        let _a = acquire_host("https://limited.example.com/1", 2).await.unwrap();
+        let _b = acquire_host("https://limited.example.com/2", 2).await.unwrap();
+        let other = acquire_host("https://other.example.com/1", 2);
+        assert!(tokio::time::timeout(Duration::from_millis(50), other).await.is_ok());
+        assert!(acquire_host("not a url", 2).await.is_none());
+    }
+}
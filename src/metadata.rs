@@ -0,0 +1,222 @@
+//! 元数据来源 (Bangumi / AniList)
+//!
+//! 搜索、条目详情与每日放送统一为 [`AnimeInfo`] / [`CalendarDay`]，来源特有的信息放在 `extra`。
+//! 按请求的 `provider` 参数选择，未指定时使用 METADATA_PROVIDER；未编译对应 feature 的来源不可用。
+
+use crate::config::CONFIG;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 可选的来源名称
+pub const PROVIDERS: &[&str] = &["bangumi", "anilist"];
+
+/// 简化的动漫信息 (用于前端显示，各来源共用)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimeInfo {
+    pub id: i64,
+    pub name: String,
+    pub name_cn: String,
+    pub summary: String,
+    /// 简介已按 `summary_len` 截断 (见 [`AnimeInfo::truncate_summary`])
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub summary_truncated: bool,
+    pub air_date: String,
+    pub image: String,
+    pub url: String,
+    pub score: Option<f64>,
+    pub rank: Option<i32>,
+    /// 来源特有的信息 (如 AniList 的英文名、MAL ID)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<Value>,
+}
+
+impl AnimeInfo {
+    /// 简介超过 `max_chars` 个字符时截断并标记 `summary_truncated` (0 为不截断)
+    pub fn truncate_summary(&mut self, max_chars: usize) {
+        if let Some(summary) = truncate_text(&self.summary, max_chars) {
+            self.summary = summary;
+            self.summary_truncated = true;
+        }
+    }
+}
+
+/// 将文本截断到最多 `max_chars` 个字符并追加省略号，未超出或 `max_chars` 为 0 时返回 None。
+/// 截断点落在单词中间时回退到前一个空白处，中日文等没有空格分词的文本按字符截断
+pub fn truncate_text(text: &str, max_chars: usize) -> Option<String> {
+    if max_chars == 0 {
+        return None;
+    }
+    let (cut, next) = text.char_indices().nth(max_chars)?;
+    let mut head = &text[..cut];
+    if is_word_char(next) && head.chars().next_back().is_some_and(is_word_char) {
+        let start = head
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !is_word_char(*c))
+            .map(|(i, c)| i + c.len_utf8());
+        // 整段都是一个单词时只能硬截断
+        if let Some(start) = start.filter(|start| *start > 0) {
+            head = &head[..start];
+        }
+    }
+    Some(format!("{}…", head.trim_end()))
+}
+
+/// 拉丁、西里尔等字母文字的单词字符 (中日文字符在 UTF-8 中占 3 字节以上，逐字均可断开)
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() && c.len_utf8() < 3
+}
+
+/// 每日放送中的一天
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarDay {
+    /// 星期 (1 = 周一 … 7 = 周日)
+    pub weekday: u8,
+    pub items: Vec<AnimeInfo>,
+}
+
+/// 元数据来源
+#[async_trait]
+pub trait MetadataProvider: Send + Sync {
+    /// 来源名称 (与 [`PROVIDERS`] 对应)
+    fn name(&self) -> &'static str;
+    /// 搜索动画条目
+    async fn search(&self, keyword: &str) -> anyhow::Result<Vec<AnimeInfo>>;
+    /// 条目详情
+    async fn subject(&self, id: i64) -> anyhow::Result<AnimeInfo>;
+    /// 每日放送 (来源不支持时返回错误)
+    async fn calendar(&self) -> anyhow::Result<Vec<CalendarDay>> {
+        anyhow::bail!("{} 不支持每日放送", self.name())
+    }
+}
+
+/// 按名称选择来源 (为空时使用 METADATA_PROVIDER)，未知或未启用时返回错误信息
+pub fn provider(name: Option<&str>) -> Result<&'static dyn MetadataProvider, String> {
+    let name = name
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .unwrap_or(&CONFIG.metadata_provider)
+        .to_ascii_lowercase();
+    match name.as_str() {
+        #[cfg(feature = "bangumi")]
+        "bangumi" => Ok(&BangumiProvider),
+        #[cfg(feature = "anilist")]
+        "anilist" => Ok(&AniListProvider),
+        other if PROVIDERS.contains(&other) => {
+            Err(format!("Metadata provider {} is not enabled", other))
+        }
+        other => Err(format!(
+            "Unknown metadata provider: {} (expected one of {})",
+            other,
+            PROVIDERS.join(", ")
+        )),
+    }
+}
+
+/// Bangumi (默认来源，使用 [`crate::bangumi`] 的缓存与配置)
+#[cfg(feature = "bangumi")]
+pub struct BangumiProvider;
+
+#[cfg(feature = "bangumi")]
+#[async_trait]
+impl MetadataProvider for BangumiProvider {
+    fn name(&self) -> &'static str {
+        "bangumi"
+    }
+
+    async fn search(&self, keyword: &str) -> anyhow::Result<Vec<AnimeInfo>> {
+        let result = crate::bangumi::search_anime(keyword).await?;
+        Ok(result.list.into_iter().map(AnimeInfo::from).collect())
+    }
+
+    async fn subject(&self, id: i64) -> anyhow::Result<AnimeInfo> {
+        Ok(crate::bangumi::get_subject(id).await?.into())
+    }
+
+    async fn calendar(&self) -> anyhow::Result<Vec<CalendarDay>> {
+        let days = crate::bangumi::get_calendar().await?;
+        Ok(days
+            .into_iter()
+            .map(|day| CalendarDay {
+                weekday: day.weekday.id.clamp(1, 7) as u8,
+                items: day.items.into_iter().map(AnimeInfo::from).collect(),
+            })
+            .collect())
+    }
+}
+
+/// AniList (GraphQL，见 [`crate::anilist`])
+#[cfg(feature = "anilist")]
+pub struct AniListProvider;
+
+#[cfg(feature = "anilist")]
+#[async_trait]
+impl MetadataProvider for AniListProvider {
+    fn name(&self) -> &'static str {
+        "anilist"
+    }
+
+    async fn search(&self, keyword: &str) -> anyhow::Result<Vec<AnimeInfo>> {
+        let media = crate::anilist::Client::from_config()
+            .search(keyword)
+            .await?;
+        Ok(media.into_iter().map(AnimeInfo::from).collect())
+    }
+
+    async fn subject(&self, id: i64) -> anyhow::Result<AnimeInfo> {
+        Ok(crate::anilist::Client::from_config()
+            .media(id)
+            .await?
+            .into())
+    }
+
+    async fn calendar(&self) -> anyhow::Result<Vec<CalendarDay>> {
+        crate::anilist::Client::from_config().calendar().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_provider_is_rejected() {
+        let err = provider(Some("mal")).err().unwrap();
+        assert!(err.contains("Unknown metadata provider: mal"), "{}", err);
+        #[cfg(not(feature = "anilist"))]
+        assert!(provider(Some("AniList"))
+            .err()
+            .unwrap()
+            .contains("not enabled"));
+        #[cfg(feature = "bangumi")]
+        assert_eq!(provider(Some(" Bangumi ")).unwrap().name(), "bangumi");
+    }
+
+    #[test]
+    fn test_truncate_long_summary_on_word_boundary() {
+        let summary = "The adventure is over, but life goes on for an elf mage just beginning to learn what living is all about.";
+        assert_eq!(
+            truncate_text(summary, 30).as_deref(),
+            Some("The adventure is over, but…")
+        );
+        // 恰好落在单词末尾时不回退
+        assert_eq!(truncate_text(summary, 13).as_deref(), Some("The adventure…"));
+        assert_eq!(truncate_text("勇者一行打倒魔王，回到了王都。", 8).as_deref(), Some("勇者一行打倒魔王…"));
+        assert_eq!(truncate_text("Supercalifragilistic", 5).as_deref(), Some("Super…"));
+        assert_eq!(truncate_text(summary, summary.chars().count()), None);
+        assert_eq!(truncate_text(summary, 0), None);
+
+        let mut info: AnimeInfo = serde_json::from_value(serde_json::json!({
+            "id": 1, "name": "", "name_cn": "", "summary": summary, "air_date": "",
+            "image": "", "url": "", "score": null, "rank": null,
+        }))
+        .unwrap();
+        info.truncate_summary(0);
+        assert!(serde_json::to_value(&info).unwrap().get("summary_truncated").is_none());
+        info.truncate_summary(30);
+        let value = serde_json::to_value(&info).unwrap();
+        assert_eq!(value["summary"], "The adventure is over, but…");
+        assert_eq!(value["summary_truncated"], true);
+    }
+}
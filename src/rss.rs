@@ -0,0 +1,289 @@
+//! RSS 规则 (`"type": "rss"`)
+//! 搜索地址返回 RSS 2.0 订阅 (如 Mikan Project 的搜索 RSS)，每个 `<item>` 转换为一个搜索结果:
+//! 标题为名称，种子 (`<enclosure>`) 地址为链接 (没有时取 `<link>`)，发布日期与文件大小作为标签。
+//! RSS 结果没有集数，不请求详情页。
+
+use crate::engine::{normalize_url, EngineError};
+use crate::types::{Rule, SearchOptions, SearchResultItem};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+/// 单个 `<item>` 中用到的字段
+#[derive(Debug, Default)]
+struct FeedItem {
+    title: String,
+    link: String,
+    enclosure_url: String,
+    pub_date: String,
+    /// 文件大小 (字节，来自 enclosure 的 length 或 Mikan 的 contentLength)
+    length: u64,
+}
+
+/// 解析 RSS 为搜索结果 (`first_only` 时只取第一个有效条目)
+pub fn parse_items(
+    rule: &Rule,
+    xml: &str,
+    options: &SearchOptions,
+) -> anyhow::Result<Vec<SearchResultItem>> {
+    let limit = if options.first_only { 1 } else { usize::MAX };
+    Ok(parse_feed(xml)?
+        .into_iter()
+        .filter_map(|item| to_result(rule, item))
+        .take(limit)
+        .collect())
+}
+
+/// `<item>` 的数量 (试运行的阶段统计)
+pub fn count_items(xml: &str) -> anyhow::Result<usize> {
+    Ok(parse_feed(xml)?.len())
+}
+
+fn to_result(rule: &Rule, item: FeedItem) -> Option<SearchResultItem> {
+    let name = item.title.trim().to_string();
+    let href = if item.enclosure_url.is_empty() {
+        item.link.trim()
+    } else {
+        item.enclosure_url.trim()
+    };
+    if name.is_empty() || href.is_empty() {
+        return None;
+    }
+
+    let tags: Vec<String> = [format_date(&item.pub_date), format_size(item.length)]
+        .into_iter()
+        .flatten()
+        .collect();
+    Some(SearchResultItem {
+        name,
+        alt_name: None,
+        url: normalize_url(href, &rule.base_url),
+        tags: (!tags.is_empty()).then_some(tags),
+        latest: None,
+        cover: None,
+        episodes: None,
+        raw_html: None,
+        source_id: None,
+        rule: rule.name.clone(),
+        rule_color: (!rule.color.is_empty()).then(|| rule.color.clone()),
+    })
+}
+
+/// 解析 `<rss>` 中的所有 `<item>` (根节点不是 rss 或 XML 无效时返回 [`EngineError::InvalidFeed`])
+fn parse_feed(xml: &str) -> anyhow::Result<Vec<FeedItem>> {
+    let invalid = |message: String| anyhow::Error::from(EngineError::InvalidFeed(message));
+    let mut reader = Reader::from_str(xml.trim_start_matches('\u{feff}'));
+    reader.config_mut().trim_text(true);
+
+    let mut items = Vec::new();
+    let mut item: Option<FeedItem> = None;
+    // 当前 item 内的元素路径 (本地名，不含命名空间前缀)
+    let mut path: Vec<String> = Vec::new();
+    let mut seen_root = false;
+
+    loop {
+        let event = reader.read_event().map_err(|e| {
+            invalid(format!(
+                "RSS 解析失败 (位置 {}): {}",
+                reader.error_position(),
+                e
+            ))
+        })?;
+        match event {
+            Event::Start(e) => {
+                let name = local_name(&e);
+                if !seen_root {
+                    if name != "rss" {
+                        return Err(invalid(format!("不是 RSS 订阅 (根节点为 <{}>)", name)));
+                    }
+                    seen_root = true;
+                } else if let Some(current) = item.as_mut() {
+                    path.push(name);
+                    read_attributes(current, &path, &e);
+                } else if name == "item" {
+                    item = Some(FeedItem::default());
+                }
+            }
+            Event::Empty(e) => {
+                if let Some(current) = item.as_mut() {
+                    let name = local_name(&e);
+                    path.push(name);
+                    read_attributes(current, &path, &e);
+                    path.pop();
+                }
+            }
+            Event::Text(e) => {
+                if let Some(current) = item.as_mut() {
+                    let text = e
+                        .unescape()
+                        .map(|t| t.into_owned())
+                        .unwrap_or_else(|_| String::from_utf8_lossy(&e).into_owned());
+                    set_text(current, &path, text);
+                }
+            }
+            Event::CData(e) => {
+                if let Some(current) = item.as_mut() {
+                    set_text(current, &path, String::from_utf8_lossy(&e).into_owned());
+                }
+            }
+            // item 自身结束时路径为空
+            Event::End(_) if item.is_some() && path.pop().is_none() => {
+                items.extend(item.take());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !seen_root {
+        return Err(invalid("响应为空或不是 XML".to_string()));
+    }
+    Ok(items)
+}
+
+fn local_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.local_name().as_ref()).into_owned()
+}
+
+fn attribute(e: &BytesStart, name: &str) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name.as_bytes())
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+/// enclosure 的 url 与 length 属性
+fn read_attributes(item: &mut FeedItem, path: &[String], e: &BytesStart) {
+    if path != ["enclosure"] {
+        return;
+    }
+    item.enclosure_url = attribute(e, "url").unwrap_or_default();
+    if let Some(length) = attribute(e, "length").and_then(|v| v.trim().parse().ok()) {
+        item.length = length;
+    }
+}
+
+/// 按元素路径写入字段 (Mikan 的发布时间与大小在 `<torrent>` 内)
+fn set_text(item: &mut FeedItem, path: &[String], text: String) {
+    let path: Vec<&str> = path.iter().map(String::as_str).collect();
+    match path.as_slice() {
+        ["title"] => item.title.push_str(&text),
+        ["link"] => item.link.push_str(&text),
+        ["pubDate"] | ["torrent", "pubDate"] if item.pub_date.is_empty() => item.pub_date = text,
+        ["torrent", "contentLength"] if item.length == 0 => {
+            item.length = text.trim().parse().unwrap_or(0);
+        }
+        _ => {}
+    }
+}
+
+/// 发布日期 (`YYYY-MM-DD`)，支持 RFC 2822 与 Mikan 的 `2024-03-22T23:25:38.13`，无法识别时原样返回
+fn format_date(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return None;
+    }
+    if let Ok(date) = chrono::DateTime::parse_from_rfc2822(raw) {
+        return Some(date.format("%Y-%m-%d").to_string());
+    }
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(date.format("%Y-%m-%d").to_string());
+    }
+    if let Ok(date) = chrono::NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(date.format("%Y-%m-%d").to_string());
+    }
+    Some(raw.to_string())
+}
+
+/// 文件大小 (如 `1.3 GB`，为 0 时没有)
+fn format_size(bytes: u64) -> Option<String> {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    if bytes == 0 {
+        return None;
+    }
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    Some(if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::error_kind;
+    use crate::types::ErrorKind;
+
+    fn mikan() -> Rule {
+        Rule {
+            name: "Mikan".to_string(),
+            rule_type: "rss".to_string(),
+            base_url: "https://mikanani.me/".to_string(),
+            search_url: "https://mikanani.me/RSS/Search?searchstr=@keyword".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mikan_search_feed() {
+        let xml = include_str!("../tests/fixtures/mikan_search.xml");
+        let items = parse_items(&mikan(), xml, &SearchOptions::default()).unwrap();
+        assert_eq!(items.len(), 3);
+
+        let first = &items[0];
+        assert_eq!(
+            first.name,
+            "[喵萌奶茶屋&LoliHouse] 葬送的芙莉莲 / Sousou no Frieren - 28 [WebRip 1080p HEVC-10bit AAC][简繁日内封字幕]"
+        );
+        assert_eq!(
+            first.url,
+            "https://mikanani.me/Download/20240322/9a1c1b0d3b0e2f6d8c9e5a7b4f3d2c1e0a9b8c7d.torrent"
+        );
+        assert_eq!(
+            first.tags.as_deref(),
+            Some(&["2024-03-22".to_string(), "1.3 GB".to_string()][..])
+        );
+        assert_eq!(items[1].tags.as_ref().unwrap()[1], "351.6 MB");
+        assert!(items.iter().all(|item| item.episodes.is_none()));
+
+        let options = SearchOptions {
+            first_only: true,
+            ..Default::default()
+        };
+        assert_eq!(parse_items(&mikan(), xml, &options).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_plain_rss_item_uses_link_and_rfc2822_date() {
+        let xml = r#"<rss version="2.0"><channel>
+            <item><title><![CDATA[Frieren - 01]]></title><link>/view/1</link>
+            <pubDate>Fri, 29 Sep 2023 14:00:00 +0000</pubDate></item>
+            <item><title></title><link>/view/2</link></item>
+        </channel></rss>"#;
+        let items = parse_items(&mikan(), xml, &SearchOptions::default()).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "Frieren - 01");
+        assert_eq!(items[0].url, "https://mikanani.me/view/1");
+        assert_eq!(
+            items[0].tags.as_deref(),
+            Some(&["2023-09-29".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn test_invalid_feed_is_parse_empty() {
+        for body in [
+            "<html><body>Just a moment...</body></html>",
+            "",
+            "<rss><channel><item><title>a</item></rss>",
+        ] {
+            let err = parse_items(&mikan(), body, &SearchOptions::default()).unwrap_err();
+            assert_eq!(error_kind(&err), ErrorKind::ParseEmpty, "{}", body);
+        }
+    }
+}
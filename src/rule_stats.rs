@@ -0,0 +1,601 @@
+//! 规则健康统计
+//! 记录每个规则最近若干次实际搜索 (不含结果缓存命中) 的成败，计算滚动失败率与 24 小时失败率；
+//! 失败率升至阈值时通知已注册的监听器 (如 webhook)，回落到阈值以下后才会再次通知。
+//! 启用 AUTO_DISABLE 时，持续失败的规则会被自动停用 (规则分组选择时跳过)，
+//! 由后台低频探测连续两次成功后恢复，或由管理员手动启用。
+//! 按探测计划可估算已停用规则的恢复时间 ([`cooldowns`])。
+//! 存储为 SQLite / Redis 时统计与停用状态写入存储 (定期 [`flush`]，状态变化时立即写入)，重启后恢复。
+
+use crate::config::CONFIG;
+use crate::storage::{self, ns};
+use crate::types::RuleCooldown;
+use chrono::{DateTime, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// 滚动窗口大小 (最近的搜索次数)
+pub const WINDOW: usize = 20;
+
+/// 计算失败率所需的最少次数 (避免刚启动时一两次失败就告警)
+pub const MIN_ATTEMPTS: usize = 10;
+
+/// 24 小时统计的小时桶数
+const DAY_HOURS: i64 = 24;
+
+/// 自动停用后恢复所需的连续探测成功次数
+pub const CANARY_SUCCESSES: u8 = 2;
+
+/// 单小时的次数与失败数
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct HourBucket {
+    hour: i64,
+    attempts: u64,
+    failures: u64,
+}
+
+/// 自动停用状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Disabled {
+    since: String,
+    reason: String,
+    /// 连续探测成功次数
+    canary_successes: u8,
+}
+
+/// 单个规则的滚动记录
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct RuleWindow {
+    /// 最近的结果 (true = 失败)
+    outcomes: VecDeque<bool>,
+    /// 最近 24 小时的按小时统计
+    hourly: VecDeque<HourBucket>,
+    consecutive_failures: u32,
+    total_attempts: u64,
+    total_failures: u64,
+    last_error: Option<String>,
+    /// 已告警 (失败率回落前不重复通知)
+    alerting: bool,
+    disabled: Option<Disabled>,
+    /// 上次写入存储后有变化
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl RuleWindow {
+    fn failures(&self) -> usize {
+        self.outcomes.iter().filter(|failed| **failed).count()
+    }
+
+    fn failure_rate(&self) -> f64 {
+        if self.outcomes.is_empty() {
+            0.0
+        } else {
+            self.failures() as f64 / self.outcomes.len() as f64
+        }
+    }
+
+    /// 最近 24 小时的 (次数, 失败数)
+    fn day_totals(&self, hour: i64) -> (u64, u64) {
+        self.hourly
+            .iter()
+            .filter(|b| hour - b.hour < DAY_HOURS)
+            .fold((0, 0), |(a, f), b| (a + b.attempts, f + b.failures))
+    }
+
+    /// 记录一次结果，返回失败率是否刚升至 `threshold`
+    fn push(&mut self, error: Option<&str>, threshold: Option<f64>, hour: i64) -> bool {
+        let failed = error.is_some();
+        if self.outcomes.len() == WINDOW {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back(failed);
+        self.total_attempts += 1;
+        if failed {
+            self.total_failures += 1;
+            self.consecutive_failures += 1;
+            self.last_error = error.map(|e| e.to_string());
+        } else {
+            self.consecutive_failures = 0;
+        }
+
+        while self
+            .hourly
+            .front()
+            .is_some_and(|b| hour - b.hour >= DAY_HOURS)
+        {
+            self.hourly.pop_front();
+        }
+        match self.hourly.back_mut() {
+            Some(bucket) if bucket.hour == hour => {
+                bucket.attempts += 1;
+                bucket.failures += failed as u64;
+            }
+            _ => self.hourly.push_back(HourBucket {
+                hour,
+                attempts: 1,
+                failures: failed as u64,
+            }),
+        }
+
+        let Some(threshold) = threshold else {
+            return false;
+        };
+        let above = self.outcomes.len() >= MIN_ATTEMPTS && self.failure_rate() >= threshold;
+        let crossed = above && !self.alerting;
+        self.alerting = above;
+        crossed
+    }
+
+    /// 按策略判断是否应停用，返回原因
+    fn disable_reason(&self, policy: &AutoDisablePolicy, hour: i64) -> Option<String> {
+        if policy.consecutive_failures > 0
+            && self.consecutive_failures >= policy.consecutive_failures
+        {
+            return Some(format!("连续失败 {} 次", self.consecutive_failures));
+        }
+        let (attempts, failures) = self.day_totals(hour);
+        if policy.failure_rate > 0.0
+            && attempts >= policy.min_attempts
+            && failures as f64 / attempts as f64 > policy.failure_rate
+        {
+            return Some(format!(
+                "24 小时内失败 {}/{} 次 ({:.0}%)",
+                failures,
+                attempts,
+                failures as f64 / attempts as f64 * 100.0
+            ));
+        }
+        None
+    }
+
+    /// 清空滚动计数 (保留累计次数)，用于重新启用
+    fn reset(&mut self) {
+        self.dirty = true;
+        self.outcomes.clear();
+        self.hourly.clear();
+        self.consecutive_failures = 0;
+        self.alerting = false;
+        self.disabled = None;
+    }
+}
+
+/// 自动停用策略
+#[derive(Debug, Clone, Copy)]
+pub struct AutoDisablePolicy {
+    /// 连续失败次数阈值 (0 = 不按连续失败停用)
+    pub consecutive_failures: u32,
+    /// 24 小时失败率阈值 (0~1，0 = 不按失败率停用)
+    pub failure_rate: f64,
+    /// 按失败率停用所需的 24 小时最少次数
+    pub min_attempts: u64,
+}
+
+impl AutoDisablePolicy {
+    /// 按配置创建 (未启用 AUTO_DISABLE 时为 None)
+    pub fn from_config() -> Option<Self> {
+        CONFIG.auto_disable.then(|| Self {
+            consecutive_failures: CONFIG.auto_disable_consecutive as u32,
+            failure_rate: CONFIG.auto_disable_failure_rate as f64 / 100.0,
+            min_attempts: CONFIG.auto_disable_min_attempts,
+        })
+    }
+}
+
+/// 规则健康快照
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleHealth {
+    pub rule: String,
+    /// 窗口内的次数与失败数
+    pub attempts: usize,
+    pub failures: usize,
+    pub failure_rate: f64,
+    pub consecutive_failures: u32,
+    /// 启动以来的累计次数与失败数
+    pub total_attempts: u64,
+    pub total_failures: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub auto_disabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled_reason: Option<String>,
+}
+
+impl RuleHealth {
+    fn new(rule: &str, window: &RuleWindow) -> Self {
+        Self {
+            rule: rule.to_string(),
+            attempts: window.outcomes.len(),
+            failures: window.failures(),
+            failure_rate: window.failure_rate(),
+            consecutive_failures: window.consecutive_failures,
+            total_attempts: window.total_attempts,
+            total_failures: window.total_failures,
+            last_error: window.last_error.clone(),
+            auto_disabled: window.disabled.is_some(),
+            disabled_reason: window.disabled.as_ref().map(|d| d.reason.clone()),
+        }
+    }
+}
+
+/// 规则启用状态变化
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleTransition {
+    pub rule: String,
+    /// auto_disabled (自动停用) / reenabled (探测恢复) / enabled (手动启用)
+    pub action: &'static str,
+    pub reason: String,
+}
+
+type FailureListener = Box<dyn Fn(RuleHealth) + Send + Sync>;
+type TransitionListener = Box<dyn Fn(RuleTransition) + Send + Sync>;
+
+static STATS: Lazy<Mutex<HashMap<String, RuleWindow>>> = Lazy::new(|| Mutex::new(load()));
+
+/// 串行化写入存储 (避免较旧的快照覆盖较新的)
+static FLUSH_LOCK: Mutex<()> = Mutex::new(());
+
+/// 失败率阈值 (0~1) 与监听器
+static FAILURE_LISTENER: OnceCell<(f64, FailureListener)> = OnceCell::new();
+
+/// 启用状态变化监听器
+static TRANSITION_LISTENER: OnceCell<TransitionListener> = OnceCell::new();
+
+static POLICY: Lazy<Option<AutoDisablePolicy>> = Lazy::new(AutoDisablePolicy::from_config);
+
+/// 下一次探测的时间与探测间隔
+type Recheck = (DateTime<Utc>, chrono::Duration);
+
+/// 探测计划 (由后台探测任务设置)
+static RECHECK: Lazy<Mutex<Option<Recheck>>> = Lazy::new(|| Mutex::new(None));
+
+/// 注册失败率告警监听器 (只能注册一次)，`threshold` 为 0~1 的失败率
+pub fn set_failure_listener(threshold: f64, listener: impl Fn(RuleHealth) + Send + Sync + 'static) {
+    if FAILURE_LISTENER
+        .set((threshold, Box::new(listener)))
+        .is_err()
+    {
+        tracing::warn!("规则失败率监听器已注册，忽略重复注册");
+    }
+}
+
+/// 注册启用状态变化监听器 (只能注册一次)
+pub fn set_transition_listener(listener: impl Fn(RuleTransition) + Send + Sync + 'static) {
+    if TRANSITION_LISTENER.set(Box::new(listener)).is_err() {
+        tracing::warn!("规则状态监听器已注册，忽略重复注册");
+    }
+}
+
+/// 从存储加载上次保存的统计 (内存存储时为空)
+fn load() -> HashMap<String, RuleWindow> {
+    if !storage::is_persistent() {
+        return HashMap::new();
+    }
+    storage::store()
+        .list(ns::RULE_STATS)
+        .into_iter()
+        .filter_map(|(rule, value)| Some((rule, serde_json::from_str(&value).ok()?)))
+        .collect()
+}
+
+/// 加载存储中的统计 (服务启动时调用，避免首次搜索时同步读取存储)
+pub fn init() {
+    Lazy::force(&STATS);
+}
+
+/// 将有变化的规则统计写入存储 (内存存储时忽略)，会阻塞，异步上下文中经 spawn_blocking 调用
+pub fn flush() {
+    if !storage::is_persistent() {
+        return;
+    }
+    let _flush = FLUSH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dirty: Vec<(String, String)> = stats()
+        .iter_mut()
+        .filter(|(_, window)| window.dirty)
+        .filter_map(|(rule, window)| {
+            window.dirty = false;
+            Some((rule.clone(), serde_json::to_string(window).ok()?))
+        })
+        .collect();
+    let store = storage::store();
+    for (rule, value) in dirty {
+        store.set(ns::RULE_STATS, &rule, &value, None);
+    }
+}
+
+fn current_hour() -> i64 {
+    chrono::Utc::now().timestamp() / 3600
+}
+
+fn stats() -> std::sync::MutexGuard<'static, HashMap<String, RuleWindow>> {
+    STATS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn notify_transition(transition: Option<RuleTransition>) {
+    if let Some(transition) = transition {
+        // 启用状态立即写入存储，不等定期写入
+        storage::spawn_write(|_| flush());
+        tracing::info!(
+            "规则 {} 状态变化: {} ({})",
+            transition.rule,
+            transition.action,
+            transition.reason
+        );
+        if let Some(listener) = TRANSITION_LISTENER.get() {
+            listener(transition);
+        }
+    }
+}
+
+/// 记录一次规则搜索结果
+pub fn record(rule: &str, error: Option<&str>) {
+    let listener = FAILURE_LISTENER.get();
+    let hour = current_hour();
+    let (alert, transition) = {
+        let mut stats = stats();
+        let window = stats.entry(rule.to_string()).or_default();
+        window.dirty = true;
+        let alert = window
+            .push(error, listener.map(|(threshold, _)| *threshold), hour)
+            .then(|| RuleHealth::new(rule, window));
+        let transition = match POLICY.as_ref() {
+            Some(policy) if window.disabled.is_none() => {
+                window.disable_reason(policy, hour).map(|reason| {
+                    window.disabled = Some(Disabled {
+                        since: chrono::Utc::now().to_rfc3339(),
+                        reason: reason.clone(),
+                        canary_successes: 0,
+                    });
+                    RuleTransition {
+                        rule: rule.to_string(),
+                        action: "auto_disabled",
+                        reason,
+                    }
+                })
+            }
+            _ => None,
+        };
+        (alert, transition)
+    };
+    // 在锁外通知，监听器可以安全地读取统计
+    if let (Some(health), Some((_, listener))) = (alert, listener) {
+        listener(health);
+    }
+    notify_transition(transition);
+}
+
+/// 规则是否已被自动停用
+pub fn is_auto_disabled(rule: &str) -> bool {
+    stats().get(rule).is_some_and(|w| w.disabled.is_some())
+}
+
+/// 已自动停用的规则 (规则名, 停用时间)
+pub fn auto_disabled_rules() -> Vec<(String, String)> {
+    let mut rules: Vec<_> = stats()
+        .iter()
+        .filter_map(|(rule, w)| w.disabled.as_ref().map(|d| (rule.clone(), d.since.clone())))
+        .collect();
+    rules.sort();
+    rules
+}
+
+/// 记录下一次探测的时间 (后台探测任务每次等待前调用)
+pub fn schedule_recheck(interval: std::time::Duration) {
+    let interval = chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::zero());
+    *RECHECK.lock().unwrap_or_else(|e| e.into_inner()) = Some((Utc::now() + interval, interval));
+}
+
+/// 预计恢复时间: 下一次探测起还需要的连续成功次数 × 探测间隔
+fn estimate_reset(disabled: &Disabled, recheck: Option<Recheck>) -> Option<DateTime<Utc>> {
+    let (next, interval) = recheck?;
+    let remaining = CANARY_SUCCESSES.saturating_sub(disabled.canary_successes).max(1) as i32;
+    Some(next + interval * (remaining - 1))
+}
+
+/// 指定规则中已自动停用的规则及预计恢复时间 (未停用的规则不出现在结果中)
+pub fn cooldowns<'a>(rules: impl IntoIterator<Item = &'a str>) -> Vec<RuleCooldown> {
+    let recheck = *RECHECK.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Utc::now();
+    let stats = stats();
+    rules
+        .into_iter()
+        .filter_map(|rule| {
+            let disabled = stats.get(rule)?.disabled.as_ref()?;
+            let reset_at = estimate_reset(disabled, recheck);
+            Some(RuleCooldown {
+                rule: rule.to_string(),
+                since: disabled.since.clone(),
+                reason: disabled.reason.clone(),
+                reset_at: reset_at.map(|at| at.to_rfc3339()),
+                retry_after_secs: reset_at.map(|at| (at - now).num_seconds().max(0) as u64),
+            })
+        })
+        .collect()
+}
+
+/// 直接停用规则 (测试用，模拟熔断)
+#[cfg(test)]
+pub(crate) fn force_disable(rule: &str, reason: &str) {
+    stats().entry(rule.to_string()).or_default().disabled = Some(Disabled {
+        since: Utc::now().to_rfc3339(),
+        reason: reason.to_string(),
+        canary_successes: 0,
+    });
+}
+
+/// 记录一次探测搜索结果，连续成功 CANARY_SUCCESSES 次后恢复
+pub fn record_canary(rule: &str, success: bool) {
+    let transition = {
+        let mut stats = stats();
+        let Some(window) = stats.get_mut(rule) else {
+            return;
+        };
+        let Some(disabled) = window.disabled.as_mut() else {
+            return;
+        };
+        if success {
+            disabled.canary_successes += 1;
+        } else {
+            disabled.canary_successes = 0;
+        }
+        window.dirty = true;
+        (disabled.canary_successes >= CANARY_SUCCESSES).then(|| {
+            window.reset();
+            RuleTransition {
+                rule: rule.to_string(),
+                action: "reenabled",
+                reason: format!("连续 {} 次探测成功", CANARY_SUCCESSES),
+            }
+        })
+    };
+    notify_transition(transition);
+}
+
+/// 手动启用规则并清空滚动计数，返回之前是否处于自动停用状态
+pub fn enable(rule: &str) -> bool {
+    let was_disabled = {
+        let mut stats = stats();
+        let window = stats.entry(rule.to_string()).or_default();
+        let was_disabled = window.disabled.is_some();
+        window.reset();
+        was_disabled
+    };
+    if was_disabled {
+        notify_transition(Some(RuleTransition {
+            rule: rule.to_string(),
+            action: "enabled",
+            reason: "管理员手动启用".to_string(),
+        }));
+    }
+    was_disabled
+}
+
+/// 所有规则的健康快照 (按规则名排序)
+pub fn snapshot() -> Vec<RuleHealth> {
+    let stats = stats();
+    let mut health: Vec<RuleHealth> = stats
+        .iter()
+        .map(|(rule, window)| RuleHealth::new(rule, window))
+        .collect();
+    health.sort_by(|a, b| a.rule.cmp(&b.rule));
+    health
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_rate_alerts_once_per_crossing() {
+        let mut window = RuleWindow::default();
+        let threshold = Some(0.5);
+
+        // 次数不足时不告警
+        for _ in 0..MIN_ATTEMPTS - 1 {
+            assert!(!window.push(Some("timeout"), threshold, 0));
+        }
+        assert!(window.push(Some("timeout"), threshold, 0));
+        assert!(!window.push(Some("timeout"), threshold, 0));
+        assert_eq!(window.consecutive_failures, MIN_ATTEMPTS as u32 + 1);
+
+        // 成功把失败率拉回阈值以下后，再次升高时重新告警
+        for _ in 0..WINDOW {
+            window.push(None, threshold, 0);
+        }
+        assert_eq!(window.consecutive_failures, 0);
+        assert_eq!(window.outcomes.len(), WINDOW);
+        let alerts = (0..WINDOW)
+            .filter(|_| window.push(Some("502"), threshold, 0))
+            .count();
+        assert_eq!(alerts, 1);
+        assert_eq!(window.last_error.as_deref(), Some("502"));
+    }
+
+    #[test]
+    fn test_auto_disable_policy_thresholds() {
+        let policy = AutoDisablePolicy {
+            consecutive_failures: 10,
+            failure_rate: 0.9,
+            min_attempts: 20,
+        };
+
+        let mut window = RuleWindow::default();
+        for _ in 0..9 {
+            window.push(Some("timeout"), None, 100);
+        }
+        assert_eq!(window.disable_reason(&policy, 100), None);
+        window.push(Some("timeout"), None, 100);
+        assert_eq!(
+            window.disable_reason(&policy, 100).as_deref(),
+            Some("连续失败 10 次")
+        );
+
+        // 24 小时失败率: 偶尔成功打断连续失败，但整体失败率超过 90%
+        let mut window = RuleWindow::default();
+        for i in 0..40 {
+            window.push((i % 20 != 0).then_some("502"), None, 100 + i / 10);
+        }
+        let rate_only = AutoDisablePolicy {
+            consecutive_failures: 0,
+            ..policy
+        };
+        let reason = window.disable_reason(&rate_only, 103).unwrap();
+        assert!(reason.starts_with("24 小时内失败 38/40"), "{}", reason);
+        // 超过 24 小时的记录不再计入
+        assert_eq!(window.day_totals(100 + 24), (30, 29));
+        assert_eq!(window.day_totals(103 + 24), (0, 0));
+
+        window.reset();
+        assert_eq!(window.disable_reason(&rate_only, 103), None);
+        assert_eq!(window.total_attempts, 40);
+    }
+
+    #[test]
+    fn test_window_round_trips_through_storage_format() {
+        let mut window = RuleWindow::default();
+        for _ in 0..3 {
+            window.push(Some("timeout"), None, 100);
+        }
+        window.disabled = Some(Disabled {
+            since: "2026-01-01T00:00:00Z".to_string(),
+            reason: "连续失败 3 次".to_string(),
+            canary_successes: 1,
+        });
+
+        let restored: RuleWindow =
+            serde_json::from_str(&serde_json::to_string(&window).unwrap()).unwrap();
+        assert_eq!(restored.outcomes, window.outcomes);
+        assert_eq!(restored.consecutive_failures, 3);
+        assert_eq!(restored.day_totals(100), (3, 3));
+        assert_eq!(restored.last_error.as_deref(), Some("timeout"));
+        let disabled = restored.disabled.unwrap();
+        assert_eq!((disabled.reason.as_str(), disabled.canary_successes), ("连续失败 3 次", 1));
+        assert!(!restored.dirty);
+    }
+
+    #[test]
+    fn test_reset_estimate_follows_recheck_schedule() {
+        let mut disabled = Disabled {
+            since: String::new(),
+            reason: String::new(),
+            canary_successes: 0,
+        };
+        let next = Utc::now();
+        let interval = chrono::Duration::minutes(60);
+        assert_eq!(estimate_reset(&disabled, None), None);
+        // 还需要两次成功: 下一次探测之后再等一个间隔
+        assert_eq!(
+            estimate_reset(&disabled, Some((next, interval))),
+            Some(next + interval)
+        );
+        disabled.canary_successes = 1;
+        assert_eq!(estimate_reset(&disabled, Some((next, interval))), Some(next));
+
+        force_disable("冷却测试", "连续失败 10 次");
+        let cooldowns = cooldowns(["冷却测试", "未停用"]);
+        assert_eq!(cooldowns.len(), 1);
+        assert_eq!(cooldowns[0].reason, "连续失败 10 次");
+    }
+}
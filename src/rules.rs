@@ -1,32 +1,572 @@
 //! 规则管理器
-//! 从 rules/ 目录读取 JSON 规则文件，兼容 Kazumi 规则格式
+//! 从规则目录 (默认 rules/，`RULES_DIR` 配置) 读取 JSON 规则文件，兼容 Kazumi 规则格式
 
+use crate::config::CONFIG;
 use crate::types::Rule;
 use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use tracing::{info, warn};
 
-/// 规则目录路径
-const RULES_DIR: &str = "rules";
+/// 规则分组文件 (位于规则目录，分组名 -> 规则名列表)
+const GROUPS_FILE: &str = "groups.json";
 
-/// 全局规则列表
-static RULES: Lazy<Vec<Arc<Rule>>> = Lazy::new(load_all_rules);
+/// 全局规则列表 (从规则目录加载，可通过 [`reload_builtin_rules`] 重新加载)
+static RULES: Lazy<RwLock<Arc<RuleSet>>> =
+    Lazy::new(|| RwLock::new(Arc::new(RuleSet::load(&CONFIG.rules_dir))));
+
+/// 当前的全局规则集
+fn builtin() -> Arc<RuleSet> {
+    RULES.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// 规则字段 (规范名 + 兼容别名)，与 [`Rule`] 的 serde 定义保持一致
+const RULE_FIELDS: &[(&str, &[&str])] = &[
+    ("api", &[]),
+    ("type", &["ruleType"]),
+    ("name", &[]),
+    ("version", &[]),
+    ("muli_sources", &["muliSources"]),
+    ("use_webview", &["useWebview"]),
+    ("use_native_player", &["useNativePlayer"]),
+    ("use_post", &["usePost"]),
+    ("use_legacy_parser", &["useLegacyParser"]),
+    ("ad_blocker", &["adBlocker"]),
+    ("user_agent", &["userAgent"]),
+    ("base_url", &["baseURL"]),
+    ("search_url", &["searchURL"]),
+    ("search_list", &["searchList"]),
+    ("search_name", &["searchName"]),
+    ("search_result", &["searchResult"]),
+    ("chapter_roads", &["chapterRoads"]),
+    ("chapter_result", &["chapterResult"]),
+    ("chapter_road_links", &["chapterRoadLinks"]),
+    ("referer", &[]),
+    ("color", &[]),
+    ("icon", &[]),
+    ("tags", &[]),
+    ("magic", &[]),
+    ("search_update", &["searchUpdate"]),
+    ("search_cover", &["searchCover"]),
+    ("search_alt_name", &["searchAltName"]),
+    ("episode_fallback", &["episodeFallback"]),
+    ("episode_href_pattern", &["episodeHrefPattern"]),
+    ("token_xpath", &["tokenXpath", "tokenXPath"]),
+    ("token_field", &["tokenField"]),
+    ("episode_name_template", &["episodeNameTemplate"]),
+    ("episode_number_regex", &["episodeNumberRegex"]),
+    ("search_next_page", &["searchNextPage"]),
+    ("dedup_items", &["dedupItems"]),
+    ("preferred_road_pattern", &["preferredRoadPattern"]),
+    ("id_regex", &["idRegex"]),
+    ("max_pages", &["maxPages"]),
+    ("timeout_secs", &["timeoutSecs"]),
+    ("mock_results", &["mockResults"]),
+    ("mock", &[]),
+];
+
+/// 存在但本服务不使用的字段 (不视为未知字段): Kazumi 的 deprecated 与编辑器使用的 $schema
+const IGNORED_FIELDS: &[&str] = &["deprecated", "$schema"];
+
+/// 缺失时规则无法使用的字段
+const REQUIRED_FIELDS: &[&str] = &["name", "base_url", "search_url"];
+
+/// 缺失时规则通常搜不到结果或没有集数的字段
+const RECOMMENDED_FIELDS: &[&str] = &[
+    "search_list",
+    "search_name",
+    "search_result",
+    "chapter_roads",
+    "chapter_result",
+];
+
+/// 规则 JSON 的字段检查结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RuleFieldReport {
+    /// 未知字段 (多为拼写错误，加载时会被忽略)
+    pub unknown_fields: Vec<String>,
+    /// 缺失的必填字段
+    pub missing_required: Vec<String>,
+    /// 缺失的推荐字段
+    pub missing_recommended: Vec<String>,
+}
+
+impl RuleFieldReport {
+    pub fn is_clean(&self) -> bool {
+        self.unknown_fields.is_empty()
+            && self.missing_required.is_empty()
+            && self.missing_recommended.is_empty()
+    }
+
+    /// 逐条描述问题 (用于日志与检查报告)
+    pub fn messages(&self) -> Vec<String> {
+        let mut messages: Vec<String> = self
+            .unknown_fields
+            .iter()
+            .map(|f| match suggest_field(f) {
+                Some(s) => format!("未知字段 {} (是否为 {}?)", f, s),
+                None => format!("未知字段 {}", f),
+            })
+            .collect();
+        messages.extend(self.missing_required.iter().map(|f| format!("缺少必填字段 {}", f)));
+        messages.extend(self.missing_recommended.iter().map(|f| format!("缺少推荐字段 {}", f)));
+        messages
+    }
+}
+
+/// 严格检查规则 JSON: 未知字段、缺失的必填与推荐字段 (不影响正常的宽松加载)
+pub fn check_rule_fields(value: &Value) -> RuleFieldReport {
+    let Some(object) = value.as_object() else {
+        return RuleFieldReport {
+            missing_required: REQUIRED_FIELDS.iter().map(|f| display_name(f)).collect(),
+            ..Default::default()
+        };
+    };
+
+    let present = |field: &str| {
+        field_names(field).any(|name| match object.get(name) {
+            Some(Value::String(s)) => !s.trim().is_empty(),
+            Some(Value::Null) | None => false,
+            Some(_) => true,
+        })
+    };
+
+    let is_type = |expected: &str| {
+        field_names("type").any(|name| {
+            object
+                .get(name)
+                .and_then(Value::as_str)
+                .is_some_and(|t| t.eq_ignore_ascii_case(expected))
+        })
+    };
+    // RSS 规则按条目解析、模拟源不请求站点，都不需要选择器
+    let needs_selectors = !is_type("rss") && !is_type("mock");
+
+    RuleFieldReport {
+        unknown_fields: object
+            .keys()
+            .filter(|key| !IGNORED_FIELDS.contains(&key.as_str()))
+            .filter(|key| !RULE_FIELDS.iter().any(|(f, _)| field_names(f).any(|n| n == *key)))
+            .cloned()
+            .collect(),
+        missing_required: REQUIRED_FIELDS
+            .iter()
+            .filter(|f| !present(f))
+            .map(|f| display_name(f))
+            .collect(),
+        missing_recommended: RECOMMENDED_FIELDS
+            .iter()
+            .filter(|f| needs_selectors && !present(f))
+            .map(|f| display_name(f))
+            .collect(),
+    }
+}
+
+/// 规范化规则 JSON: `$schema` 在前，已知字段按 [`Rule`] 的定义顺序排列 (保留文件中的拼写)，
+/// 其余字段按字母序放在最后；4 空格缩进，以换行结尾
+pub fn format_rule_json(content: &str) -> anyhow::Result<String> {
+    let value: Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))?;
+    let object = value
+        .as_object()
+        .ok_or_else(|| anyhow::anyhow!("规则必须是 JSON 对象"))?;
+
+    let rank = |key: &str| -> usize {
+        if key == "$schema" {
+            return 0;
+        }
+        if let Some(i) = RULE_FIELDS.iter().position(|(f, _)| field_names(f).any(|n| n == key)) {
+            return 1 + i;
+        }
+        match IGNORED_FIELDS.iter().position(|f| *f == key) {
+            Some(i) => 1 + RULE_FIELDS.len() + i,
+            None => usize::MAX,
+        }
+    };
+    let mut keys: Vec<&String> = object.keys().collect();
+    keys.sort_by(|a, b| rank(a).cmp(&rank(b)).then_with(|| a.cmp(b)));
+
+    let mut out = String::from("{\n");
+    for (i, key) in keys.iter().enumerate() {
+        let mut buf = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+        let mut serializer = serde_json::Serializer::with_formatter(&mut buf, formatter);
+        object[key.as_str()].serialize(&mut serializer)?;
+        // 字符串中的换行已转义，按行缩进不会改变取值
+        let value = String::from_utf8(buf)?.replace('\n', "\n    ");
+        let comma = if i + 1 < keys.len() { "," } else { "" };
+        out.push_str(&format!("    {}: {}{}\n", serde_json::to_string(key)?, value, comma));
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// 新规则模板 (未提供的字段使用占位符，需要按站点修改)
+pub fn rule_template(name: &str, base_url: &str, search_url: &str) -> String {
+    let value = serde_json::json!({
+        "api": "1",
+        "type": "anime",
+        "name": name,
+        "version": "1.0",
+        "muliSources": true,
+        "useWebview": true,
+        "useNativePlayer": true,
+        "usePost": false,
+        "useLegacyParser": false,
+        "userAgent": "",
+        "baseURL": base_url,
+        "searchURL": search_url,
+        "searchList": "//div[@class='TODO-list-item']",
+        "searchName": "//a[@class='TODO-title']",
+        "searchResult": "//a[@class='TODO-title']",
+        "chapterRoads": "//div[@class='TODO-playlist']",
+        "chapterResult": "//ul/li/a",
+    });
+    format_rule_json(&value.to_string()).expect("template is a JSON object")
+}
+
+/// 字段的所有可接受名称
+fn field_names(field: &str) -> impl Iterator<Item = &str> {
+    let aliases = RULE_FIELDS
+        .iter()
+        .find(|(f, _)| *f == field)
+        .map(|(_, aliases)| *aliases)
+        .unwrap_or(&[]);
+    std::iter::once(field).chain(aliases.iter().copied())
+}
+
+/// 报告中使用的字段名 (优先 Kazumi 风格的驼峰别名)
+fn display_name(field: &str) -> String {
+    field_names(field).nth(1).unwrap_or(field).to_string()
+}
+
+/// 为未知字段推荐最接近的已知字段 (忽略大小写与下划线)
+fn suggest_field(unknown: &str) -> Option<String> {
+    let normalize = |s: &str| s.replace('_', "").to_ascii_lowercase();
+    let target = normalize(unknown);
+    RULE_FIELDS
+        .iter()
+        .map(|(f, _)| (display_name(f), edit_distance(&target, &normalize(f))))
+        .filter(|(_, d)| *d <= 2)
+        .min_by_key(|(_, d)| *d)
+        .map(|(name, _)| name)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut cur = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// 规则集合 (按名称排序)
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Arc<Rule>>,
+    /// 规则分组 (分组名 -> 规则名，只保留存在的规则)
+    groups: BTreeMap<String, Vec<String>>,
+    /// 加载时的字段检查警告 (文件名: 问题)
+    warnings: Vec<String>,
+    /// 规则文件名 -> 规则名
+    sources: BTreeMap<String, String>,
+    /// 无法解析的规则文件名
+    failed: Vec<String>,
+    /// 无法解析的规则文件的错误详情 (与 `failed` 对应)
+    errors: Vec<RuleFileError>,
+}
+
+impl RuleSet {
+    /// 从指定目录加载所有 JSON 规则 (跳过 index.json、groups.json 与无法解析的文件)
+    /// 及规则分组 (groups.json，可选)
+    pub fn load(dir: impl AsRef<Path>) -> Self {
+        Self::load_keeping(dir, None)
+    }
+
+    /// 重新加载目录: 无法解析的文件沿用 `previous` 中由同一文件加载的规则，不影响其他规则
+    pub fn reload(&self, dir: impl AsRef<Path>) -> Self {
+        Self::load_keeping(dir, Some(self))
+    }
+
+    fn load_keeping(dir: impl AsRef<Path>, previous: Option<&RuleSet>) -> Self {
+        let loaded = load_all_rules(dir.as_ref());
+        let mut warnings = loaded.warnings;
+        let mut set = Self {
+            rules: loaded.rules,
+            groups: BTreeMap::new(),
+            warnings: Vec::new(),
+            sources: loaded.sources,
+            failed: loaded.failed,
+            errors: loaded.errors,
+        };
+        if let Some(previous) = previous {
+            for file in &set.failed {
+                let Some(name) = previous.sources.get(file) else {
+                    continue;
+                };
+                let Some(rule) = previous.get(name) else {
+                    continue;
+                };
+                if set.rules.iter().any(|r| &r.name == name) {
+                    continue;
+                }
+                warn!("⚠️ 规则文件 {} 解析失败，沿用已加载的 {} v{}", file, rule.name, rule.version);
+                warnings.push(format!("{}: 解析失败，沿用上一版本", file));
+                set.sources.insert(file.clone(), name.clone());
+                set.rules.push(rule);
+            }
+            set.rules.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        crate::types::assign_colors(&mut set.rules);
+        let path = dir.as_ref().join(GROUPS_FILE);
+        if path.exists() {
+            match load_groups(&path) {
+                Ok(groups) => warnings.extend(set.set_groups(groups)),
+                Err(e) => {
+                    warn!("⚠️ 加载规则分组失败 {}: {}", path.display(), e);
+                    warnings.push(format!("{}: {}", GROUPS_FILE, e));
+                }
+            }
+        }
+        set.warnings = warnings;
+        set
+    }
+
+    /// 由已有规则构建
+    pub fn from_rules(rules: impl IntoIterator<Item = Rule>) -> Self {
+        let mut rules: Vec<Arc<Rule>> = rules.into_iter().map(Arc::new).collect();
+        rules.sort_by(|a, b| a.name.cmp(&b.name));
+        crate::types::assign_colors(&mut rules);
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    /// 设置规则分组，返回引用了不存在规则的警告 (这些规则名会被移除，空分组保留)
+    pub fn set_groups(&mut self, groups: BTreeMap<String, Vec<String>>) -> Vec<String> {
+        let mut warnings = Vec::new();
+        self.groups = groups
+            .into_iter()
+            .map(|(group, names)| {
+                let names = names
+                    .into_iter()
+                    .filter(|name| {
+                        let exists = self.rules.iter().any(|r| &r.name == name);
+                        if !exists {
+                            warn!("⚠️ 规则分组 {} 引用了不存在的规则: {}", group, name);
+                            warnings.push(format!(
+                                "{}: 分组 {} 引用了不存在的规则 {}",
+                                GROUPS_FILE, group, name
+                            ));
+                        }
+                        exists
+                    })
+                    .collect();
+                (group, names)
+            })
+            .collect();
+        warnings
+    }
+
+    /// 规则分组
+    pub fn groups(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.groups
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Rule>> {
+        self.rules.iter()
+    }
+
+    /// 加载时的字段检查警告
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// 无法解析的规则文件名
+    pub fn failed_files(&self) -> &[String] {
+        &self.failed
+    }
+
+    /// 无法解析的规则文件的错误详情
+    pub fn file_errors(&self) -> &[RuleFileError] {
+        &self.errors
+    }
+
+    /// 按名称查找规则
+    pub fn get(&self, name: &str) -> Option<Arc<Rule>> {
+        self.rules.iter().find(|r| r.name == name).cloned()
+    }
+
+    /// 所有规则
+    pub fn to_vec(&self) -> Vec<Arc<Rule>> {
+        self.rules.clone()
+    }
+
+    /// 按逗号分隔的规则名筛选规则
+    pub fn select(&self, names: Option<&str>) -> Result<Vec<Arc<Rule>>, &'static str> {
+        self.select_with_group(names, None)
+    }
+
+    /// 按规则名与分组筛选规则 (分组展开为其中的规则，与规则名取并集)
+    pub fn select_with_group(
+        &self,
+        names: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<Vec<Arc<Rule>>, &'static str> {
+        let names = names.map(str::trim).filter(|s| !s.is_empty());
+        let group = group.map(str::trim).filter(|s| !s.is_empty());
+        if names.is_none() && group.is_none() {
+            return Err(
+                "Rules are required. Use 'rules' field to specify rule names (comma separated) or 'group' field to specify a rule group",
+            );
+        }
+
+        let name_list: Vec<&str> = names
+            .map(|names| names.split(',').map(|s| s.trim()).collect())
+            .unwrap_or_default();
+        let mut group_members: Vec<&str> = Vec::new();
+        if let Some(group) = group {
+            let members = self.groups.get(group).ok_or("Unknown rule group")?;
+            group_members.extend(members.iter().map(String::as_str));
+        }
+        // 显式指定的规则总是执行；经分组选中的规则跳过已自动停用的
+        let mut skipped = 0;
+        let selected: Vec<_> = self
+            .rules
+            .iter()
+            .filter(|r| {
+                let name = r.name.as_str();
+                if name_list.contains(&name) {
+                    return true;
+                }
+                if !group_members.contains(&name) {
+                    return false;
+                }
+                if crate::rule_stats::is_auto_disabled(name) {
+                    skipped += 1;
+                    return false;
+                }
+                true
+            })
+            .cloned()
+            .collect();
+
+        if selected.is_empty() {
+            return Err(if skipped > 0 {
+                "All matching rules are auto-disabled"
+            } else {
+                "No matching rules found"
+            });
+        }
+
+        Ok(selected)
+    }
+}
 
 /// 获取所有规则
 pub fn get_builtin_rules() -> Vec<Arc<Rule>> {
-    RULES.clone()
+    builtin().to_vec()
+}
+
+/// 内置规则加载时的字段检查警告
+pub fn builtin_warnings() -> Vec<String> {
+    builtin().warnings().to_vec()
+}
+
+/// 按逗号分隔的规则名筛选内置规则
+pub fn select_rules(names: Option<&str>) -> Result<Vec<Arc<Rule>>, &'static str> {
+    builtin().select(names)
+}
+
+/// 按规则名与分组筛选内置规则
+pub fn select_rules_with_group(
+    names: Option<&str>,
+    group: Option<&str>,
+) -> Result<Vec<Arc<Rule>>, &'static str> {
+    builtin().select_with_group(names, group)
+}
+
+/// 内置规则目录中无法解析的文件
+pub fn rule_file_errors() -> Vec<RuleFileError> {
+    builtin().file_errors().to_vec()
 }
 
-/// 从 rules/ 目录加载所有规则
-fn load_all_rules() -> Vec<Arc<Rule>> {
-    let mut rules = Vec::new();
-    let rules_path = Path::new(RULES_DIR);
+/// 内置规则分组
+pub fn rule_groups() -> BTreeMap<String, Vec<String>> {
+    builtin().groups().clone()
+}
+
+/// 重新加载规则目录并替换全局规则集，返回新的规则集
+pub fn reload_builtin_rules() -> Arc<RuleSet> {
+    let mut rules = RULES.write().unwrap_or_else(|e| e.into_inner());
+    let next = Arc::new(rules.reload(&CONFIG.rules_dir));
+    *rules = next.clone();
+    next
+}
+
+/// 目录加载结果
+#[derive(Default)]
+struct LoadedRules {
+    rules: Vec<Arc<Rule>>,
+    warnings: Vec<String>,
+    sources: BTreeMap<String, String>,
+    failed: Vec<String>,
+    errors: Vec<RuleFileError>,
+}
+
+/// 无法解析的规则文件 (JSON 语法错误时附带行列号，从 1 开始)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleFileError {
+    pub file: String,
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+impl RuleFileError {
+    fn new(file: &str, error: &anyhow::Error) -> Self {
+        // 字段类型错误等非语法错误的行号为 0 (由 Value 转换时没有位置信息)
+        let position = error
+            .downcast_ref::<serde_json::Error>()
+            .filter(|e| e.line() > 0)
+            .map(|e| (e.line(), e.column()));
+        Self {
+            file: file.to_string(),
+            error: error.to_string(),
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+        }
+    }
+}
+
+/// 从目录加载所有规则，同时返回字段检查警告与无法解析的文件
+fn load_all_rules(rules_path: &Path) -> LoadedRules {
+    let mut loaded = LoadedRules::default();
 
     if !rules_path.exists() {
-        warn!("规则目录 {} 不存在，请创建并添加规则文件", RULES_DIR);
-        return rules;
+        warn!("规则目录 {} 不存在，请创建并添加规则文件", rules_path.display());
+        return loaded;
     }
 
     // 读取目录中的所有 JSON 文件
@@ -34,19 +574,30 @@ fn load_all_rules() -> Vec<Arc<Rule>> {
         Ok(entries) => {
             for entry in entries.flatten() {
                 let path = entry.path();
-                // 跳过 index.json (Kazumi 索引文件)
+                // 跳过 index.json (Kazumi 索引文件) 与 groups.json (规则分组)
                 let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-                if filename == "index.json" {
+                if filename == "index.json" || filename == GROUPS_FILE {
                     continue;
                 }
                 if path.extension().map(|e| e == "json").unwrap_or(false) {
                     match load_rule_from_file(&path) {
-                        Ok(rule) => {
+                        Ok((rule, _)) if rule.is_mock() && !CONFIG.enable_mock_rules => {
+                            warn!("⚠️ 跳过模拟规则 {} (需设置 ENABLE_MOCK_RULES=1)", filename);
+                            loaded.warnings.push(format!("{}: 模拟规则未启用 (ENABLE_MOCK_RULES)", filename));
+                        }
+                        Ok((rule, report)) => {
                             info!("📦 加载规则: {} v{}", rule.name, rule.version);
-                            rules.push(Arc::new(rule));
+                            for message in report.messages() {
+                                warn!("⚠️ 规则 {}: {}", filename, message);
+                                loaded.warnings.push(format!("{}: {}", filename, message));
+                            }
+                            loaded.sources.insert(filename.to_string(), rule.name.clone());
+                            loaded.rules.push(Arc::new(rule));
                         }
                         Err(e) => {
                             warn!("⚠️ 加载规则失败 {}: {}", path.display(), e);
+                            loaded.failed.push(filename.to_string());
+                            loaded.errors.push(RuleFileError::new(filename, &e));
                         }
                     }
                 }
@@ -58,14 +609,367 @@ fn load_all_rules() -> Vec<Arc<Rule>> {
     }
 
     // 按名称排序
-    rules.sort_by(|a, b| a.name.cmp(&b.name));
+    loaded.rules.sort_by(|a, b| a.name.cmp(&b.name));
+    loaded.failed.sort();
+    loaded.errors.sort_by(|a, b| a.file.cmp(&b.file));
 
-    rules
+    loaded
 }
 
-/// 从 JSON 文件加载单个规则
-fn load_rule_from_file(path: &Path) -> anyhow::Result<Rule> {
+/// 加载规则分组文件
+fn load_groups(path: &Path) -> anyhow::Result<BTreeMap<String, Vec<String>>> {
     let content = fs::read_to_string(path)?;
-    let rule: Rule = serde_json::from_str(&content)?;
-    Ok(rule)
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// 解析规则文件内容: 忽略开头的 UTF-8 BOM；启用 RULES_LENIENT_JSON 时还允许注释与尾随逗号
+pub fn parse_rule_json(content: &str) -> serde_json::Result<Value> {
+    parse_rule_json_with(content, CONFIG.rules_lenient_json)
+}
+
+fn parse_rule_json_with(content: &str, lenient: bool) -> serde_json::Result<Value> {
+    let content = content.trim_start_matches('\u{feff}');
+    if lenient {
+        serde_json::from_str(&strip_json_extras(content))
+    } else {
+        serde_json::from_str(content)
+    }
+}
+
+/// 把 `//`、`/* */` 注释与 `}`/`]` 前的尾随逗号替换为等长的空白 (字符串内不处理)，
+/// 保留换行与字节位置，解析错误的行列号仍对应原文件
+fn strip_json_extras(content: &str) -> String {
+    let blank = |out: &mut String, c: char| {
+        if c == '\n' {
+            out.push('\n');
+        } else {
+            out.extend(std::iter::repeat_n(' ', c.len_utf8()));
+        }
+    };
+
+    // 第一遍去掉注释
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                blank(&mut out, c);
+                while let Some(c) = chars.next_if(|c| *c != '\n') {
+                    blank(&mut out, c);
+                }
+            }
+            ('/', Some('*')) => {
+                blank(&mut out, c);
+                blank(&mut out, chars.next().unwrap_or('*'));
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    blank(&mut out, c);
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    // 第二遍去掉尾随逗号 (逗号之后只有空白就遇到 `}` 或 `]`)
+    let mut bytes = out.into_bytes();
+    let mut in_string = false;
+    let mut escaped = false;
+    for i in 0..bytes.len() {
+        let b = bytes[i];
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b',' => {
+                let next = bytes[i + 1..].iter().find(|b| !b.is_ascii_whitespace());
+                if matches!(next, Some(b'}' | b']')) {
+                    bytes[i] = b' ';
+                }
+            }
+            _ => {}
+        }
+    }
+    // 只替换了 ASCII 字节，仍是合法的 UTF-8
+    String::from_utf8(bytes).unwrap_or_default()
+}
+
+/// 从 JSON 文件加载单个规则 (宽松解析，附带字段检查结果)
+fn load_rule_from_file(path: &Path) -> anyhow::Result<(Rule, RuleFieldReport)> {
+    let content = fs::read_to_string(path)?;
+    let value: Value = parse_rule_json(&content)?;
+    let report = check_rule_fields(&value);
+    let mut rule: Rule = serde_json::from_value(value)?;
+    rule.color = crate::types::normalize_color(&rule.color);
+    Ok((rule, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_fields_cover_rule_definition() {
+        let value = serde_json::to_value(Rule::default()).unwrap();
+        let report = check_rule_fields(&value);
+        assert!(report.unknown_fields.is_empty(), "{:?}", report.unknown_fields);
+        assert_eq!(value.as_object().unwrap().len(), RULE_FIELDS.len());
+    }
+
+    #[test]
+    fn test_check_rule_fields_reports_typos() {
+        let value = serde_json::json!({
+            "name": "AGE",
+            "baseURL": "https://example.com",
+            "searchURL": "https://example.com/search?q=@keyword",
+            "serachName": "//h3/a",
+            "searchList": "//div[@class='item']",
+            "chapterRoads": "",
+        });
+        let report = check_rule_fields(&value);
+        assert_eq!(report.unknown_fields, vec!["serachName"]);
+        assert!(report.missing_required.is_empty());
+        assert_eq!(
+            report.missing_recommended,
+            vec!["searchName", "searchResult", "chapterRoads", "chapterResult"]
+        );
+        assert!(report.messages()[0].contains("是否为 searchName"));
+    }
+
+    #[test]
+    fn test_rss_rule_needs_no_selectors() {
+        let value = serde_json::json!({
+            "name": "Mikan",
+            "ruleType": "rss",
+            "baseURL": "https://mikanani.me/",
+            "searchURL": "https://mikanani.me/RSS/Search?searchstr=@keyword",
+        });
+        let report = check_rule_fields(&value);
+        assert!(report.is_clean(), "{:?}", report);
+        let rule: Rule = serde_json::from_value(value).unwrap();
+        assert!(rule.is_rss());
+    }
+
+    #[test]
+    fn test_builtin_rule_files_match_schema() {
+        let schema = serde_json::to_value(crate::types::rule_schema()).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+
+        let mut checked = 0;
+        for entry in fs::read_dir("rules").unwrap().flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|e| e != "json")
+                || path.ends_with("index.json")
+                || path.ends_with(GROUPS_FILE)
+            {
+                continue;
+            }
+            let value: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            let errors: Vec<String> = validator
+                .iter_errors(&value)
+                .map(|e| format!("{}: {}", e.instance_path(), e))
+                .collect();
+            assert!(errors.is_empty(), "{}: {:?}", path.display(), errors);
+            checked += 1;
+        }
+        assert!(checked > 0);
+        assert!(!validator.is_valid(&serde_json::json!({"name": "x", "usePost": "yes"})));
+    }
+
+    #[test]
+    fn test_builtin_rules_get_distinct_colors() {
+        let set = RuleSet::load("rules");
+        assert!(set.len() > 1);
+        let hexes: Vec<(&str, &str)> = set.iter().map(|r| (r.name.as_str(), r.color_hex.as_str())).collect();
+        let distinct: std::collections::HashSet<_> = hexes.iter().map(|(_, hex)| hex).collect();
+        assert_eq!(distinct.len(), hexes.len(), "{:?}", hexes);
+        assert!(hexes.iter().all(|(_, hex)| hex.len() == 7 && hex.starts_with('#')));
+    }
+
+    #[test]
+    fn test_rule_groups_expand_and_validate() {
+        let mut set = RuleSet::from_rules(["AGE", "MX", "NT"].map(|name| Rule {
+            name: name.to_string(),
+            ..Default::default()
+        }));
+        let groups: BTreeMap<String, Vec<String>> = serde_json::from_value(serde_json::json!({
+            "正版": ["AGE", "Missing"],
+            "聚合": ["MX"]
+        }))
+        .unwrap();
+        let warnings = set.set_groups(groups);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("Missing"));
+        assert_eq!(set.groups()["正版"], vec!["AGE"]);
+
+        let names = |rules: Vec<Arc<Rule>>| rules.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(set.select_with_group(None, Some("正版")).unwrap()), vec!["AGE"]);
+        assert_eq!(
+            names(set.select_with_group(Some("NT"), Some("聚合")).unwrap()),
+            vec!["MX", "NT"]
+        );
+        assert!(set.select_with_group(None, Some("里番")).is_err());
+        assert!(set.select_with_group(None, None).is_err());
+    }
+
+    #[test]
+    fn test_reload_keeps_previous_rule_when_file_breaks() {
+        let dir = std::env::temp_dir().join(format!("rules-reload-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let rule = |name: &str, version: &str| {
+            serde_json::json!({
+                "name": name,
+                "version": version,
+                "baseURL": "https://example.com/",
+                "searchURL": "https://example.com/search?q=@keyword",
+            })
+            .to_string()
+        };
+        fs::write(dir.join("AGE.json"), rule("AGE", "1.0")).unwrap();
+        fs::write(dir.join("MX.json"), rule("MX", "1.0")).unwrap();
+        fs::write(dir.join("groups.json"), r#"{"全部": ["AGE", "MX"]}"#).unwrap();
+        let set = RuleSet::load(&dir);
+        assert_eq!(set.len(), 2);
+
+        fs::write(dir.join("AGE.json"), "{\"name\": \"AGE\",").unwrap();
+        fs::write(dir.join("MX.json"), rule("MX", "2.0")).unwrap();
+        fs::write(dir.join("NT.json"), rule("NT", "1.0")).unwrap();
+        let reloaded = set.reload(&dir);
+        assert_eq!(reloaded.failed_files(), ["AGE.json"]);
+        assert_eq!(reloaded.get("AGE").unwrap().version, "1.0");
+        assert_eq!(reloaded.get("MX").unwrap().version, "2.0");
+        assert!(reloaded.get("NT").is_some());
+        assert_eq!(reloaded.groups()["全部"], vec!["AGE", "MX"]);
+
+        // 没有上一版本时直接跳过无法解析的文件
+        assert!(RuleSet::load(&dir).get("AGE").is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bom_prefixed_rule_loads_and_errors_have_position() {
+        let dir = std::env::temp_dir().join(format!("rules-bom-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let rule = serde_json::json!({
+            "name": "AGE",
+            "baseURL": "https://example.com/",
+            "searchURL": "https://example.com/search?q=@keyword",
+        });
+        fs::write(dir.join("AGE.json"), format!("\u{feff}{}", rule)).unwrap();
+        fs::write(
+            dir.join("MX.json"),
+            "{\n    \"name\": \"MX\",\n    \"baseURL\": \"https://example.com/\",\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("NT.json"), r#"{"name": 1}"#).unwrap();
+
+        let set = RuleSet::load(&dir);
+        assert!(set.get("AGE").is_some());
+        assert_eq!(set.failed_files(), ["MX.json", "NT.json"]);
+        let errors = set.file_errors();
+        assert_eq!((errors[0].line, errors[0].column), (Some(4), Some(1)));
+        assert!(errors[0].error.contains("trailing comma"), "{}", errors[0].error);
+        // 字段类型错误没有位置信息
+        assert_eq!(errors[1].file, "NT.json");
+        assert_eq!(errors[1].line, None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_lenient_rule_json_allows_comments_and_trailing_commas() {
+        let content = "\u{feff}{\n  // 站点: 示例\n  \"name\": \"AGE\", /* 名称 */\n  \"tags\": [\"在线\", \"a//b,]\",],\n  \"searchURL\": \"https://example.com/s?q=@keyword\",\n}";
+        assert!(parse_rule_json_with(content, false).is_err());
+        let value = parse_rule_json_with(content, true).unwrap();
+        assert_eq!(value["name"], "AGE");
+        assert_eq!(value["tags"], serde_json::json!(["在线", "a//b,]"]));
+        assert_eq!(value["searchURL"], "https://example.com/s?q=@keyword");
+
+        // 替换为等长空白，错误位置仍对应原文
+        let stripped = strip_json_extras("{\"a\": 1, /* 注释 */ }");
+        assert_eq!(stripped.len(), "{\"a\": 1, /* 注释 */ }".len());
+        let lenient = parse_rule_json_with("{\n  /* 注释 */ \"a\": x\n}", true).unwrap_err();
+        let strict = parse_rule_json_with(&format!("{{\n{}\"a\": x\n}}", " ".repeat(15)), false)
+            .unwrap_err();
+        assert_eq!((lenient.line(), lenient.column()), (strict.line(), strict.column()));
+        assert_eq!(lenient.line(), 2);
+    }
+
+    #[test]
+    fn test_mock_rules_require_opt_in() {
+        let value = serde_json::json!({
+            "name": "mock1",
+            "type": "mock",
+            "baseURL": "mock://mock1/",
+            "searchURL": "mock://mock1/search?q=@keyword",
+            "mock": {"latencyMs": 200},
+        });
+        assert!(check_rule_fields(&value).is_clean());
+        let rule: Rule = serde_json::from_value(value.clone()).unwrap();
+        assert!(rule.is_mock());
+        assert_eq!(rule.mock.as_ref().unwrap().latency_ms, 200);
+        assert_eq!(rule.mock.as_ref().unwrap().items, 5);
+
+        // 测试环境未设置 ENABLE_MOCK_RULES，模拟规则不会被加载
+        let dir = std::env::temp_dir().join(format!("rules-mock-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("mock1.json"), value.to_string()).unwrap();
+        let set = RuleSet::load(&dir);
+        assert!(set.is_empty());
+        assert_eq!(set.warnings().len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_format_rule_json_orders_fields() {
+        let content = "\u{feff}{\"searchURL\": \"https://example.com/s?q=@keyword\", \"zzz\": 1, \"tags\": [\"在线\", \"正版\"], \"name\": \"AGE\", \"deprecated\": false, \"$schema\": \"./schema.json\", \"baseURL\": \"https://example.com/\"}";
+        let formatted = format_rule_json(content).unwrap();
+        assert_eq!(
+            formatted,
+            r#"{
+    "$schema": "./schema.json",
+    "name": "AGE",
+    "baseURL": "https://example.com/",
+    "searchURL": "https://example.com/s?q=@keyword",
+    "tags": [
+        "在线",
+        "正版"
+    ],
+    "deprecated": false,
+    "zzz": 1
+}
+"#
+        );
+        assert_eq!(format_rule_json(&formatted).unwrap(), formatted);
+
+        let template: Value = serde_json::from_str(&rule_template("X", "https://x/", "https://x/s?q=@keyword")).unwrap();
+        assert!(check_rule_fields(&template).is_clean());
+        assert!(format_rule_json("[]").is_err());
+    }
 }
@@ -0,0 +1,71 @@
+//! 简繁转换
+//! 基于 OpenCC 词表 (zhconv)，将搜索结果名称统一为指定字形
+
+use crate::types::SearchResultItem;
+use zhconv::{zhconv, Variant};
+
+/// 目标字形
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script {
+    /// 简体
+    Simplified,
+    /// 繁体
+    Traditional,
+}
+
+impl Script {
+    /// 解析请求参数 (simplified / traditional，也接受 hans / hant)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "simplified" | "hans" | "zh-hans" => Some(Script::Simplified),
+            "traditional" | "hant" | "zh-hant" => Some(Script::Traditional),
+            _ => None,
+        }
+    }
+
+    fn variant(self) -> Variant {
+        match self {
+            Script::Simplified => Variant::ZhHans,
+            Script::Traditional => Variant::ZhHant,
+        }
+    }
+}
+
+/// 转换单个字符串
+pub fn convert(text: &str, script: Script) -> String {
+    zhconv(text, script.variant())
+}
+
+/// 转换搜索结果中的所有名称 (条目名、播放源名、集数名)
+pub fn convert_items(items: &mut [SearchResultItem], script: Script) {
+    for item in items.iter_mut() {
+        item.name = convert(&item.name, script);
+        for road in item.episodes.iter_mut().flatten() {
+            if let Some(name) = road.name.as_mut() {
+                *name = convert(name, script);
+            }
+            for episode in road.episodes.iter_mut() {
+                episode.name = convert(&episode.name, script);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traditional_to_simplified() {
+        assert_eq!(convert("葬送的芙莉蓮", Script::Simplified), "葬送的芙莉莲");
+        assert_eq!(convert("進擊的巨人", Script::Simplified), "进击的巨人");
+        assert_eq!(convert("进击的巨人", Script::Traditional), "進擊的巨人");
+    }
+
+    #[test]
+    fn test_parse_script() {
+        assert_eq!(Script::parse("simplified"), Some(Script::Simplified));
+        assert_eq!(Script::parse("Traditional"), Some(Script::Traditional));
+        assert_eq!(Script::parse("latin"), None);
+    }
+}
@@ -0,0 +1,295 @@
+//! 审计日志
+//! 以 JSON Lines 追加写入 `<DATA_DIR>/audit.log`，记录所有会修改状态的操作
+//! (Bangumi 收藏写入、规则更新、token 档案变更等)，单文件超过上限后轮转为 `audit.log.1`
+
+// token 指纹与 Bangumi 写操作识别仅由 bangumi 功能使用
+#![cfg_attr(not(feature = "bangumi"), allow(dead_code))]
+
+use crate::config::CONFIG;
+use axum::http::HeaderMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// 审计日志文件名
+const AUDIT_FILE: &str = "audit.log";
+
+/// 写入锁 (保证多行不交错，轮转时不丢行)
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// 请求 ID 计数器
+static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 单条审计记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// 时间 (RFC 3339)
+    pub timestamp: String,
+    /// 请求 ID
+    pub request_id: String,
+    /// 客户端 IP
+    pub client_ip: String,
+    /// 路由 (方法 + 路径)
+    pub route: String,
+    /// 操作对象 (subject_id / 规则名等)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// 结果 (如 "ok", "HTTP 404", "failed: ...")
+    pub outcome: String,
+    /// Token 指纹 (SHA-256 前缀，不记录原文)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_fingerprint: Option<String>,
+    /// 使用的 token 档案名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_profile: Option<String>,
+}
+
+impl AuditEntry {
+    /// 创建记录，时间戳取当前时间
+    pub fn new(request_id: String, client_ip: String, route: String, outcome: String) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            request_id,
+            client_ip,
+            route,
+            target: None,
+            outcome,
+            token_fingerprint: None,
+            token_profile: None,
+        }
+    }
+
+    pub fn target(mut self, target: Option<String>) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn token(mut self, token: Option<&str>) -> Self {
+        self.token_fingerprint = token.map(token_fingerprint);
+        self
+    }
+
+    pub fn profile(mut self, profile: Option<String>) -> Self {
+        self.token_profile = profile;
+        self
+    }
+}
+
+fn audit_path() -> PathBuf {
+    PathBuf::from(&CONFIG.data_dir).join(AUDIT_FILE)
+}
+
+fn rotated_path() -> PathBuf {
+    PathBuf::from(&CONFIG.data_dir).join(format!("{}.1", AUDIT_FILE))
+}
+
+/// 追加一条审计记录 (失败只打印警告，不影响请求)
+pub fn record(entry: AuditEntry) {
+    if let Err(e) = append(&entry) {
+        warn!("写入审计日志失败: {}", e);
+    }
+}
+
+fn append(entry: &AuditEntry) -> anyhow::Result<()> {
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    fs::create_dir_all(&CONFIG.data_dir)?;
+
+    let path = audit_path();
+    if let Ok(meta) = fs::metadata(&path) {
+        if meta.len() >= CONFIG.audit_max_bytes {
+            fs::rename(&path, rotated_path())?;
+        }
+    }
+
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// 读取最近的 `limit` 条记录 (新的在前)
+pub fn read_recent(limit: usize) -> Vec<AuditEntry> {
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries: Vec<AuditEntry> = Vec::new();
+
+    // 当前文件不足时从轮转文件补齐
+    for path in [audit_path(), rotated_path()] {
+        if entries.len() >= limit {
+            break;
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        entries.extend(
+            content
+                .lines()
+                .rev()
+                .filter_map(|l| serde_json::from_str(l).ok())
+                .take(limit - entries.len()),
+        );
+    }
+
+    entries
+}
+
+/// 计算 token 指纹 (SHA-256 十六进制前 12 位)
+pub fn token_fingerprint(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    digest.iter().take(6).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 从请求头提取 Bearer token (scheme 大小写不敏感；非 Bearer 方案返回 None)
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get("Authorization")?.to_str().ok()?.trim();
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("Bearer")
+        .then(|| token.trim())
+        .filter(|v| !v.is_empty())
+}
+
+/// 获取请求 ID (优先使用 X-Request-Id 请求头，否则生成)
+pub fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get("X-Request-Id")
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| {
+            let seq = REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+            format!("{:x}-{:x}", chrono::Utc::now().timestamp_millis(), seq)
+        })
+}
+
+/// 获取客户端 IP (TRUST_FORWARDED 时优先反代头，否则取连接地址)
+pub fn client_ip(headers: &HeaderMap, addr: &SocketAddr) -> String {
+    resolve_client_ip(headers, addr, CONFIG.trust_forwarded)
+}
+
+fn resolve_client_ip(headers: &HeaderMap, addr: &SocketAddr, trust_forwarded: bool) -> String {
+    let forwarded = trust_forwarded
+        .then(|| {
+            headers
+                .get("X-Forwarded-For")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .or_else(|| headers.get("X-Real-IP").and_then(|v| v.to_str().ok()))
+                .and_then(|v| v.trim().parse::<IpAddr>().ok())
+        })
+        .flatten();
+    forwarded.unwrap_or_else(|| addr.ip()).to_string()
+}
+
+/// 常量时间比较密钥 (先取 SHA-256 摘要，避免长度与前缀泄露耗时差异)
+pub fn secret_eq(provided: &str, expected: &str) -> bool {
+    let a = Sha256::digest(provided.as_bytes());
+    let b = Sha256::digest(expected.as_bytes());
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 判断 Bangumi 代理请求是否为写操作，返回操作对象
+///
+/// 覆盖: 条目收藏新增/修改、章节收藏更新、角色/人物/目录收藏
+pub fn bangumi_mutation_target(method: &str, path: &str) -> Option<String> {
+    if !matches!(method, "POST" | "PUT" | "PATCH" | "DELETE") {
+        return None;
+    }
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        // v0/users/-/collections/{subject_id}[/episodes]
+        ["v0", "users", "-", "collections", "-", "episodes", episode_id] => {
+            Some(format!("episode:{}", episode_id))
+        }
+        ["v0", "users", "-", "collections", subject_id, ..] => {
+            Some(format!("subject:{}", subject_id))
+        }
+        // v0/{characters|persons|indices}/{id}/collect
+        ["v0", kind @ ("characters" | "persons" | "indices"), id, "collect"] => {
+            let kind = match *kind {
+                "characters" => "character",
+                "persons" => "person",
+                _ => "index",
+            };
+            Some(format!("{}:{}", kind, id))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_fingerprint() {
+        let fp = token_fingerprint("secret-token");
+        assert_eq!(fp.len(), 12);
+        assert!(!fp.contains("secret"));
+        assert_eq!(fp, token_fingerprint("secret-token"));
+    }
+
+    #[test]
+    fn test_bangumi_mutation_target() {
+        assert_eq!(
+            bangumi_mutation_target("POST", "v0/users/-/collections/328609"),
+            Some("subject:328609".to_string())
+        );
+        assert_eq!(
+            bangumi_mutation_target("PATCH", "v0/users/-/collections/328609/episodes"),
+            Some("subject:328609".to_string())
+        );
+        assert_eq!(
+            bangumi_mutation_target("PUT", "v0/users/-/collections/-/episodes/1234"),
+            Some("episode:1234".to_string())
+        );
+        assert_eq!(
+            bangumi_mutation_target("DELETE", "v0/persons/5/collect"),
+            Some("person:5".to_string())
+        );
+        assert_eq!(bangumi_mutation_target("GET", "v0/users/-/collections/1"), None);
+        assert_eq!(bangumi_mutation_target("POST", "v0/search/subjects"), None);
+    }
+
+    #[test]
+    fn test_bearer_token_scheme() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "bearer abc".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("abc"));
+        headers.insert("Authorization", "Bearer  abc ".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("abc"));
+        headers.insert("Authorization", "Basic dXNlcjpwYXNz".parse().unwrap());
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert("Authorization", "rawtoken".parse().unwrap());
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert("Authorization", "Bearer ".parse().unwrap());
+        assert_eq!(bearer_token(&headers), None);
+    }
+
+    #[test]
+    fn test_client_ip_only_trusts_forwarded_when_enabled() {
+        let addr: SocketAddr = "10.0.0.2:5000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Forwarded-For", "203.0.113.7, 10.0.0.1".parse().unwrap());
+        assert_eq!(resolve_client_ip(&headers, &addr, true), "203.0.113.7");
+        assert_eq!(resolve_client_ip(&headers, &addr, false), "10.0.0.2");
+
+        headers.insert("X-Forwarded-For", "not-an-ip".parse().unwrap());
+        assert_eq!(resolve_client_ip(&headers, &addr, true), "10.0.0.2");
+    }
+
+    #[test]
+    fn test_secret_eq() {
+        assert!(secret_eq("admin-key", "admin-key"));
+        assert!(!secret_eq("admin-ke", "admin-key"));
+        assert!(!secret_eq("", "admin-key"));
+    }
+}
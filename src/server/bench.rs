@@ -0,0 +1,105 @@
+//! 解析基准
+//! 对规则最近一次请求成功的搜索页 (没有时使用自检的内置样例) 重复执行搜索结果解析，
+//! 报告吞吐与延迟分位数，排除网络波动，用于评估 XPath 解析的性能变化
+
+use super::selftest::FIXTURE_HTML;
+use crate::engine::{last_search_page, parse_search_results};
+use crate::types::Rule;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 缺省迭代次数
+pub const DEFAULT_ITERATIONS: usize = 100;
+
+/// 迭代次数上限
+pub const MAX_ITERATIONS: usize = 10_000;
+
+/// 基准结果
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    pub rule: String,
+    /// 页面来源: `last_fetched` (最近一次搜索页) 或 `fixture` (内置样例)
+    pub source: &'static str,
+    pub body_bytes: usize,
+    pub iterations: usize,
+    /// 每次解析得到的结果数
+    pub items: usize,
+    pub total_ms: f64,
+    /// 每秒解析次数
+    pub throughput_per_sec: f64,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+}
+
+/// 执行基准 (CPU 密集，调用方应在阻塞线程中执行)
+pub fn run(rule: &Rule, iterations: usize) -> anyhow::Result<BenchReport> {
+    let iterations = iterations.clamp(1, MAX_ITERATIONS);
+    let (source, html): (&'static str, Arc<str>) = match last_search_page(&rule.name) {
+        Some(html) => ("last_fetched", html),
+        None => ("fixture", Arc::from(FIXTURE_HTML)),
+    };
+
+    let mut samples = Vec::with_capacity(iterations);
+    let mut items = 0;
+    let started = Instant::now();
+    for _ in 0..iterations {
+        let iteration = Instant::now();
+        items = parse_search_results(rule, &html)?.len();
+        samples.push(iteration.elapsed());
+    }
+    let total = started.elapsed();
+    samples.sort();
+
+    Ok(BenchReport {
+        rule: rule.name.clone(),
+        source,
+        body_bytes: html.len(),
+        iterations,
+        items,
+        total_ms: millis(total),
+        throughput_per_sec: iterations as f64 / total.as_secs_f64().max(f64::EPSILON),
+        p50_ms: millis(percentile(&samples, 0.50)),
+        p99_ms: millis(percentile(&samples, 0.99)),
+    })
+}
+
+/// 已排序样本的分位数 (nearest-rank)
+pub(super) fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+pub(super) fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_uses_fixture_and_reports_percentiles() {
+        let rule = Rule {
+            name: "bench-fixture".to_string(),
+            base_url: "https://example.com".to_string(),
+            search_list: "//div[@class='item']".to_string(),
+            search_name: "//h3/a".to_string(),
+            ..Default::default()
+        };
+        let report = run(&rule, 20).unwrap();
+        assert_eq!(report.source, "fixture");
+        assert_eq!(report.iterations, 20);
+        assert_eq!(report.items, 2);
+        assert!(report.p50_ms <= report.p99_ms);
+        assert!(report.throughput_per_sec > 0.0);
+
+        let samples: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 0.50), Duration::from_millis(50));
+        assert_eq!(percentile(&samples, 0.99), Duration::from_millis(99));
+        assert_eq!(percentile(&samples[..1], 0.99), Duration::from_millis(1));
+    }
+}
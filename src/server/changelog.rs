@@ -0,0 +1,250 @@
+//! 规则变更记录
+//! 记录规则更新中新增/更新/失败的规则 (含新旧版本) 以及规则的自动停用/重新启用，
+//! 保存在全局存储的 `rule_changelog` 命名空间，
+//! 最多保留 MAX_ENTRIES 条；由 `/rules/changelog` (JSON) 与 `/feeds/rules.atom` (Atom) 输出。
+//! 每条记录写入时生成 id，之后不再变化，订阅器不会重复提醒。
+
+use crate::rule_stats::RuleTransition;
+use crate::storage::{self, ns};
+use crate::updater::UpdateResult;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 最多保留的记录数
+const MAX_ENTRIES: usize = 200;
+
+/// Feed 输出的记录数
+pub const FEED_ENTRIES: usize = 50;
+
+/// 同一毫秒内写入多条记录时的序号
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// 变更记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangelogEntry {
+    /// 稳定 id (`urn:anime-search:rule-change:<毫秒时间戳>-<序号>`)
+    pub id: String,
+    pub rule: String,
+    /// added / updated / failed / auto_disabled / reenabled / enabled
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_version: Option<String>,
+    /// 失败或停用/启用原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub timestamp: String,
+}
+
+impl ChangelogEntry {
+    /// Feed 中的标题，如 "AGE 已更新 (1.0 → 1.1)"
+    fn title(&self) -> String {
+        let action = match self.action.as_str() {
+            "added" => "已新增",
+            "updated" => "已更新",
+            "failed" => "更新失败",
+            "auto_disabled" => "已自动停用",
+            "reenabled" | "enabled" => "已重新启用",
+            other => other,
+        };
+        let version = match (&self.old_version, &self.new_version) {
+            (Some(old), Some(new)) if old != new => format!(" ({} → {})", old, new),
+            (_, Some(new)) => format!(" ({})", new),
+            _ => String::new(),
+        };
+        format!("{} {}{}", self.rule, action, version)
+    }
+}
+
+/// 生成一条记录 (id 与时间戳在此生成)，返回存储键与记录
+fn entry(
+    now: DateTime<Utc>,
+    rule: &str,
+    action: &str,
+    versions: (Option<String>, Option<String>),
+    message: Option<String>,
+) -> (String, ChangelogEntry) {
+    let seq = SEQUENCE.fetch_add(1, Ordering::Relaxed) % 10_000;
+    // 键按时间排序，存储层的 list 即为时间顺序
+    let key = format!("{:013}-{:04}", now.timestamp_millis(), seq);
+    let entry = ChangelogEntry {
+        id: format!("urn:anime-search:rule-change:{}", key),
+        rule: rule.to_string(),
+        action: action.to_string(),
+        old_version: versions.0,
+        new_version: versions.1,
+        message,
+        timestamp: now.to_rfc3339(),
+    };
+    (key, entry)
+}
+
+/// 在后台写入记录并删除超出上限的旧记录
+fn append(entries: Vec<(String, ChangelogEntry)>) {
+    storage::spawn_write(move |_| {
+        for (key, entry) in &entries {
+            storage::set_json(ns::RULE_CHANGELOG, key, entry, None);
+        }
+        prune();
+    });
+}
+
+/// 记录一次规则更新中的变更 (未变化的规则不记录)
+pub fn record_update(result: &UpdateResult) {
+    let now = Utc::now();
+    let entries = result
+        .details
+        .iter()
+        .filter(|d| d.action != "unchanged")
+        .map(|detail| {
+            entry(
+                now,
+                &detail.name,
+                &detail.action,
+                (detail.old_version.clone(), detail.new_version.clone()),
+                (detail.action == "failed").then(|| detail.message.clone()),
+            )
+        })
+        .collect();
+    append(entries);
+}
+
+/// 记录规则的自动停用/重新启用
+pub fn record_transition(transition: &RuleTransition) {
+    append(vec![entry(
+        Utc::now(),
+        &transition.rule,
+        transition.action,
+        (None, None),
+        Some(transition.reason.clone()),
+    )]);
+}
+
+/// 删除超出上限的旧记录
+fn prune() {
+    let store = storage::store();
+    let keys = store.list(ns::RULE_CHANGELOG);
+    if keys.len() > MAX_ENTRIES {
+        for (key, _) in &keys[..keys.len() - MAX_ENTRIES] {
+            store.delete(ns::RULE_CHANGELOG, key);
+        }
+    }
+}
+
+/// 最近的记录 (最新的在前)
+pub fn recent(limit: usize) -> Vec<ChangelogEntry> {
+    storage::store()
+        .list(ns::RULE_CHANGELOG)
+        .into_iter()
+        .rev()
+        .filter_map(|(_, value)| serde_json::from_str(&value).ok())
+        .take(limit)
+        .collect()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 渲染 Atom feed (`self_url` 为 feed 自身的绝对地址)
+pub fn render_atom(entries: &[ChangelogEntry], self_url: &str) -> String {
+    // 无记录时以固定时间作为 updated，避免每次请求都变化
+    let updated = entries
+        .first()
+        .map(|e| e.timestamp.clone())
+        .unwrap_or_else(|| DateTime::<Utc>::UNIX_EPOCH.to_rfc3339());
+    let mut xml = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+            "<feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+            "  <id>urn:anime-search:rule-changelog</id>\n",
+            "  <title>动漫聚搜 规则变更</title>\n",
+            "  <link rel=\"self\" href=\"{}\"/>\n",
+            "  <updated>{}</updated>\n",
+            "  <author><name>anime-search-api</name></author>\n",
+        ),
+        escape_xml(self_url),
+        escape_xml(&updated)
+    );
+    for entry in entries {
+        let mut summary = entry.title();
+        if let Some(message) = &entry.message {
+            summary.push_str(&format!(": {}", message));
+        }
+        xml.push_str(&format!(
+            concat!(
+                "  <entry>\n",
+                "    <id>{}</id>\n",
+                "    <title>{}</title>\n",
+                "    <updated>{}</updated>\n",
+                "    <category term=\"{}\"/>\n",
+                "    <summary>{}</summary>\n",
+                "  </entry>\n",
+            ),
+            escape_xml(&entry.id),
+            escape_xml(&entry.title()),
+            escape_xml(&entry.timestamp),
+            escape_xml(&entry.action),
+            escape_xml(&summary)
+        ));
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::updater::UpdateDetail;
+
+    #[test]
+    fn test_changelog_records_changes_and_renders_atom() {
+        storage::store().clear(ns::RULE_CHANGELOG);
+        let detail =
+            |name: &str, action: &str, old: Option<&str>, new: Option<&str>| UpdateDetail {
+                name: name.to_string(),
+                action: action.to_string(),
+                message: "ok".to_string(),
+                old_version: old.map(|v| v.to_string()),
+                new_version: new.map(|v| v.to_string()),
+            };
+        record_update(&UpdateResult {
+            total: 3,
+            updated: 1,
+            added: 1,
+            failed: 0,
+            details: vec![
+                detail("AGE", "updated", Some("1.0"), Some("1.1")),
+                detail("MX", "unchanged", Some("2"), Some("2")),
+                detail("A&B", "added", None, Some("1")),
+            ],
+        });
+
+        let entries = recent(FEED_ENTRIES);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].rule, "A&B");
+        assert_eq!(entries[1].title(), "AGE 已更新 (1.0 → 1.1)");
+        // id 在写入时确定，重复读取不变
+        assert_eq!(recent(FEED_ENTRIES)[1].id, entries[1].id);
+
+        let xml = render_atom(&entries, "http://localhost:3000/feeds/rules.atom");
+        assert!(xml.contains("<title>A&amp;B 已新增 (1)</title>"));
+        assert!(xml.contains(&format!("<id>{}</id>", entries[1].id)));
+        assert_eq!(xml.matches("<entry>").count(), 2);
+
+        record_transition(&RuleTransition {
+            rule: "NT".to_string(),
+            action: "auto_disabled",
+            reason: "连续失败 10 次".to_string(),
+        });
+        let latest = &recent(1)[0];
+        assert_eq!(latest.title(), "NT 已自动停用");
+        assert_eq!(latest.message.as_deref(), Some("连续失败 10 次"));
+        storage::store().clear(ns::RULE_CHANGELOG);
+    }
+}
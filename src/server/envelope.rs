@@ -0,0 +1,200 @@
+//! 统一响应信封
+//! 设置 ENVELOPE=1 (或请求带 `?envelope=1`) 时，JSON 响应包装为 `{"data": ..., "error": null}`，
+//! 错误响应包装为 `{"data": null, "error": {"status": 状态码, "message": "...", ...}}`。
+//! 原错误对象的其他字段 (如 `retry_after`) 保留在 `error` 中；没有响应体的错误 (超时、未知路由) 同样包装。
+//! 流式、HTML、CSV、图片等非 JSON 响应保持原样。`?envelope=0` 可在全局开启时为单个请求关闭。
+
+use crate::config::parse_bool;
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+};
+use futures::future::BoxFuture;
+use serde_json::{json, Map, Value};
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// 包装时读取的响应体上限 (超出或读取失败时按 500 错误包装)
+const MAX_BODY_BYTES: usize = 32 * 1024 * 1024;
+
+/// 响应信封 tower layer
+#[derive(Clone, Copy)]
+pub struct EnvelopeLayer {
+    /// 默认是否包装 (ENVELOPE)
+    enabled: bool,
+}
+
+impl EnvelopeLayer {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl<S> Layer<S> for EnvelopeLayer {
+    type Service = Envelope<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Envelope {
+            inner,
+            enabled: self.enabled,
+        }
+    }
+}
+
+/// 响应信封服务
+#[derive(Clone)]
+pub struct Envelope<S> {
+    inner: S,
+    enabled: bool,
+}
+
+impl<S> Service<Request> for Envelope<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let enabled = requested(request.uri().query()).unwrap_or(self.enabled);
+        let future = self.inner.call(request);
+        if !enabled {
+            return Box::pin(future);
+        }
+        Box::pin(async move { Ok(wrap(future.await?).await) })
+    }
+}
+
+/// 查询参数中的 `envelope` 开关 (未携带或无法解析时为 None)
+fn requested(query: Option<&str>) -> Option<bool> {
+    url::form_urlencoded::parse(query?.as_bytes())
+        .find(|(key, _)| key == "envelope")
+        .and_then(|(_, value)| parse_bool(&value))
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+/// 包装单个响应: JSON 响应与没有响应体的错误，其余原样返回
+async fn wrap(response: Response) -> Response {
+    let status = response.status();
+    let is_error = status.is_client_error() || status.is_server_error();
+    let has_body = response.headers().contains_key(header::CONTENT_TYPE);
+    if !is_json(&response) && (has_body || !is_error) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let value = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) if bytes.is_empty() => Value::Null,
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        Err(_) => {
+            parts.status = StatusCode::INTERNAL_SERVER_ERROR;
+            Value::Null
+        }
+    };
+    let status = parts.status;
+    let wrapped = if status.is_client_error() || status.is_server_error() {
+        json!({ "data": null, "error": error_object(status, value) })
+    } else {
+        json!({ "data": value, "error": null })
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(wrapped.to_string()))
+}
+
+/// 错误对象: 原 `{"error": "..."}` 的消息作为 `message`，其余字段原样保留
+fn error_object(status: StatusCode, value: Value) -> Value {
+    let mut error = Map::new();
+    error.insert("status".into(), json!(status.as_u16()));
+    let mut message = None;
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                match (key.as_str(), field) {
+                    ("error", Value::String(text)) => message = Some(text),
+                    (_, field) => {
+                        error.insert(key, field);
+                    }
+                }
+            }
+        }
+        Value::String(text) => message = Some(text),
+        _ => {}
+    }
+    let message =
+        message.unwrap_or_else(|| status.canonical_reason().unwrap_or("Error").to_string());
+    error.insert("message".into(), json!(message));
+    Value::Object(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{response::IntoResponse, Json};
+
+    async fn body_json(response: Response) -> Value {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wrap_success_error_and_passthrough() {
+        let ok = wrap(Json(json!([1, 2])).into_response()).await;
+        assert_eq!(body_json(ok).await, json!({"data": [1, 2], "error": null}));
+
+        let limited = (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(json!({"error": "Rate limit exceeded", "retry_after": 3})),
+        )
+            .into_response();
+        let limited = wrap(limited).await;
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            body_json(limited).await,
+            json!({
+                "data": null,
+                "error": {"status": 429, "message": "Rate limit exceeded", "retry_after": 3}
+            })
+        );
+
+        // 没有响应体的错误使用标准原因短语
+        let timeout = wrap(StatusCode::GATEWAY_TIMEOUT.into_response()).await;
+        assert_eq!(
+            body_json(timeout).await["error"]["message"],
+            "Gateway Timeout"
+        );
+
+        // 非 JSON 响应保持原样
+        let html = wrap(axum::response::Html("<p>hi</p>").into_response()).await;
+        let bytes = to_bytes(html.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"<p>hi</p>");
+    }
+
+    #[test]
+    fn test_query_overrides_default() {
+        assert_eq!(requested(Some("q=a&envelope=1")), Some(true));
+        assert_eq!(requested(Some("envelope=0")), Some(false));
+        assert_eq!(requested(Some("q=envelope")), None);
+        assert_eq!(requested(None), None);
+    }
+}
@@ -0,0 +1,232 @@
+//! 统一错误格式的请求提取器
+//! 包装 axum 的 `Json` / `Path` / `Query`：解析失败时返回与其他接口一致的 `{"error": "..."}` JSON
+//! (axum 默认返回纯文本)，消息中保留出错的字段或参数名，状态码沿用 axum 的判断 (400 / 415 / 422)
+
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{FromRequest, FromRequestParts, OptionalFromRequest, Path, Query, Request};
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+/// JSON 请求体
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiJson<T>(pub T);
+
+/// 路径参数
+#[cfg_attr(not(any(feature = "scraper", feature = "bangumi")), allow(dead_code))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiPath<T>(pub T);
+
+/// 查询参数
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiQuery<T>(pub T);
+
+fn rejection(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+fn json_rejection(e: JsonRejection) -> Response {
+    let message = match &e {
+        JsonRejection::JsonSyntaxError(_) => format!("Malformed JSON body: {}", e.body_text()),
+        JsonRejection::JsonDataError(_) => format!("Invalid JSON body: {}", e.body_text()),
+        _ => e.body_text(),
+    };
+    rejection(e.status(), message)
+}
+
+#[cfg_attr(not(any(feature = "scraper", feature = "bangumi")), allow(dead_code))]
+fn path_rejection(e: PathRejection) -> Response {
+    rejection(
+        e.status(),
+        format!("Invalid path parameter: {}", e.body_text()),
+    )
+}
+
+fn query_rejection(e: QueryRejection) -> Response {
+    rejection(
+        e.status(),
+        format!("Invalid query parameter: {}", e.body_text()),
+    )
+}
+
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match <Json<T> as FromRequest<S>>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(ApiJson(value)),
+            Err(e) => Err(json_rejection(e)),
+        }
+    }
+}
+
+/// 可选 JSON 请求体 (没有 JSON Content-Type 时为 None，有但解析失败时仍返回错误)
+impl<T, S> OptionalFromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        match <Json<T> as OptionalFromRequest<S>>::from_request(req, state).await {
+            Ok(value) => Ok(value.map(|Json(value)| ApiJson(value))),
+            Err(e) => Err(json_rejection(e)),
+        }
+    }
+}
+
+impl<T, S> FromRequestParts<S> for ApiPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Path::<T>::from_request_parts(parts, state).await {
+            Ok(Path(value)) => Ok(ApiPath(value)),
+            Err(e) => Err(path_rejection(e)),
+        }
+    }
+}
+
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => Ok(ApiQuery(value)),
+            Err(e) => Err(query_rejection(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Debug, Deserialize)]
+    struct NameBody {
+        name: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Paging {
+        limit: Option<usize>,
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/json",
+                post(|ApiJson(body): ApiJson<NameBody>| async move { body.name }),
+            )
+            .route(
+                "/optional",
+                post(|body: Option<ApiJson<NameBody>>| async move {
+                    body.map(|ApiJson(b)| b.name).unwrap_or_default()
+                }),
+            )
+            .route(
+                "/items/{id}",
+                get(|ApiPath(id): ApiPath<i64>| async move { id.to_string() }),
+            )
+            .route(
+                "/list",
+                get(|ApiQuery(paging): ApiQuery<Paging>| async move {
+                    paging.limit.unwrap_or_default().to_string()
+                }),
+            )
+    }
+
+    async fn send(request: Request) -> (StatusCode, serde_json::Value) {
+        let response = app().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let value = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+        (status, value)
+    }
+
+    fn json_request(uri: &str, body: &str) -> Request {
+        Request::post(uri)
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bad_json_syntax_and_types_return_json_errors() {
+        let (status, body) = send(json_request("/json", "{\"name\": ")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Malformed JSON body"));
+
+        let (status, body) = send(json_request("/json", "{\"name\": 1}")).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body["error"].as_str().unwrap().contains("name"), "{}", body);
+
+        let request = Request::post("/json").body(Body::from("{}")).unwrap();
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(body["error"].is_string());
+
+        // 可选请求体: 缺省时为 None，给出但格式错误时仍报错
+        let request = Request::post("/optional").body(Body::empty()).unwrap();
+        assert_eq!(
+            app().oneshot(request).await.unwrap().status(),
+            StatusCode::OK
+        );
+        let (status, _) = send(json_request("/optional", "not json")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_bad_path_and_query_return_json_errors() {
+        let request = Request::get("/items/abc").body(Body::empty()).unwrap();
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let message = body["error"].as_str().unwrap();
+        assert!(
+            message.starts_with("Invalid path parameter") && message.contains("abc"),
+            "{}",
+            message
+        );
+
+        let request = Request::get("/list?limit=ten").body(Body::empty()).unwrap();
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let message = body["error"].as_str().unwrap();
+        assert!(
+            message.starts_with("Invalid query parameter") && message.contains("limit"),
+            "{}",
+            message
+        );
+
+        let request = Request::get("/items/42").body(Body::empty()).unwrap();
+        assert_eq!(
+            app().oneshot(request).await.unwrap().status(),
+            StatusCode::OK
+        );
+    }
+}
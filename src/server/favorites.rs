@@ -0,0 +1,189 @@
+//! 收藏
+//! 保存搜索结果的详情页链接 (连同规则与关键词)，下次无需重新搜索。
+//! 存储于全局存储的 `favorites` 命名空间 (未配置 DATABASE_PATH / REDIS_URL 时重启丢失)，
+//! 以 (规则, 链接) 的哈希作为 id，重复收藏同一链接时覆盖原条目。
+
+use crate::storage::{self, ns};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// 收藏条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Favorite {
+    pub id: String,
+    pub keyword: String,
+    pub rule: String,
+    /// 来源规则颜色
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_color: Option<String>,
+    pub name: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+    /// 关联的 Bangumi 条目 (用于分组)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_id: Option<i64>,
+    pub created_at: String,
+}
+
+/// 新增收藏的请求体
+#[derive(Debug, Deserialize)]
+pub struct NewFavorite {
+    #[serde(default)]
+    pub keyword: String,
+    pub rule: String,
+    #[serde(default)]
+    pub rule_color: Option<String>,
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub cover: Option<String>,
+    #[serde(default)]
+    pub subject_id: Option<i64>,
+}
+
+/// 列表筛选与分页
+#[derive(Debug, Default, Deserialize)]
+pub struct FavoriteQuery {
+    /// 按名称、关键词、规则名筛选 (不区分大小写)
+    pub q: Option<String>,
+    pub subject_id: Option<i64>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// 默认/最大分页大小
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 200;
+
+/// 由 (规则, 链接) 生成的稳定 id
+fn favorite_id(rule: &str, url: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}", rule, url).as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 新增收藏 (同一规则的同一链接只保留一条，保留首次收藏时间)，返回条目与是否新建
+pub fn add(new: NewFavorite) -> Result<(Favorite, bool), &'static str> {
+    let rule = new.rule.trim();
+    let name = new.name.trim();
+    let url = new.url.trim();
+    if rule.is_empty() || name.is_empty() || url.is_empty() {
+        return Err("'rule', 'name' and 'url' are required");
+    }
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err("'url' must be an http(s) link");
+    }
+
+    let id = favorite_id(rule, url);
+    let existing: Option<Favorite> = storage::get_json(ns::FAVORITES, &id);
+    let created = existing.is_none();
+    let favorite = Favorite {
+        id: id.clone(),
+        keyword: new.keyword.trim().to_string(),
+        rule: rule.to_string(),
+        rule_color: new
+            .rule_color
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty()),
+        name: name.to_string(),
+        url: url.to_string(),
+        cover: new.cover.map(|c| c.trim().to_string()).filter(|c| !c.is_empty()),
+        subject_id: new.subject_id,
+        created_at: existing
+            .map(|f| f.created_at)
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+    };
+    storage::set_json(ns::FAVORITES, &id, &favorite, None);
+    Ok((favorite, created))
+}
+
+/// 按条件列出收藏 (最新的在前)，返回 (总数, 当前页)
+pub fn list(query: &FavoriteQuery) -> (usize, Vec<Favorite>) {
+    let mut favorites: Vec<Favorite> = storage::store()
+        .list(ns::FAVORITES)
+        .into_iter()
+        .filter_map(|(_, value)| serde_json::from_str(&value).ok())
+        .collect();
+    favorites.retain(|f| matches(f, query));
+    favorites.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    let total = favorites.len();
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let page = favorites
+        .into_iter()
+        .skip(query.offset.unwrap_or(0))
+        .take(limit)
+        .collect();
+    (total, page)
+}
+
+fn matches(favorite: &Favorite, query: &FavoriteQuery) -> bool {
+    if query.subject_id.is_some() && favorite.subject_id != query.subject_id {
+        return false;
+    }
+    match query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        Some(q) => {
+            let q = q.to_lowercase();
+            [&favorite.name, &favorite.keyword, &favorite.rule]
+                .iter()
+                .any(|field| field.to_lowercase().contains(&q))
+        }
+        None => true,
+    }
+}
+
+/// 删除收藏，返回是否存在
+pub fn remove(id: &str) -> bool {
+    storage::store().delete(ns::FAVORITES, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_favorite(rule: &str, name: &str, url: &str) -> NewFavorite {
+        NewFavorite {
+            keyword: "芙莉莲".to_string(),
+            rule: rule.to_string(),
+            rule_color: None,
+            name: name.to_string(),
+            url: url.to_string(),
+            cover: None,
+            subject_id: Some(400602),
+        }
+    }
+
+    #[test]
+    fn test_favorites_deduplicate_filter_and_page() {
+        storage::store().clear(ns::FAVORITES);
+
+        let (first, created) =
+            add(new_favorite("AGE", "葬送的芙莉莲", "https://age.example/1")).unwrap();
+        assert!(created);
+        let (again, created) =
+            add(new_favorite("AGE", "葬送的芙莉莲 (重复)", "https://age.example/1")).unwrap();
+        assert!(!created);
+        assert_eq!(again.id, first.id);
+        assert_eq!(again.created_at, first.created_at);
+        add(new_favorite("NT", "葬送的芙莉莲", "https://nt.example/1")).unwrap();
+        assert!(add(new_favorite("NT", "x", "javascript:alert(1)")).is_err());
+
+        let (total, _) = list(&FavoriteQuery::default());
+        assert_eq!(total, 2);
+        let (total, items) = list(&FavoriteQuery {
+            q: Some("nt".to_string()),
+            ..Default::default()
+        });
+        assert_eq!((total, items[0].rule.as_str()), (1, "NT"));
+        let (total, items) = list(&FavoriteQuery {
+            limit: Some(1),
+            offset: Some(1),
+            ..Default::default()
+        });
+        assert_eq!((total, items.len()), (2, 1));
+
+        assert!(remove(&first.id));
+        assert!(!remove(&first.id));
+        storage::store().clear(ns::FAVORITES);
+    }
+}
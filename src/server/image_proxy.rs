@@ -0,0 +1,643 @@
+//! 图片代理与磁盘缓存
+//! `GET /image?url=` 代为请求封面图片 (以图片所在站点为 Referer，绕过防盗链)，
+//! 只接受 http(s) 地址与 `image/*` 响应，单张最大 10 MB。
+//! 只代理已加载规则 baseURL 的域名与 Bangumi / AniList 封面 CDN (含子域名)，
+//! 重定向逐跳校验域名，域名解析到回环、内网、链路本地等地址时拒绝连接；
+//! 上游错误只记录日志，响应中不包含细节。
+//! 设置 IMAGE_CACHE_DIR 时图片按上游地址的 SHA-256 保存到磁盘 (`<hash>.img` + `<hash>.json` 元数据)，
+//! 两个文件都先写临时文件再重命名，元数据最后写入并记录大小与内容哈希；
+//! 读取时校验，不一致 (写入中途崩溃、文件损坏) 则删除并重新请求。
+//! 命中时刷新文件修改时间，后台清理按修改时间淘汰最久未用的图片，使总大小不超过 IMAGE_CACHE_MAX_MB。
+
+use crate::config::CONFIG;
+use crate::http_client::{NonPublicAddress, PUBLIC_CLIENT};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+/// 单张图片大小上限
+pub const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// 响应的缓存策略 (同一地址的图片基本不变，浏览器缓存 30 天)
+pub const CACHE_CONTROL: &str = "public, max-age=2592000";
+
+/// 后台清理间隔
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// 超过该时间的临时文件与缺少元数据的图片视为中断的写入
+const STALE_AFTER: Duration = Duration::from_secs(3600);
+
+/// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 5;
+
+/// 规则站点之外允许代理的域名 (Bangumi / AniList 封面 CDN)
+const CDN_HOSTS: &[&str] = &["bgm.tv", "bangumi.tv", "anilist.co", "anili.st"];
+
+/// 临时文件序号 (同一图片并发写入时不冲突)
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+/// 全局磁盘缓存 (未设置 IMAGE_CACHE_DIR 或目录无法创建时为 None)
+static DISK_CACHE: Lazy<Option<DiskCache>> = Lazy::new(|| {
+    let dir = CONFIG.image_cache_dir.as_deref()?;
+    match DiskCache::open(dir, CONFIG.image_cache_max_mb * 1024 * 1024) {
+        Ok(cache) => Some(cache),
+        Err(e) => {
+            warn!("图片缓存目录 {} 不可用，不缓存图片: {}", dir, e);
+            None
+        }
+    }
+});
+
+pub fn disk_cache() -> Option<&'static DiskCache> {
+    DISK_CACHE.as_ref()
+}
+
+/// `GET /image` 查询参数
+#[derive(Debug, Deserialize)]
+pub struct ImageQuery {
+    pub url: String,
+}
+
+/// 图片内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+    /// 由内容哈希生成的强 ETag
+    pub etag: String,
+}
+
+impl Image {
+    fn new(content_type: String, bytes: Vec<u8>) -> Self {
+        let etag = etag(&sha256_hex(&bytes));
+        Image {
+            content_type,
+            bytes,
+            etag,
+        }
+    }
+
+    /// `If-None-Match` 是否包含该图片的 ETag
+    pub fn matches(&self, if_none_match: &str) -> bool {
+        if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == self.etag)
+    }
+}
+
+/// 缓存元数据 (写入完成的标记)
+#[derive(Debug, Serialize, Deserialize)]
+struct Meta {
+    url: String,
+    content_type: String,
+    size: u64,
+    sha256: String,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn etag(sha256: &str) -> String {
+    format!("\"{}\"", &sha256[..16])
+}
+
+/// 图片请求失败的原因 (细节只记录日志)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FetchError {
+    /// 域名不在允许列表中，或指向非公网地址
+    Forbidden,
+    /// 上游请求失败、非图片响应或超过大小上限
+    Upstream,
+}
+
+/// 允许代理的目标
+#[derive(Debug)]
+pub struct Policy {
+    /// 允许的域名 (含子域名)
+    hosts: Vec<String>,
+    /// 是否允许 IP 形式的非公网地址 (仅测试)
+    allow_private: bool,
+}
+
+impl Policy {
+    /// 当前允许的域名: 已加载规则 baseURL 的域名 (去掉 `www.`) 与封面 CDN
+    pub fn current() -> Self {
+        #[allow(unused_mut)]
+        let mut hosts: Vec<String> = CDN_HOSTS.iter().map(|host| host.to_string()).collect();
+        #[cfg(feature = "scraper")]
+        hosts.extend(crate::rules::get_builtin_rules().iter().filter_map(|rule| {
+            let url = url::Url::parse(&rule.base_url).ok()?;
+            let host = url.host_str()?.to_ascii_lowercase();
+            Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+        }));
+        Policy {
+            hosts,
+            allow_private: false,
+        }
+    }
+
+    /// 校验地址: 域名须在允许列表中，IP 形式的主机须为公网地址
+    fn check(&self, url: &url::Url) -> Result<(), FetchError> {
+        let host = match url.host() {
+            Some(url::Host::Domain(domain)) => domain.to_ascii_lowercase(),
+            Some(url::Host::Ipv4(ip)) if self.allow_private || crate::http_client::is_public_ip(ip.into()) => {
+                ip.to_string()
+            }
+            Some(url::Host::Ipv6(ip)) if self.allow_private || crate::http_client::is_public_ip(ip.into()) => {
+                ip.to_string()
+            }
+            _ => return Err(FetchError::Forbidden),
+        };
+        let allowed = self.hosts.iter().any(|allowed| {
+            host == *allowed
+                || host
+                    .strip_suffix(allowed.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        });
+        if allowed {
+            Ok(())
+        } else {
+            Err(FetchError::Forbidden)
+        }
+    }
+}
+
+/// 记录上游错误并返回不含细节的错误
+fn upstream(url: &url::Url, detail: impl std::fmt::Display) -> FetchError {
+    warn!("图片代理请求失败 ({}): {}", url, detail);
+    FetchError::Upstream
+}
+
+/// 请求错误: 解析到非公网地址时为 Forbidden
+fn request_error(url: &url::Url, e: reqwest::Error) -> FetchError {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&e);
+    while let Some(err) = source {
+        if err.downcast_ref::<NonPublicAddress>().is_some() {
+            warn!("图片代理拒绝访问 {}: {}", url, err);
+            return FetchError::Forbidden;
+        }
+        source = err.source();
+    }
+    upstream(url, e)
+}
+
+/// 校验图片地址: 只允许 http(s)
+pub fn validate_url(raw: &str) -> Result<url::Url, String> {
+    let url = url::Url::parse(raw.trim()).map_err(|_| format!("Invalid image url: {}", raw))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(format!("Unsupported image url: {}", raw));
+    }
+    Ok(url)
+}
+
+/// 请求上游图片 (Referer 为图片所在站点，逐跳校验重定向，非图片响应与超过大小上限时失败)
+async fn fetch(url: &url::Url, policy: &Policy) -> Result<Image, FetchError> {
+    let mut current = url.clone();
+    let mut redirects = 0;
+    let mut response = loop {
+        policy.check(&current)?;
+        let referer = format!("{}/", current.origin().ascii_serialization());
+        let response = PUBLIC_CLIENT
+            .get(current.as_str())
+            .header(reqwest::header::REFERER, referer)
+            .send()
+            .await
+            .map_err(|e| request_error(&current, e))?;
+        if !response.status().is_redirection() {
+            break response;
+        }
+        redirects += 1;
+        if redirects > MAX_REDIRECTS {
+            return Err(upstream(url, "too many redirects"));
+        }
+        current = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|location| current.join(location).ok())
+            .filter(|next| matches!(next.scheme(), "http" | "https"))
+            .ok_or_else(|| upstream(&current, "invalid redirect"))?;
+    };
+    if !response.status().is_success() {
+        return Err(upstream(&current, format!("HTTP {}", response.status())));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !content_type.starts_with("image/") {
+        return Err(upstream(&current, format!("not an image: {}", content_type)));
+    }
+    let too_large = || upstream(&current, format!("image exceeds {} bytes", MAX_IMAGE_BYTES));
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_IMAGE_BYTES as u64)
+    {
+        return Err(too_large());
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| upstream(&current, e))?
+    {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err(too_large());
+        }
+    }
+    Ok(Image::new(content_type, bytes))
+}
+
+/// 读取图片: 先查磁盘缓存，未命中时请求上游并写入缓存，返回图片与是否命中
+pub async fn load(url: &url::Url, policy: &Policy) -> Result<(Image, bool), FetchError> {
+    policy.check(url)?;
+    let Some(cache) = disk_cache() else {
+        return Ok((fetch(url, policy).await?, false));
+    };
+    let key = url.to_string();
+    let cached = {
+        let key = key.clone();
+        tokio::task::spawn_blocking(move || cache.get(&key))
+            .await
+            .ok()
+            .flatten()
+    };
+    if let Some(image) = cached {
+        return Ok((image, true));
+    }
+
+    let image = fetch(url, policy).await?;
+    let stored = image.clone();
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = cache.put(&key, &stored) {
+            warn!("写入图片缓存失败 ({}): {}", key, e);
+        }
+    });
+    Ok((image, false))
+}
+
+/// 磁盘图片缓存
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+/// 一次清理的结果
+#[derive(Debug, Default, PartialEq)]
+pub struct SweepStats {
+    /// 按大小预算淘汰的图片
+    pub evicted: usize,
+    /// 清理的中断写入 (临时文件、缺少元数据或图片的记录)
+    pub broken: usize,
+    /// 清理后的总大小
+    pub total_bytes: u64,
+}
+
+impl DiskCache {
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(DiskCache { dir, max_bytes })
+    }
+
+    /// (图片文件, 元数据文件)
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = sha256_hex(url.as_bytes());
+        (
+            self.dir.join(format!("{}.img", key)),
+            self.dir.join(format!("{}.json", key)),
+        )
+    }
+
+    /// 读取并校验缓存的图片，损坏时删除并返回 None
+    pub fn get(&self, url: &str) -> Option<Image> {
+        let (data_path, meta_path) = self.paths(url);
+        let meta: Meta = fs::read_to_string(&meta_path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())?;
+        let valid = |bytes: &[u8]| {
+            meta.url == url && bytes.len() as u64 == meta.size && sha256_hex(bytes) == meta.sha256
+        };
+        let bytes = match fs::read(&data_path) {
+            Ok(bytes) if valid(&bytes) => bytes,
+            _ => {
+                warn!("图片缓存文件损坏，重新请求: {}", url);
+                remove_pair(&data_path, &meta_path);
+                return None;
+            }
+        };
+        // 刷新修改时间，供按最近使用淘汰
+        let _ = fs::File::options()
+            .append(true)
+            .open(&data_path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        Some(Image {
+            content_type: meta.content_type,
+            bytes,
+            etag: etag(&meta.sha256),
+        })
+    }
+
+    /// 写入图片: 先写图片再写元数据 (均为临时文件 + 重命名)
+    pub fn put(&self, url: &str, image: &Image) -> io::Result<()> {
+        let (data_path, meta_path) = self.paths(url);
+        let meta = Meta {
+            url: url.to_string(),
+            content_type: image.content_type.clone(),
+            size: image.bytes.len() as u64,
+            sha256: sha256_hex(&image.bytes),
+        };
+        write_atomic(&data_path, &image.bytes)?;
+        write_atomic(&meta_path, &serde_json::to_vec(&meta)?)
+    }
+
+    /// 清理中断的写入，并按最近使用时间淘汰图片直到总大小不超过预算
+    pub fn sweep(&self) -> io::Result<SweepStats> {
+        let mut stats = SweepStats::default();
+        let now = SystemTime::now();
+        let is_stale = |modified: SystemTime| {
+            now.duration_since(modified)
+                .is_ok_and(|age| age >= STALE_AFTER)
+        };
+
+        let mut images: Vec<(SystemTime, u64, String)> = Vec::new();
+        let mut metas: HashSet<String> = HashSet::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            let modified = metadata.modified().unwrap_or(now);
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".tmp") {
+                if is_stale(modified) && fs::remove_file(&path).is_ok() {
+                    stats.broken += 1;
+                }
+            } else if let Some(key) = name.strip_suffix(".img") {
+                images.push((modified, metadata.len(), key.to_string()));
+            } else if let Some(key) = name.strip_suffix(".json") {
+                metas.insert(key.to_string());
+            }
+        }
+
+        // 图片与元数据必须成对 (刚写入图片、尚未写入元数据的不清理)
+        let keys: HashSet<&String> = images.iter().map(|(_, _, key)| key).collect();
+        for key in metas.iter().filter(|key| !keys.contains(key)) {
+            if fs::remove_file(self.dir.join(format!("{}.json", key))).is_ok() {
+                stats.broken += 1;
+            }
+        }
+        let (mut images, orphans): (Vec<_>, Vec<_>) = images
+            .into_iter()
+            .partition(|(modified, _, key)| metas.contains(key) || !is_stale(*modified));
+        for (_, _, key) in orphans {
+            if fs::remove_file(self.dir.join(format!("{}.img", key))).is_ok() {
+                stats.broken += 1;
+            }
+        }
+
+        stats.total_bytes = images.iter().map(|(_, size, _)| size).sum();
+        images.sort_by_key(|(modified, _, _)| *modified);
+        for (_, size, key) in images {
+            if stats.total_bytes <= self.max_bytes {
+                break;
+            }
+            remove_pair(
+                &self.dir.join(format!("{}.img", key)),
+                &self.dir.join(format!("{}.json", key)),
+            );
+            stats.total_bytes -= size;
+            stats.evicted += 1;
+        }
+        Ok(stats)
+    }
+}
+
+/// 先删元数据 (使记录失效)，再删图片
+fn remove_pair(data_path: &Path, meta_path: &Path) {
+    let _ = fs::remove_file(meta_path);
+    let _ = fs::remove_file(data_path);
+}
+
+/// 写入临时文件后重命名，读取方不会看到写了一半的文件
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let temp = path.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result = fs::write(&temp, bytes).and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn temp_cache(name: &str, max_bytes: u64) -> DiskCache {
+        let dir = std::env::temp_dir().join(format!("image-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        DiskCache::open(dir, max_bytes).unwrap()
+    }
+
+    fn image(bytes: &[u8]) -> Image {
+        Image::new("image/png".to_string(), bytes.to_vec())
+    }
+
+    #[test]
+    fn test_corrupt_and_partial_files_are_refetched() {
+        let cache = temp_cache("corrupt", u64::MAX);
+        let url = "https://img.example.com/cover.png";
+        let stored = image(b"\x89PNG cover");
+        cache.put(url, &stored).unwrap();
+        assert_eq!(cache.get(url), Some(stored.clone()));
+        assert!(stored.matches(&format!("W/{}, \"other\"", stored.etag)));
+        assert!(!stored.matches("\"other\""));
+
+        // 内容被截断: 校验失败，记录被删除
+        let (data_path, meta_path) = cache.paths(url);
+        fs::write(&data_path, b"\x89PNG").unwrap();
+        assert_eq!(cache.get(url), None);
+        assert!(!data_path.exists() && !meta_path.exists());
+
+        // 只写入了图片 (元数据未写入): 不命中
+        fs::write(&data_path, b"\x89PNG cover").unwrap();
+        assert_eq!(cache.get(url), None);
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn test_sweep_evicts_least_recently_used() {
+        let cache = temp_cache("sweep", 25);
+        let old = SystemTime::now() - Duration::from_secs(7200);
+        for (n, url) in ["https://a/1.png", "https://a/2.png", "https://a/3.png"]
+            .iter()
+            .enumerate()
+        {
+            cache.put(url, &image(&[n as u8; 10])).unwrap();
+            let (data_path, _) = cache.paths(url);
+            let modified = old + Duration::from_secs(n as u64 * 60);
+            fs::File::options()
+                .append(true)
+                .open(data_path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        // 读取刷新使用时间: 最早写入的 1.png 变为最近使用
+        assert!(cache.get("https://a/1.png").is_some());
+        // 中断的写入
+        fs::write(cache.dir.join("dangling.img.1-1.tmp"), b"x").unwrap();
+        fs::File::options()
+            .append(true)
+            .open(cache.dir.join("dangling.img.1-1.tmp"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        fs::write(cache.dir.join("orphan.json"), b"{}").unwrap();
+
+        let stats = cache.sweep().unwrap();
+        assert_eq!(
+            stats,
+            SweepStats {
+                evicted: 1,
+                broken: 2,
+                total_bytes: 20
+            }
+        );
+        assert!(cache.get("https://a/2.png").is_none());
+        assert!(cache.get("https://a/1.png").is_some());
+        assert!(cache.get("https://a/3.png").is_some());
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_non_images() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/cover.webp"))
+            .and(header("referer", format!("{}/", server.uri()).as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(b"RIFF".to_vec(), "image/webp"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/page.html"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("<html>", "text/html"))
+            .mount(&server)
+            .await;
+
+        let policy = local_policy(&["127.0.0.1"]);
+        let url = validate_url(&format!("{}/cover.webp", server.uri())).unwrap();
+        let fetched = fetch(&url, &policy).await.unwrap();
+        assert_eq!(fetched, image_with_type(b"RIFF", "image/webp"));
+        let page = validate_url(&format!("{}/page.html", server.uri())).unwrap();
+        assert_eq!(fetch(&page, &policy).await, Err(FetchError::Upstream));
+        assert!(validate_url("file:///etc/passwd").is_err());
+        assert!(validate_url("javascript:alert(1)").is_err());
+    }
+
+    /// 允许指定域名与本机地址 (模拟服务监听在 127.0.0.1)
+    fn local_policy(hosts: &[&str]) -> Policy {
+        Policy {
+            hosts: hosts.iter().map(|host| host.to_string()).collect(),
+            allow_private: true,
+        }
+    }
+
+    #[test]
+    fn test_policy_allows_rule_hosts_and_subdomains() {
+        let policy = Policy {
+            hosts: vec!["bgm.tv".to_string(), "agedm.org".to_string()],
+            allow_private: false,
+        };
+        let check = |raw: &str| policy.check(&validate_url(raw).unwrap());
+        assert_eq!(check("https://lain.bgm.tv/pic/cover.jpg"), Ok(()));
+        assert_eq!(check("https://www.agedm.org/a.png"), Ok(()));
+        assert_eq!(check("https://AGEDM.org/a.png"), Ok(()));
+        assert_eq!(check("https://evilbgm.tv/a.png"), Err(FetchError::Forbidden));
+        assert_eq!(check("https://bgm.tv.evil.com/a.png"), Err(FetchError::Forbidden));
+        assert_eq!(check("http://127.0.0.1/a.png"), Err(FetchError::Forbidden));
+        assert_eq!(check("http://169.254.169.254/latest/meta-data"), Err(FetchError::Forbidden));
+        assert_eq!(check("http://[::1]/a.png"), Err(FetchError::Forbidden));
+        assert!(Policy::current().hosts.iter().any(|host| host == "bgm.tv"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_blocks_redirects_and_private_addresses() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/moved.png"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/cover.png"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/cover.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(b"PNG".to_vec(), "image/png"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/escape.png"))
+            .respond_with(
+                ResponseTemplate::new(302).insert_header("location", "http://169.254.169.254/latest/meta-data"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/loop.png"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/loop.png"))
+            .mount(&server)
+            .await;
+
+        let policy = local_policy(&["127.0.0.1"]);
+        let url = |p: &str| validate_url(&format!("{}{}", server.uri(), p)).unwrap();
+        // 同一站点内的重定向照常跟随
+        assert_eq!(
+            fetch(&url("/moved.png"), &policy).await,
+            Ok(image_with_type(b"PNG", "image/png"))
+        );
+        // 重定向到不在允许列表中的地址
+        assert_eq!(
+            fetch(&url("/escape.png"), &policy).await,
+            Err(FetchError::Forbidden)
+        );
+        assert_eq!(fetch(&url("/loop.png"), &policy).await, Err(FetchError::Upstream));
+        // 不在允许列表中的域名不发出请求
+        assert_eq!(
+            load(&url("/cover.png"), &local_policy(&["bgm.tv"])).await,
+            Err(FetchError::Forbidden)
+        );
+
+        // 允许的域名解析到回环地址时拒绝连接
+        let port = url("/").port().unwrap();
+        let localhost = validate_url(&format!("http://localhost:{}/cover.png", port)).unwrap();
+        let public_only = Policy {
+            hosts: vec!["localhost".to_string()],
+            allow_private: false,
+        };
+        assert_eq!(fetch(&localhost, &public_only).await, Err(FetchError::Forbidden));
+    }
+
+    fn image_with_type(bytes: &[u8], content_type: &str) -> Image {
+        Image::new(content_type.to_string(), bytes.to_vec())
+    }
+}
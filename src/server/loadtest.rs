@@ -0,0 +1,119 @@
+//! 流式搜索压测
+//! 用模拟规则 (`"type": "mock"`，需 `ENABLE_MOCK_RULES=1`) 并发执行多次内部流式搜索，
+//! 统计事件送达延迟的分位数；不请求任何站点，用于复现流式管线 (背压、并发限制) 的行为
+
+use super::bench::{millis, percentile};
+use crate::core::search_stream_with_rules;
+use crate::types::{Rule, SearchOptions};
+use futures::StreamExt;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 缺省并发搜索数
+pub const DEFAULT_CONCURRENCY: usize = 10;
+
+/// 并发搜索数上限
+pub const MAX_CONCURRENCY: usize = 200;
+
+/// 缺省搜索关键词
+pub const DEFAULT_KEYWORD: &str = "loadtest";
+
+/// 压测结果 (延迟均从各次搜索开始计时)
+#[derive(Debug, Serialize)]
+pub struct LoadTestReport {
+    pub rules: Vec<String>,
+    pub keyword: String,
+    pub concurrency: usize,
+    /// 收到的事件总数 (含 init 与 done)
+    pub events: usize,
+    /// 全部搜索完成的耗时
+    pub total_ms: f64,
+    /// 事件送达延迟 (搜索开始到收到事件)
+    pub event_p50_ms: f64,
+    pub event_p90_ms: f64,
+    pub event_p99_ms: f64,
+    pub event_max_ms: f64,
+    /// 单次搜索耗时 (搜索开始到流结束)
+    pub search_p50_ms: f64,
+    pub search_p99_ms: f64,
+}
+
+/// 并发执行 `concurrency` 次流式搜索并汇总延迟
+pub async fn run(keyword: &str, rules: Vec<Arc<Rule>>, concurrency: usize) -> LoadTestReport {
+    let concurrency = concurrency.clamp(1, MAX_CONCURRENCY);
+    let started = Instant::now();
+    let searches = (0..concurrency).map(|_| {
+        let stream =
+            search_stream_with_rules(keyword.to_string(), rules.clone(), SearchOptions::default());
+        tokio::spawn(async move {
+            let begin = Instant::now();
+            let delays: Vec<Duration> = stream.map(|_| begin.elapsed()).collect().await;
+            (delays, begin.elapsed())
+        })
+    });
+
+    let mut delays = Vec::new();
+    let mut durations = Vec::with_capacity(concurrency);
+    for (events, duration) in futures::future::join_all(searches)
+        .await
+        .into_iter()
+        .flatten()
+    {
+        delays.extend(events);
+        durations.push(duration);
+    }
+    let total = started.elapsed();
+    delays.sort();
+    durations.sort();
+
+    LoadTestReport {
+        rules: rules.iter().map(|r| r.name.clone()).collect(),
+        keyword: keyword.to_string(),
+        concurrency,
+        events: delays.len(),
+        total_ms: millis(total),
+        event_p50_ms: millis(percentile(&delays, 0.50)),
+        event_p90_ms: millis(percentile(&delays, 0.90)),
+        event_p99_ms: millis(percentile(&delays, 0.99)),
+        event_max_ms: millis(delays.last().copied().unwrap_or_default()),
+        search_p50_ms: millis(percentile(&durations, 0.50)),
+        search_p99_ms: millis(percentile(&durations, 0.99)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MockSource;
+
+    #[tokio::test]
+    async fn test_loadtest_reports_event_latency() {
+        let rule = |name: &str, latency_ms: u64, failure_rate: f64| {
+            Arc::new(Rule {
+                name: name.to_string(),
+                rule_type: "mock".to_string(),
+                mock: Some(MockSource {
+                    latency_ms,
+                    items: 3,
+                    failure_rate,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+        };
+        let rules = vec![
+            rule("loadtest-fast", 0, 0.0),
+            rule("loadtest-slow", 50, 1.0),
+        ];
+        let report = run("压测", rules, 4).await;
+
+        assert_eq!(report.concurrency, 4);
+        assert_eq!(report.rules, vec!["loadtest-fast", "loadtest-slow"]);
+        // 每次搜索: init + 2 个结果 + done
+        assert_eq!(report.events, 4 * 4);
+        assert!(report.event_max_ms >= 50.0);
+        assert!(report.event_p50_ms <= report.event_p99_ms);
+        assert!(report.search_p50_ms >= 50.0);
+    }
+}
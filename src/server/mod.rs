@@ -0,0 +1,2641 @@
+//! HTTP 服务
+//! 路由、处理函数与服务端专用组件 (审计、限流、监管、自检)，由 `server` feature 启用
+
+mod audit;
+#[cfg(feature = "scraper")]
+mod bench;
+#[cfg(feature = "scraper")]
+mod changelog;
+mod envelope;
+mod extract;
+mod image_proxy;
+#[cfg(feature = "scraper")]
+mod favorites;
+#[cfg(feature = "scraper")]
+mod loadtest;
+mod rate_limit;
+mod reload;
+#[cfg(feature = "scraper")]
+mod rule_cli;
+#[cfg(feature = "scraper")]
+mod rules_watch;
+mod selftest;
+mod supervisor;
+#[cfg(feature = "bangumi")]
+mod token_profiles;
+#[cfg(feature = "scraper")]
+mod watchlist;
+#[cfg(feature = "scraper")]
+mod webhook;
+
+use crate::config::{self, Config, CONFIG};
+#[cfg(any(feature = "scraper", feature = "bangumi", feature = "anilist"))]
+use extract::ApiPath;
+use extract::{ApiJson, ApiQuery};
+use crate::{cache, limiter, shutdown};
+#[cfg(feature = "bangumi")]
+use crate::{bangumi, http_client};
+#[cfg(feature = "scraper")]
+use crate::{export, storage, unified, updater};
+
+use axum::{
+    extract::ConnectInfo,
+    http::{header, HeaderMap, HeaderName, Method, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use axum::body::Body;
+#[cfg(feature = "scraper")]
+use axum::extract::Multipart;
+#[cfg(any(feature = "scraper", feature = "bangumi"))]
+use axum::routing::delete;
+#[cfg(feature = "bangumi")]
+use axum::{extract::Request, routing::any};
+#[cfg(any(feature = "scraper", feature = "bangumi"))]
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use std::net::SocketAddr;
+use tower_http::cors::{Any, CorsLayer};
+use tower_http::timeout::TimeoutLayer;
+use tracing::{error, info, warn};
+use tracing_subscriber::{EnvFilter, FmtSubscriber};
+
+#[cfg(feature = "scraper")]
+use crate::core::{normalize_keyword, search_all, search_stream_with_rules};
+#[cfg(feature = "scraper")]
+use crate::events::{EventSchema, Framing};
+#[cfg(feature = "scraper")]
+use crate::export::{ArchiveFormat, ExportFormat};
+#[cfg(feature = "scraper")]
+use crate::rules::{get_builtin_rules, rule_groups, select_rules, select_rules_with_group};
+#[cfg(feature = "scraper")]
+use crate::script::Script;
+#[cfg(feature = "scraper")]
+use crate::types::{RuleInfo, SearchOptions, UpdateResponse};
+
+/// 编译时启用的功能 (与 Cargo features 对应)
+/// `POST /api` 每个规则结果数上限的最大值 (表单 `limit` 与 X-Per-Rule-Limit)
+#[cfg(feature = "scraper")]
+const MAX_PER_RULE_LIMIT: usize = 200;
+
+/// `POST /api` 合计结果数上限的最大值 (表单 `total_limit` 与 X-Total-Limit)
+#[cfg(feature = "scraper")]
+const MAX_TOTAL_LIMIT: usize = 2000;
+
+/// `POST /api` 搜索截止时间覆盖的最大值 (表单 `timeout_ms`，毫秒)
+#[cfg(feature = "scraper")]
+const MAX_TIMEOUT_MS: usize = 120_000;
+
+const FEATURES: &[&str] = &[
+    #[cfg(feature = "scraper")]
+    "scraper",
+    #[cfg(feature = "bangumi")]
+    "bangumi",
+    #[cfg(feature = "frontend")]
+    "frontend",
+    #[cfg(feature = "anilist")]
+    "anilist",
+    #[cfg(feature = "danmaku")]
+    "danmaku",
+];
+
+/// 启动服务 (解析命令行、校验配置、拉取规则并监听端口)
+pub async fn run() {
+    // 配置文件 (CONFIG_FILE) 需在首次读取配置前载入
+    let config_file = config::read_config_file().map(config::set_file_vars);
+
+    // 初始化日志 (LOG_LEVEL 可在重载配置时调整)
+    let filter = EnvFilter::try_new(&CONFIG.log_level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = FmtSubscriber::builder()
+        .with_env_filter(filter)
+        .with_filter_reloading()
+        .with_target(false)
+        .with_thread_ids(false)
+        .with_file(false)
+        .with_line_number(false);
+    let log_handle = subscriber.reload_handle();
+    reload::set_log_reloader(move |level| {
+        let filter = EnvFilter::try_new(level).map_err(|e| e.to_string())?;
+        log_handle.reload(filter).map_err(|e| e.to_string())
+    });
+    subscriber.init();
+
+    // 规则作者工具: `rule new|check|fmt` 执行后退出
+    #[cfg(feature = "scraper")]
+    if std::env::args().nth(1).as_deref() == Some("rule") {
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        std::process::exit(rule_cli::main(&args).await);
+    }
+
+    // 校验配置 (--check-config / CONFIG_CHECK=1 时校验后退出)
+    let check_only = std::env::args().any(|a| a == "--check-config")
+        || config::var("CONFIG_CHECK")
+            .and_then(|v| config::parse_bool(&v))
+            .unwrap_or(false);
+    let mut report = config::validate_env();
+    if let Err(e) = config_file {
+        report.errors.push(e);
+    }
+    for warning in &report.warnings {
+        warn!("⚠️ {}", warning);
+    }
+    for error in &report.errors {
+        error!("❌ {}", error);
+    }
+    info!("⚙️ 生效配置:");
+    for (name, value) in CONFIG.effective_entries() {
+        info!("   {:<24} {}", name, value);
+    }
+    if !report.errors.is_empty() {
+        error!("配置校验失败 ({} 个错误)", report.errors.len());
+        std::process::exit(1);
+    }
+    if check_only {
+        info!("✅ 配置校验通过");
+        return;
+    }
+
+    // 打开存储 (DATABASE_PATH 已设置但无法打开时直接退出，避免静默丢数据)
+    match crate::storage::init() {
+        Ok(backend) => info!("🗄️ 存储后端: {}", backend),
+        Err(e) => {
+            error!("❌ 打开存储失败: {}", e);
+            std::process::exit(1);
+        }
+    }
+    #[cfg(feature = "scraper")]
+    if let Err(e) = crate::history::init() {
+        error!("❌ 打开搜索历史数据库失败: {}", e);
+        std::process::exit(1);
+    }
+    #[cfg(feature = "scraper")]
+    crate::rule_stats::init();
+
+    // 自检: `self-test` 子命令执行后退出 (失败时非零退出码)，SELF_TEST=1 时启动前执行
+    let self_test_cli = std::env::args().nth(1).as_deref() == Some("self-test");
+    if self_test_cli || CONFIG.self_test {
+        let report = selftest::run().await;
+        selftest::print_report(&report);
+        if self_test_cli {
+            std::process::exit(if report.passed { 0 } else { 1 });
+        }
+    }
+
+    info!("🧩 已启用功能: {}", FEATURES.join(", "));
+
+    // SIGHUP 重载配置
+    reload::spawn_sighup_listener();
+
+    crate::stats::init();
+    start_stats_flush();
+    start_dns_cache_cleanup();
+    start_image_cache_sweeper();
+    #[cfg(feature = "scraper")]
+    webhook::init();
+    #[cfg(feature = "scraper")]
+    start_auto_disable();
+    #[cfg(feature = "scraper")]
+    start_rule_updates().await;
+    #[cfg(feature = "scraper")]
+    start_rules_watch();
+    #[cfg(feature = "scraper")]
+    start_watchlist_checker();
+
+    let app = build_app(&CONFIG);
+
+    // 启动服务器
+    let addr = SocketAddr::from(([0, 0, 0, 0], CONFIG.port));
+
+    info!("🚀 动漫聚搜 API 启动在 http://{}", addr);
+    #[cfg(feature = "scraper")]
+    info!("📚 已加载 {} 个规则", get_builtin_rules().len());
+
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown::wait_for_shutdown());
+    shutdown::serve_until_drained(server).await.unwrap();
+    crate::stats::flush();
+    #[cfg(feature = "scraper")]
+    crate::rule_stats::flush();
+}
+
+/// 构建路由 (含超时、限流与 CORS 中间件)
+///
+/// `config` 决定注册哪些路由与中间件参数；处理函数本身仍读取全局 [`CONFIG`]。
+/// 不执行存储初始化、规则更新等启动任务，集成测试可直接以此启动服务
+pub fn build_app(config: &Config) -> Router {
+    // CORS 配置
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            HeaderName::from_static("x-per-rule-limit"),
+            HeaderName::from_static("x-total-limit"),
+        ]);
+
+    // 路由 (非流式接口，统一处理超时)
+    let mut app = Router::new()
+        .route("/info", get(api_info_handler))
+        .route("/health", get(health_handler))
+        .route("/health/ready", get(ready_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/stats", get(stats_handler))
+        .route("/image", get(image_handler))
+        // 管理接口 (需要 X-Admin-Key)
+        .route("/admin/audit", get(audit_handler))
+        .route("/admin/reload-config", post(reload_config_handler))
+        .route("/admin/selftest", get(selftest_handler))
+        .route("/admin/caches", get(caches_handler))
+        .route("/debug/config", get(debug_config_handler));
+
+    // 首页 (ROOT_MODE): 只影响 GET /，缺少跳转地址时按 html 处理
+    match (config.root_mode.as_str(), config.root_redirect_url.clone()) {
+        ("api", _) => app = app.route("/", get(api_info_handler)),
+        ("redirect", Some(url)) => {
+            app = app.route("/", get(move || root_redirect(url.clone())));
+        }
+        _ => {
+            #[cfg(feature = "frontend")]
+            {
+                app = app.route("/", get(index_handler));
+            }
+        }
+    }
+
+    #[cfg(feature = "scraper")]
+    {
+        app = app
+            .route("/search", get(json_search_handler))
+            .route("/search/csv", get(export_handler))
+            .route("/search/export", get(archive_handler))
+            .route("/search/unified", get(unified_handler))
+            .route("/source/{rule}/search", get(source_search_handler))
+            .route("/episodes", get(road_episodes_handler))
+            .route("/episodes/stream", get(road_episodes_stream_handler))
+            .route("/export/m3u", get(m3u_handler))
+            .route("/rules", get(rules_handler))
+            .route("/rules/groups", get(rule_groups_handler))
+            .route("/rules/changelog", get(rule_changelog_handler))
+            .route("/rules/errors", get(rule_errors_handler))
+            .route("/feeds/rules.atom", get(rule_feed_handler))
+            .route("/history/stats", get(history_stats_handler))
+            .route("/cache/stats", get(cache_stats_handler))
+            .route("/cache/clear", post(cache_clear_handler))
+            .route("/rules/schema.json", get(rule_schema_handler))
+            .route("/schema/stream", get(stream_schema_handler))
+            .route("/events/schema.json", get(event_schema_handler))
+            .route("/favorites", get(favorites_list_handler).post(favorites_add_handler))
+            .route("/favorites/{id}", delete(favorites_delete_handler))
+            .route("/watchlist", get(watchlist_list_handler).post(watchlist_add_handler))
+            .route("/watchlist/{id}", delete(watchlist_delete_handler))
+            .route("/watchlist/{id}/check", post(watchlist_check_handler))
+            .route("/watchlist/{id}/changes", get(watchlist_changes_handler))
+            .route("/admin/rules/{name}/enable", post(rule_enable_handler))
+            .route("/debug/bench", get(bench_handler))
+            .route("/dev/loadtest", get(loadtest_handler))
+            .route("/debug/dry-run", post(dry_run_handler));
+    }
+
+    #[cfg(feature = "bangumi")]
+    {
+        app = app
+            .route(
+                "/admin/token-profiles",
+                get(token_profiles_list_handler).post(token_profiles_upsert_handler),
+            )
+            .route("/admin/token-profiles/{name}", delete(token_profiles_delete_handler))
+            // Bangumi API 通用代理 (透传到 api.bgm.tv，自动添加 CORS)
+            .route("/bgm/{*path}", any(bangumi_proxy_handler));
+    }
+
+    #[cfg(any(feature = "bangumi", feature = "anilist"))]
+    {
+        app = app
+            .route("/suggest", get(suggest_handler))
+            .route("/metadata/subjects/{id}", get(metadata_subject_handler))
+            .route("/metadata/calendar", get(metadata_calendar_handler));
+    }
+
+    #[cfg(feature = "danmaku")]
+    {
+        app = app
+            .route("/danmaku/search", get(danmaku_search_handler))
+            .route("/danmaku/comments", get(danmaku_comments_handler))
+            .route("/danmaku/match", get(danmaku_match_handler));
+    }
+
+    // 远程停机仅在配置了 ADMIN_KEY 时注册，默认部署无此路由
+    if config.admin_key.is_some() {
+        app = app.route("/admin/shutdown", post(shutdown_handler));
+    }
+
+    let app = app.layer(TimeoutLayer::with_status_code(
+        StatusCode::GATEWAY_TIMEOUT,
+        std::time::Duration::from_secs(config.request_timeout_seconds),
+    ));
+
+    // 流式接口与规则更新自行控制时长，不受统一超时限制
+    #[cfg(feature = "scraper")]
+    let app = app
+        .route("/api", post(search_handler).get(search_get_handler))
+        .route("/update", get(update_handler));
+
+    // Bangumi 流式搜索 (搜索命中 + 条目详情)
+    #[cfg(feature = "bangumi")]
+    let app = app
+        .route("/bangumi/search/{keyword}/stream", get(bangumi_search_stream_handler))
+        .route("/bangumi/subjects/{id}/episodes", get(bangumi_episodes_handler));
+
+    // 公开模式: 按客户端 IP 限流 (在 CORS 内层，429 响应同样带 CORS 头；
+    // 始终挂载，上限在请求时读取配置，重载后可开启/关闭)
+    if config.public_rate_limit > 0 {
+        info!(
+            "🚦 按 IP 限流: {} 次/分钟{}",
+            config.public_rate_limit,
+            if config.trust_forwarded { " (信任 X-Forwarded-For)" } else { "" }
+        );
+    }
+    let app = app.layer(rate_limit::RateLimitLayer::new());
+
+    // 响应信封 (ENVELOPE 或 ?envelope=1): 在限流与超时外层，429/504 同样包装
+    let app = app.layer(envelope::EnvelopeLayer::new(config.envelope));
+
+    app.layer(cors)
+}
+
+/// 启用 DNS 缓存时定期清理过期记录
+fn start_dns_cache_cleanup() {
+    if CONFIG.dns_cache_seconds == 0 {
+        return;
+    }
+    let interval = std::time::Duration::from_secs(CONFIG.dns_cache_seconds);
+    let heartbeat_timeout = interval + std::time::Duration::from_secs(60);
+    supervisor::spawn("dns_cache", false, heartbeat_timeout, move |hb| async move {
+        loop {
+            hb.beat();
+            tokio::time::sleep(interval).await;
+            let purged = crate::http_client::purge_dns_cache();
+            if purged > 0 {
+                tracing::debug!("清理 {} 条过期 DNS 缓存", purged);
+            }
+        }
+    });
+}
+
+/// 定期将当天的使用统计 (供 `/stats?days=`) 与规则健康统计写入存储
+fn start_stats_flush() {
+    let interval = crate::stats::FLUSH_INTERVAL;
+    let heartbeat_timeout = interval + std::time::Duration::from_secs(60);
+    supervisor::spawn("stats_flush", false, heartbeat_timeout, move |hb| async move {
+        loop {
+            hb.beat();
+            tokio::time::sleep(interval).await;
+            let _ = tokio::task::spawn_blocking(|| {
+                crate::stats::flush();
+                #[cfg(feature = "scraper")]
+                crate::rule_stats::flush();
+            })
+            .await;
+        }
+    });
+}
+
+/// 设置 IMAGE_CACHE_DIR 时定期清理图片缓存 (中断的写入与超出大小预算的图片)
+fn start_image_cache_sweeper() {
+    let Some(cache) = image_proxy::disk_cache() else {
+        return;
+    };
+    let interval = image_proxy::SWEEP_INTERVAL;
+    let heartbeat_timeout = interval + std::time::Duration::from_secs(60);
+    supervisor::spawn("image_cache", false, heartbeat_timeout, move |hb| async move {
+        loop {
+            hb.beat();
+            match tokio::task::spawn_blocking(|| cache.sweep()).await {
+                Ok(Ok(stats)) if stats.evicted + stats.broken > 0 => tracing::debug!(
+                    "图片缓存清理: 淘汰 {} 张，清理中断写入 {} 个，当前 {} 字节",
+                    stats.evicted,
+                    stats.broken,
+                    stats.total_bytes
+                ),
+                Ok(Err(e)) => warn!("图片缓存清理失败: {}", e),
+                _ => {}
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+/// 拉取规则 (本地无规则且 BOOTSTRAP_RULES，或 AUTO_UPDATE) 并启动定时更新
+#[cfg(feature = "scraper")]
+async fn start_rule_updates() {
+    if !updater::has_local_rules() {
+        bootstrap_rules().await;
+    } else if CONFIG.auto_update {
+        info!("📡 正在拉取规则...");
+        let result = updater::update_rules().await;
+        after_update(&result);
+        info!(
+            "📦 更新完成: {} 新增, {} 更新, {} 失败",
+            result.added, result.updated, result.failed
+        );
+    }
+
+    // 定时更新规则 (受监管的后台任务)
+    if CONFIG.update_interval_hours > 0 {
+        let interval = std::time::Duration::from_secs(CONFIG.update_interval_hours * 3600);
+        // 心跳超时 = 间隔 + 单次更新的宽限时间
+        let heartbeat_timeout = interval + std::time::Duration::from_secs(600);
+        supervisor::spawn("rule_updater", true, heartbeat_timeout, move |hb| async move {
+            loop {
+                hb.beat();
+                tokio::time::sleep(interval).await;
+                hb.beat();
+                info!("⏰ 定时更新规则...");
+                let result = updater::update_rules().await;
+                after_update(&result);
+            }
+        });
+    }
+}
+
+/// 规则目录不存在或为空: 首次部署时自动拉取规则并重新加载，避免启动后零规则、所有搜索都无结果
+#[cfg(feature = "scraper")]
+async fn bootstrap_rules() {
+    if !CONFIG.bootstrap_rules {
+        warn!(
+            "⚠️ 规则目录 {} 不存在或为空，且 BOOTSTRAP_RULES=0，未拉取规则；所有搜索将没有结果",
+            CONFIG.rules_dir
+        );
+        return;
+    }
+
+    info!(
+        "🆕 规则目录 {} 不存在或为空，从 {} 引导拉取规则 (BOOTSTRAP_RULES=0 可关闭)",
+        CONFIG.rules_dir, CONFIG.rules_repo
+    );
+    let result = updater::update_rules().await;
+    after_update(&result);
+    let rules = crate::rules::reload_builtin_rules();
+    if rules.is_empty() {
+        warn!(
+            "⚠️ 引导拉取后仍没有可用规则 ({} 失败)，请检查网络、GITHUB_PROXY 或 RULES_REPO；所有搜索将没有结果",
+            result.failed
+        );
+    } else {
+        info!("✅ 引导完成: 已加载 {} 个规则", rules.len());
+    }
+}
+
+/// 启用 WATCH_RULES 时: 监视规则目录，文件变化后重新加载规则 (受监管的后台任务)
+#[cfg(feature = "scraper")]
+fn start_rules_watch() {
+    if !CONFIG.watch_rules {
+        return;
+    }
+    info!("👀 监视规则目录: {}", CONFIG.rules_dir);
+    let heartbeat_timeout = rules_watch::RESCAN_INTERVAL * 3;
+    supervisor::spawn("rules_watch", false, heartbeat_timeout, |hb| async move {
+        rules_watch::watch(|| hb.beat()).await;
+    });
+}
+
+/// 设置 WATCHLIST_CHECK_INTERVAL_MINUTES 时: 定期检查到期的追更条目 (受监管的后台任务)
+#[cfg(feature = "scraper")]
+fn start_watchlist_checker() {
+    if CONFIG.watchlist_check_interval_minutes == 0 {
+        return;
+    }
+    let interval = std::time::Duration::from_secs(CONFIG.watchlist_check_interval_minutes * 60);
+    // 心跳超时 = 间隔 + 单轮检查的宽限时间
+    let heartbeat_timeout = interval + std::time::Duration::from_secs(600);
+    supervisor::spawn("watchlist_checker", false, heartbeat_timeout, move |hb| async move {
+        loop {
+            hb.beat();
+            tokio::time::sleep(interval).await;
+            hb.beat();
+            let checked = watchlist::check_due().await;
+            if checked > 0 {
+                tracing::debug!("定期检查 {} 个追更条目", checked);
+            }
+        }
+    });
+}
+
+/// 启用 AUTO_DISABLE 时: 记录规则停用/启用变化，并定期探测已停用的规则
+#[cfg(feature = "scraper")]
+fn start_auto_disable() {
+    if !CONFIG.auto_disable {
+        return;
+    }
+    crate::rule_stats::set_transition_listener(|transition| {
+        changelog::record_transition(&transition);
+        webhook::rule_transition(&transition);
+    });
+    info!(
+        "⛔ 规则自动停用已启用 (每 {} 分钟探测已停用的规则)",
+        CONFIG.auto_disable_recheck_minutes
+    );
+
+    let interval = std::time::Duration::from_secs(CONFIG.auto_disable_recheck_minutes * 60);
+    let heartbeat_timeout = interval + std::time::Duration::from_secs(600);
+    supervisor::spawn("rule_recheck", false, heartbeat_timeout, move |hb| async move {
+        loop {
+            hb.beat();
+            crate::rule_stats::schedule_recheck(interval);
+            tokio::time::sleep(interval).await;
+            hb.beat();
+            let disabled = crate::rule_stats::auto_disabled_rules();
+            if disabled.is_empty() {
+                continue;
+            }
+            let keyword = &CONFIG.auto_disable_canary_keyword;
+            // 逐个探测，不与用户搜索争抢并发；绕过结果缓存，实际请求源站
+            for rule in get_builtin_rules()
+                .iter()
+                .filter(|r| disabled.iter().any(|(name, _)| *name == r.name))
+            {
+                let result = crate::engine::search_uncached(rule, keyword, &SearchOptions::default()).await;
+                info!(
+                    "🩺 探测规则 {}: {}",
+                    rule.name,
+                    result.error.as_deref().unwrap_or("成功")
+                );
+                crate::rule_stats::record_canary(&rule.name, result.error.is_none());
+                hb.beat();
+            }
+        }
+    });
+}
+
+/// 规则更新后: 记录变更并发送 webhook 通知
+#[cfg(feature = "scraper")]
+fn after_update(result: &updater::UpdateResult) {
+    changelog::record_update(result);
+    webhook::rules_updated(result);
+    webhook::update_finished(result);
+}
+
+/// GET / - ROOT_MODE=redirect 时 302 跳转
+async fn root_redirect(url: String) -> Response {
+    (StatusCode::FOUND, [(header::LOCATION, url)]).into_response()
+}
+
+/// GET / - 最小前端页面
+#[cfg(feature = "frontend")]
+async fn index_handler() -> axum::response::Html<&'static str> {
+    axum::response::Html(INDEX_HTML)
+}
+
+/// GET /api - API 信息 (只列出编译启用的接口)
+async fn api_info_handler() -> impl IntoResponse {
+    let mut core = serde_json::Map::new();
+    let mut admin = serde_json::Map::new();
+    let mut endpoints = serde_json::Map::new();
+
+    match CONFIG.root_mode.as_str() {
+        "api" => {
+            core.insert("GET /".into(), json!("API 信息 (同 /info)"));
+        }
+        "redirect" if CONFIG.root_redirect_url.is_some() => {
+            core.insert("GET /".into(), json!("跳转到自定义首页 (ROOT_REDIRECT_URL)"));
+        }
+        _ => {
+            #[cfg(feature = "frontend")]
+            core.insert("GET /".into(), json!("搜索页面"));
+        }
+    }
+
+    #[cfg(feature = "scraper")]
+    {
+        core.insert("POST /api".into(), json!("搜索动漫 (FormData: anime=关键词, rules=规则名1,规则名2, group=规则分组, script=simplified|traditional, first_only=1 仅首个结果, limit=每个规则最多结果数, total_limit=合计最多结果数 (也可用请求头 X-Per-Rule-Limit / X-Total-Limit，表单优先), offset=偏移 (替换 searchURL 的 @limit/@offset), enrich=1 标注规范名称 (来源见 ?provider=bangumi|anilist), top_rating=1 查询各平台第一个结果的评分 (rating 事件), include_raw=1 附带原始 HTML[仅管理员], debug_timing=1 附带各规则耗时分解[仅管理员], concurrency=并发数[仅管理员])；响应为 SSE 帧 (event/id/data)，?framing=ndjson 时为每行一个 JSON"));
+        core.insert("GET /api".into(), json!("搜索动漫 (查询参数: anime, rules, group，可用 EventSource 订阅)"));
+        core.insert("GET /source/{rule}/search".into(), json!("只用一个规则搜索，返回带集数的精简结果 (anime=关键词, script=字形, episodes=0 不获取集数)"));
+        core.insert("GET /search".into(), json!("非流式搜索，全部规则完成后一次性返回 {keyword, results, elapsed_ms} (anime=关键词, rules=规则名, group=规则分组, script=字形, episodes=0 不获取集数)"));
+        core.insert("GET /search/unified".into(), json!("按名称合并各规则的结果 (anime=关键词, rules=规则名, group=规则分组, script=字形, episodes=1 合并各来源的集数)"));
+        core.insert("GET /episodes".into(), json!("获取详情页的播放源与集数 (rule=规则名, url=详情页链接, road_id=只返回该播放源)"));
+        core.insert("GET /episodes/stream".into(), json!("同上，逐个播放源返回 (每行一个 JSON: {road}，失败时 {error}，最后 {done, roads})"));
+        core.insert("GET /export/m3u".into(), json!("将播放源导出为 M3U (rule, url, road_id=播放源 id，缺省为第一个播放源)"));
+        core.insert("GET /search/export".into(), json!("搜索并下载结果归档 (keyword=关键词, rules=规则名, group=规则分组, format=json|csv)"));
+        core.insert("GET /search/csv".into(), json!("搜索并导出表格 (anime=关键词, rules=规则名, group=规则分组, format=csv|tsv)"));
+        core.insert("GET /rules".into(), json!("获取所有规则列表"));
+        core.insert("GET /rules/groups".into(), json!("规则分组 (分组名 -> 规则名列表)"));
+        core.insert("GET /rules/changelog".into(), json!("规则变更记录 (limit=条数，默认 50)"));
+        core.insert("GET /rules/errors".into(), json!("无法解析的规则文件 (file, error, line, column)"));
+        core.insert("GET /feeds/rules.atom".into(), json!("规则变更的 Atom feed (最近 50 条)"));
+        core.insert("GET /cache/stats".into(), json!("搜索结果缓存统计 (enabled, entries, hits, misses, hit_rate, ttl_seconds)"));
+        core.insert("GET /history/stats".into(), json!("搜索历史统计 (days=天数, top=热门关键词数，需设置 HISTORY_DB)"));
+        core.insert("GET /favorites".into(), json!("收藏列表 (q=筛选, subject_id=Bangumi 条目, limit, offset)"));
+        core.insert("POST /favorites".into(), json!("收藏搜索结果 (JSON: keyword, rule, name, url, cover?, subject_id?)"));
+        core.insert("DELETE /favorites/{id}".into(), json!("取消收藏"));
+        core.insert("GET /watchlist".into(), json!("追更列表 (含未读新集数与检查状态)"));
+        core.insert("POST /watchlist".into(), json!("添加追更 (JSON: keyword, rules, subject_id?)"));
+        core.insert("DELETE /watchlist/{id}".into(), json!("取消追更 (连同集数快照)"));
+        core.insert("POST /watchlist/{id}/check".into(), json!("立即检查追更条目，返回本次新出现的集数"));
+        core.insert("GET /watchlist/{id}/changes".into(), json!("未读的新集数 (返回后标记为已读)"));
+        core.insert("GET /rules/schema.json".into(), json!("规则文件的 JSON Schema (可用于编辑器补全与校验)"));
+        core.insert("GET /schema/stream".into(), json!("流式搜索事件的 JSON Schema"));
+        core.insert("GET /events/schema.json".into(), json!("v2 流式事件的 JSON Schema (POST /api?schema=2 或 Accept profile=\"events/v2\")"));
+        core.insert("GET /update".into(), json!("从 KazumiRules 更新规则"));
+    }
+
+    #[cfg(any(feature = "bangumi", feature = "anilist"))]
+    {
+        core.insert("GET /suggest?keyword=&provider=".into(), json!("搜索元数据条目 (provider=bangumi|anilist，默认 METADATA_PROVIDER)"));
+        core.insert("GET /metadata/subjects/{id}?provider=".into(), json!("元数据条目详情 (统一字段，来源特有信息在 extra；summary_len=N 截断简介)"));
+        core.insert("GET /metadata/calendar?provider=".into(), json!("每日放送 (按星期分组)"));
+    }
+
+    #[cfg(feature = "danmaku")]
+    {
+        core.insert("GET /danmaku/search?anime=".into(), json!("弹弹play 番剧搜索"));
+        core.insert("GET /danmaku/comments?episode_id=".into(), json!("剧集弹幕 (含关联弹幕)"));
+        core.insert("GET /danmaku/match?keyword=&episode=".into(), json!("按番剧名与集数匹配弹弹play 剧集 ID"));
+    }
+
+    core.insert("GET /image?url=".into(), json!("图片代理 (以图片所在站点为 Referer，设置 IMAGE_CACHE_DIR 时缓存到磁盘)"));
+    core.insert("GET /health".into(), json!("健康检查 (存活)"));
+    core.insert("GET /health/ready".into(), json!("就绪检查 (关键后台任务失活时返回 503)"));
+    core.insert("GET /metrics".into(), json!("Prometheus 指标"));
+    core.insert("GET /stats?days=".into(), json!("使用统计 (搜索次数、各规则次数、热门关键词、Bangumi 代理调用、缓存命中率、进行中的流；days=N 附带最近 N 天汇总)"));
+
+    admin.insert("GET /admin/audit?limit=100".into(), json!("审计日志 (请求头 X-Admin-Key)"));
+    admin.insert("POST /admin/reload-config".into(), json!("重载配置 (同 SIGHUP)，返回已生效与需要重启的配置项"));
+    admin.insert("GET /admin/selftest".into(), json!("执行自检 (规则、Bangumi 连通性、规则目录可写、样例解析)"));
+    admin.insert("GET /admin/caches".into(), json!("进程内缓存统计 (条目数、命中率、淘汰数)"));
+    admin.insert("GET /debug/config".into(), json!("进程实际生效的配置与已启用功能 (密钥脱敏，代理只报告是否设置)"));
+    #[cfg(feature = "scraper")]
+    admin.insert("POST /cache/clear".into(), json!("清空搜索结果缓存，返回清除的条目数"));
+    #[cfg(feature = "scraper")]
+    admin.insert("POST /admin/rules/{name}/enable".into(), json!("手动启用规则 (清空失败计数，覆盖自动停用)"));
+    #[cfg(feature = "scraper")]
+    admin.insert("GET /debug/bench?rule=&iterations=100".into(), json!("解析基准 (对最近一次搜索页或内置样例重复解析，报告吞吐与 p50/p99)"));
+    #[cfg(feature = "scraper")]
+    admin.insert("GET /dev/loadtest?rules=&concurrency=10".into(), json!("用模拟规则并发执行流式搜索，报告事件送达延迟分位数 (需 ENABLE_MOCK_RULES=1)"));
+    #[cfg(feature = "scraper")]
+    admin.insert("POST /debug/dry-run".into(), json!("试运行规则 (JSON: rule, keyword)，报告各 XPath 阶段匹配数与解析结果"));
+    admin.insert("POST /admin/shutdown".into(), json!("优雅停机 (JSON 可选: drain_seconds)，仅配置 ADMIN_KEY 时可用"));
+
+    #[cfg(feature = "bangumi")]
+    {
+        admin.insert("GET /admin/token-profiles".into(), json!("Bangumi token 档案列表"));
+        admin.insert("POST /admin/token-profiles".into(), json!("新增/覆盖档案 (JSON: name, token)"));
+        admin.insert("DELETE /admin/token-profiles/{name}".into(), json!("删除档案"));
+    }
+
+    endpoints.insert("core".into(), core.into());
+    endpoints.insert("admin".into(), admin.into());
+
+    #[cfg(feature = "bangumi")]
+    {
+        endpoints.insert("bangumi".into(), json!({
+            "GET /bangumi/search/{keyword}/stream": "流式搜索: 先返回搜索命中，再逐条返回条目详情",
+            "GET /bangumi/subjects/{id}/episodes": "章节列表 (type, limit, offset；默认精简字段，full=1 返回完整字段，summary_len=N 截断简介)"
+        }));
+        endpoints.insert("bangumi_proxy".into(), json!({
+            "ANY /bgm/*": "Bangumi API 通用代理 (透传到 api.bgm.tv，自动添加 CORS)",
+            "example": "GET /bgm/v0/subjects/328609 → https://api.bgm.tv/v0/subjects/328609"
+        }));
+    }
+
+    let mut info = serde_json::Map::new();
+    info.insert("name".into(), json!("AnimeSearch API"));
+    info.insert("version".into(), json!("0.3.0"));
+    info.insert("description".into(), json!("在线动漫聚合搜索后端"));
+    info.insert("features".into(), json!(FEATURES));
+    info.insert("endpoints".into(), endpoints.into());
+
+    #[cfg(feature = "bangumi")]
+    {
+        info.insert("auth".into(), json!({
+            "note": "Bangumi API 需要认证的端点请在请求头添加 Authorization: Bearer <token>，或通过 X-Token-Profile: <档案名> 使用管理员登记的 token",
+            "get_token": "https://next.bgm.tv/demo/access-token"
+        }));
+    }
+
+    Json(info)
+}
+
+/// 停机排空期间拒绝新搜索
+#[cfg(feature = "scraper")]
+fn draining_rejection() -> Option<Response> {
+    if !shutdown::is_draining() {
+        return None;
+    }
+    Some(
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "30")],
+            Json(json!({"error": "Server is shutting down"})),
+        )
+            .into_response(),
+    )
+}
+
+/// 获取全局搜索槽位，已满时返回 429
+#[cfg(feature = "scraper")]
+async fn acquire_search_slot() -> Result<limiter::SearchSlot, Response> {
+    limiter::acquire_search_slot().await.map_err(|_| {
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, "5")],
+            Json(json!({
+                "error": "Too many concurrent searches, please retry later",
+                "retry_after": 5
+            })),
+        )
+            .into_response()
+    })
+}
+
+/// POST /api 与 GET /api 共用的查询参数
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct StreamQuery {
+    schema: Option<String>,
+    /// enrich 使用的元数据来源
+    provider: Option<String>,
+    /// 分帧方式: sse (默认) 或 ndjson (每行一个 JSON)
+    framing: Option<String>,
+}
+
+/// GET /api 查询参数 (供 EventSource 使用，字段同 POST 表单)
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    anime: Option<String>,
+    rules: Option<String>,
+    group: Option<String>,
+    /// 搜索截止时间覆盖 (毫秒)
+    timeout_ms: Option<String>,
+    #[serde(flatten)]
+    stream: StreamQuery,
+}
+
+/// 按查询参数与请求头协商事件格式、分帧方式与结果数上限 (参数无效时返回错误消息)
+#[cfg(feature = "scraper")]
+fn stream_options(query: StreamQuery, headers: &HeaderMap) -> Result<SearchOptions, String> {
+    // 事件格式: ?schema=2 或 Accept 的 profile 参数，默认 v1
+    let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok());
+    let event_schema = EventSchema::negotiate(query.schema.as_deref(), accept)?;
+    let framing = Framing::from_param(query.framing.as_deref())?;
+    if let Some(provider) = query.provider.as_deref() {
+        crate::metadata::provider(Some(provider))?;
+    }
+
+    // 结果数上限: 请求头在前，表单字段 (limit / total_limit) 覆盖请求头
+    let header_limit = |name: &str, max: usize| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_limit(v, max))
+    };
+    Ok(SearchOptions {
+        event_schema,
+        framing,
+        metadata_provider: query.provider,
+        limit: header_limit("X-Per-Rule-Limit", MAX_PER_RULE_LIMIT),
+        total_limit: header_limit("X-Total-Limit", MAX_TOTAL_LIMIT),
+        ..Default::default()
+    })
+}
+
+/// GET /api - 动漫搜索处理器 (查询参数版本，供 EventSource 使用)
+#[cfg(feature = "scraper")]
+async fn search_get_handler(
+    ApiQuery(query): ApiQuery<SearchQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(resp) = draining_rejection() {
+        return resp;
+    }
+    let mut options = match stream_options(query.stream, &headers) {
+        Ok(options) => options,
+        Err(message) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response();
+        }
+    };
+    options.deadline = query.timeout_ms.as_deref().and_then(parse_timeout);
+    let rule_names = query.rules.as_deref().map(str::trim);
+    let group = query.group.as_deref().map(str::trim);
+    stream_search_response(query.anime.as_deref(), rule_names, group, options).await
+}
+
+/// POST / - 动漫搜索处理器 (SSE 流式响应)
+#[cfg(feature = "scraper")]
+async fn search_handler(
+    ApiQuery(query): ApiQuery<StreamQuery>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Response {
+    if let Some(resp) = draining_rejection() {
+        return resp;
+    }
+    let mut options = match stream_options(query, &headers) {
+        Ok(options) => options,
+        Err(message) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response();
+        }
+    };
+
+    // 解析 FormData
+    let mut keyword: Option<String> = None;
+    let mut rule_names: Option<String> = None;
+    let mut group: Option<String> = None;
+
+    while let Ok(Some(field)) = multipart.next_field().await {
+        match field.name() {
+            Some("anime") => {
+                if let Ok(text) = field.text().await {
+                    keyword = Some(text);
+                }
+            }
+            Some("rules") => {
+                if let Ok(text) = field.text().await {
+                    rule_names = Some(text.trim().to_string());
+                }
+            }
+            Some("group") => {
+                if let Ok(text) = field.text().await {
+                    group = Some(text.trim().to_string());
+                }
+            }
+            Some("script") => {
+                if let Ok(text) = field.text().await {
+                    options.script = Script::parse(&text);
+                }
+            }
+            Some("first_only") => {
+                if let Ok(text) = field.text().await {
+                    options.first_only = config::parse_bool(&text).unwrap_or(false);
+                }
+            }
+            Some("enrich") => {
+                if let Ok(text) = field.text().await {
+                    options.enrich = config::parse_bool(&text).unwrap_or(false);
+                }
+            }
+            Some("top_rating") => {
+                if let Ok(text) = field.text().await {
+                    options.top_rating = config::parse_bool(&text).unwrap_or(false);
+                }
+            }
+            Some("limit") => {
+                let text = field.text().await.unwrap_or_default();
+                if let Some(limit) = parse_limit(&text, MAX_PER_RULE_LIMIT) {
+                    options.limit = Some(limit);
+                }
+            }
+            Some("total_limit") => {
+                let text = field.text().await.unwrap_or_default();
+                if let Some(limit) = parse_limit(&text, MAX_TOTAL_LIMIT) {
+                    options.total_limit = Some(limit);
+                }
+            }
+            Some("offset") => {
+                if let Ok(text) = field.text().await {
+                    options.offset = text.trim().parse().ok();
+                }
+            }
+            Some("timeout_ms") => {
+                let text = field.text().await.unwrap_or_default();
+                if let Some(deadline) = parse_timeout(&text) {
+                    options.deadline = Some(deadline);
+                }
+            }
+            // 原始 HTML、耗时分解与并发数覆盖仅对管理员生效，其他请求忽略
+            Some("include_raw") if is_admin(&headers) => {
+                if let Ok(text) = field.text().await {
+                    options.include_raw = config::parse_bool(&text).unwrap_or(false);
+                }
+            }
+            Some("debug_timing") if is_admin(&headers) => {
+                if let Ok(text) = field.text().await {
+                    options.debug_timing = config::parse_bool(&text).unwrap_or(false);
+                }
+            }
+            Some("concurrency") if is_admin(&headers) => {
+                if let Ok(text) = field.text().await {
+                    options.concurrency = text.trim().parse().ok();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    stream_search_response(keyword.as_deref(), rule_names.as_deref(), group.as_deref(), options)
+        .await
+}
+
+/// 校验关键词与规则后开始流式搜索
+#[cfg(feature = "scraper")]
+async fn stream_search_response(
+    keyword: Option<&str>,
+    rule_names: Option<&str>,
+    group: Option<&str>,
+    options: SearchOptions,
+) -> Response {
+    let keyword = match normalize_keyword(keyword.unwrap_or(""), CONFIG.max_keyword_len) {
+        Ok(k) => k,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                [(header::CONTENT_TYPE, "application/json")],
+                Json(json!({"error": message})),
+            )
+                .into_response();
+        }
+    };
+
+    // 筛选规则
+    let selected_rules = match select_rules_with_group(rule_names, group) {
+        Ok(rules) => rules,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                [(header::CONTENT_TYPE, "application/json")],
+                Json(json!({"error": message})),
+            )
+                .into_response();
+        }
+    };
+
+    info!(
+        "🔍 搜索: {} (规则: {})",
+        keyword,
+        selected_rules
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let slot = match acquire_search_slot().await {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    // 创建 SSE 流
+    let content_type = options.framing.content_type();
+    let stream = search_stream_with_rules(keyword, selected_rules, options);
+
+    // 将流转换为字节流 (槽位随响应体释放，客户端断开时同样释放)
+    let active = crate::stats::StreamGuard::acquire();
+    let body = Body::from_stream(stream.map(move |s| {
+        let _ = (&slot, &active);
+        Ok::<_, std::convert::Infallible>(s)
+    }));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::CONNECTION, "keep-alive")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(body)
+        .unwrap()
+}
+
+/// 结果数上限: 正整数，超出 `max` 时截断 (0 或无法解析时忽略)
+#[cfg(feature = "scraper")]
+fn parse_limit(text: &str, max: usize) -> Option<usize> {
+    text.trim().parse::<usize>().ok().filter(|l| *l > 0).map(|l| l.min(max))
+}
+
+/// 搜索截止时间覆盖: 毫秒数，超出 MAX_TIMEOUT_MS 时截断 (0 或无法解析时忽略)
+#[cfg(feature = "scraper")]
+fn parse_timeout(text: &str) -> Option<std::time::Duration> {
+    parse_limit(text, MAX_TIMEOUT_MS).map(|ms| std::time::Duration::from_millis(ms as u64))
+}
+
+/// GET /search 查询参数
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct JsonSearchQuery {
+    anime: Option<String>,
+    rules: Option<String>,
+    group: Option<String>,
+    script: Option<String>,
+    /// 是否同时获取集数 (默认获取)
+    episodes: Option<String>,
+    /// 搜索截止时间覆盖 (毫秒)
+    timeout_ms: Option<String>,
+}
+
+/// GET /search - 非流式搜索，所有规则完成后一次性返回 JSON (按规则顺序)
+#[cfg(feature = "scraper")]
+async fn json_search_handler(ApiQuery(query): ApiQuery<JsonSearchQuery>) -> Response {
+    let bad_request = |message: String| {
+        (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response()
+    };
+
+    if let Some(resp) = draining_rejection() {
+        return resp;
+    }
+
+    let keyword = match normalize_keyword(query.anime.as_deref().unwrap_or(""), CONFIG.max_keyword_len) {
+        Ok(k) => k,
+        Err(message) => return bad_request(message),
+    };
+    let selected_rules = match select_rules_with_group(query.rules.as_deref(), query.group.as_deref()) {
+        Ok(rules) => rules,
+        Err(message) => return bad_request(message.to_string()),
+    };
+    let options = SearchOptions {
+        script: query.script.as_deref().and_then(Script::parse),
+        skip_episodes: !query
+            .episodes
+            .as_deref()
+            .and_then(config::parse_bool)
+            .unwrap_or(true),
+        deadline: query.timeout_ms.as_deref().and_then(parse_timeout),
+        ..Default::default()
+    };
+
+    let _slot = match acquire_search_slot().await {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    let started = std::time::Instant::now();
+    let results = search_all(keyword.clone(), selected_rules, options).await;
+    Json(json!({
+        "keyword": keyword,
+        "results": results,
+        "elapsed_ms": started.elapsed().as_millis() as u64,
+    }))
+    .into_response()
+}
+
+/// GET /search/csv 查询参数
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    anime: Option<String>,
+    rules: Option<String>,
+    group: Option<String>,
+    format: Option<String>,
+    script: Option<String>,
+    first_only: Option<String>,
+}
+
+/// GET /search/csv - 搜索并导出为 CSV/TSV (`format=tsv`)
+#[cfg(feature = "scraper")]
+async fn export_handler(ApiQuery(query): ApiQuery<ExportQuery>) -> Response {
+    let bad_request = |message: String| {
+        (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response()
+    };
+
+    if let Some(resp) = draining_rejection() {
+        return resp;
+    }
+
+    let Some(format) = ExportFormat::parse(query.format.as_deref()) else {
+        return bad_request("Unsupported format, expected 'csv' or 'tsv'".to_string());
+    };
+    let keyword = match normalize_keyword(query.anime.as_deref().unwrap_or(""), CONFIG.max_keyword_len) {
+        Ok(k) => k,
+        Err(message) => return bad_request(message),
+    };
+    let selected_rules = match select_rules_with_group(query.rules.as_deref(), query.group.as_deref()) {
+        Ok(rules) => rules,
+        Err(message) => return bad_request(message.to_string()),
+    };
+    let options = SearchOptions {
+        script: query.script.as_deref().and_then(Script::parse),
+        first_only: query
+            .first_only
+            .as_deref()
+            .and_then(config::parse_bool)
+            .unwrap_or(false),
+        ..Default::default()
+    };
+
+    let _slot = match acquire_search_slot().await {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    info!("📄 导出搜索: {} ({} 个规则)", keyword, selected_rules.len());
+    let results = search_all(keyword.clone(), selected_rules, options).await;
+
+    let body = match export::to_table(&results, format) {
+        Ok(body) => body,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Failed to export results: {}", e)})),
+            )
+                .into_response();
+        }
+    };
+
+    let disposition = format!(
+        "attachment; filename=\"search.{ext}\"; filename*=UTF-8''{}.{ext}",
+        urlencoding::encode(&keyword),
+        ext = format.extension()
+    );
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// GET /search/export 查询参数
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct ArchiveQuery {
+    #[serde(alias = "anime")]
+    keyword: Option<String>,
+    rules: Option<String>,
+    group: Option<String>,
+    format: Option<String>,
+}
+
+/// GET /search/export - 搜索并下载结果归档 (`format=json|csv`，复用结果缓存)
+#[cfg(feature = "scraper")]
+async fn archive_handler(ApiQuery(query): ApiQuery<ArchiveQuery>) -> Response {
+    let bad_request = |message: String| {
+        (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response()
+    };
+
+    if let Some(resp) = draining_rejection() {
+        return resp;
+    }
+
+    let Some(format) = ArchiveFormat::parse(query.format.as_deref()) else {
+        return bad_request("Unsupported format, expected 'json' or 'csv'".to_string());
+    };
+    let keyword = match normalize_keyword(query.keyword.as_deref().unwrap_or(""), CONFIG.max_keyword_len) {
+        Ok(k) => k,
+        Err(message) => return bad_request(message),
+    };
+    let selected_rules = match select_rules_with_group(query.rules.as_deref(), query.group.as_deref()) {
+        Ok(rules) => rules,
+        Err(message) => return bad_request(message.to_string()),
+    };
+
+    let _slot = match acquire_search_slot().await {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    info!("📦 导出归档: {} ({} 个规则)", keyword, selected_rules.len());
+    let mut results = search_all(keyword.clone(), selected_rules, SearchOptions::default()).await;
+    let truncated = export::cap_items(&mut results, export::MAX_EXPORT_ITEMS);
+
+    let body = match export::to_archive(&keyword, &results, truncated, format) {
+        Ok(body) => body,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Failed to export results: {}", e)})),
+            )
+                .into_response();
+        }
+    };
+
+    let disposition = format!(
+        "attachment; filename=\"search.{ext}\"; filename*=UTF-8''{}.{ext}",
+        urlencoding::encode(&keyword),
+        ext = format.extension()
+    );
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// GET /search/unified 查询参数
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct UnifiedQuery {
+    anime: Option<String>,
+    rules: Option<String>,
+    group: Option<String>,
+    script: Option<String>,
+    episodes: Option<String>,
+}
+
+/// GET /search/unified - 按名称合并各规则的结果 (`episodes=1` 时合并各来源的集数)
+#[cfg(feature = "scraper")]
+async fn unified_handler(ApiQuery(query): ApiQuery<UnifiedQuery>) -> Response {
+    let bad_request = |message: String| {
+        (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response()
+    };
+
+    if let Some(resp) = draining_rejection() {
+        return resp;
+    }
+
+    let keyword = match normalize_keyword(query.anime.as_deref().unwrap_or(""), CONFIG.max_keyword_len) {
+        Ok(k) => k,
+        Err(message) => return bad_request(message),
+    };
+    let selected_rules = match select_rules_with_group(query.rules.as_deref(), query.group.as_deref()) {
+        Ok(rules) => rules,
+        Err(message) => return bad_request(message.to_string()),
+    };
+    let with_episodes = query
+        .episodes
+        .as_deref()
+        .and_then(config::parse_bool)
+        .unwrap_or(false);
+    // 先只搜索列表，分组后再按需获取集数
+    let options = SearchOptions {
+        script: query.script.as_deref().and_then(Script::parse),
+        skip_episodes: true,
+        ..Default::default()
+    };
+
+    let _slot = match acquire_search_slot().await {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    info!("🧩 合并搜索: {} ({} 个规则)", keyword, selected_rules.len());
+    let results = search_all(keyword.clone(), selected_rules.clone(), options).await;
+    let mut titles = unified::group_results(&results);
+    if with_episodes {
+        unified::attach_episodes(&mut titles, &selected_rules, CONFIG.search_concurrency).await;
+    }
+    let errors: serde_json::Map<String, serde_json::Value> = results
+        .iter()
+        .filter_map(|r| r.error.as_ref().map(|e| (r.name.clone(), json!(e))))
+        .collect();
+
+    Json(json!({
+        "keyword": keyword,
+        "titles": titles,
+        "errors": errors,
+    }))
+    .into_response()
+}
+
+/// GET /source/{rule}/search 查询参数
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct SourceSearchQuery {
+    anime: Option<String>,
+    script: Option<String>,
+    /// 是否同时获取集数 (默认获取)
+    episodes: Option<String>,
+}
+
+/// GET /source/{rule}/search - 只用一个规则搜索，返回带集数的精简结果 (非流式)
+#[cfg(feature = "scraper")]
+async fn source_search_handler(
+    ApiPath(rule_name): ApiPath<String>,
+    ApiQuery(query): ApiQuery<SourceSearchQuery>,
+) -> Response {
+    if let Some(resp) = draining_rejection() {
+        return resp;
+    }
+    let Some(rule) = get_builtin_rules().into_iter().find(|r| r.name == rule_name) else {
+        return (StatusCode::NOT_FOUND, Json(json!({"error": "Rule not found"}))).into_response();
+    };
+    let keyword = match normalize_keyword(query.anime.as_deref().unwrap_or(""), CONFIG.max_keyword_len) {
+        Ok(k) => k,
+        Err(message) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response();
+        }
+    };
+    let options = SearchOptions {
+        script: query.script.as_deref().and_then(Script::parse),
+        skip_episodes: !query
+            .episodes
+            .as_deref()
+            .and_then(config::parse_bool)
+            .unwrap_or(true),
+        ..Default::default()
+    };
+
+    let _slot = match acquire_search_slot().await {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    info!("🎯 单源搜索: {} ({})", keyword, rule.name);
+    let Some(result) = search_all(keyword.clone(), vec![rule], options).await.pop() else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    if let Some(error) = result.error {
+        let body = json!({
+            "rule": result.name,
+            "keyword": keyword,
+            "error": error,
+            "error_kind": result.error_kind,
+        });
+        return (StatusCode::BAD_GATEWAY, Json(body)).into_response();
+    }
+    let items: Vec<serde_json::Value> = result
+        .items
+        .iter()
+        .map(|item| {
+            json!({
+                "name": item.name,
+                "url": item.url,
+                "episodes": item.episodes.as_deref().unwrap_or_default(),
+            })
+        })
+        .collect();
+    Json(json!({"rule": result.name, "keyword": keyword, "items": items})).into_response()
+}
+
+/// GET /episodes 与 /export/m3u 查询参数
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct RoadQuery {
+    rule: String,
+    url: String,
+    road_id: Option<String>,
+}
+
+/// 查找规则并校验详情页属于该规则的站点 (失败时为状态码与错误消息)
+#[cfg(feature = "scraper")]
+fn road_rule(
+    query: &RoadQuery,
+) -> Result<std::sync::Arc<crate::types::Rule>, (StatusCode, &'static str)> {
+    let Some(rule) = get_builtin_rules().into_iter().find(|r| r.name == query.rule) else {
+        return Err((StatusCode::NOT_FOUND, "Rule not found"));
+    };
+    let host = |url: &str| {
+        url::Url::parse(url)
+            .ok()
+            .filter(|u| matches!(u.scheme(), "http" | "https"))
+            .and_then(|u| u.host_str().map(|h| h.trim_start_matches("www.").to_string()))
+    };
+    match (host(&query.url), host(&rule.base_url)) {
+        (Some(url_host), Some(base_host))
+            if url_host == base_host || url_host.ends_with(&format!(".{}", base_host)) =>
+        {
+            Ok(rule)
+        }
+        _ => Err((
+            StatusCode::BAD_REQUEST,
+            "'url' must be a detail page of the rule's site",
+        )),
+    }
+}
+
+/// 获取详情页的播放源 (详情页必须属于该规则的站点)，`road_id` 不匹配时返回 404
+#[cfg(feature = "scraper")]
+async fn fetch_roads(query: &RoadQuery) -> Result<Vec<crate::types::EpisodeRoad>, Response> {
+    let error = |status: StatusCode, message: String| {
+        (status, Json(json!({"error": message}))).into_response()
+    };
+
+    let rule = road_rule(query).map_err(|(status, message)| error(status, message.to_string()))?;
+    let mut roads = crate::engine::fetch_episodes(&rule, &query.url)
+        .await
+        .map_err(|e| error(StatusCode::BAD_GATEWAY, format!("Failed to fetch episodes: {}", e)))?;
+    if let Some(road_id) = query.road_id.as_deref().filter(|id| !id.is_empty()) {
+        roads.retain(|road| road.id == road_id);
+        if roads.is_empty() {
+            return Err(error(StatusCode::NOT_FOUND, "Road not found".to_string()));
+        }
+    }
+    Ok(roads)
+}
+
+/// GET /episodes - 获取详情页的播放源与集数 (`road_id=` 只返回该播放源)
+#[cfg(feature = "scraper")]
+async fn road_episodes_handler(ApiQuery(query): ApiQuery<RoadQuery>) -> Response {
+    match fetch_roads(&query).await {
+        Ok(roads) => Json(json!({"rule": query.rule, "url": query.url, "roads": roads})).into_response(),
+        Err(resp) => resp,
+    }
+}
+
+/// GET /episodes/stream - 逐个播放源返回详情页的集数 (每行一个 JSON，最后为完成信号)
+#[cfg(feature = "scraper")]
+async fn road_episodes_stream_handler(ApiQuery(query): ApiQuery<RoadQuery>) -> Response {
+    let rule = match road_rule(&query) {
+        Ok(rule) => rule,
+        Err((status, message)) => return (status, Json(json!({"error": message}))).into_response(),
+    };
+    let road_id = query.road_id.filter(|id| !id.is_empty());
+    let stream = crate::engine::fetch_episodes_stream(rule, query.url, road_id)
+        .map(|event| {
+            let line = format!("{}\n", serde_json::to_string(&event).unwrap_or_default());
+            Ok::<_, std::convert::Infallible>(line)
+        });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/x-ndjson; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Body::from_stream(stream))
+        .unwrap()
+}
+
+/// GET /export/m3u - 将播放源导出为 M3U 播放列表 (未指定 `road_id` 时为第一个播放源)
+#[cfg(feature = "scraper")]
+async fn m3u_handler(ApiQuery(query): ApiQuery<RoadQuery>) -> Response {
+    let roads = match fetch_roads(&query).await {
+        Ok(roads) => roads,
+        Err(resp) => return resp,
+    };
+    let Some(road) = roads.first() else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "No episodes found"})),
+        )
+            .into_response();
+    };
+
+    let title = match &road.name {
+        Some(name) => format!("{} {}", query.rule, name),
+        None => query.rule.clone(),
+    };
+    let disposition = format!(
+        "attachment; filename=\"playlist.m3u\"; filename*=UTF-8''{}.m3u",
+        urlencoding::encode(&title)
+    );
+    (
+        [
+            (header::CONTENT_TYPE, export::M3U_CONTENT_TYPE.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        export::to_m3u(&title, road),
+    )
+        .into_response()
+}
+
+/// 获取规则列表
+#[cfg(feature = "scraper")]
+async fn rules_handler() -> impl IntoResponse {
+    let rules = get_builtin_rules();
+    let rule_info: Vec<RuleInfo> = rules
+        .iter()
+        .map(|r| RuleInfo {
+            name: r.name.clone(),
+            version: r.version.clone(),
+            base_url: r.base_url.clone(),
+            color: r.color.clone(),
+            color_hex: r.color_hex(),
+            icon: r.icon_url(),
+            tags: r.tags.clone(),
+            magic: r.magic,
+            auto_disabled: crate::rule_stats::is_auto_disabled(&r.name),
+        })
+        .collect();
+
+    Json(rule_info)
+}
+
+/// POST /admin/rules/{name}/enable - 手动启用规则 (清空失败计数，覆盖自动停用)
+#[cfg(feature = "scraper")]
+async fn rule_enable_handler(
+    ApiPath(name): ApiPath<String>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+    if !get_builtin_rules().iter().any(|r| r.name == name) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "Rule not found"})),
+        )
+            .into_response();
+    }
+
+    let was_disabled = crate::rule_stats::enable(&name);
+    audit::record(
+        audit::AuditEntry::new(
+            audit::request_id(&headers),
+            audit::client_ip(&headers, &addr),
+            format!("POST /admin/rules/{}/enable", name),
+            if was_disabled { "enabled" } else { "reset" }.to_string(),
+        )
+        .target(Some(format!("rule:{}", name))),
+    );
+    Json(json!({"success": true, "rule": name, "was_auto_disabled": was_disabled})).into_response()
+}
+
+/// GET /cache/stats - 搜索结果缓存的命中统计 (CACHE_SEARCH_TTL_SECS=0 时 enabled 为 false)
+#[cfg(feature = "scraper")]
+async fn cache_stats_handler() -> Response {
+    let Some(report) = crate::engine::result_cache_report() else {
+        return Json(json!({"enabled": false})).into_response();
+    };
+    Json(json!({
+        "enabled": true,
+        "entries": report.entries,
+        "hits": report.hits,
+        "misses": report.misses,
+        "hit_rate": report.hit_rate,
+        "ttl_seconds": report.ttl_seconds,
+    }))
+    .into_response()
+}
+
+/// POST /cache/clear - 清空搜索结果缓存 (需要 X-Admin-Key)
+#[cfg(feature = "scraper")]
+async fn cache_clear_handler(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+    let cleared = crate::engine::clear_result_cache().unwrap_or(0);
+    audit::record(audit::AuditEntry::new(
+        audit::request_id(&headers),
+        audit::client_ip(&headers, &addr),
+        "POST /cache/clear".to_string(),
+        format!("cleared {}", cleared),
+    ));
+    Json(json!({"success": true, "cleared": cleared})).into_response()
+}
+
+/// GET /debug/bench 查询参数
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct BenchQuery {
+    rule: Option<String>,
+    iterations: Option<usize>,
+}
+
+/// GET /debug/bench - 解析基准 (不请求源站)
+#[cfg(feature = "scraper")]
+async fn bench_handler(ApiQuery(query): ApiQuery<BenchQuery>, headers: HeaderMap) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+    let name = query.rule.as_deref().map(str::trim).unwrap_or_default();
+    let Some(rule) = get_builtin_rules().into_iter().find(|r| r.name == name) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "Rule not found"})),
+        )
+            .into_response();
+    };
+    let iterations = query.iterations.unwrap_or(bench::DEFAULT_ITERATIONS);
+
+    match tokio::task::spawn_blocking(move || bench::run(&rule, iterations)).await {
+        Ok(Ok(report)) => Json(report).into_response(),
+        Ok(Err(e)) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("Failed to parse: {}", e)})),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Benchmark failed: {}", e)})),
+        )
+            .into_response(),
+    }
+}
+
+/// GET /dev/loadtest 查询参数
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct LoadTestQuery {
+    rules: Option<String>,
+    concurrency: Option<usize>,
+    anime: Option<String>,
+}
+
+/// GET /dev/loadtest - 用模拟规则并发执行流式搜索，报告事件送达延迟 (需 ENABLE_MOCK_RULES=1 与管理密钥)
+#[cfg(feature = "scraper")]
+async fn loadtest_handler(ApiQuery(query): ApiQuery<LoadTestQuery>, headers: HeaderMap) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+    if !CONFIG.enable_mock_rules {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "Load testing is disabled. Set ENABLE_MOCK_RULES=1 to enable it"})),
+        )
+            .into_response();
+    }
+    if let Some(resp) = draining_rejection() {
+        return resp;
+    }
+    let rules = match select_rules(query.rules.as_deref()) {
+        Ok(rules) => rules,
+        Err(message) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response();
+        }
+    };
+    let real: Vec<&str> = rules.iter().filter(|r| !r.is_mock()).map(|r| r.name.as_str()).collect();
+    if !real.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("Load tests only run mock rules: {}", real.join(", "))})),
+        )
+            .into_response();
+    }
+    let keyword = query.anime.as_deref().map(str::trim).filter(|k| !k.is_empty());
+    let keyword = keyword.unwrap_or(loadtest::DEFAULT_KEYWORD);
+    let concurrency = query.concurrency.unwrap_or(loadtest::DEFAULT_CONCURRENCY);
+
+    Json(loadtest::run(keyword, rules, concurrency).await).into_response()
+}
+
+/// POST /debug/dry-run 请求体
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct DryRunRequest {
+    /// 待试运行的规则 JSON (与规则文件格式相同)
+    rule: serde_json::Value,
+    keyword: String,
+}
+
+/// POST /debug/dry-run - 试运行规则 (请求源站，报告各 XPath 阶段的匹配数)
+#[cfg(feature = "scraper")]
+async fn dry_run_handler(headers: HeaderMap, ApiJson(req): ApiJson<DryRunRequest>) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+    let rule: crate::types::Rule = match serde_json::from_value(req.rule) {
+        Ok(rule) => rule,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": format!("Invalid rule: {}", e)})),
+            )
+                .into_response()
+        }
+    };
+    Json(crate::engine::dry_run(&rule, req.keyword.trim()).await).into_response()
+}
+
+/// GET /rules/groups - 规则分组 (rules/groups.json，分组名 -> 规则名列表)
+#[cfg(feature = "scraper")]
+async fn rule_groups_handler() -> impl IntoResponse {
+    Json(rule_groups())
+}
+
+/// GET /rules/errors - 规则目录中无法解析的文件 (JSON 语法错误附带行列号)
+#[cfg(feature = "scraper")]
+async fn rule_errors_handler() -> impl IntoResponse {
+    Json(crate::rules::rule_file_errors())
+}
+
+/// GET /rules/changelog - 规则变更记录 (最新的在前)
+#[cfg(feature = "scraper")]
+async fn rule_changelog_handler(ApiQuery(query): ApiQuery<LimitQuery>) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(changelog::FEED_ENTRIES).clamp(1, 200);
+    Json(storage::blocking(move || changelog::recent(limit)).await)
+}
+
+/// GET /feeds/rules.atom - 规则变更的 Atom feed
+#[cfg(feature = "scraper")]
+async fn rule_feed_handler(headers: HeaderMap) -> Response {
+    let header_value = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let host = header_value("Host").unwrap_or("localhost");
+    let scheme = header_value("X-Forwarded-Proto").unwrap_or("http");
+    let self_url = format!("{}://{}/feeds/rules.atom", scheme, host);
+    let entries = storage::blocking(|| changelog::recent(changelog::FEED_ENTRIES)).await;
+    let xml = changelog::render_atom(&entries, &self_url);
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        xml,
+    )
+        .into_response()
+}
+
+/// 查询参数: 搜索历史统计
+#[cfg(feature = "scraper")]
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    days: Option<u32>,
+    top: Option<usize>,
+}
+
+/// GET /history/stats - 搜索历史统计 (热门关键词、规则每日成功率)
+#[cfg(feature = "scraper")]
+async fn history_stats_handler(ApiQuery(query): ApiQuery<HistoryQuery>) -> Response {
+    let days = query.days.unwrap_or(7).clamp(1, 365);
+    let top = query.top.unwrap_or(20).clamp(1, 100);
+    match tokio::task::spawn_blocking(move || crate::history::stats(days, top)).await {
+        Ok(Ok(Some(stats))) => Json(stats).into_response(),
+        Ok(Ok(None)) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "Search history is disabled (set HISTORY_DB, or DATABASE_PATH / REDIS_URL to count in the shared store)"})),
+        )
+            .into_response(),
+        Ok(Err(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": e.to_string()})),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
+}
+
+/// GET /rules/schema.json - 规则文件的 JSON Schema
+#[cfg(feature = "scraper")]
+async fn rule_schema_handler() -> impl IntoResponse {
+    Json(crate::types::rule_schema())
+}
+
+/// GET /schema/stream - 流式搜索事件的 JSON Schema
+#[cfg(feature = "scraper")]
+async fn stream_schema_handler() -> impl IntoResponse {
+    Json(crate::types::stream_event_schema())
+}
+
+/// GET /events/schema.json - v2 流式事件的 JSON Schema
+#[cfg(feature = "scraper")]
+async fn event_schema_handler() -> impl IntoResponse {
+    Json(crate::events::event_schema())
+}
+
+/// GET /favorites - 收藏列表 (`q=` 筛选，`subject_id=` 按 Bangumi 条目分组，`limit`/`offset` 分页)
+#[cfg(feature = "scraper")]
+async fn favorites_list_handler(ApiQuery(query): ApiQuery<favorites::FavoriteQuery>) -> Response {
+    let (total, items) = storage::blocking(move || favorites::list(&query)).await;
+    Json(json!({"total": total, "items": items})).into_response()
+}
+
+/// POST /favorites - 收藏搜索结果 (同一规则的同一链接去重)
+#[cfg(feature = "scraper")]
+async fn favorites_add_handler(ApiJson(body): ApiJson<favorites::NewFavorite>) -> Response {
+    match storage::blocking(move || favorites::add(body)).await {
+        Ok((favorite, true)) => (StatusCode::CREATED, Json(favorite)).into_response(),
+        Ok((favorite, false)) => Json(favorite).into_response(),
+        Err(message) => (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response(),
+    }
+}
+
+/// DELETE /favorites/{id} - 取消收藏
+#[cfg(feature = "scraper")]
+async fn favorites_delete_handler(ApiPath(id): ApiPath<String>) -> Response {
+    if storage::blocking(move || favorites::remove(&id)).await {
+        Json(json!({"success": true})).into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "Favorite not found"})),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(feature = "scraper")]
+fn watch_entry_not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({"error": "Watchlist entry not found"})),
+    )
+        .into_response()
+}
+
+/// GET /watchlist - 追更列表 (每个条目附带未读新集数 `unseen` 与检查状态 `status`)
+#[cfg(feature = "scraper")]
+async fn watchlist_list_handler() -> Response {
+    let items: Vec<serde_json::Value> = storage::blocking(|| {
+        watchlist::list()
+            .into_iter()
+            .map(|entry| {
+                let unseen = watchlist::unseen_count(&entry.id);
+                let status = watchlist::status(&entry.id);
+                let mut value = json!(entry);
+                value["unseen"] = json!(unseen);
+                value["status"] = json!(status);
+                value
+            })
+            .collect()
+    })
+    .await;
+    Json(json!({"total": items.len(), "items": items})).into_response()
+}
+
+/// POST /watchlist - 添加追更 (同一关键词与规则去重)
+#[cfg(feature = "scraper")]
+async fn watchlist_add_handler(ApiJson(body): ApiJson<watchlist::NewWatchEntry>) -> Response {
+    match storage::blocking(move || watchlist::add(body)).await {
+        Ok((entry, true)) => (StatusCode::CREATED, Json(entry)).into_response(),
+        Ok((entry, false)) => Json(entry).into_response(),
+        Err(message) => (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response(),
+    }
+}
+
+/// DELETE /watchlist/{id} - 取消追更
+#[cfg(feature = "scraper")]
+async fn watchlist_delete_handler(ApiPath(id): ApiPath<String>) -> Response {
+    if storage::blocking(move || watchlist::remove(&id)).await {
+        Json(json!({"success": true})).into_response()
+    } else {
+        watch_entry_not_found()
+    }
+}
+
+/// POST /watchlist/{id}/check - 立即检查，返回本次新出现的集数 (同时记入未读变化)
+/// 规则全部停用或全部失败时返回 502 与检查状态
+#[cfg(feature = "scraper")]
+async fn watchlist_check_handler(ApiPath(id): ApiPath<String>) -> Response {
+    let lookup = id.clone();
+    let Some(entry) = storage::blocking(move || watchlist::get(&lookup)).await else {
+        return watch_entry_not_found();
+    };
+    match watchlist::check(&entry).await {
+        Ok(changes) => Json(json!({"id": id, "changes": changes})).into_response(),
+        Err(error) => {
+            let lookup = id.clone();
+            let status = storage::blocking(move || watchlist::status(&lookup)).await;
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(json!({"id": id, "error": error, "status": status})),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /watchlist/{id}/changes - 未读的新集数，返回后标记为已读
+#[cfg(feature = "scraper")]
+async fn watchlist_changes_handler(ApiPath(id): ApiPath<String>) -> Response {
+    let lookup = id.clone();
+    match storage::blocking(move || watchlist::take_unseen(&lookup)).await {
+        Some(changes) => Json(json!({"id": id, "changes": changes})).into_response(),
+        None => watch_entry_not_found(),
+    }
+}
+
+/// GET /image?url= - 图片代理 (支持 If-None-Match，命中磁盘缓存时 `X-Cache: HIT`)
+async fn image_handler(
+    ApiQuery(query): ApiQuery<image_proxy::ImageQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let url = match image_proxy::validate_url(&query.url) {
+        Ok(url) => url,
+        Err(message) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response();
+        }
+    };
+    let (image, hit) = match image_proxy::load(&url, &image_proxy::Policy::current()).await {
+        Ok(loaded) => loaded,
+        Err(image_proxy::FetchError::Forbidden) => {
+            return (StatusCode::FORBIDDEN, Json(json!({"error": "Image host not allowed"}))).into_response();
+        }
+        Err(image_proxy::FetchError::Upstream) => {
+            return (StatusCode::BAD_GATEWAY, Json(json!({"error": "Image request failed"}))).into_response();
+        }
+    };
+    let cache_status = if hit { "HIT" } else { "MISS" };
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| image.matches(v));
+    let builder = Response::builder()
+        .header(header::ETAG, &image.etag)
+        .header(header::CACHE_CONTROL, image_proxy::CACHE_CONTROL)
+        .header("X-Cache", cache_status);
+    let response = if not_modified {
+        builder.status(StatusCode::NOT_MODIFIED).body(Body::empty())
+    } else {
+        builder
+            .header(header::CONTENT_TYPE, &image.content_type)
+            .body(Body::from(image.bytes))
+    };
+    response.unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+/// 健康检查
+async fn health_handler() -> impl IntoResponse {
+    Json(json!({
+        "status": "ok",
+        "active_searches": limiter::active_searches(),
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    }))
+}
+
+/// 就绪检查 (关键后台任务失活时返回 503)
+async fn ready_handler() -> impl IntoResponse {
+    let tasks = supervisor::reports();
+    let ready = supervisor::is_ready(&tasks);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(json!({
+            "status": if ready { "ready" } else { "unavailable" },
+            "tasks": tasks,
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        })),
+    )
+}
+
+/// Prometheus 指标
+async fn metrics_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        format!(
+            "{}{}{}",
+            supervisor::render_metrics(&supervisor::reports()),
+            limiter::render_metrics(),
+            cache::render_metrics(&cache::reports())
+        ),
+    )
+}
+
+/// 查询参数: 统计天数
+#[derive(Debug, Deserialize)]
+struct StatsQuery {
+    days: Option<u32>,
+}
+
+/// GET /stats - 使用统计 (`days=N` 时附带最近 N 天的每日汇总)
+async fn stats_handler(ApiQuery(query): ApiQuery<StatsQuery>) -> Response {
+    match tokio::task::spawn_blocking(move || crate::stats::report(query.days)).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
+}
+
+/// GET /update - 从 KazumiRules 更新规则
+#[cfg(feature = "scraper")]
+async fn update_handler(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    info!("📡 手动触发规则更新...");
+    let result = updater::update_rules().await;
+    after_update(&result);
+    audit::record(audit::AuditEntry::new(
+        audit::request_id(&headers),
+        audit::client_ip(&headers, &addr),
+        "GET /update".to_string(),
+        format!(
+            "added={} updated={} failed={}",
+            result.added, result.updated, result.failed
+        ),
+    ));
+    Json(UpdateResponse {
+        success: true,
+        result,
+    })
+}
+
+/// 查询参数: 条数限制
+#[derive(Debug, Deserialize)]
+struct LimitQuery {
+    limit: Option<usize>,
+}
+
+/// 请求是否携带了正确的管理密钥
+fn is_admin(headers: &HeaderMap) -> bool {
+    match CONFIG.admin_key.as_deref() {
+        Some(expected) => {
+            headers
+                .get("X-Admin-Key")
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|key| audit::secret_eq(key, expected))
+        }
+        None => false,
+    }
+}
+
+/// 校验管理密钥 (X-Admin-Key 请求头)，失败时返回拒绝响应
+fn admin_rejection(headers: &HeaderMap) -> Option<Response> {
+    if CONFIG.admin_key.is_none() {
+        return Some((
+            StatusCode::FORBIDDEN,
+            Json(json!({"error": "Admin API is disabled. Set ADMIN_KEY to enable it"})),
+        )
+            .into_response());
+    }
+
+    if !is_admin(headers) {
+        return Some((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Invalid admin key"})),
+        )
+            .into_response());
+    }
+
+    None
+}
+
+/// GET /admin/audit - 查看最近的审计日志
+async fn audit_handler(headers: HeaderMap, ApiQuery(query): ApiQuery<LimitQuery>) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+
+    let limit = query.limit.unwrap_or(100).min(1000);
+    Json(audit::read_recent(limit)).into_response()
+}
+
+/// POST /admin/reload-config - 重载配置 (与 SIGHUP 相同路径)
+async fn reload_config_handler(ConnectInfo(addr): ConnectInfo<SocketAddr>, headers: HeaderMap) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+
+    let report = reload::reload();
+    let outcome = if report.is_ok() {
+        format!("applied={} restart_required={}", report.applied.join(","), report.restart_required.join(","))
+    } else {
+        format!("failed: {}", report.errors.join("; "))
+    };
+    audit::record(audit::AuditEntry::new(
+        audit::request_id(&headers),
+        audit::client_ip(&headers, &addr),
+        "POST /admin/reload-config".to_string(),
+        outcome,
+    ));
+
+    let status = if report.is_ok() { StatusCode::OK } else { StatusCode::BAD_REQUEST };
+    (status, Json(report)).into_response()
+}
+
+/// GET /admin/selftest - 执行自检并返回结果
+async fn selftest_handler(headers: HeaderMap) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+    let report = selftest::run().await;
+    let status = if report.passed {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report)).into_response()
+}
+
+/// GET /admin/caches - 进程内缓存统计 (容量、条目数、命中率、淘汰数)
+async fn caches_handler(headers: HeaderMap) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+    Json(cache::reports()).into_response()
+}
+
+/// GET /debug/config - 生效配置 (密钥脱敏) 与已启用功能
+async fn debug_config_handler(headers: HeaderMap) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+    Json(json!({
+        "features": FEATURES,
+        "config": CONFIG.debug_entries(),
+    }))
+    .into_response()
+}
+
+/// POST /admin/shutdown 请求体
+#[derive(Debug, Default, Deserialize)]
+struct ShutdownBody {
+    drain_seconds: Option<u64>,
+}
+
+/// POST /admin/shutdown - 远程优雅停机 (与 SIGTERM 相同路径)
+async fn shutdown_handler(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Option<ApiJson<ShutdownBody>>,
+) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+
+    let ApiJson(body) = body.unwrap_or_default();
+    let drain_seconds = body.drain_seconds.unwrap_or(CONFIG.shutdown_drain_seconds);
+    audit::record(audit::AuditEntry::new(
+        audit::request_id(&headers),
+        audit::client_ip(&headers, &addr),
+        "POST /admin/shutdown".to_string(),
+        format!("accepted drain_seconds={}", drain_seconds),
+    ));
+    shutdown::trigger(Some(drain_seconds));
+
+    (
+        StatusCode::ACCEPTED,
+        Json(json!({"accepted": true, "drain_seconds": drain_seconds})),
+    )
+        .into_response()
+}
+
+/// POST /admin/token-profiles 请求体
+#[cfg(feature = "bangumi")]
+#[derive(Debug, Deserialize)]
+struct TokenProfileBody {
+    name: String,
+    token: String,
+}
+
+/// GET /admin/token-profiles - 档案名列表 (不返回 token)
+#[cfg(feature = "bangumi")]
+async fn token_profiles_list_handler(headers: HeaderMap) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+    Json(json!({"profiles": token_profiles::list()})).into_response()
+}
+
+/// POST /admin/token-profiles - 新增或覆盖档案
+#[cfg(feature = "bangumi")]
+async fn token_profiles_upsert_handler(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    ApiJson(body): ApiJson<TokenProfileBody>,
+) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+
+    let name = body.name.trim();
+    if name.is_empty() || body.token.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "Both 'name' and 'token' are required"})),
+        )
+            .into_response();
+    }
+
+    let result = token_profiles::upsert(name, body.token.trim());
+    audit::record(
+        audit::AuditEntry::new(
+            audit::request_id(&headers),
+            audit::client_ip(&headers, &addr),
+            "POST /admin/token-profiles".to_string(),
+            match &result {
+                Ok(()) => "ok".to_string(),
+                Err(e) => format!("failed: {}", e),
+            },
+        )
+        .target(Some(format!("profile:{}", name)))
+        .token(Some(body.token.trim())),
+    );
+
+    match result {
+        Ok(()) => Json(json!({"success": true, "name": name})).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Failed to save profile: {}", e)})),
+        )
+            .into_response(),
+    }
+}
+
+/// DELETE /admin/token-profiles/{name} - 删除档案
+#[cfg(feature = "bangumi")]
+async fn token_profiles_delete_handler(
+    ApiPath(name): ApiPath<String>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(resp) = admin_rejection(&headers) {
+        return resp;
+    }
+
+    let result = token_profiles::remove(&name);
+    audit::record(
+        audit::AuditEntry::new(
+            audit::request_id(&headers),
+            audit::client_ip(&headers, &addr),
+            format!("DELETE /admin/token-profiles/{}", name),
+            match &result {
+                Ok(true) => "ok".to_string(),
+                Ok(false) => "not found".to_string(),
+                Err(e) => format!("failed: {}", e),
+            },
+        )
+        .target(Some(format!("profile:{}", name))),
+    );
+
+    match result {
+        Ok(true) => Json(json!({"success": true})).into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "Profile not found"})),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Failed to save profiles: {}", e)})),
+        )
+            .into_response(),
+    }
+}
+
+// ============================================================================
+// Bangumi API 通用代理
+// ============================================================================
+
+/// GET /bangumi/search/{keyword}/stream - 流式返回搜索命中与条目详情
+#[cfg(feature = "bangumi")]
+async fn bangumi_search_stream_handler(ApiPath(keyword): ApiPath<String>) -> Response {
+    let stream = bangumi::search_with_details_stream(keyword);
+    let active = crate::stats::StreamGuard::acquire();
+    let body = Body::from_stream(stream.map(move |s| {
+        let _ = &active;
+        Ok::<_, std::convert::Infallible>(s)
+    }));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/event-stream; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(body)
+        .unwrap()
+}
+
+/// 查询参数: 章节列表
+#[cfg(feature = "bangumi")]
+#[derive(Debug, Deserialize)]
+struct EpisodesQuery {
+    #[serde(rename = "type")]
+    episode_type: Option<i32>,
+    limit: Option<i32>,
+    offset: Option<i32>,
+    /// 返回完整字段 (简介、时长、上游新增字段等)
+    full: Option<String>,
+    /// 完整字段中简介的截断长度 (默认 SUMMARY_LEN，0 为不截断)
+    summary_len: Option<usize>,
+}
+
+/// GET /bangumi/subjects/{id}/episodes - 章节列表 (默认精简字段)
+#[cfg(feature = "bangumi")]
+async fn bangumi_episodes_handler(
+    ApiPath(id): ApiPath<i64>,
+    ApiQuery(query): ApiQuery<EpisodesQuery>,
+) -> Response {
+    let full = query.full.as_deref().and_then(config::parse_bool).unwrap_or(false);
+    let token = CONFIG.bangumi_access_token.as_deref();
+    match bangumi::get_episodes(id, query.episode_type, query.limit, query.offset, token).await {
+        Ok(mut list) => {
+            let data = if full {
+                let max_chars = query.summary_len.unwrap_or(CONFIG.summary_len);
+                for episode in &mut list.data {
+                    if let Some(desc) = crate::metadata::truncate_text(&episode.desc, max_chars) {
+                        episode.desc = desc;
+                        episode.extra.insert("summary_truncated".into(), json!(true));
+                    }
+                }
+                json!(list.data)
+            } else {
+                json!(list.data.iter().map(bangumi::EpisodeSummary::from).collect::<Vec<_>>())
+            };
+            Json(json!({
+                "total": list.total,
+                "limit": list.limit,
+                "offset": list.offset,
+                "data": data
+            }))
+            .into_response()
+        }
+        Err(e) => (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
+}
+
+/// 通用 Bangumi API 代理
+/// 将 /bgm/* 的请求透传到 api.bgm.tv/*，自动添加 CORS 头
+#[cfg(feature = "bangumi")]
+async fn bangumi_proxy_handler(
+    ApiPath(path): ApiPath<String>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    req: Request,
+) -> Response {
+    use http_client::HTTP_CLIENT;
+    
+    // 构建目标 URL
+    let query = req.uri().query().map(|q| format!("?{}", q)).unwrap_or_default();
+    let target_url = format!("{}/{}{}", CONFIG.bangumi_api_base, path, query);
+    
+    // 解析 token (显式 Bearer 优先，其次 X-Token-Profile 档案)
+    let token = match token_profiles::extract_token(&headers) {
+        Ok(token) => token,
+        Err(message) => {
+            return (StatusCode::UNAUTHORIZED, Json(json!({"error": message}))).into_response();
+        }
+    };
+
+    // 构建请求
+    let method = req.method().clone();
+    let audit_target = audit::bangumi_mutation_target(method.as_str(), &path);
+    let audit_entry = |outcome: String| {
+        audit::AuditEntry::new(
+            audit::request_id(&headers),
+            audit::client_ip(&headers, &addr),
+            format!("{} /bgm/{}", method, path),
+            outcome,
+        )
+        .target(audit_target.clone())
+        .token(token.as_ref().map(|t| t.token.as_str()))
+        .profile(token.as_ref().and_then(|t| t.profile.clone()))
+    };
+    let mut request_builder = HTTP_CLIENT.request(method.clone(), &target_url)
+        .header("User-Agent", &CONFIG.bangumi_user_agent);
+    
+    // 转发 Authorization 头 (非 Bearer 方案原样转发)
+    if let Some(token) = &token {
+        request_builder = request_builder.header("Authorization", format!("Bearer {}", token.token));
+    } else if let Some(auth) = headers.get("Authorization") {
+        request_builder = request_builder.header("Authorization", auth.clone());
+    }
+
+    // 转发 Content-Type 头
+    if let Some(ct) = headers.get("Content-Type") {
+        if let Ok(ct_str) = ct.to_str() {
+            request_builder = request_builder.header("Content-Type", ct_str);
+        }
+    }
+
+    // 如果有 body，转发 body
+    let body_bytes = match axum::body::to_bytes(req.into_body(), 10 * 1024 * 1024).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": format!("Failed to read request body: {}", e)})),
+            ).into_response();
+        }
+    };
+
+    if !body_bytes.is_empty() {
+        request_builder = request_builder.body(body_bytes.to_vec());
+    }
+    
+    // 发送请求
+    let response = match request_builder.send().await {
+        Ok(resp) => {
+            crate::stats::record_bangumi_proxy(resp.status().is_success());
+            resp
+        }
+        Err(e) => {
+            crate::stats::record_bangumi_proxy(false);
+            if audit_target.is_some() {
+                audit::record(audit_entry(format!("failed: {}", e)));
+            }
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(json!({"error": format!("Proxy request failed: {}", e)})),
+            ).into_response();
+        }
+    };
+
+    if audit_target.is_some() {
+        audit::record(audit_entry(format!("HTTP {}", response.status().as_u16())));
+    }
+
+    // 构建响应
+    let status = StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::OK);
+    let content_type = response
+        .headers()
+        .get("Content-Type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json")
+        .to_string();
+    
+    let response_body = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(json!({"error": format!("Failed to read response: {}", e)})),
+            )
+                .into_response();
+        }
+    };
+    
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET, POST, PUT, PATCH, DELETE, OPTIONS")
+        .header(header::ACCESS_CONTROL_ALLOW_HEADERS, "Content-Type, Authorization, X-Token-Profile")
+        .body(Body::from(response_body.to_vec()))
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+/// 内嵌前端 HTML (编译时从 static/index.html 读取)
+#[cfg(feature = "frontend")]
+const INDEX_HTML: &str = include_str!("../../static/index.html");
+
+// ============================================================================
+// 元数据来源 (Bangumi / AniList)
+// ============================================================================
+
+/// 元数据接口的来源参数
+#[cfg(any(feature = "bangumi", feature = "anilist"))]
+#[derive(Debug, Deserialize)]
+struct ProviderQuery {
+    provider: Option<String>,
+    /// 简介截断长度 (默认 SUMMARY_LEN，0 为不截断)
+    summary_len: Option<usize>,
+}
+
+/// 按请求或 SUMMARY_LEN 截断条目简介
+#[cfg(any(feature = "bangumi", feature = "anilist"))]
+fn truncate_summaries<'a>(items: impl IntoIterator<Item = &'a mut crate::metadata::AnimeInfo>, summary_len: Option<usize>) {
+    let max_chars = summary_len.unwrap_or(CONFIG.summary_len);
+    for item in items {
+        item.truncate_summary(max_chars);
+    }
+}
+
+/// GET /suggest 查询参数
+#[cfg(any(feature = "bangumi", feature = "anilist"))]
+#[derive(Debug, Deserialize)]
+struct SuggestQuery {
+    keyword: Option<String>,
+    provider: Option<String>,
+    summary_len: Option<usize>,
+}
+
+#[cfg(any(feature = "bangumi", feature = "anilist"))]
+fn bad_provider(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response()
+}
+
+/// 规范化查询参数中的关键词 (去除控制字符与首尾空白)，为空或过长时为 None
+#[cfg(any(feature = "danmaku", feature = "bangumi", feature = "anilist"))]
+fn query_keyword(raw: Option<&str>) -> Option<String> {
+    let keyword: String = raw.unwrap_or("").chars().filter(|c| !c.is_control()).collect();
+    let keyword = keyword.trim();
+    (!keyword.is_empty() && keyword.chars().count() <= CONFIG.max_keyword_len)
+        .then(|| keyword.to_string())
+}
+
+#[cfg(any(feature = "danmaku", feature = "bangumi", feature = "anilist"))]
+fn bad_keyword() -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({"error": "Keyword is required"})),
+    )
+        .into_response()
+}
+
+#[cfg(any(feature = "danmaku", feature = "bangumi", feature = "anilist"))]
+fn upstream_error(e: anyhow::Error) -> Response {
+    (
+        StatusCode::BAD_GATEWAY,
+        Json(json!({"error": e.to_string()})),
+    )
+        .into_response()
+}
+
+/// GET /suggest?keyword=&provider= - 搜索元数据条目 (统一为 AnimeInfo)
+#[cfg(any(feature = "bangumi", feature = "anilist"))]
+async fn suggest_handler(ApiQuery(query): ApiQuery<SuggestQuery>) -> Response {
+    let provider = match crate::metadata::provider(query.provider.as_deref()) {
+        Ok(provider) => provider,
+        Err(message) => return bad_provider(message),
+    };
+    let Some(keyword) = query_keyword(query.keyword.as_deref()) else {
+        return bad_keyword();
+    };
+    match provider.search(&keyword).await {
+        Ok(mut items) => {
+            truncate_summaries(&mut items, query.summary_len);
+            Json(json!({"provider": provider.name(), "items": items})).into_response()
+        }
+        Err(e) => upstream_error(e),
+    }
+}
+
+/// GET /metadata/subjects/{id}?provider= - 条目详情
+#[cfg(any(feature = "bangumi", feature = "anilist"))]
+async fn metadata_subject_handler(
+    ApiPath(id): ApiPath<i64>,
+    ApiQuery(query): ApiQuery<ProviderQuery>,
+) -> Response {
+    let provider = match crate::metadata::provider(query.provider.as_deref()) {
+        Ok(provider) => provider,
+        Err(message) => return bad_provider(message),
+    };
+    match provider.subject(id).await {
+        Ok(mut subject) => {
+            subject.truncate_summary(query.summary_len.unwrap_or(CONFIG.summary_len));
+            Json(subject).into_response()
+        }
+        Err(e) => upstream_error(e),
+    }
+}
+
+/// GET /metadata/calendar?provider= - 每日放送
+#[cfg(any(feature = "bangumi", feature = "anilist"))]
+async fn metadata_calendar_handler(ApiQuery(query): ApiQuery<ProviderQuery>) -> Response {
+    let provider = match crate::metadata::provider(query.provider.as_deref()) {
+        Ok(provider) => provider,
+        Err(message) => return bad_provider(message),
+    };
+    match provider.calendar().await {
+        Ok(mut days) => {
+            truncate_summaries(days.iter_mut().flat_map(|day| &mut day.items), query.summary_len);
+            Json(days).into_response()
+        }
+        Err(e) => upstream_error(e),
+    }
+}
+
+// ============================================================================
+// 弹幕 (弹弹play)
+// ============================================================================
+
+/// GET /danmaku/search 查询参数
+#[cfg(feature = "danmaku")]
+#[derive(Debug, Deserialize)]
+struct DanmakuSearchQuery {
+    anime: Option<String>,
+}
+
+/// GET /danmaku/comments 查询参数
+#[cfg(feature = "danmaku")]
+#[derive(Debug, Deserialize)]
+struct DanmakuCommentsQuery {
+    episode_id: i64,
+}
+
+/// GET /danmaku/match 查询参数
+#[cfg(feature = "danmaku")]
+#[derive(Debug, Deserialize)]
+struct DanmakuMatchQuery {
+    keyword: Option<String>,
+    episode: u32,
+}
+
+/// 未配置 AppId/AppSecret 时的 503 响应
+#[cfg(feature = "danmaku")]
+fn danmaku_unconfigured() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({"error": "Danmaku is not configured. Set DANDANPLAY_APP_ID and DANDANPLAY_APP_SECRET"})),
+    )
+        .into_response()
+}
+
+/// GET /danmaku/search?anime= - 搜索番剧
+#[cfg(feature = "danmaku")]
+async fn danmaku_search_handler(ApiQuery(query): ApiQuery<DanmakuSearchQuery>) -> Response {
+    let Some(client) = crate::danmaku::Client::from_config() else {
+        return danmaku_unconfigured();
+    };
+    let Some(keyword) = query_keyword(query.anime.as_deref()) else {
+        return bad_keyword();
+    };
+    match crate::danmaku::search_anime(&client, &keyword).await {
+        Ok(animes) => Json(json!({"animes": animes})).into_response(),
+        Err(e) => upstream_error(e),
+    }
+}
+
+/// GET /danmaku/comments?episode_id= - 剧集弹幕
+#[cfg(feature = "danmaku")]
+async fn danmaku_comments_handler(ApiQuery(query): ApiQuery<DanmakuCommentsQuery>) -> Response {
+    let Some(client) = crate::danmaku::Client::from_config() else {
+        return danmaku_unconfigured();
+    };
+    match crate::danmaku::comments(&client, query.episode_id).await {
+        Ok(comments) => Json(comments).into_response(),
+        Err(e) => upstream_error(e),
+    }
+}
+
+/// GET /danmaku/match?keyword=&episode= - 按番剧名与集数匹配剧集 ID
+#[cfg(feature = "danmaku")]
+async fn danmaku_match_handler(ApiQuery(query): ApiQuery<DanmakuMatchQuery>) -> Response {
+    let Some(client) = crate::danmaku::Client::from_config() else {
+        return danmaku_unconfigured();
+    };
+    let Some(keyword) = query_keyword(query.keyword.as_deref()) else {
+        return bad_keyword();
+    };
+    match crate::danmaku::match_episode(&client, &keyword, query.episode).await {
+        Ok(Some(found)) => Json(found).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "No matching episode"})),
+        )
+            .into_response(),
+        Err(e) => upstream_error(e),
+    }
+}
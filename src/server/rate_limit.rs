@@ -0,0 +1,232 @@
+//! 公开模式的按 IP 限流
+//! 设置 PUBLIC_RATE_LIMIT 后，每个客户端 IP 每分钟最多请求指定次数 (滑动窗口)，超出返回 429 与 Retry-After。
+//! `/health` 探针不计入。计数保存在分片的内存表中，只对当前实例生效。
+//! 上限与是否信任反代头在每次请求时读取配置，重载配置后立即生效 (上限为 0 时不限流)。
+
+use crate::config::CONFIG;
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::future::BoxFuture;
+use serde_json::json;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// 限流窗口
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// 分片数 (降低高并发下的锁竞争)
+const SHARDS: usize = 16;
+
+/// 单个分片的 IP 数超过该值时清理窗口外的记录
+const PRUNE_THRESHOLD: usize = 4096;
+
+type Shard = Mutex<HashMap<IpAddr, VecDeque<Instant>>>;
+
+/// 滑动窗口计数 (记录窗口内每次请求的时间，每个 IP 最多保存 `limit` 条)
+pub struct SlidingWindow {
+    limit: AtomicUsize,
+    window: Duration,
+    shards: Vec<Shard>,
+}
+
+impl SlidingWindow {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit: AtomicUsize::new(limit),
+            window,
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+
+    fn shard(&self, ip: &IpAddr) -> &Shard {
+        let mut hasher = DefaultHasher::new();
+        ip.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    /// 修改上限 (已记录的请求保留)
+    pub fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::Relaxed);
+    }
+
+    /// 记录一次请求；超出上限时返回需要等待的时间
+    pub fn check(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let window = self.window;
+        let mut shard = self.shard(&ip).lock().unwrap_or_else(|e| e.into_inner());
+        if shard.len() > PRUNE_THRESHOLD {
+            shard.retain(|_, hits| hits.back().is_some_and(|t| now.duration_since(*t) < window));
+        }
+
+        let hits = shard.entry(ip).or_default();
+        while hits
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= window)
+        {
+            hits.pop_front();
+        }
+        if hits.len() >= self.limit.load(Ordering::Relaxed) {
+            let oldest = hits.front().copied().unwrap_or(now);
+            return Err(window.saturating_sub(now.duration_since(oldest)));
+        }
+        hits.push_back(now);
+        Ok(())
+    }
+}
+
+/// 按 IP 限流的 tower layer (PUBLIC_RATE_LIMIT / TRUST_FORWARDED)
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<SlidingWindow>,
+}
+
+impl RateLimitLayer {
+    pub fn new() -> Self {
+        Self {
+            limiter: Arc::new(SlidingWindow::new(CONFIG.public_rate_limit, WINDOW)),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// 按 IP 限流的服务
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    layer: RateLimitLayer,
+}
+
+impl<S> Service<Request> for RateLimit<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let limit = CONFIG.public_rate_limit;
+        let exempt = limit == 0 || is_exempt(request.uri().path());
+        if let Some(ip) = client_ip(&request, CONFIG.trust_forwarded).filter(|_| !exempt) {
+            self.layer.limiter.set_limit(limit);
+            if let Err(wait) = self.layer.limiter.check(ip, Instant::now()) {
+                return Box::pin(async move { Ok(too_many_requests(wait)) });
+            }
+        }
+        Box::pin(self.inner.call(request))
+    }
+}
+
+/// 健康检查不限流 (含 /health/ready)
+fn is_exempt(path: &str) -> bool {
+    path == "/health" || path.starts_with("/health/")
+}
+
+/// 客户端 IP: 信任反代时取 X-Forwarded-For 第一项 (或 X-Real-IP)，否则取连接地址
+fn client_ip(request: &Request, trust_forwarded: bool) -> Option<IpAddr> {
+    let headers = request.headers();
+    let forwarded = trust_forwarded
+        .then(|| {
+            headers
+                .get("X-Forwarded-For")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .or_else(|| headers.get("X-Real-IP").and_then(|v| v.to_str().ok()))
+                .and_then(|v| v.trim().parse().ok())
+        })
+        .flatten();
+    forwarded.or_else(|| {
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    })
+}
+
+fn too_many_requests(wait: Duration) -> Response {
+    // 向上取整，至少 1 秒
+    let retry_after = (wait.as_millis() as u64).div_ceil(1000).max(1);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        Json(json!({
+            "error": "Rate limit exceeded, please retry later",
+            "retry_after": retry_after
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    #[test]
+    fn test_sliding_window_limits_per_ip() {
+        let limiter = SlidingWindow::new(2, Duration::from_secs(60));
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check(a, start).is_ok());
+        assert!(limiter.check(a, start + Duration::from_secs(20)).is_ok());
+        let wait = limiter
+            .check(a, start + Duration::from_secs(30))
+            .unwrap_err();
+        assert_eq!(wait, Duration::from_secs(30));
+        assert!(limiter.check(b, start + Duration::from_secs(30)).is_ok());
+
+        // 第一条记录滑出窗口后恢复一个名额
+        assert!(limiter.check(a, start + Duration::from_secs(60)).is_ok());
+        assert!(limiter.check(a, start + Duration::from_secs(61)).is_err());
+    }
+
+    #[test]
+    fn test_client_ip_honors_forwarded_only_when_trusted() {
+        let request = || {
+            let mut request = Request::builder()
+                .uri("/api")
+                .header("X-Forwarded-For", "203.0.113.7, 10.0.0.1")
+                .body(Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 1234))));
+            request
+        };
+        assert_eq!(client_ip(&request(), true), "203.0.113.7".parse().ok());
+        assert_eq!(client_ip(&request(), false), "127.0.0.1".parse().ok());
+        assert!(is_exempt("/health") && is_exempt("/health/ready"));
+        assert!(!is_exempt("/healthz"));
+
+        let response = too_many_requests(Duration::from_millis(1500));
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+    }
+}
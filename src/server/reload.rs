@@ -0,0 +1,241 @@
+//! 配置重载
+//! SIGHUP 与 `POST /admin/reload-config` 走同一条路径：重新读取 CONFIG_FILE 与环境变量，
+//! 校验通过后整体替换运行时可变的配置 (超时、User-Agent、反代前缀、规则仓库、并发数、限流、
+//! 缓存有效期、日志级别等)，并记录变更的配置项；监听地址、存储后端、缓存容量等只在启动时生效的
+//! 配置报告为需要重启，不会静默忽略
+
+use crate::config::{self, Config, CONFIG};
+use crate::{cache, http_client};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+/// 只在启动时生效的配置项 (重载时保留运行中的值，报告为需要重启)
+const RESTART_REQUIRED: &[&str] = &[
+    // 监听地址
+    "PORT",
+    // 配置文件本身只能由启动时的环境变量指定
+    "CONFIG_FILE",
+    // 数据目录 (token 档案在启动时加载)
+    "DATA_DIR",
+    // 停机路由只在配置了 ADMIN_KEY 时注册
+    "ADMIN_KEY",
+    // 启动时更新与定时更新任务在启动时决定
+    "AUTO_UPDATE",
+    "UPDATE_INTERVAL_HOURS",
+    // 统一超时层与响应信封层在构建路由时创建
+    "REQUEST_TIMEOUT_SECONDS",
+    "ENVELOPE",
+    // 首页路由在启动时注册
+    "ROOT_MODE",
+    "ROOT_REDIRECT_URL",
+    // 全局搜索并发的信号量在首次搜索时创建
+    "MAX_CONCURRENT_SEARCHES",
+    // 存储后端与历史数据库在启动时打开
+    "DATABASE_PATH",
+    "REDIS_URL",
+    "HISTORY_DB",
+    "IMAGE_CACHE_DIR",
+    "IMAGE_CACHE_MAX_MB",
+    // 缓存容量与压缩方式在创建缓存时确定 (有效期可重载)
+    "CACHE_BANGUMI_CAPACITY",
+    "CACHE_SEARCH_CAPACITY",
+    "CACHE_COMPRESS",
+    // 后台任务在启动时决定是否运行及间隔
+    "SELF_TEST",
+    "BOOTSTRAP_RULES",
+    "WATCH_RULES",
+    "RULES_DIR",
+    "DNS_CACHE_SECONDS",
+    "WATCHLIST_CHECK_INTERVAL_MINUTES",
+    "AUTO_DISABLE_RECHECK_MINUTES",
+    "WEBHOOK_FAILURE_RATE",
+];
+
+/// 日志级别重载函数 (由启动时的 tracing reload handle 注册)
+type LogReloader = Box<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+
+static LOG_RELOADER: OnceCell<LogReloader> = OnceCell::new();
+
+/// 注册日志级别重载函数 (启动时调用一次)
+pub fn set_log_reloader(reloader: impl Fn(&str) -> Result<(), String> + Send + Sync + 'static) {
+    let _ = LOG_RELOADER.set(Box::new(reloader));
+}
+
+/// 重载结果
+#[derive(Debug, Default, Serialize)]
+pub struct ReloadReport {
+    /// 已生效的变更项
+    pub applied: Vec<String>,
+    /// 已变更但需要重启才能生效的项
+    pub restart_required: Vec<String>,
+    /// 错误 (有错误时不替换配置)
+    pub errors: Vec<String>,
+}
+
+impl ReloadReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// 重新读取配置并替换运行时可变的部分
+pub fn reload() -> ReloadReport {
+    let mut report = ReloadReport::default();
+    // 只在校验通过后替换配置文件变量，进程环境变量从不改写 (其他线程可能同时读取)
+    match config::read_config_file() {
+        Ok(file) => {
+            report.errors.extend(config::validate_with_file(&file).errors);
+            if report.is_ok() {
+                config::set_file_vars(file);
+            }
+        }
+        Err(e) => report.errors.push(e),
+    }
+    if !report.is_ok() {
+        for e in &report.errors {
+            error!("❌ 配置重载失败: {}", e);
+        }
+        return report;
+    }
+
+    let mut next = Config::from_env();
+    let (applied, restart_required) = changed_keys(&CONFIG, &next);
+    keep_startup_values(&mut next, &CONFIG);
+    let log_level_changed = next.log_level != CONFIG.log_level;
+    CONFIG.replace(next);
+    http_client::rebuild_clients();
+    cache::refresh_ttls();
+
+    if log_level_changed {
+        if let Some(reloader) = LOG_RELOADER.get() {
+            if let Err(e) = reloader(&CONFIG.log_level) {
+                warn!("⚠️ 日志级别 {:?} 无效: {}", CONFIG.log_level, e);
+            }
+        }
+    }
+
+    if applied.is_empty() && restart_required.is_empty() {
+        info!("🔄 配置已重载，没有变更");
+    }
+    if !applied.is_empty() {
+        info!("🔄 配置已重载，已生效: {}", applied.join(", "));
+    }
+    if !restart_required.is_empty() {
+        warn!("⚠️ 以下配置需要重启才能生效: {}", restart_required.join(", "));
+    }
+    report.applied = applied;
+    report.restart_required = restart_required;
+    report
+}
+
+/// 比较新旧配置，返回 (可运行时生效的变更项, 需要重启的变更项)
+fn changed_keys(current: &Config, next: &Config) -> (Vec<String>, Vec<String>) {
+    let next_entries = next.entries(false);
+    let changed = current
+        .entries(false)
+        .into_iter()
+        .zip(next_entries)
+        .filter(|(old, new)| old.1 != new.1)
+        .map(|(old, _)| old.0);
+    let (restart, applied): (Vec<&str>, Vec<&str>) = changed.partition(|key| RESTART_REQUIRED.contains(key));
+    let to_strings = |keys: Vec<&str>| keys.into_iter().map(String::from).collect();
+    (to_strings(applied), to_strings(restart))
+}
+
+/// 只在启动时生效的配置保留运行中的值 (避免 /debug 等输出与实际运行状态不一致)
+fn keep_startup_values(next: &mut Config, current: &Config) {
+    next.port = current.port;
+    next.config_file = current.config_file.clone();
+    next.data_dir = current.data_dir.clone();
+    next.admin_key = current.admin_key.clone();
+    next.auto_update = current.auto_update;
+    next.update_interval_hours = current.update_interval_hours;
+    next.request_timeout_seconds = current.request_timeout_seconds;
+    next.envelope = current.envelope;
+    next.root_mode = current.root_mode.clone();
+    next.root_redirect_url = current.root_redirect_url.clone();
+    next.max_concurrent_searches = current.max_concurrent_searches;
+    next.database_path = current.database_path.clone();
+    next.redis_url = current.redis_url.clone();
+    next.history_db = current.history_db.clone();
+    next.image_cache_dir = current.image_cache_dir.clone();
+    next.image_cache_max_mb = current.image_cache_max_mb;
+    next.cache_bangumi_capacity = current.cache_bangumi_capacity;
+    next.cache_search_capacity = current.cache_search_capacity;
+    next.cache_compress = current.cache_compress;
+    next.self_test = current.self_test;
+    next.bootstrap_rules = current.bootstrap_rules;
+    next.watch_rules = current.watch_rules;
+    next.rules_dir = current.rules_dir.clone();
+    next.dns_cache_seconds = current.dns_cache_seconds;
+    next.watchlist_check_interval_minutes = current.watchlist_check_interval_minutes;
+    next.auto_disable_recheck_minutes = current.auto_disable_recheck_minutes;
+    next.webhook_failure_rate = current.webhook_failure_rate;
+}
+
+/// 监听 SIGHUP 并重载配置
+pub fn spawn_sighup_listener() {
+    #[cfg(unix)]
+    tokio::spawn(async {
+        use tokio::signal::unix::{signal, SignalKind};
+        let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+            warn!("⚠️ 无法监听 SIGHUP，只能通过 POST /admin/reload-config 重载配置");
+            return;
+        };
+        while hangup.recv().await.is_some() {
+            info!("🔄 收到 SIGHUP，重载配置");
+            reload();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_keys_split_runtime_and_restart() {
+        let current = Config::from_env();
+        let mut next = current.clone();
+        next.timeout_seconds += 5;
+        next.log_level = "debug".to_string();
+        next.port = current.port.wrapping_add(1);
+        next.admin_key = Some("rotated".to_string());
+        next.public_rate_limit += 10;
+        next.cache_search_ttl_secs += 60;
+        next.cache_search_capacity += 1;
+
+        let (applied, restart) = changed_keys(&current, &next);
+        assert_eq!(
+            applied,
+            vec![
+                "LOG_LEVEL",
+                "TIMEOUT_SECONDS",
+                "CACHE_SEARCH_TTL_SECS",
+                "PUBLIC_RATE_LIMIT"
+            ]
+        );
+        assert_eq!(restart, vec!["PORT", "ADMIN_KEY", "CACHE_SEARCH_CAPACITY"]);
+
+        keep_startup_values(&mut next, &current);
+        assert_eq!(next.port, current.port);
+        assert_eq!(next.admin_key, current.admin_key);
+        assert_eq!(next.cache_search_capacity, current.cache_search_capacity);
+        assert_eq!(next.timeout_seconds, current.timeout_seconds + 5);
+        assert_eq!(next.public_rate_limit, current.public_rate_limit + 10);
+    }
+
+    /// 需要重启的配置项都能在配置列表中找到 (避免改名后漏报)
+    #[test]
+    fn test_restart_required_keys_exist() {
+        let keys: Vec<&str> = Config::from_env()
+            .entries(false)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        for key in RESTART_REQUIRED {
+            assert!(keys.contains(key), "{}", key);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! 规则作者命令行 (`rule new` / `rule check` / `rule fmt`)
+//! 检查与试运行复用服务端代码: 字段检查同规则加载，lint 同自检，
+//! `--keyword` 的阶段匹配数与 `POST /debug/dry-run` 一致 (同为 [`engine::dry_run`])
+
+use super::selftest::lint_rule;
+use crate::config::CONFIG;
+use crate::engine::{self, DryRunReport};
+use crate::rules::{check_rule_fields, format_rule_json, parse_rule_json, rule_template};
+use crate::types::Rule;
+use serde_json::Value;
+use std::fs;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "用法:
+  rule new   [--name 名称] [--base-url URL] [--search-url URL] [-o 输出路径]
+  rule check <规则.json> [--keyword 关键词] [--json]
+  rule fmt   <规则.json> [--check]";
+
+/// 执行 `rule` 子命令 (`args` 为 `rule` 之后的参数)，返回退出码
+pub async fn main(args: &[String]) -> i32 {
+    let result = match args.first().map(String::as_str) {
+        Some("new") => new(&args[1..]),
+        Some("check") => check(&args[1..]).await,
+        Some("fmt") => fmt(&args[1..]),
+        _ => Err(anyhow::anyhow!("{}", USAGE)),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        }
+    }
+}
+
+/// 解析后的参数: 位置参数与 `--flag [值]`
+#[derive(Debug, Default)]
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    /// `switches` 为不带值的开关，其余以 `-` 开头的参数均取下一个参数作为值
+    fn parse(args: &[String], switches: &[&str]) -> anyhow::Result<Self> {
+        let mut parsed = Args::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if !arg.starts_with('-') {
+                parsed.positional.push(arg.clone());
+            } else if switches.contains(&arg.as_str()) {
+                parsed.options.push((arg.clone(), None));
+            } else {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("{} 缺少参数值\n{}", arg, USAGE))?;
+                parsed.options.push((arg.clone(), Some(value.clone())));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn value(&self, names: &[&str]) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(n, _)| names.contains(&n.as_str()))
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(n, _)| n == name)
+    }
+
+    /// 唯一的位置参数 (规则文件路径)
+    fn path(&self) -> anyhow::Result<&Path> {
+        match self.positional.as_slice() {
+            [path] => Ok(Path::new(path)),
+            _ => Err(anyhow::anyhow!("需要一个规则文件路径\n{}", USAGE)),
+        }
+    }
+}
+
+/// 交互式输入 (标准输入不是终端时直接使用默认值)
+fn prompt(label: &str, default: &str) -> anyhow::Result<String> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return Ok(default.to_string());
+    }
+    print!("{} [{}]: ", label, default);
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    stdin.lock().read_line(&mut line)?;
+    let line = line.trim();
+    Ok(if line.is_empty() { default } else { line }.to_string())
+}
+
+/// rule new - 生成规则模板 (不覆盖已有文件)
+fn new(args: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(args, &[])?;
+    let ask = |names: &[&str], label: &str, default: &str| match args.value(names) {
+        Some(v) => Ok(v.to_string()),
+        None => prompt(label, default),
+    };
+    let name = ask(&["--name"], "规则名称", "NewRule")?;
+    let base_url = ask(&["--base-url"], "站点地址 (baseURL)", "https://example.com/")?;
+    let search_url = ask(
+        &["--search-url"],
+        "搜索地址 (searchURL，关键词用 @keyword)",
+        &format!("{}/search?wd=@keyword", base_url.trim_end_matches('/')),
+    )?;
+
+    let path = match args.value(&["-o", "--output"]) {
+        Some(p) => PathBuf::from(p),
+        None => Path::new(&CONFIG.rules_dir).join(format!("{}.json", name)),
+    };
+    if path.exists() {
+        anyhow::bail!("{} 已存在，不会覆盖", path.display());
+    }
+    fs::write(&path, rule_template(&name, &base_url, &search_url))?;
+    println!("✅ 已生成 {}", path.display());
+    println!("   修改 TODO 选择器后运行: rule check {} --keyword 关键词", path.display());
+    Ok(0)
+}
+
+/// rule check - 字段检查 + lint，指定 `--keyword` 时试运行
+async fn check(args: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(args, &["--json"])?;
+    let path = args.path()?;
+    let content = fs::read_to_string(path)?;
+    let value: Value = parse_rule_json(&content)
+        .map_err(|e| anyhow::anyhow!("{}: JSON 解析失败: {}", path.display(), e))?;
+
+    let mut failed = false;
+    let report = check_rule_fields(&value);
+    for message in report.messages() {
+        println!("⚠️ {}", message);
+    }
+    failed |= !report.missing_required.is_empty();
+
+    let rule: Rule = match serde_json::from_value(value) {
+        Ok(rule) => rule,
+        Err(e) => {
+            println!("❌ 无法加载为规则: {}", e);
+            return Ok(1);
+        }
+    };
+    for warning in lint_rule(&rule) {
+        println!("⚠️ {}", warning);
+    }
+
+    if let Some(keyword) = args.value(&["--keyword"]) {
+        let report = engine::dry_run(&rule, keyword).await;
+        if args.flag("--json") {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            print_dry_run(&report);
+        }
+        failed |= report.error.is_some() || report.items.is_empty();
+    }
+
+    if failed {
+        println!("❌ {} 检查未通过", path.display());
+        Ok(1)
+    } else {
+        println!("✅ {} 检查通过", path.display());
+        Ok(0)
+    }
+}
+
+/// 输出试运行结果 (各阶段匹配数与前几个结果)
+fn print_dry_run(report: &DryRunReport) {
+    println!("🔎 {} {}", report.rule, report.search_url);
+    if let Some(status) = report.http_status {
+        println!("   HTTP {}", status);
+    }
+    for stage in &report.stages {
+        println!("   {:<14} {:>4}  {}", stage.stage, stage.matched, stage.xpath);
+    }
+    for item in report.items.iter().take(5) {
+        println!("   · {} {}", item.name, item.url);
+    }
+    if report.items.len() > 5 {
+        println!("   … 共 {} 个结果", report.items.len());
+    }
+    if let Some(url) = &report.detail_url {
+        println!("   详情页: {}", url);
+    }
+    match &report.error {
+        Some(e) => println!("❌ {}", e),
+        None if report.items.is_empty() => println!("❌ 未解析到结果"),
+        None => {}
+    }
+}
+
+/// rule fmt - 规范字段顺序与缩进，`--check` 时只检查不写入
+fn fmt(args: &[String]) -> anyhow::Result<i32> {
+    let args = Args::parse(args, &["--check"])?;
+    let path = args.path()?;
+    let content = fs::read_to_string(path)?;
+    let formatted = format_rule_json(&content)
+        .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    if formatted == content {
+        return Ok(0);
+    }
+    if args.flag("--check") {
+        println!("{} 需要格式化", path.display());
+        return Ok(1);
+    }
+    fs::write(path, formatted)?;
+    println!("✅ 已格式化 {}", path.display());
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_parse() {
+        let argv: Vec<String> = ["AGE.json", "--keyword", "海贼王", "--json"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let args = Args::parse(&argv, &["--json"]).unwrap();
+        assert_eq!(args.path().unwrap(), Path::new("AGE.json"));
+        assert_eq!(args.value(&["--keyword"]), Some("海贼王"));
+        assert!(args.flag("--json"));
+        assert!(Args::parse(&argv[..2], &[]).is_err());
+    }
+}
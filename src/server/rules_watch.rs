@@ -0,0 +1,215 @@
+//! 规则目录监视 (WATCH_RULES)
+//! 通过文件系统事件 (inotify 等) 得知规则目录变化，事件不可用时 (目录不存在、网络文件系统、
+//! 监视数量超限等) 退回定期扫描。变化以顶层 JSON 文件的快照 (修改时间 + 大小) 为准，
+//! 等待写入稳定后再重新加载规则，记录变化的文件及其能否解析。
+//! 忽略隐藏文件、原子写入产生的临时文件与子目录 (.history / .removed 等)。
+
+use crate::config::CONFIG;
+use crate::rules::reload_builtin_rules;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// 无文件系统事件时的扫描间隔
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 有文件系统事件时的兜底扫描间隔 (补漏丢失的事件，同时作为心跳)
+pub const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 发现变化后，目录需保持不变的时间 (合并编辑器连续写入产生的多次变化)
+const DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// 文件名 -> (修改时间, 大小)
+pub type Snapshot = BTreeMap<String, (SystemTime, u64)>;
+
+/// 是否为需要监视的规则文件 (含 groups.json)
+fn is_watched(name: &str) -> bool {
+    name.ends_with(".json") && !name.starts_with('.') && name != "index.json"
+}
+
+/// 扫描规则目录顶层文件 (目录不存在时为空)
+pub fn snapshot(dir: &Path) -> Snapshot {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Snapshot::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let metadata = entry.metadata().ok()?;
+            if !metadata.is_file() || !is_watched(&name) {
+                return None;
+            }
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((name, (modified, metadata.len())))
+        })
+        .collect()
+}
+
+/// 两次扫描之间新增、修改或删除的文件 (按文件名排序)
+pub fn changed_files(before: &Snapshot, after: &Snapshot) -> Vec<String> {
+    let mut changed: Vec<String> = after
+        .iter()
+        .filter(|(name, state)| before.get(*name) != Some(state))
+        .map(|(name, _)| name.clone())
+        .chain(
+            before
+                .keys()
+                .filter(|name| !after.contains_key(*name))
+                .cloned(),
+        )
+        .collect();
+    changed.sort();
+    changed
+}
+
+/// 监视规则目录 (非递归)，事件经通道唤醒监视循环；无法监视时返回 None
+fn fs_events(dir: &Path) -> Option<(RecommendedWatcher, mpsc::UnboundedReceiver<()>)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok() {
+            let _ = tx.send(());
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("⚠️ 无法创建文件系统监视 ({})，改为每 {}s 扫描", e, POLL_INTERVAL.as_secs());
+            return None;
+        }
+    };
+    if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+        warn!(
+            "⚠️ 无法监视 {} ({})，改为每 {}s 扫描",
+            dir.display(),
+            e,
+            POLL_INTERVAL.as_secs()
+        );
+        return None;
+    }
+    Some((watcher, rx))
+}
+
+/// 等待下一次检查: 有事件时等待事件 (最长 RESCAN_INTERVAL)，否则等待 POLL_INTERVAL
+async fn next_tick(events: &mut Option<(RecommendedWatcher, mpsc::UnboundedReceiver<()>)>) {
+    let Some((_, rx)) = events else {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        return;
+    };
+    match tokio::time::timeout(RESCAN_INTERVAL, rx.recv()).await {
+        Ok(Some(())) => while rx.try_recv().is_ok() {},
+        Ok(None) => {
+            warn!("⚠️ 文件系统监视已停止，改为每 {}s 扫描", POLL_INTERVAL.as_secs());
+            *events = None;
+        }
+        Err(_) => {}
+    }
+}
+
+/// 监视循环 (由后台任务运行，`beat` 在每轮检查时调用)
+pub async fn watch(beat: impl Fn()) {
+    let dir = Path::new(&CONFIG.rules_dir);
+    let mut events = fs_events(dir);
+    let mut current = snapshot(dir);
+    loop {
+        beat();
+        next_tick(&mut events).await;
+        let mut latest = snapshot(dir);
+        if latest == current {
+            continue;
+        }
+        // 等待写入稳定
+        loop {
+            tokio::time::sleep(DEBOUNCE).await;
+            let next = snapshot(dir);
+            if next == latest {
+                break;
+            }
+            latest = next;
+        }
+        // 丢弃等待期间积压的事件 (已包含在最新快照中)
+        if let Some((_, rx)) = events.as_mut() {
+            while rx.try_recv().is_ok() {}
+        }
+
+        let changed = changed_files(&current, &latest);
+        current = latest;
+        reload(&changed);
+    }
+}
+
+/// 重新加载规则并记录变化文件的解析结果
+fn reload(changed: &[String]) {
+    info!("👀 规则文件变化: {}", changed.join(", "));
+    let rules = reload_builtin_rules();
+    for file in changed {
+        if rules.failed_files().contains(file) {
+            warn!("❌ {} 解析失败 (已加载的同名规则保持不变)", file);
+        } else if Path::new(&CONFIG.rules_dir).join(file).exists() {
+            info!("✅ {} 已重新加载", file);
+        } else {
+            info!("🗑️ {} 已删除", file);
+        }
+    }
+    info!("📚 当前共 {} 个规则", rules.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_ignores_temp_files_and_subdirs() {
+        let dir = std::env::temp_dir().join(format!("rules-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join(".history")).unwrap();
+        fs::create_dir_all(dir.join(".removed")).unwrap();
+        fs::write(dir.join("AGE.json"), "{}").unwrap();
+        fs::write(dir.join("groups.json"), "{}").unwrap();
+        fs::write(dir.join(".AGE.json.tmp"), "{").unwrap();
+        fs::write(dir.join("AGE.json.swp"), "{").unwrap();
+        fs::write(dir.join(".history").join("AGE.json"), "{}").unwrap();
+
+        let before = snapshot(&dir);
+        assert_eq!(
+            before.keys().collect::<Vec<_>>(),
+            vec!["AGE.json", "groups.json"]
+        );
+
+        fs::write(dir.join("AGE.json"), r#"{"name": "AGE"}"#).unwrap();
+        fs::write(dir.join("MX.json"), "{}").unwrap();
+        fs::remove_file(dir.join("groups.json")).unwrap();
+        fs::write(dir.join(".removed").join("old.json"), "{}").unwrap();
+        let after = snapshot(&dir);
+        assert_eq!(
+            changed_files(&before, &after),
+            vec!["AGE.json", "MX.json", "groups.json"]
+        );
+        assert!(changed_files(&after, &after).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_fs_events_wake_on_write() {
+        let dir = std::env::temp_dir().join(format!("rules-watch-events-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let Some((_watcher, mut rx)) = fs_events(&dir) else {
+            // 环境不支持文件系统事件 (将退回轮询)
+            let _ = fs::remove_dir_all(&dir);
+            return;
+        };
+
+        fs::write(dir.join("AGE.json"), "{}").unwrap();
+        let woke = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await;
+        assert!(matches!(woke, Ok(Some(()))));
+
+        assert!(fs_events(&dir.join("missing")).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,219 @@
+//! 启动自检
+//! 部署后验证运行环境：规则加载与检查、Bangumi 连通性、规则目录可写、内置样例解析
+
+#[cfg(feature = "bangumi")]
+use crate::bangumi;
+#[cfg(feature = "scraper")]
+use crate::config::CONFIG;
+#[cfg(feature = "scraper")]
+use crate::engine::parse_search_results;
+#[cfg(feature = "scraper")]
+use crate::rules::{builtin_warnings, get_builtin_rules};
+#[cfg(feature = "scraper")]
+use crate::types::Rule;
+#[cfg(feature = "scraper")]
+use crate::xpath_to_css::xpath_to_css;
+use serde::Serialize;
+#[cfg(feature = "scraper")]
+use std::fs;
+#[cfg(feature = "scraper")]
+use std::path::Path;
+use tracing::{error, info, warn};
+
+/// 内置样例页面 (用于验证解析流程)
+#[cfg(feature = "scraper")]
+pub(super) const FIXTURE_HTML: &str = r#"
+<html><body>
+  <div class="search-box">
+    <div class="item"><h3><a href="/video/1">葬送的芙莉莲</a></h3></div>
+    <div class="item"><h3><a href="/video/2">葬送的芙莉莲 第二季</a></h3></div>
+  </div>
+</body></html>
+"#;
+
+/// 单项检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+    /// 非致命的警告 (如规则检查问题)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// 自检报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub checks: Vec<CheckResult>,
+    pub timestamp: String,
+}
+
+impl CheckResult {
+    #[cfg_attr(not(any(feature = "scraper", feature = "bangumi")), allow(dead_code))]
+    fn new(name: &str, result: Result<String, String>) -> Self {
+        let (passed, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+        Self {
+            name: name.to_string(),
+            passed,
+            detail,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// 执行所有检查 (只包含编译启用的功能)
+pub async fn run() -> SelfTestReport {
+    #[allow(unused_mut)]
+    let mut checks: Vec<CheckResult> = Vec::new();
+    #[cfg(feature = "scraper")]
+    checks.push(check_rules());
+    #[cfg(feature = "bangumi")]
+    checks.push(check_bangumi().await);
+    #[cfg(feature = "scraper")]
+    {
+        checks.push(check_rules_dir_writable());
+        checks.push(check_fixture_parse());
+    }
+
+    SelfTestReport {
+        passed: checks.iter().all(|c| c.passed),
+        checks,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+/// 打印结果表
+pub fn print_report(report: &SelfTestReport) {
+    info!("🩺 自检结果:");
+    for check in &report.checks {
+        let mark = if check.passed { "✅ PASS" } else { "❌ FAIL" };
+        info!("   {:<20} {}  {}", check.name, mark, check.detail);
+        for warning in &check.warnings {
+            warn!("   {:<20}   ⚠️ {}", "", warning);
+        }
+    }
+    if report.passed {
+        info!("🩺 自检通过");
+    } else {
+        error!("🩺 自检失败");
+    }
+}
+
+/// 规则加载与检查
+#[cfg(feature = "scraper")]
+fn check_rules() -> CheckResult {
+    let rules = get_builtin_rules();
+    let mut warnings = builtin_warnings();
+    warnings.extend(rules.iter().flat_map(|r| lint_rule(r)));
+
+    let result = if rules.is_empty() {
+        Err("未加载任何规则".to_string())
+    } else {
+        Ok(format!("{} 个规则，{} 条警告", rules.len(), warnings.len()))
+    };
+
+    CheckResult {
+        warnings,
+        ..CheckResult::new("rules", result)
+    }
+}
+
+/// 检查单个规则的常见问题
+#[cfg(feature = "scraper")]
+pub(super) fn lint_rule(rule: &Rule) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if url::Url::parse(&rule.base_url).is_err() {
+        warnings.push(format!("{}: baseURL 无效", rule.name));
+    }
+    if !rule.search_url.contains("@keyword") {
+        warnings.push(format!("{}: searchURL 缺少 @keyword", rule.name));
+    }
+    if rule.is_rss() {
+        return warnings;
+    }
+    for (field, xpath) in [
+        ("searchList", &rule.search_list),
+        ("searchName", &rule.search_name),
+    ] {
+        if xpath.is_empty() {
+            warnings.push(format!("{}: {} 为空", rule.name, field));
+        } else if let Err(e) = xpath_to_css(xpath) {
+            warnings.push(format!("{}: {} 无法转换 ({})", rule.name, field, e));
+        }
+    }
+
+    warnings
+}
+
+/// Bangumi 连通性 (每日放送)
+#[cfg(feature = "bangumi")]
+async fn check_bangumi() -> CheckResult {
+    let result = match bangumi::get_calendar().await {
+        Ok(calendar) => Ok(format!("每日放送 {} 天", calendar.len())),
+        Err(e) => Err(format!("请求失败: {}", e)),
+    };
+    CheckResult::new("bangumi_calendar", result)
+}
+
+/// 规则目录可写 (更新规则需要)
+#[cfg(feature = "scraper")]
+fn check_rules_dir_writable() -> CheckResult {
+    let dir = &CONFIG.rules_dir;
+    let probe = Path::new(dir).join(".selftest");
+    let result = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b"ok"))
+        .and_then(|_| fs::remove_file(&probe))
+        .map(|_| format!("{} 可写", dir))
+        .map_err(|e| format!("{} 不可写: {}", dir, e));
+    CheckResult::new("rules_dir_writable", result)
+}
+
+/// 内置样例解析
+#[cfg(feature = "scraper")]
+fn check_fixture_parse() -> CheckResult {
+    let rule = Rule {
+        name: "selftest".to_string(),
+        base_url: "https://example.com".to_string(),
+        search_url: "https://example.com/search?q=@keyword".to_string(),
+        search_list: "//div[@class='item']".to_string(),
+        search_name: "//h3/a".to_string(),
+        search_result: "//h3/a".to_string(),
+        ..Default::default()
+    };
+
+    let result = match parse_search_results(&rule, FIXTURE_HTML) {
+        Ok(items) if items.len() == 2 && items[0].url == "https://example.com/video/1" => {
+            Ok(format!("解析出 {} 个结果", items.len()))
+        }
+        Ok(items) => Err(format!("解析结果不符合预期 ({} 个)", items.len())),
+        Err(e) => Err(format!("解析失败: {}", e)),
+    };
+    CheckResult::new("fixture_parse", result)
+}
+
+#[cfg(all(test, feature = "scraper"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_parse_passes() {
+        assert!(check_fixture_parse().passed);
+    }
+
+    #[test]
+    fn test_lint_rule() {
+        let rule = Rule {
+            name: "bad".to_string(),
+            base_url: "not a url".to_string(),
+            search_url: "https://example.com/search".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(lint_rule(&rule).len(), 4);
+    }
+}
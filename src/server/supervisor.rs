@@ -0,0 +1,218 @@
+//! 后台任务监管
+//! 登记每个后台任务的名称与心跳时间，任务崩溃 (panic 或意外退出) 后按退避策略重启，
+//! 并为 `/health/ready` 与 `/metrics` 提供状态
+
+// 目前受监管的后台任务只有规则定时更新 (scraper 功能)
+#![cfg_attr(not(feature = "scraper"), allow(dead_code))]
+
+use futures::FutureExt;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+/// 重启退避初始值
+const BACKOFF_INITIAL: Duration = Duration::from_secs(1);
+/// 重启退避上限
+const BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+/// 已登记的任务 (按名称排序，输出稳定)
+static TASKS: Lazy<Mutex<BTreeMap<String, TaskState>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Debug, Clone)]
+struct TaskState {
+    critical: bool,
+    up: bool,
+    last_heartbeat: Instant,
+    heartbeat_timeout: Duration,
+    restarts: u32,
+    last_error: Option<String>,
+}
+
+/// 任务状态报告
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskReport {
+    pub name: String,
+    pub critical: bool,
+    pub up: bool,
+    /// 距上次心跳的秒数
+    pub last_heartbeat_secs: u64,
+    pub restarts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// 是否健康 (运行中且心跳未超时)
+    pub healthy: bool,
+}
+
+/// 心跳句柄，任务在每轮工作时调用 `beat()`
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    name: String,
+}
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        with_task(&self.name, |t| t.last_heartbeat = Instant::now());
+    }
+}
+
+fn with_task(name: &str, f: impl FnOnce(&mut TaskState)) {
+    let mut tasks = TASKS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(task) = tasks.get_mut(name) {
+        f(task);
+    }
+}
+
+/// 启动受监管的后台任务
+///
+/// `factory` 每次 (重新) 启动时被调用以生成任务 future；
+/// `heartbeat_timeout` 内未调用 `Heartbeat::beat()` 视为失活
+pub fn spawn<F, Fut>(name: &str, critical: bool, heartbeat_timeout: Duration, factory: F)
+where
+    F: Fn(Heartbeat) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let name = name.to_string();
+    TASKS.lock().unwrap_or_else(|e| e.into_inner()).insert(
+        name.clone(),
+        TaskState {
+            critical,
+            up: false,
+            last_heartbeat: Instant::now(),
+            heartbeat_timeout,
+            restarts: 0,
+            last_error: None,
+        },
+    );
+
+    tokio::spawn(async move {
+        let mut backoff = BACKOFF_INITIAL;
+        loop {
+            with_task(&name, |t| {
+                t.up = true;
+                t.last_heartbeat = Instant::now();
+            });
+            info!("▶️ 后台任务启动: {}", name);
+
+            let started = Instant::now();
+            let heartbeat = Heartbeat { name: name.clone() };
+            let outcome = AssertUnwindSafe(factory(heartbeat)).catch_unwind().await;
+
+            let reason = match outcome {
+                Ok(()) => "任务意外退出".to_string(),
+                Err(panic) => panic_message(&panic),
+            };
+            error!("💥 后台任务 {} 停止: {}，{:?} 后重启", name, reason, backoff);
+            with_task(&name, |t| {
+                t.up = false;
+                t.restarts += 1;
+                t.last_error = Some(reason);
+            });
+
+            // 稳定运行足够久后重置退避
+            if started.elapsed() > BACKOFF_MAX {
+                backoff = BACKOFF_INITIAL;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(BACKOFF_MAX);
+        }
+    });
+}
+
+fn panic_message(panic: &Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        format!("panic: {}", s)
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        format!("panic: {}", s)
+    } else {
+        "panic".to_string()
+    }
+}
+
+/// 所有任务的状态
+pub fn reports() -> Vec<TaskReport> {
+    let tasks = TASKS.lock().unwrap_or_else(|e| e.into_inner());
+    tasks
+        .iter()
+        .map(|(name, t)| {
+            let silent = t.last_heartbeat.elapsed();
+            TaskReport {
+                name: name.clone(),
+                critical: t.critical,
+                up: t.up,
+                last_heartbeat_secs: silent.as_secs(),
+                restarts: t.restarts,
+                last_error: t.last_error.clone(),
+                healthy: t.up && silent <= t.heartbeat_timeout,
+            }
+        })
+        .collect()
+}
+
+/// 是否就绪 (所有关键任务都健康)
+pub fn is_ready(reports: &[TaskReport]) -> bool {
+    reports.iter().all(|r| !r.critical || r.healthy)
+}
+
+/// Prometheus 文本格式指标
+pub fn render_metrics(reports: &[TaskReport]) -> String {
+    let mut out = String::new();
+    out.push_str("# HELP background_task_up Whether the background task is running and heartbeating\n");
+    out.push_str("# TYPE background_task_up gauge\n");
+    for r in reports {
+        out.push_str(&format!(
+            "background_task_up{{name=\"{}\"}} {}\n",
+            r.name,
+            if r.healthy { 1 } else { 0 }
+        ));
+    }
+    out.push_str("# HELP background_task_restarts_total Number of times the background task was restarted\n");
+    out.push_str("# TYPE background_task_restarts_total counter\n");
+    for r in reports {
+        out.push_str(&format!(
+            "background_task_restarts_total{{name=\"{}\"}} {}\n",
+            r.name, r.restarts
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_restart_after_panic() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        spawn("test_panic_task", true, Duration::from_secs(60), move |hb| {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("boom");
+                }
+                loop {
+                    hb.beat();
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(runs.load(Ordering::SeqCst) >= 2);
+
+        let report = reports()
+            .into_iter()
+            .find(|r| r.name == "test_panic_task")
+            .unwrap();
+        assert!(report.healthy);
+        assert_eq!(report.restarts, 1);
+        assert!(render_metrics(&[report]).contains("background_task_up{name=\"test_panic_task\"} 1"));
+    }
+}
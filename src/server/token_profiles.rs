@@ -0,0 +1,176 @@
+//! Bangumi token 档案
+//! 管理员登记 "档案名 -> token"，客户端通过 `X-Token-Profile` 请求头选择档案，
+//! 无需在每个客户端粘贴原始 token。存储于 `<DATA_DIR>/token_profiles.json` (权限 0600)
+
+use super::audit;
+use crate::config::CONFIG;
+use axum::http::HeaderMap;
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tracing::warn;
+
+/// 档案文件名
+const PROFILES_FILE: &str = "token_profiles.json";
+
+/// 选择档案的请求头
+pub const PROFILE_HEADER: &str = "X-Token-Profile";
+
+/// 全局档案表
+static PROFILES: Lazy<RwLock<BTreeMap<String, String>>> = Lazy::new(|| RwLock::new(load()));
+
+/// 请求使用的 token
+#[derive(Debug, Clone)]
+pub struct ResolvedToken {
+    pub token: String,
+    /// 来自档案时的档案名
+    pub profile: Option<String>,
+}
+
+fn profiles_path() -> PathBuf {
+    PathBuf::from(&CONFIG.data_dir).join(PROFILES_FILE)
+}
+
+fn load() -> BTreeMap<String, String> {
+    match fs::read_to_string(profiles_path()) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("解析 token 档案失败: {}", e);
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+fn save(profiles: &BTreeMap<String, String>) -> anyhow::Result<()> {
+    fs::create_dir_all(&CONFIG.data_dir)?;
+    let content = serde_json::to_string_pretty(profiles)?;
+    write_private(&profiles_path(), &content)?;
+    Ok(())
+}
+
+/// 写入只有所有者可读写的文件
+///
+/// 先写入同目录下以 0600 新建的临时文件再改名覆盖，目标文件原有的权限 (如旧版本创建的 0644) 不会保留
+fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    let _ = fs::remove_file(&tmp);
+
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut file = fs::OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .mode(0o600)
+            .open(&tmp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    fs::write(&tmp, content)?;
+
+    fs::rename(&tmp, path)
+}
+
+/// 档案名列表 (不返回 token)
+pub fn list() -> Vec<String> {
+    PROFILES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// 新增或覆盖档案
+pub fn upsert(name: &str, token: &str) -> anyhow::Result<()> {
+    let mut profiles = PROFILES.write().unwrap_or_else(|e| e.into_inner());
+    profiles.insert(name.to_string(), token.to_string());
+    save(&profiles)
+}
+
+/// 删除档案，返回是否存在
+pub fn remove(name: &str) -> anyhow::Result<bool> {
+    let mut profiles = PROFILES.write().unwrap_or_else(|e| e.into_inner());
+    let existed = profiles.remove(name).is_some();
+    if existed {
+        save(&profiles)?;
+    }
+    Ok(existed)
+}
+
+/// 解析请求使用的 token
+///
+/// 显式 `Authorization: Bearer` 优先；其次 `X-Token-Profile` 档案；
+/// 档案不存在时返回错误 (不回退到默认 token)
+pub fn extract_token(headers: &HeaderMap) -> Result<Option<ResolvedToken>, String> {
+    if let Some(token) = audit::bearer_token(headers) {
+        return Ok(Some(ResolvedToken {
+            token: token.to_string(),
+            profile: None,
+        }));
+    }
+
+    let Some(name) = headers
+        .get(PROFILE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    let profiles = PROFILES.read().unwrap_or_else(|e| e.into_inner());
+    match profiles.get(name) {
+        Some(token) => Ok(Some(ResolvedToken {
+            token: token.clone(),
+            profile: Some(name.to_string()),
+        })),
+        None => Err(format!("Unknown token profile: {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearer_wins_over_profile() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "Bearer explicit".parse().unwrap());
+        headers.insert(PROFILE_HEADER, "nobody".parse().unwrap());
+        let resolved = extract_token(&headers).unwrap().unwrap();
+        assert_eq!(resolved.token, "explicit");
+        assert!(resolved.profile.is_none());
+    }
+
+    #[test]
+    fn test_unknown_profile_is_rejected() {
+        let mut headers = HeaderMap::new();
+        headers.insert(PROFILE_HEADER, "no-such-profile".parse().unwrap());
+        assert!(extract_token(&headers).is_err());
+        assert!(extract_token(&HeaderMap::new()).unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_tightens_existing_world_readable_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("token-profiles-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(PROFILES_FILE);
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private(&path, r#"{"main": "secret"}"#).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"main": "secret"}"#);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,656 @@
+//! 追更列表
+//! 保存关键词 + 规则 (可关联 Bangumi 条目)，检查时重新搜索 (含集数)，
+//! 按 (规则, 详情页链接) 与上次的集数快照比较，新出现的集数记为未读变化。
+//! 条目存于全局存储的 `watchlist` 命名空间，快照与变化记录存于 `watchlist_state`，
+//! 每个条目的状态整体写入一条记录 (不会出现快照与变化记录不一致)。
+//! 设置 WATCHLIST_CHECK_INTERVAL_MINUTES 时由后台任务定期检查 (见 [`check_due`])，
+//! 新集数通过 Webhook 通知；连续失败的条目按指数退避推迟下次检查。
+
+use crate::config::CONFIG;
+use crate::engine::{dedup_key, search_uncached};
+use crate::rule_stats;
+use crate::rules::get_builtin_rules;
+use crate::shutdown;
+use crate::storage::{self, ns};
+use crate::types::{PlatformSearchResult, Rule, SearchOptions};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 快照中每个条目最多保留的条目数 (按搜索结果顺序)
+pub const MAX_SNAPSHOT_ITEMS: usize = 60;
+
+/// 快照中每个结果最多保留的集数链接 (保留最新的)
+pub const MAX_SNAPSHOT_EPISODES: usize = 500;
+
+/// 每个条目最多保留的变化记录 (超出时丢弃最早的)
+pub const MAX_CHANGES: usize = 200;
+
+/// 定期检查时同时检查的条目数
+const CHECK_CONCURRENCY: usize = 2;
+
+/// 同一站点两次检查请求的最小间隔
+const HOST_SPACING: Duration = Duration::from_secs(2);
+
+/// 退避倍数上限 (2^4 = 16 倍检查间隔)
+const MAX_BACKOFF_EXPONENT: u32 = 4;
+
+/// 追更条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchEntry {
+    pub id: String,
+    pub keyword: String,
+    /// 搜索的规则名
+    pub rules: Vec<String>,
+    /// 关联的 Bangumi 条目
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_id: Option<i64>,
+    pub created_at: String,
+}
+
+/// 新增追更的请求体
+#[derive(Debug, Deserialize)]
+pub struct NewWatchEntry {
+    pub keyword: String,
+    pub rules: Vec<String>,
+    #[serde(default)]
+    pub subject_id: Option<i64>,
+}
+
+/// 快照中的单个结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotItem {
+    pub rule: String,
+    pub name: String,
+    pub url: String,
+    /// 所有播放源的集数 (按链接去重，保持顺序)
+    pub episodes: Vec<SnapshotEpisode>,
+}
+
+/// 快照中的集数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEpisode {
+    pub name: String,
+    pub url: String,
+}
+
+/// 新出现的集数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpisodeChange {
+    pub rule: String,
+    /// 结果名称与详情页链接
+    pub name: String,
+    pub url: String,
+    pub episode_name: String,
+    pub episode_url: String,
+    pub detected_at: String,
+    #[serde(default)]
+    pub seen: bool,
+}
+
+/// 条目的检查状态 (快照 + 变化记录)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchState {
+    /// (规则, 规范化详情页链接) -> 结果
+    #[serde(default)]
+    pub snapshot: BTreeMap<String, SnapshotItem>,
+    #[serde(default)]
+    pub changes: Vec<EpisodeChange>,
+    #[serde(default)]
+    pub status: CheckStatus,
+}
+
+/// 条目的检查状态
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheckStatus {
+    /// `pending` (未检查) / `ok` / `error` / `backoff` (连续失败，推迟检查) / `skipped` (规则均已停用)
+    pub state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_checked_at: Option<String>,
+    /// 最近一次检查的错误 (部分规则失败时为这些规则的错误)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    /// 定期检查的下次时间 (未启用定期检查时为空)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_check_at: Option<String>,
+}
+
+/// 站点 -> 下一个可用的请求时间 (定期检查与手动检查共用)
+static HOST_SLOTS: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 状态的读-改-写在锁内完成 (检查与标记已读并发时不丢失更新)
+static STATE_LOCK: Mutex<()> = Mutex::new(());
+
+fn state_lock() -> std::sync::MutexGuard<'static, ()> {
+    STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 由关键词与规则生成的稳定 id (规则顺序与大小写不影响)
+fn entry_id(keyword: &str, rules: &[String]) -> String {
+    let mut rules: Vec<String> = rules.iter().map(|r| r.to_lowercase()).collect();
+    rules.sort();
+    let digest =
+        Sha256::digest(format!("{}\n{}", keyword.to_lowercase(), rules.join(",")).as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 快照键: 规则名 + 规范化的详情页链接
+fn snapshot_key(rule: &str, url: &str) -> String {
+    format!("{}\n{}", rule, dedup_key(url))
+}
+
+/// 新增追更 (同一关键词与规则只保留一条)，返回条目与是否新建
+pub fn add(new: NewWatchEntry) -> Result<(WatchEntry, bool), String> {
+    let keyword = new.keyword.trim();
+    if keyword.is_empty() {
+        return Err("'keyword' is required".to_string());
+    }
+    let known = get_builtin_rules();
+    let mut rules: Vec<String> = Vec::new();
+    for name in new.rules.iter().map(|r| r.trim()).filter(|r| !r.is_empty()) {
+        let Some(rule) = known.iter().find(|r| r.name.eq_ignore_ascii_case(name)) else {
+            return Err(format!("Unknown rule: {}", name));
+        };
+        if !rules.contains(&rule.name) {
+            rules.push(rule.name.clone());
+        }
+    }
+    if rules.is_empty() {
+        return Err("'rules' must not be empty".to_string());
+    }
+
+    let id = entry_id(keyword, &rules);
+    let existing: Option<WatchEntry> = storage::get_json(ns::WATCHLIST, &id);
+    let created = existing.is_none();
+    let entry = WatchEntry {
+        id: id.clone(),
+        keyword: keyword.to_string(),
+        rules,
+        subject_id: new.subject_id,
+        created_at: existing
+            .map(|e| e.created_at)
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+    };
+    storage::set_json(ns::WATCHLIST, &id, &entry, None);
+    Ok((entry, created))
+}
+
+/// 所有追更条目 (最新的在前)
+pub fn list() -> Vec<WatchEntry> {
+    let mut entries: Vec<WatchEntry> = storage::store()
+        .list(ns::WATCHLIST)
+        .into_iter()
+        .filter_map(|(_, value)| serde_json::from_str(&value).ok())
+        .collect();
+    entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    entries
+}
+
+pub fn get(id: &str) -> Option<WatchEntry> {
+    storage::get_json(ns::WATCHLIST, id)
+}
+
+/// 未读变化数
+pub fn unseen_count(id: &str) -> usize {
+    let state: WatchState = storage::get_json(ns::WATCHLIST_STATE, id).unwrap_or_default();
+    state.changes.iter().filter(|c| !c.seen).count()
+}
+
+/// 检查状态 (从未检查时为 `pending`)
+pub fn status(id: &str) -> CheckStatus {
+    let state: WatchState = storage::get_json(ns::WATCHLIST_STATE, id).unwrap_or_default();
+    let mut status = state.status;
+    if status.state.is_empty() {
+        status.state = "pending".to_string();
+    }
+    status
+}
+
+/// 删除追更 (连同快照)，返回是否存在
+pub fn remove(id: &str) -> bool {
+    let _guard = state_lock();
+    storage::store().delete(ns::WATCHLIST_STATE, id);
+    storage::store().delete(ns::WATCHLIST, id)
+}
+
+/// 返回未读变化并标记为已读，条目不存在时为 None
+pub fn take_unseen(id: &str) -> Option<Vec<EpisodeChange>> {
+    get(id)?;
+    let _guard = state_lock();
+    let mut state: WatchState = storage::get_json(ns::WATCHLIST_STATE, id).unwrap_or_default();
+    let unseen: Vec<EpisodeChange> = state.changes.iter().filter(|c| !c.seen).cloned().collect();
+    if !unseen.is_empty() {
+        state.changes.iter_mut().for_each(|c| c.seen = true);
+        storage::set_json(ns::WATCHLIST_STATE, id, &state, None);
+    }
+    Some(unseen)
+}
+
+/// 检查条目: 重新搜索并记录新出现的集数，返回本次的变化
+/// 跳过已自动停用的规则；规则全部停用或全部失败时返回错误 (快照不变)
+pub async fn check(entry: &WatchEntry) -> Result<Vec<EpisodeChange>, String> {
+    let rules: Vec<Arc<Rule>> = get_builtin_rules()
+        .into_iter()
+        .filter(|r| entry.rules.contains(&r.name) && !rule_stats::is_auto_disabled(&r.name))
+        .collect();
+    if rules.is_empty() {
+        let reason = "All rules of this entry are disabled".to_string();
+        let (id, error) = (entry.id.clone(), reason.clone());
+        storage::blocking(move || {
+            update_status(&id, |status| {
+                status.state = "skipped".to_string();
+                status.last_error = Some(error);
+                status.next_check_at = next_check_at(0);
+            })
+        })
+        .await;
+        return Err(reason);
+    }
+    check_with_rules(entry, &rules).await
+}
+
+/// 用给定规则检查条目 (规则搜索失败时保留其上次的快照)
+/// 快照、变化记录与检查状态在同一次写入中更新，停机时不会留下只更新了一部分的状态；
+/// 不读取结果缓存，否则有效期内的新集数会被缓存的旧结果掩盖
+async fn check_with_rules(
+    entry: &WatchEntry,
+    rules: &[Arc<Rule>],
+) -> Result<Vec<EpisodeChange>, String> {
+    let options = SearchOptions::default();
+    let results: Vec<(String, PlatformSearchResult)> =
+        futures::future::join_all(rules.iter().map(|rule| {
+            let options = &options;
+            async move {
+                if let Some(host) = rule_host(rule) {
+                    wait_for_host(&host).await;
+                }
+                let result = search_uncached(rule, &entry.keyword, options).await;
+                (rule.name.clone(), result)
+            }
+        }))
+        .await;
+
+    let id = entry.id.clone();
+    storage::blocking(move || record_results(&id, &results)).await
+}
+
+/// 在锁内合并本次检查的结果，写入快照、变化记录与检查状态
+fn record_results(
+    id: &str,
+    results: &[(String, PlatformSearchResult)],
+) -> Result<Vec<EpisodeChange>, String> {
+    let errors: Vec<String> = results
+        .iter()
+        .filter_map(|(rule, result)| Some(format!("{}: {}", rule, result.error.as_ref()?)))
+        .collect();
+    let failed = !results.is_empty() && errors.len() == results.len();
+
+    let _guard = state_lock();
+    let mut state: WatchState = storage::get_json(ns::WATCHLIST_STATE, id).unwrap_or_default();
+    let now = Utc::now().to_rfc3339();
+    let snapshot = merge_snapshot(&state.snapshot, results);
+    let changes = diff(&state.snapshot, &snapshot, &now);
+    state.snapshot = snapshot;
+    state.changes.extend(changes.iter().cloned());
+    let overflow = state.changes.len().saturating_sub(MAX_CHANGES);
+    state.changes.drain(..overflow);
+
+    let status = &mut state.status;
+    status.last_checked_at = Some(now);
+    status.last_error = (!errors.is_empty()).then(|| errors.join("; "));
+    if failed {
+        status.consecutive_failures += 1;
+        status.state = if status.consecutive_failures > 1 {
+            "backoff"
+        } else {
+            "error"
+        }
+        .to_string();
+    } else {
+        status.consecutive_failures = 0;
+        status.state = "ok".to_string();
+    }
+    status.next_check_at = next_check_at(status.consecutive_failures);
+    let result = match &status.last_error {
+        Some(error) if failed => Err(error.clone()),
+        _ => Ok(changes),
+    };
+
+    // 条目可能在检查期间被删除
+    if get(id).is_some() {
+        storage::set_json(ns::WATCHLIST_STATE, id, &state, None);
+    }
+    result
+}
+
+/// 在锁内只更新检查状态
+fn update_status(id: &str, update: impl FnOnce(&mut CheckStatus)) {
+    let _guard = state_lock();
+    let mut state: WatchState = storage::get_json(ns::WATCHLIST_STATE, id).unwrap_or_default();
+    update(&mut state.status);
+    if get(id).is_some() {
+        storage::set_json(ns::WATCHLIST_STATE, id, &state, None);
+    }
+}
+
+/// 定期检查的间隔 (未启用时为 None)
+fn check_interval() -> Option<Duration> {
+    let minutes = CONFIG.watchlist_check_interval_minutes;
+    (minutes > 0).then(|| Duration::from_secs(minutes * 60))
+}
+
+/// 连续失败 n 次后的检查间隔: 间隔 × 2^(n-1)，最多 16 倍
+fn backoff_delay(interval: Duration, failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(MAX_BACKOFF_EXPONENT);
+    interval * 2u32.pow(exponent)
+}
+
+fn next_check_at(failures: u32) -> Option<String> {
+    let delay = backoff_delay(check_interval()?, failures);
+    let next = Utc::now() + chrono::Duration::from_std(delay).ok()?;
+    Some(next.to_rfc3339())
+}
+
+/// 是否到了定期检查的时间 (从未检查或已过下次检查时间)
+fn is_due(status: &CheckStatus, now: DateTime<Utc>) -> bool {
+    status
+        .next_check_at
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .is_none_or(|next| next <= now)
+}
+
+/// 规则请求的站点
+fn rule_host(rule: &Rule) -> Option<String> {
+    let url = if rule.search_url.is_empty() {
+        &rule.base_url
+    } else {
+        &rule.search_url
+    };
+    url::Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(str::to_ascii_lowercase)
+}
+
+/// 同一站点的请求至少间隔 HOST_SPACING (预约时间片，先到先得)
+async fn wait_for_host(host: &str) {
+    let slot = {
+        let mut slots = HOST_SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let slot = slots.get(host).copied().filter(|t| *t > now).unwrap_or(now);
+        slots.insert(host.to_string(), slot + HOST_SPACING);
+        slots.retain(|_, t| *t > now);
+        slot
+    };
+    tokio::time::sleep_until(slot).await;
+}
+
+/// 定期检查: 检查所有到期的条目 (有限并发)，新集数通过 Webhook 通知，返回检查的条目数
+/// 停机排空开始后不再开始新的检查，进行中的检查计入排空等待
+pub async fn check_due() -> usize {
+    let now = Utc::now();
+    let due: Vec<WatchEntry> = storage::blocking(move || {
+        list()
+            .into_iter()
+            .filter(|entry| is_due(&status(&entry.id), now))
+            .collect()
+    })
+    .await;
+    let checked = std::sync::atomic::AtomicUsize::new(0);
+    futures::stream::iter(due)
+        .for_each_concurrent(CHECK_CONCURRENCY, |entry| {
+            let checked = &checked;
+            async move {
+                if shutdown::is_draining() {
+                    return;
+                }
+                let _guard = shutdown::SearchGuard::acquire();
+                checked.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                match check(&entry).await {
+                    Ok(changes) if !changes.is_empty() => {
+                        super::webhook::watchlist_new_episodes(&entry, &changes);
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!("追更检查失败 ({}): {}", entry.keyword, e),
+                }
+            }
+        })
+        .await;
+    checked.into_inner()
+}
+
+/// 由搜索结果生成新快照: 成功的规则使用本次结果，失败的规则沿用上次的条目
+fn merge_snapshot(
+    previous: &BTreeMap<String, SnapshotItem>,
+    results: &[(String, PlatformSearchResult)],
+) -> BTreeMap<String, SnapshotItem> {
+    let mut snapshot = BTreeMap::new();
+    for (rule, result) in results {
+        if result.error.is_some() {
+            snapshot.extend(
+                previous
+                    .iter()
+                    .filter(|(_, item)| item.rule == *rule)
+                    .map(|(key, item)| (key.clone(), item.clone())),
+            );
+            continue;
+        }
+        for item in result.items.iter().take(MAX_SNAPSHOT_ITEMS) {
+            let mut seen = HashSet::new();
+            let mut episodes: Vec<SnapshotEpisode> = item
+                .episodes
+                .iter()
+                .flatten()
+                .flat_map(|road| &road.episodes)
+                .filter(|ep| seen.insert(ep.url.clone()))
+                .map(|ep| SnapshotEpisode {
+                    name: ep.name.clone(),
+                    url: ep.url.clone(),
+                })
+                .collect();
+            let overflow = episodes.len().saturating_sub(MAX_SNAPSHOT_EPISODES);
+            episodes.drain(..overflow);
+            snapshot.insert(
+                snapshot_key(rule, &item.url),
+                SnapshotItem {
+                    rule: rule.clone(),
+                    name: item.name.clone(),
+                    url: item.url.clone(),
+                    episodes,
+                },
+            );
+        }
+    }
+    snapshot
+}
+
+/// 比较快照: 上次已有的结果中新出现的集数链接 (首次出现的结果作为基准，不报告)
+fn diff(
+    previous: &BTreeMap<String, SnapshotItem>,
+    current: &BTreeMap<String, SnapshotItem>,
+    now: &str,
+) -> Vec<EpisodeChange> {
+    let mut changes = Vec::new();
+    for (key, item) in current {
+        let Some(old) = previous.get(key) else {
+            continue;
+        };
+        let known: HashSet<&str> = old.episodes.iter().map(|ep| ep.url.as_str()).collect();
+        changes.extend(
+            item.episodes
+                .iter()
+                .filter(|ep| !known.contains(ep.url.as_str()))
+                .map(|ep| EpisodeChange {
+                    rule: item.rule.clone(),
+                    name: item.name.clone(),
+                    url: item.url.clone(),
+                    episode_name: ep.name.clone(),
+                    episode_url: ep.url.clone(),
+                    detected_at: now.to_string(),
+                    seen: false,
+                }),
+        );
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Episode, EpisodeRoad, SearchResultItem};
+
+    fn mock_rule(episodes: usize) -> Arc<Rule> {
+        let url = "https://mock.invalid/detail/1";
+        let episodes = (1..=episodes)
+            .map(|n| Episode {
+                name: format!("第{}集", n),
+                url: format!("https://mock.invalid/play/1-{}", n),
+                display_name: None,
+            })
+            .collect();
+        Arc::new(Rule {
+            name: "追更模拟".to_string(),
+            mock_results: Some(vec![SearchResultItem {
+                name: "葬送的芙莉莲".to_string(),
+                alt_name: None,
+                url: url.to_string(),
+                tags: None,
+                latest: None,
+                cover: None,
+                episodes: Some(vec![EpisodeRoad::new(url, 0, None, episodes)]),
+                raw_html: None,
+                source_id: None,
+                rule: String::new(),
+                rule_color: None,
+            }]),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_check_records_new_episodes_once() {
+        let entry = WatchEntry {
+            id: "watch-test".to_string(),
+            keyword: "芙莉莲".to_string(),
+            rules: vec!["追更模拟".to_string()],
+            subject_id: None,
+            created_at: String::new(),
+        };
+        storage::set_json(ns::WATCHLIST, &entry.id, &entry, None);
+
+        // 首次检查只建立基准
+        assert!(check_with_rules(&entry, &[mock_rule(2)]).await.unwrap().is_empty());
+        let changes = check_with_rules(&entry, &[mock_rule(4)]).await.unwrap();
+        let urls: Vec<_> = changes.iter().map(|c| c.episode_url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://mock.invalid/play/1-3",
+                "https://mock.invalid/play/1-4"
+            ]
+        );
+        assert_eq!(changes[0].name, "葬送的芙莉莲");
+        assert_eq!(changes[0].episode_name, "第3集");
+        assert!(check_with_rules(&entry, &[mock_rule(4)]).await.unwrap().is_empty());
+        assert_eq!(status(&entry.id).state, "ok");
+
+        assert_eq!(unseen_count(&entry.id), 2);
+        assert_eq!(take_unseen(&entry.id).unwrap().len(), 2);
+        assert!(take_unseen(&entry.id).unwrap().is_empty());
+        assert!(remove(&entry.id));
+        assert!(take_unseen(&entry.id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_repeated_failures_back_off() {
+        let entry = WatchEntry {
+            id: "watch-backoff-test".to_string(),
+            keyword: "芙莉莲".to_string(),
+            rules: vec!["追更失败".to_string()],
+            subject_id: None,
+            created_at: String::new(),
+        };
+        storage::set_json(ns::WATCHLIST, &entry.id, &entry, None);
+        assert_eq!(status(&entry.id).state, "pending");
+
+        // 无效的搜索地址: 请求失败
+        let failing = Arc::new(Rule {
+            name: "追更失败".to_string(),
+            search_url: "not a url @keyword".to_string(),
+            ..Default::default()
+        });
+        assert!(check_with_rules(&entry, std::slice::from_ref(&failing)).await.is_err());
+        let first = status(&entry.id);
+        assert_eq!((first.state.as_str(), first.consecutive_failures), ("error", 1));
+        assert!(first.last_checked_at.is_some() && first.last_error.is_some());
+        assert!(check_with_rules(&entry, &[failing]).await.is_err());
+        let second = status(&entry.id);
+        assert_eq!((second.state.as_str(), second.consecutive_failures), ("backoff", 2));
+
+        let ok = check_with_rules(&entry, &[mock_rule(1)]).await;
+        assert!(ok.is_ok());
+        let recovered = status(&entry.id);
+        assert_eq!((recovered.state.as_str(), recovered.consecutive_failures), ("ok", 0));
+        assert!(recovered.last_error.is_none());
+        assert!(remove(&entry.id));
+
+        let interval = Duration::from_secs(600);
+        assert_eq!(backoff_delay(interval, 0), interval);
+        assert_eq!(backoff_delay(interval, 1), interval);
+        assert_eq!(backoff_delay(interval, 3), interval * 4);
+        assert_eq!(backoff_delay(interval, 50), interval * 16);
+
+        let now = Utc::now();
+        let later = CheckStatus {
+            next_check_at: Some((now + chrono::Duration::minutes(5)).to_rfc3339()),
+            ..Default::default()
+        };
+        assert!(!is_due(&later, now));
+        assert!(is_due(&later, now + chrono::Duration::minutes(6)));
+        assert!(is_due(&CheckStatus::default(), now));
+    }
+
+    #[test]
+    fn test_snapshot_keys_are_stable_and_bounded() {
+        assert_eq!(
+            snapshot_key("AGE", "https://Age.example/detail/1/#play"),
+            snapshot_key("AGE", "https://age.example/detail/1")
+        );
+        assert_eq!(
+            entry_id("芙莉莲", &["NT".to_string(), "AGE".to_string()]),
+            entry_id("芙莉莲", &["age".to_string(), "nt".to_string()])
+        );
+
+        let result = PlatformSearchResult::with_items(
+            mock_rule(MAX_SNAPSHOT_EPISODES + 20)
+                .mock_results
+                .clone()
+                .unwrap(),
+        );
+        let snapshot = merge_snapshot(&BTreeMap::new(), &[("AGE".to_string(), result)]);
+        let episodes = &snapshot.values().next().unwrap().episodes;
+        assert_eq!(episodes.len(), MAX_SNAPSHOT_EPISODES);
+        assert!(episodes
+            .last()
+            .unwrap()
+            .url
+            .ends_with(&format!("1-{}", MAX_SNAPSHOT_EPISODES + 20)));
+
+        // 失败的规则沿用上次的快照
+        let failed = PlatformSearchResult {
+            error: Some("timeout".to_string()),
+            ..Default::default()
+        };
+        let kept = merge_snapshot(&snapshot, &[("AGE".to_string(), failed)]);
+        assert_eq!(kept, snapshot);
+    }
+}
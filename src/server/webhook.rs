@@ -0,0 +1,409 @@
+//! Webhook 通知
+//! 设置 WEBHOOK_URL 后，在规则更新有变动或失败时、规则滚动失败率升至 WEBHOOK_FAILURE_RATE 时、
+//! 规则被自动停用或重新启用时、以及定期检查发现追更条目的新集数时发送 JSON 通知。
+//! 消息格式按 WEBHOOK_FORMAT 适配 Slack (`text`)、Discord (`content`) 或通用 JSON；
+//! 设置 WEBHOOK_SECRET 时以 `X-Webhook-Signature: sha256=<hex>` 附带请求体的 HMAC-SHA256 签名。
+//! 发送在后台进行 (失败重试一次)，不阻塞更新与搜索。
+//!
+//! 另可设置 UPDATE_WEBHOOK_URL 单独接收规则更新结果: 每次更新完成后 (含无变动) POST `UpdateResult`，
+//! 或按 UPDATE_WEBHOOK_TEMPLATE 生成请求体 (见 [`render_update_template`])；只发送一次，失败仅记录日志。
+
+use super::watchlist::{EpisodeChange, WatchEntry};
+use crate::config::CONFIG;
+use crate::http_client::HTTP_CLIENT;
+use crate::rule_stats::{self, RuleHealth, RuleTransition};
+use crate::updater::UpdateResult;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 单次发送超时
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 规则更新结果通知的超时
+const UPDATE_HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 重试前的等待时间
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Discord 消息长度上限
+const DISCORD_MAX_CHARS: usize = 2000;
+
+/// 消息中最多列出的规则名
+const MAX_LISTED_NAMES: usize = 10;
+
+/// 消息格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WebhookFormat {
+    Slack,
+    Discord,
+    Generic,
+}
+
+impl WebhookFormat {
+    fn parse(value: &str) -> Self {
+        match value {
+            "slack" => WebhookFormat::Slack,
+            "discord" => WebhookFormat::Discord,
+            _ => WebhookFormat::Generic,
+        }
+    }
+}
+
+/// 启用时注册规则失败率告警
+pub fn init() {
+    if CONFIG.webhook_url.is_none() {
+        return;
+    }
+    let threshold = CONFIG.webhook_failure_rate as f64 / 100.0;
+    rule_stats::set_failure_listener(threshold, rule_failing);
+    info!(
+        "🔔 Webhook 通知已启用 ({}，失败率告警阈值 {}%)",
+        CONFIG.webhook_format, CONFIG.webhook_failure_rate
+    );
+}
+
+/// 规则更新完成 (无变动时不通知)
+pub fn rules_updated(result: &UpdateResult) {
+    if result.details.iter().all(|d| d.action == "unchanged") {
+        return;
+    }
+    send("rules_updated", update_summary(result), json!(result));
+}
+
+/// 规则更新完成: 发送到 UPDATE_WEBHOOK_URL (每次更新都发送，不重试)
+pub fn update_finished(result: &UpdateResult) {
+    let Some(url) = CONFIG.update_webhook_url.clone() else {
+        return;
+    };
+    let payload = match CONFIG.update_webhook_template.as_deref() {
+        Some(template) => match serde_json::from_str::<Value>(template) {
+            Ok(template) => render_update_template(&template, result),
+            Err(e) => {
+                warn!(
+                    "UPDATE_WEBHOOK_TEMPLATE 不是有效的 JSON，发送原始结果: {}",
+                    e
+                );
+                json!(result)
+            }
+        },
+        None => json!(result),
+    };
+    let body = match serde_json::to_vec(&payload) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("规则更新通知序列化失败: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        let delivered = deliver(
+            &url,
+            "rules_update_result",
+            &body,
+            None,
+            UPDATE_HOOK_TIMEOUT,
+        )
+        .await;
+        if let Err(e) = delivered {
+            warn!("规则更新通知发送失败: {}", e);
+        }
+    });
+}
+
+/// 按模板生成规则更新通知: 字符串中的 `{{summary}}`、`{{total}}`、`{{added}}`、`{{updated}}`、
+/// `{{failed}}` 替换为对应的值，恰好为 `"{{result}}"` 的字符串替换为完整的 UpdateResult 对象
+fn render_update_template(template: &Value, result: &UpdateResult) -> Value {
+    match template {
+        Value::String(s) if s == "{{result}}" => json!(result),
+        Value::String(s) => Value::String(
+            s.replace("{{summary}}", &update_summary(result))
+                .replace("{{total}}", &result.total.to_string())
+                .replace("{{added}}", &result.added.to_string())
+                .replace("{{updated}}", &result.updated.to_string())
+                .replace("{{failed}}", &result.failed.to_string()),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| render_update_template(v, result))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), render_update_template(v, result)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// 规则失败率告警
+fn rule_failing(health: RuleHealth) {
+    let mut text = format!(
+        "⚠️ 规则 {} 最近 {} 次搜索失败 {} 次 ({:.0}%)",
+        health.rule,
+        health.attempts,
+        health.failures,
+        health.failure_rate * 100.0
+    );
+    if let Some(error) = &health.last_error {
+        text.push_str(&format!("，最近错误: {}", error));
+    }
+    send("rule_failing", text, json!(health));
+}
+
+/// 规则被自动停用或重新启用
+pub fn rule_transition(transition: &RuleTransition) {
+    let text = match transition.action {
+        "auto_disabled" => format!(
+            "⛔ 规则 {} 已自动停用: {}",
+            transition.rule, transition.reason
+        ),
+        _ => format!(
+            "✅ 规则 {} 已重新启用: {}",
+            transition.rule, transition.reason
+        ),
+    };
+    send("rule_transition", text, json!(transition));
+}
+
+/// 定期检查发现追更条目的新集数
+pub fn watchlist_new_episodes(entry: &WatchEntry, changes: &[EpisodeChange]) {
+    let mut episodes: Vec<String> = changes
+        .iter()
+        .take(MAX_LISTED_NAMES)
+        .map(|c| format!("{} {} ({})", c.name, c.episode_name, c.rule))
+        .collect();
+    if changes.len() > MAX_LISTED_NAMES {
+        episodes.push(format!("等 {} 集", changes.len()));
+    }
+    let text = format!(
+        "📺 追更 {} 有新集数: {}",
+        entry.keyword,
+        episodes.join("，")
+    );
+    send(
+        "watchlist_new_episodes",
+        text,
+        json!({"entry": entry, "changes": changes}),
+    );
+}
+
+/// 更新结果摘要，如 "🔄 规则更新: 新增 1 (AGE)，更新 3，失败 1 (NT: 下载失败: timeout)"
+fn update_summary(result: &UpdateResult) -> String {
+    let names = |action: &str, with_message: bool| {
+        let matched: Vec<String> = result
+            .details
+            .iter()
+            .filter(|d| d.action == action)
+            .map(|d| {
+                if with_message {
+                    format!("{}: {}", d.name, d.message)
+                } else {
+                    d.name.clone()
+                }
+            })
+            .collect();
+        match matched.len() {
+            0 => String::new(),
+            n if n > MAX_LISTED_NAMES => {
+                format!(" ({} 等 {} 个)", matched[..MAX_LISTED_NAMES].join(", "), n)
+            }
+            _ => format!(" ({})", matched.join(", ")),
+        }
+    };
+    // 获取 commit/文件列表失败时 failed 计数为 0，按明细统计
+    let failed = result
+        .details
+        .iter()
+        .filter(|d| d.action == "failed")
+        .count();
+    format!(
+        "🔄 规则更新: 新增 {}{}，更新 {}，失败 {}{}",
+        result.added,
+        names("added", false),
+        result.updated,
+        failed,
+        names("failed", true)
+    )
+}
+
+/// 按格式生成请求体
+fn payload(format: WebhookFormat, event: &str, text: &str, data: Value) -> Value {
+    match format {
+        WebhookFormat::Slack => json!({ "text": text }),
+        WebhookFormat::Discord => {
+            json!({ "content": text.chars().take(DISCORD_MAX_CHARS).collect::<String>() })
+        }
+        WebhookFormat::Generic => json!({
+            "event": event,
+            "text": text,
+            "data": data,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        }),
+    }
+}
+
+/// HMAC-SHA256 (RFC 2104)
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+fn signature(secret: &str, body: &[u8]) -> String {
+    let mac = hmac_sha256(secret.as_bytes(), body);
+    let hex: String = mac.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+/// 后台发送 (失败时重试一次)
+fn send(event: &'static str, text: String, data: Value) {
+    let Some(url) = CONFIG.webhook_url.clone() else {
+        return;
+    };
+    let format = WebhookFormat::parse(&CONFIG.webhook_format);
+    let body = match serde_json::to_vec(&payload(format, event, &text, data)) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Webhook 消息序列化失败: {}", e);
+            return;
+        }
+    };
+    let signature = CONFIG
+        .webhook_secret
+        .as_deref()
+        .map(|s| signature(s, &body));
+
+    tokio::spawn(async move {
+        for attempt in 1..=2 {
+            match deliver(&url, event, &body, signature.as_deref(), SEND_TIMEOUT).await {
+                Ok(()) => return,
+                Err(e) if attempt == 1 => {
+                    warn!(
+                        "Webhook 发送失败 ({})，{} 秒后重试: {}",
+                        event,
+                        RETRY_DELAY.as_secs(),
+                        e
+                    );
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+                Err(e) => warn!("Webhook 发送失败 ({})，已放弃: {}", event, e),
+            }
+        }
+    });
+}
+
+async fn deliver(
+    url: &str,
+    event: &str,
+    body: &[u8],
+    signature: Option<&str>,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let mut request = HTTP_CLIENT
+        .post(url)
+        .timeout(timeout)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Webhook-Event", event)
+        .body(body.to_vec());
+    if let Some(signature) = signature {
+        request = request.header("X-Webhook-Signature", signature);
+    }
+    let status = request.send().await?.status();
+    if !status.is_success() {
+        anyhow::bail!("HTTP {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::updater::UpdateDetail;
+
+    #[test]
+    fn test_hmac_sha256_matches_rfc4231() {
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        let hex: String = mac.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            hex,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert!(signature("Jefe", b"{}").starts_with("sha256="));
+    }
+
+    #[test]
+    fn test_update_summary_and_payload_formats() {
+        let detail = |name: &str, action: &str, message: &str| UpdateDetail {
+            name: name.to_string(),
+            action: action.to_string(),
+            message: message.to_string(),
+            old_version: None,
+            new_version: None,
+        };
+        let result = UpdateResult {
+            total: 3,
+            updated: 1,
+            added: 1,
+            failed: 1,
+            details: vec![
+                detail("AGE", "added", "ok"),
+                detail("MX", "updated", "ok"),
+                detail("NT", "failed", "下载失败: timeout"),
+            ],
+        };
+        let text = update_summary(&result);
+        assert_eq!(
+            text,
+            "🔄 规则更新: 新增 1 (AGE)，更新 1，失败 1 (NT: 下载失败: timeout)"
+        );
+
+        let data = json!(result);
+        assert_eq!(
+            payload(WebhookFormat::Slack, "rules_updated", &text, data.clone()),
+            json!({ "text": text })
+        );
+        assert_eq!(
+            payload(
+                WebhookFormat::Discord,
+                "rules_updated",
+                &"x".repeat(3000),
+                data.clone()
+            )["content"]
+                .as_str()
+                .unwrap()
+                .len(),
+            DISCORD_MAX_CHARS
+        );
+        let generic = payload(WebhookFormat::Generic, "rules_updated", &text, data);
+        assert_eq!(generic["event"], "rules_updated");
+        assert_eq!(generic["data"]["details"][2]["name"], "NT");
+
+        let template = json!({
+            "content": "{{summary}}",
+            "embeds": [{"title": "{{added}} added / {{failed}} failed of {{total}}"}],
+            "result": "{{result}}",
+            "silent": true
+        });
+        let rendered = render_update_template(&template, &result);
+        assert_eq!(rendered["content"], text);
+        assert_eq!(rendered["embeds"][0]["title"], "1 added / 1 failed of 3");
+        assert_eq!(rendered["result"]["details"][0]["name"], "AGE");
+        assert_eq!(rendered["silent"], true);
+    }
+}
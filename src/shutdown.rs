@@ -0,0 +1,149 @@
+//! 优雅停机
+//! SIGTERM / Ctrl+C / `POST /admin/shutdown` 走同一条路径：
+//! 进入排空状态 (新搜索返回 503)，等待进行中的搜索结束或排空超时，再停止服务；
+//! 排空时间到达后仍未关闭的连接 (如卡住的流式响应) 直接断开
+
+use crate::config::CONFIG;
+use once_cell::sync::Lazy;
+use std::future::IntoFuture;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{watch, Notify};
+use tokio::time::Instant;
+use tracing::info;
+
+/// 是否处于排空状态
+static DRAINING: AtomicBool = AtomicBool::new(false);
+
+/// 进行中的搜索数
+static ACTIVE_SEARCHES: AtomicUsize = AtomicUsize::new(0);
+
+/// 远程触发停机时指定的排空时间 (秒)
+static REQUESTED_DRAIN: AtomicU64 = AtomicU64::new(0);
+
+/// 远程触发信号
+static TRIGGER: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// 排空截止时间 (收到停机信号后设置)
+static DEADLINE: Lazy<watch::Sender<Option<Instant>>> = Lazy::new(|| watch::Sender::new(None));
+
+/// 进行中搜索的计数守卫 (drop 时计数减一)
+pub struct SearchGuard;
+
+impl SearchGuard {
+    pub fn acquire() -> Self {
+        ACTIVE_SEARCHES.fetch_add(1, Ordering::SeqCst);
+        SearchGuard
+    }
+}
+
+impl Drop for SearchGuard {
+    fn drop(&mut self) {
+        ACTIVE_SEARCHES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 是否正在排空 (拒绝新搜索)
+pub fn is_draining() -> bool {
+    DRAINING.load(Ordering::SeqCst)
+}
+
+/// 远程触发停机 (`drain_seconds` 缺省时使用 SHUTDOWN_DRAIN_SECONDS)
+pub fn trigger(drain_seconds: Option<u64>) {
+    REQUESTED_DRAIN.store(
+        drain_seconds.unwrap_or(CONFIG.shutdown_drain_seconds),
+        Ordering::SeqCst,
+    );
+    TRIGGER.notify_one();
+}
+
+/// 等待停机信号并完成排空，用于 `axum::serve(..).with_graceful_shutdown(..)`
+pub async fn wait_for_shutdown() {
+    let drain_seconds = tokio::select! {
+        _ = ctrl_c() => {
+            info!("🛑 收到 Ctrl+C");
+            CONFIG.shutdown_drain_seconds
+        }
+        _ = terminate() => {
+            info!("🛑 收到 SIGTERM");
+            CONFIG.shutdown_drain_seconds
+        }
+        _ = TRIGGER.notified() => {
+            info!("🛑 收到远程停机请求");
+            REQUESTED_DRAIN.load(Ordering::SeqCst)
+        }
+    };
+
+    DRAINING.store(true, Ordering::SeqCst);
+    info!("⏳ 排空中: 拒绝新搜索，最多等待 {}s", drain_seconds);
+
+    let deadline = Instant::now() + Duration::from_secs(drain_seconds);
+    DEADLINE.send_replace(Some(deadline));
+    while ACTIVE_SEARCHES.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    let remaining = ACTIVE_SEARCHES.load(Ordering::SeqCst);
+    if remaining > 0 {
+        info!("⌛ 排空超时，仍有 {} 个搜索进行中", remaining);
+    }
+    info!("👋 停止服务");
+}
+
+/// 运行服务直到结束；收到停机信号后最多运行到排空截止时间
+///
+/// axum 的优雅停机会无限等待所有连接关闭，卡住的流式响应会让进程一直不退出，
+/// 因此截止时间到达后直接放弃服务 future (进程随后退出，剩余连接断开)
+pub async fn serve_until_drained<F>(server: F) -> std::io::Result<()>
+where
+    F: IntoFuture<Output = std::io::Result<()>>,
+{
+    let server = server.into_future();
+    tokio::pin!(server);
+    tokio::select! {
+        result = &mut server => result,
+        deadline = drain_deadline() => {
+            match tokio::time::timeout_at(deadline, server).await {
+                Ok(result) => result,
+                Err(_) => {
+                    info!("⌛ 排空超时，断开剩余连接");
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/// 等待收到停机信号，返回排空截止时间
+async fn drain_deadline() -> Instant {
+    let mut deadline = DEADLINE.subscribe();
+    loop {
+        if let Some(deadline) = *deadline.borrow_and_update() {
+            return deadline;
+        }
+        if deadline.changed().await.is_err() {
+            // 发送端是静态变量，不会关闭
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+async fn ctrl_c() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(unix)]
+async fn terminate() {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+        Ok(mut sig) => {
+            sig.recv().await;
+        }
+        Err(_) => std::future::pending::<()>().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn terminate() {
+    std::future::pending::<()>().await
+}
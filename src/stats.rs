@@ -0,0 +1,390 @@
+//! 使用统计
+//! 进程内计数: 启动以来的搜索次数、各规则的搜索次数、热门关键词、Bangumi 代理调用与进行中的流式响应，
+//! 供 `GET /stats` 在不部署 Prometheus 时查看。
+//! 热门关键词用 Space-Saving 算法统计: 最多跟踪 [`KEYWORD_SLOTS`] 个关键词，
+//! 已满时新关键词替换计数最小的一个并继承其计数 (内存有界，靠前的排名基本准确)；
+//! STATS_KEYWORDS=0 时不记录关键词。
+//! 当天的汇总定期写入全局存储的 `stats_daily` 命名空间 (按 UTC 日期)，重启后当天的计数从存储继续累加。
+
+use crate::cache;
+use crate::config::CONFIG;
+use crate::limiter;
+use crate::storage::{self, ns};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 热门关键词最多跟踪的关键词数
+pub const KEYWORD_SLOTS: usize = 200;
+
+/// 返回的热门关键词数
+pub const TOP_KEYWORDS: usize = 20;
+
+/// 当天汇总写入存储的间隔
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// `days` 的上限
+pub const MAX_DAYS: u32 = 90;
+
+/// 启动时间
+static STARTED: Lazy<(Instant, DateTime<Utc>)> = Lazy::new(|| (Instant::now(), Utc::now()));
+
+/// 进行中的流式响应
+static ACTIVE_STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+static STATE: Lazy<Mutex<State>> = Lazy::new(|| Mutex::new(State::default()));
+
+/// 关键词搜索次数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeywordCount {
+    pub keyword: String,
+    pub searches: u64,
+}
+
+/// 有界的热门关键词计数 (Space-Saving)
+#[derive(Debug, Default)]
+struct TopKeywords {
+    counts: HashMap<String, u64>,
+}
+
+impl TopKeywords {
+    fn add(&mut self, keyword: &str, n: u64) {
+        if let Some(count) = self.counts.get_mut(keyword) {
+            *count += n;
+            return;
+        }
+        let base = if self.counts.len() < KEYWORD_SLOTS {
+            0
+        } else {
+            // 替换计数最小的关键词 (新关键词继承其计数)
+            let Some((min_keyword, min)) = self
+                .counts
+                .iter()
+                .min_by_key(|(_, count)| **count)
+                .map(|(k, c)| (k.clone(), *c))
+            else {
+                return;
+            };
+            self.counts.remove(&min_keyword);
+            min
+        };
+        self.counts.insert(keyword.to_string(), base + n);
+    }
+
+    fn top(&self, n: usize) -> Vec<KeywordCount> {
+        let mut top: Vec<KeywordCount> = self
+            .counts
+            .iter()
+            .map(|(keyword, searches)| KeywordCount {
+                keyword: keyword.clone(),
+                searches: *searches,
+            })
+            .collect();
+        top.sort_by(|a, b| {
+            b.searches
+                .cmp(&a.searches)
+                .then_with(|| a.keyword.cmp(&b.keyword))
+        });
+        top.truncate(n);
+        top
+    }
+}
+
+/// 单日汇总
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailyStats {
+    /// UTC 日期 (YYYY-MM-DD)
+    pub date: String,
+    pub searches: u64,
+    /// 规则名 -> 搜索次数
+    #[serde(default)]
+    pub rules: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub top_keywords: Vec<KeywordCount>,
+    #[serde(default)]
+    pub bangumi_proxy_calls: u64,
+    #[serde(default)]
+    pub bangumi_proxy_errors: u64,
+}
+
+/// 当天的计数 (关键词单独计数，写入时取前 TOP_KEYWORDS 个)
+#[derive(Debug, Default)]
+struct Day {
+    stats: DailyStats,
+    keywords: TopKeywords,
+}
+
+impl Day {
+    /// 从存储加载当天已有的汇总 (重启后继续累加)
+    fn load(date: String) -> Self {
+        let stats: DailyStats = storage::get_json(ns::STATS_DAILY, &date).unwrap_or_default();
+        let mut keywords = TopKeywords::default();
+        for k in &stats.top_keywords {
+            keywords.add(&k.keyword, k.searches);
+        }
+        Day {
+            stats: DailyStats { date, ..stats },
+            keywords,
+        }
+    }
+
+    fn rollup(&self) -> DailyStats {
+        DailyStats {
+            top_keywords: self.keywords.top(TOP_KEYWORDS),
+            ..self.stats.clone()
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    searches: u64,
+    rules: BTreeMap<String, u64>,
+    keywords: TopKeywords,
+    bangumi_proxy_calls: u64,
+    bangumi_proxy_errors: u64,
+    day: Option<Day>,
+}
+
+impl State {
+    /// 当天的计数 (日期变化时先写入前一天的汇总)
+    fn today(&mut self) -> &mut Day {
+        let date = today();
+        if self.day.as_ref().is_some_and(|d| d.stats.date != date) {
+            if let Some(previous) = self.day.take() {
+                storage::save_json(
+                    ns::STATS_DAILY,
+                    previous.stats.date.clone(),
+                    &previous.rollup(),
+                    None,
+                );
+            }
+        }
+        self.day.get_or_insert_with(|| Day::load(date))
+    }
+}
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// 关键词归一化: 小写、合并空白
+fn normalize_keyword(keyword: &str) -> String {
+    keyword
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// 记录启动时间
+pub fn init() {
+    Lazy::force(&STARTED);
+}
+
+/// 记录一次完成的搜索及其使用的规则
+pub fn record_search<'a>(keyword: &str, rules: impl IntoIterator<Item = &'a str>) {
+    let keyword = CONFIG.stats_keywords.then(|| normalize_keyword(keyword));
+    let rules: Vec<&str> = rules.into_iter().collect();
+    let mut state = state();
+    state.searches += 1;
+    for rule in &rules {
+        *state.rules.entry(rule.to_string()).or_default() += 1;
+    }
+    if let Some(keyword) = keyword.as_deref().filter(|k| !k.is_empty()) {
+        state.keywords.add(keyword, 1);
+    }
+    let day = state.today();
+    day.stats.searches += 1;
+    for rule in &rules {
+        *day.stats.rules.entry(rule.to_string()).or_default() += 1;
+    }
+    if let Some(keyword) = keyword.as_deref().filter(|k| !k.is_empty()) {
+        day.keywords.add(keyword, 1);
+    }
+}
+
+/// 记录一次 Bangumi 代理调用
+pub fn record_bangumi_proxy(success: bool) {
+    let mut state = state();
+    state.bangumi_proxy_calls += 1;
+    state.bangumi_proxy_errors += u64::from(!success);
+    let day = state.today();
+    day.stats.bangumi_proxy_calls += 1;
+    day.stats.bangumi_proxy_errors += u64::from(!success);
+}
+
+/// 进行中流式响应的计数守卫 (随响应体 drop，客户端断开时同样减一)
+pub struct StreamGuard;
+
+impl StreamGuard {
+    pub fn acquire() -> Self {
+        ACTIVE_STREAMS.fetch_add(1, Ordering::SeqCst);
+        StreamGuard
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        ACTIVE_STREAMS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 将当天的汇总写入存储
+pub fn flush() {
+    let mut state = state();
+    let rollup = state.today().rollup();
+    storage::set_json(ns::STATS_DAILY, &rollup.date, &rollup, None);
+}
+
+/// 缓存命中率
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheHitRate {
+    pub name: &'static str,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+}
+
+/// Bangumi 代理调用次数
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyCalls {
+    pub calls: u64,
+    pub errors: u64,
+}
+
+/// `GET /stats` 的响应
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsReport {
+    pub started_at: String,
+    pub uptime_seconds: u64,
+    /// 启动以来完成的搜索数
+    pub searches: u64,
+    /// 规则名 -> 启动以来的搜索次数
+    pub rules: BTreeMap<String, u64>,
+    /// 是否记录关键词 (STATS_KEYWORDS)
+    pub keyword_tracking: bool,
+    pub top_keywords: Vec<KeywordCount>,
+    pub bangumi_proxy: ProxyCalls,
+    pub caches: Vec<CacheHitRate>,
+    /// 占用搜索槽位的搜索数
+    pub active_searches: usize,
+    /// 进行中的流式响应数
+    pub active_streams: usize,
+    /// 最近几天的汇总 (最新的在前，含当天)，请求带 `days` 时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<Vec<DailyStats>>,
+}
+
+/// 当前统计，`days` 为 Some 时附带最近几天的汇总 (最多 MAX_DAYS 天)
+pub fn report(days: Option<u32>) -> StatsReport {
+    let (started, started_at) = *STARTED;
+    let mut state = state();
+    let history = days.map(|days| {
+        let today = state.today().rollup();
+        let now = Utc::now();
+        let mut history = vec![today];
+        for offset in 1..days.clamp(1, MAX_DAYS) {
+            let date = (now - chrono::Duration::days(offset as i64))
+                .format("%Y-%m-%d")
+                .to_string();
+            history.push(
+                storage::get_json(ns::STATS_DAILY, &date).unwrap_or(DailyStats {
+                    date,
+                    ..Default::default()
+                }),
+            );
+        }
+        history
+    });
+    StatsReport {
+        started_at: started_at.to_rfc3339(),
+        uptime_seconds: started.elapsed().as_secs(),
+        searches: state.searches,
+        rules: state.rules.clone(),
+        keyword_tracking: CONFIG.stats_keywords,
+        top_keywords: state.keywords.top(TOP_KEYWORDS),
+        bangumi_proxy: ProxyCalls {
+            calls: state.bangumi_proxy_calls,
+            errors: state.bangumi_proxy_errors,
+        },
+        caches: cache::reports()
+            .into_iter()
+            .map(|r| CacheHitRate {
+                name: r.name,
+                hits: r.hits,
+                misses: r.misses,
+                hit_rate: r.hit_rate,
+            })
+            .collect(),
+        active_searches: limiter::active_searches(),
+        active_streams: ACTIVE_STREAMS.load(Ordering::SeqCst),
+        history,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_keywords_stay_bounded() {
+        let mut top = TopKeywords::default();
+        for _ in 0..50 {
+            top.add("芙莉莲", 1);
+        }
+        for _ in 0..30 {
+            top.add("迷宫饭", 1);
+        }
+        // 大量只出现一次的关键词不会挤掉热门关键词
+        for n in 0..KEYWORD_SLOTS * 5 {
+            top.add(&format!("keyword-{}", n), 1);
+        }
+        assert!(top.counts.len() <= KEYWORD_SLOTS);
+        let ranked = top.top(2);
+        assert_eq!(ranked[0].keyword, "芙莉莲");
+        assert_eq!(ranked[1].keyword, "迷宫饭");
+        assert_eq!(
+            normalize_keyword("  Frieren   Beyond\tJourney "),
+            "frieren beyond journey"
+        );
+    }
+
+    #[test]
+    fn test_search_counts_roll_up_per_day() {
+        let before = report(Some(3));
+        record_search("统计  测试", ["统计规则A", "统计规则B"]);
+        record_search("统计 测试", ["统计规则A"]);
+        record_bangumi_proxy(false);
+        let _stream = StreamGuard::acquire();
+
+        let after = report(Some(3));
+        // 其他测试可能同时搜索，全局计数只检查下限
+        assert!(after.searches >= before.searches + 2);
+        assert_eq!(after.rules["统计规则A"], 2);
+        assert_eq!(after.rules["统计规则B"], 1);
+        assert!(after.top_keywords.iter().any(|k| k.keyword == "统计 测试"));
+        assert!(after.bangumi_proxy.errors > before.bangumi_proxy.errors);
+        assert!(after.active_streams >= 1);
+
+        let history = after.history.unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].date, today());
+        assert_eq!(history[0].rules["统计规则A"], 2);
+        assert!(history[1].date < history[0].date);
+
+        // 写入存储后可按日期读回
+        flush();
+        let stored: DailyStats = storage::get_json(ns::STATS_DAILY, &today()).unwrap();
+        assert!(stored.searches >= 2);
+        assert!(stored.top_keywords.iter().any(|k| k.keyword == "统计 测试"));
+    }
+}
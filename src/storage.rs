@@ -0,0 +1,751 @@
+//! 存储层
+//! 按命名空间组织的键值存储 (值为 JSON 文本，可设置过期时间)，供缓存与持久化数据使用。
+//! 默认使用内存存储 (重启即丢失)；启用 `sqlite` feature 并设置 DATABASE_PATH 后使用 SQLite 持久化；
+//! 启用 `redis` feature 并设置 REDIS_URL 后使用 Redis (多实例共享，优先于 SQLite)。
+//! SQLite / Redis 为同步 I/O，异步上下文中通过 [`blocking`] / [`spawn_write`] 在阻塞线程池执行。
+
+use crate::config::CONFIG;
+use once_cell::sync::OnceCell;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{error, warn};
+
+/// 命名空间
+pub mod ns {
+    /// Bangumi 条目详情缓存
+    pub const BANGUMI_SUBJECT: &str = "bangumi_subject";
+    /// 收藏的搜索结果
+    pub const FAVORITES: &str = "favorites";
+    /// 规则变更记录
+    pub const RULE_CHANGELOG: &str = "rule_changelog";
+    /// 追更列表
+    pub const WATCHLIST: &str = "watchlist";
+    /// 追更条目的集数快照与新集记录
+    pub const WATCHLIST_STATE: &str = "watchlist_state";
+    /// 使用统计的每日汇总
+    pub const STATS_DAILY: &str = "stats_daily";
+    /// 搜索结果缓存 (持久化存储时与进程内缓存共用有效期)
+    pub const SEARCH_RESULT: &str = "search_result";
+    /// 规则健康统计与自动停用状态
+    pub const RULE_STATS: &str = "rule_stats";
+    /// 搜索历史计数 (未设置 HISTORY_DB 时)
+    pub const SEARCH_HISTORY: &str = "search_history";
+}
+
+/// 键值存储
+///
+/// 过期的条目对 `get` / `list` 不可见，由实现自行清理
+pub trait Store: Send + Sync {
+    /// 存储后端名称 (用于日志)
+    fn backend(&self) -> &'static str;
+    fn get(&self, ns: &str, key: &str) -> Option<String>;
+    fn set(&self, ns: &str, key: &str, value: &str, ttl: Option<Duration>);
+    /// 删除条目，返回是否存在
+    fn delete(&self, ns: &str, key: &str) -> bool;
+    /// 命名空间下的所有条目 (按键排序)
+    fn list(&self, ns: &str) -> Vec<(String, String)>;
+    fn clear(&self, ns: &str);
+    /// 计数器加一并返回新值 (固定窗口: 首次计数时设置 `window` 过期时间)，后端不可用时返回 `None`
+    fn incr(&self, ns: &str, key: &str, window: Duration) -> Option<u64>;
+}
+
+/// 读取并反序列化 (无法解析的旧数据视为不存在)
+pub fn get_json<T: DeserializeOwned>(ns: &str, key: &str) -> Option<T> {
+    let value = store().get(ns, key)?;
+    serde_json::from_str(&value).ok()
+}
+
+/// 序列化并写入
+pub fn set_json<T: Serialize>(ns: &str, key: &str, value: &T, ttl: Option<Duration>) {
+    match serde_json::to_string(value) {
+        Ok(value) => store().set(ns, key, &value, ttl),
+        Err(e) => warn!("序列化存储条目失败 {}/{}: {}", ns, key, e),
+    }
+}
+
+/// 异步上下文中读取并反序列化 (见 [`blocking`])
+pub async fn load_json<T: DeserializeOwned + Send + 'static>(ns: &'static str, key: String) -> Option<T> {
+    blocking(move || get_json(ns, &key)).await
+}
+
+/// 异步上下文中序列化并在后台写入 (见 [`spawn_write`])
+pub fn save_json<T: Serialize>(ns: &'static str, key: String, value: &T, ttl: Option<Duration>) {
+    match serde_json::to_string(value) {
+        Ok(value) => spawn_write(move |store| store.set(ns, &key, &value, ttl)),
+        Err(e) => warn!("序列化存储条目失败 {}/{}: {}", ns, key, e),
+    }
+}
+
+static STORE: OnceCell<Box<dyn Store>> = OnceCell::new();
+
+/// 过期条目的清理间隔 (毫秒)，写入时最多每隔该时间清理一次
+const SWEEP_INTERVAL_MS: i64 = 60_000;
+
+/// 全局存储 (未调用 [`init`] 时按配置打开，失败则回退到内存存储)
+pub fn store() -> &'static dyn Store {
+    STORE
+        .get_or_init(|| {
+            open_configured().unwrap_or_else(|e| {
+                error!("打开存储失败，使用内存存储: {}", e);
+                Box::new(MemoryStore::default())
+            })
+        })
+        .as_ref()
+}
+
+/// 按配置打开存储并执行迁移，返回后端名称 (服务启动时调用，失败时不回退)
+pub fn init() -> anyhow::Result<&'static str> {
+    let store = STORE.get_or_try_init(open_configured)?;
+    Ok(store.backend())
+}
+
+/// 存储是否在重启后保留 (SQLite / Redis)
+pub fn is_persistent() -> bool {
+    store().backend() != "memory"
+}
+
+/// 在阻塞线程池中执行存储操作并等待结果 (内存存储直接执行)
+pub async fn blocking<T, F>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    if !is_persistent() {
+        return f();
+    }
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => value,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+/// 在阻塞线程池中执行写入，不等待完成 (内存存储或不在 tokio 运行时中时直接执行)
+pub fn spawn_write(f: impl FnOnce(&dyn Store) + Send + 'static) {
+    let store = store();
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if is_persistent() => {
+            handle.spawn_blocking(move || f(store));
+        }
+        _ => f(store),
+    }
+}
+
+/// 到达清理间隔时返回 true 并记录本次清理时间
+fn sweep_due(last_sweep: &AtomicI64, now: i64) -> bool {
+    let last = last_sweep.load(Ordering::Relaxed);
+    now - last >= SWEEP_INTERVAL_MS
+        && last_sweep
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+}
+
+fn open_configured() -> anyhow::Result<Box<dyn Store>> {
+    if let Some(url) = CONFIG.redis_url.as_deref() {
+        #[cfg(feature = "redis")]
+        return Ok(Box::new(redis::RedisStore::open(url)?));
+        #[cfg(not(feature = "redis"))]
+        {
+            let _ = url;
+            warn!("已设置 REDIS_URL，但未启用 redis feature，忽略");
+        }
+    }
+    match CONFIG.database_path.as_deref() {
+        #[cfg(feature = "sqlite")]
+        Some(path) => Ok(Box::new(sqlite::SqliteStore::open(path)?)),
+        #[cfg(not(feature = "sqlite"))]
+        Some(_) => {
+            warn!("已设置 DATABASE_PATH，但未启用 sqlite feature，使用内存存储");
+            Ok(Box::new(MemoryStore::default()))
+        }
+        None => Ok(Box::new(MemoryStore::default())),
+    }
+}
+
+/// 当前时间 (毫秒)
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// 过期时间点 (毫秒)
+fn expires_at(ttl: Option<Duration>) -> Option<i64> {
+    ttl.map(|ttl| now_millis() + ttl.as_millis() as i64)
+}
+
+/// (命名空间, 键) -> (值, 过期时间)
+type Entries = HashMap<(String, String), (String, Option<i64>)>;
+
+/// 内存存储
+///
+/// 读取时移除遇到的过期条目，写入时每隔 [`SWEEP_INTERVAL_MS`] 清理一次全部过期条目
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: Mutex<Entries>,
+    last_sweep: AtomicI64,
+}
+
+impl MemoryStore {
+    fn entries(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn is_live(expires_at: Option<i64>, now: i64) -> bool {
+    expires_at.is_none_or(|at| at > now)
+}
+
+impl Store for MemoryStore {
+    fn backend(&self) -> &'static str {
+        "memory"
+    }
+
+    fn get(&self, ns: &str, key: &str) -> Option<String> {
+        let mut entries = self.entries();
+        let key = (ns.to_string(), key.to_string());
+        let (value, expires_at) = entries.get(&key)?;
+        if is_live(*expires_at, now_millis()) {
+            return Some(value.clone());
+        }
+        entries.remove(&key);
+        None
+    }
+
+    fn set(&self, ns: &str, key: &str, value: &str, ttl: Option<Duration>) {
+        let mut entries = self.entries();
+        let now = now_millis();
+        if sweep_due(&self.last_sweep, now) {
+            entries.retain(|_, (_, expires_at)| is_live(*expires_at, now));
+        }
+        entries.insert(
+            (ns.to_string(), key.to_string()),
+            (value.to_string(), expires_at(ttl)),
+        );
+    }
+
+    fn delete(&self, ns: &str, key: &str) -> bool {
+        self.entries()
+            .remove(&(ns.to_string(), key.to_string()))
+            .is_some_and(|(_, expires_at)| is_live(expires_at, now_millis()))
+    }
+
+    fn list(&self, ns: &str) -> Vec<(String, String)> {
+        let now = now_millis();
+        let mut items: Vec<(String, String)> = self
+            .entries()
+            .iter()
+            .filter(|((n, _), (_, expires_at))| n == ns && is_live(*expires_at, now))
+            .map(|((_, key), (value, _))| (key.clone(), value.clone()))
+            .collect();
+        items.sort();
+        items
+    }
+
+    fn clear(&self, ns: &str) {
+        self.entries().retain(|(n, _), _| n != ns);
+    }
+
+    fn incr(&self, ns: &str, key: &str, window: Duration) -> Option<u64> {
+        let mut entries = self.entries();
+        let now = now_millis();
+        let key = (ns.to_string(), key.to_string());
+        // 窗口已过期 (或尚未计数) 时从 1 重新开始
+        let (count, expires) = match entries.get(&key) {
+            Some((value, expires)) if is_live(*expires, now) => {
+                (value.parse::<u64>().unwrap_or(0) + 1, *expires)
+            }
+            _ => (1, expires_at(Some(window))),
+        };
+        entries.insert(key, (count.to_string(), expires));
+        Some(count)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite {
+    //! SQLite 存储 (单连接，在调用线程上同步执行；异步上下文经 [`super::blocking`] 调用)
+
+    use super::{expires_at, now_millis, sweep_due, Store};
+    use rusqlite::{params, Connection, OptionalExtension};
+    use std::sync::atomic::AtomicI64;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tracing::{info, warn};
+
+    /// 迁移脚本，按顺序执行，版本号记录在 `PRAGMA user_version`
+    const MIGRATIONS: &[&str] = &["CREATE TABLE kv (
+            ns TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            expires_at INTEGER,
+            PRIMARY KEY (ns, key)
+        );
+        CREATE INDEX kv_expires_at ON kv (expires_at);"];
+
+    pub struct SqliteStore {
+        conn: Mutex<Connection>,
+        last_sweep: AtomicI64,
+    }
+
+    impl SqliteStore {
+        /// 打开数据库文件 (不存在时创建) 并执行迁移
+        pub fn open(path: &str) -> anyhow::Result<Self> {
+            if let Some(dir) = std::path::Path::new(path).parent() {
+                if !dir.as_os_str().is_empty() {
+                    std::fs::create_dir_all(dir)?;
+                }
+            }
+            let store = Self::from_connection(Connection::open(path)?)?;
+            info!("🗄️ 使用 SQLite 存储: {}", path);
+            Ok(store)
+        }
+
+        /// 内存数据库 (测试用)
+        pub fn open_in_memory() -> anyhow::Result<Self> {
+            Self::from_connection(Connection::open_in_memory()?)
+        }
+
+        fn from_connection(mut conn: Connection) -> anyhow::Result<Self> {
+            conn.pragma_update(None, "journal_mode", "WAL")?;
+            migrate(&mut conn)?;
+            Ok(Self {
+                conn: Mutex::new(conn),
+                last_sweep: AtomicI64::new(0),
+            })
+        }
+
+        fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+            self.conn.lock().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+
+    fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+        let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(sql)?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+            tx.commit()?;
+            info!("🗄️ 数据库迁移到版本 {}", i + 1);
+        }
+        Ok(())
+    }
+
+    impl Store for SqliteStore {
+        fn backend(&self) -> &'static str {
+            "sqlite"
+        }
+
+        fn get(&self, ns: &str, key: &str) -> Option<String> {
+            self.conn()
+                .query_row(
+                    "SELECT value FROM kv WHERE ns = ?1 AND key = ?2
+                     AND (expires_at IS NULL OR expires_at > ?3)",
+                    params![ns, key, now_millis()],
+                    |row| row.get(0),
+                )
+                .optional()
+                .unwrap_or_else(|e| {
+                    warn!("SQLite 读取失败 {}/{}: {}", ns, key, e);
+                    None
+                })
+        }
+
+        fn set(&self, ns: &str, key: &str, value: &str, ttl: Option<Duration>) {
+            let conn = self.conn();
+            let now = now_millis();
+            // 每隔一段时间顺带清理过期条目 (读取时已按过期时间过滤)
+            let sweep = if sweep_due(&self.last_sweep, now) {
+                conn.execute(
+                    "DELETE FROM kv WHERE expires_at IS NOT NULL AND expires_at <= ?1",
+                    params![now],
+                )
+            } else {
+                Ok(0)
+            };
+            let result = sweep.and_then(|_| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO kv (ns, key, value, expires_at) VALUES (?1, ?2, ?3, ?4)",
+                    params![ns, key, value, expires_at(ttl)],
+                )
+            });
+            if let Err(e) = result {
+                warn!("SQLite 写入失败 {}/{}: {}", ns, key, e);
+            }
+        }
+
+        fn delete(&self, ns: &str, key: &str) -> bool {
+            self.conn()
+                .execute(
+                    "DELETE FROM kv WHERE ns = ?1 AND key = ?2
+                     AND (expires_at IS NULL OR expires_at > ?3)",
+                    params![ns, key, now_millis()],
+                )
+                .map(|n| n > 0)
+                .unwrap_or_else(|e| {
+                    warn!("SQLite 删除失败 {}/{}: {}", ns, key, e);
+                    false
+                })
+        }
+
+        fn list(&self, ns: &str) -> Vec<(String, String)> {
+            let conn = self.conn();
+            let result = conn
+                .prepare(
+                    "SELECT key, value FROM kv WHERE ns = ?1
+                     AND (expires_at IS NULL OR expires_at > ?2) ORDER BY key",
+                )
+                .and_then(|mut stmt| {
+                    stmt.query_map(params![ns, now_millis()], |row| {
+                        Ok((row.get(0)?, row.get(1)?))
+                    })?
+                    .collect()
+                });
+            result.unwrap_or_else(|e| {
+                warn!("SQLite 查询失败 {}: {}", ns, e);
+                Vec::new()
+            })
+        }
+
+        fn clear(&self, ns: &str) {
+            if let Err(e) = self
+                .conn()
+                .execute("DELETE FROM kv WHERE ns = ?1", params![ns])
+            {
+                warn!("SQLite 清空失败 {}: {}", ns, e);
+            }
+        }
+
+        fn incr(&self, ns: &str, key: &str, window: Duration) -> Option<u64> {
+            let conn = self.conn();
+            let result = conn
+                .execute(
+                    "DELETE FROM kv WHERE ns = ?1 AND key = ?2 AND expires_at <= ?3",
+                    params![ns, key, now_millis()],
+                )
+                .and_then(|_| {
+                    conn.query_row(
+                        "INSERT INTO kv (ns, key, value, expires_at) VALUES (?1, ?2, '1', ?3)
+                         ON CONFLICT (ns, key) DO UPDATE SET value = CAST(value AS INTEGER) + 1
+                         RETURNING value",
+                        params![ns, key, expires_at(Some(window))],
+                        |row| row.get::<_, String>(0),
+                    )
+                });
+            match result {
+                Ok(value) => value.parse().ok(),
+                Err(e) => {
+                    warn!("SQLite 计数失败 {}/{}: {}", ns, key, e);
+                    None
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+pub mod redis {
+    //! Redis 存储 (多实例共享缓存与计数)
+    //!
+    //! 键为 `anime-search:{命名空间}:{键}`，过期交给 Redis 处理。Redis 不可用时所有操作降级为
+    //! 未命中 / 不写入 (不影响请求)，错误日志每分钟最多一条，断线后每 5 秒重连一次。
+
+    use super::Store;
+    use redis::{Client, Commands, Connection, RedisResult};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+    use tracing::{info, warn};
+
+    const KEY_PREFIX: &str = "anime-search";
+    /// 连接与读写超时 (Redis 慢时宁可当作未命中)
+    const IO_TIMEOUT: Duration = Duration::from_millis(500);
+    /// 连接失败后的重连间隔
+    const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+    /// 错误日志间隔
+    const LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// 固定窗口计数: INCR 后首次计数时设置过期 (原子执行)
+    const INCR_SCRIPT: &str = "local n = redis.call('INCR', KEYS[1]) \
+        if n == 1 then redis.call('PEXPIRE', KEYS[1], ARGV[1]) end \
+        return n";
+
+    #[derive(Default)]
+    struct State {
+        conn: Option<Connection>,
+        /// 上次连接失败时间 (重连间隔内直接跳过)
+        failed_at: Option<Instant>,
+        /// 上次输出错误日志的时间
+        logged_at: Option<Instant>,
+    }
+
+    pub struct RedisStore {
+        client: Client,
+        state: Mutex<State>,
+    }
+
+    impl RedisStore {
+        /// 解析连接地址 (不立即连接，Redis 暂时不可用不影响启动)
+        pub fn open(url: &str) -> anyhow::Result<Self> {
+            let client = Client::open(url)?;
+            info!("🗄️ 使用 Redis 存储");
+            Ok(Self {
+                client,
+                state: Mutex::new(State::default()),
+            })
+        }
+
+        /// 在连接上执行命令，失败时返回 `None` 并丢弃连接
+        fn with_conn<T>(
+            &self,
+            op: &str,
+            f: impl FnOnce(&mut Connection) -> RedisResult<T>,
+        ) -> Option<T> {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if state.conn.is_none() {
+                if state
+                    .failed_at
+                    .is_some_and(|at| at.elapsed() < RECONNECT_INTERVAL)
+                {
+                    return None;
+                }
+                match self.connect() {
+                    Ok(conn) => {
+                        if state.failed_at.take().is_some() {
+                            info!("Redis 已恢复连接");
+                        }
+                        state.conn = Some(conn);
+                    }
+                    Err(e) => {
+                        state.failed_at = Some(Instant::now());
+                        Self::log_error(&mut state, op, &e);
+                        return None;
+                    }
+                }
+            }
+            let result = f(state.conn.as_mut()?);
+            match result {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    if e.is_io_error() || e.is_connection_dropped() || e.is_timeout() {
+                        state.conn = None;
+                        state.failed_at = Some(Instant::now());
+                    }
+                    Self::log_error(&mut state, op, &e);
+                    None
+                }
+            }
+        }
+
+        fn connect(&self) -> RedisResult<Connection> {
+            let conn = self.client.get_connection_with_timeout(IO_TIMEOUT)?;
+            conn.set_read_timeout(Some(IO_TIMEOUT))?;
+            conn.set_write_timeout(Some(IO_TIMEOUT))?;
+            Ok(conn)
+        }
+
+        fn log_error(state: &mut State, op: &str, error: &redis::RedisError) {
+            if state
+                .logged_at
+                .is_none_or(|at| at.elapsed() >= LOG_INTERVAL)
+            {
+                state.logged_at = Some(Instant::now());
+                warn!("Redis {} 失败，按未命中处理: {}", op, error);
+            }
+        }
+
+        fn key(ns: &str, key: &str) -> String {
+            format!("{}:{}:{}", KEY_PREFIX, ns, key)
+        }
+
+        /// 命名空间下的所有键 (SCAN，不阻塞 Redis)
+        fn scan_keys(conn: &mut Connection, ns: &str) -> RedisResult<Vec<String>> {
+            let pattern = format!("{}:{}:*", KEY_PREFIX, ns);
+            let keys = conn
+                .scan_match::<_, String>(pattern)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(keys)
+        }
+    }
+
+    impl Store for RedisStore {
+        fn backend(&self) -> &'static str {
+            "redis"
+        }
+
+        fn get(&self, ns: &str, key: &str) -> Option<String> {
+            self.with_conn("读取", |conn| conn.get(Self::key(ns, key)))
+                .flatten()
+        }
+
+        fn set(&self, ns: &str, key: &str, value: &str, ttl: Option<Duration>) {
+            let key = Self::key(ns, key);
+            self.with_conn("写入", |conn| match ttl {
+                Some(ttl) => conn.pset_ex::<_, _, ()>(key, value, ttl.as_millis().max(1) as u64),
+                None => conn.set::<_, _, ()>(key, value),
+            });
+        }
+
+        fn delete(&self, ns: &str, key: &str) -> bool {
+            self.with_conn("删除", |conn| conn.del::<_, u64>(Self::key(ns, key)))
+                .is_some_and(|n| n > 0)
+        }
+
+        fn list(&self, ns: &str) -> Vec<(String, String)> {
+            let prefix = Self::key(ns, "");
+            self.with_conn("查询", |conn| {
+                let keys = Self::scan_keys(conn, ns)?;
+                if keys.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let values: Vec<Option<String>> = conn.mget(&keys)?;
+                let mut items: Vec<(String, String)> = keys
+                    .iter()
+                    .zip(values)
+                    .filter_map(|(k, v)| Some((k.strip_prefix(&prefix)?.to_string(), v?)))
+                    .collect();
+                items.sort();
+                Ok(items)
+            })
+            .unwrap_or_default()
+        }
+
+        fn clear(&self, ns: &str) {
+            self.with_conn("清空", |conn| {
+                let keys = Self::scan_keys(conn, ns)?;
+                for chunk in keys.chunks(500) {
+                    conn.del::<_, ()>(chunk)?;
+                }
+                Ok(())
+            });
+        }
+
+        fn incr(&self, ns: &str, key: &str, window: Duration) -> Option<u64> {
+            self.with_conn("计数", |conn| {
+                redis::cmd("EVAL")
+                    .arg(INCR_SCRIPT)
+                    .arg(1)
+                    .arg(Self::key(ns, key))
+                    .arg(window.as_millis().max(1) as u64)
+                    .query(conn)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 各后端共用的行为测试
+    fn exercise(store: &dyn Store) {
+        assert_eq!(store.get("a", "k"), None);
+
+        store.set("a", "k", "1", None);
+        store.set("a", "j", "2", None);
+        store.set("b", "k", "3", None);
+        assert_eq!(store.get("a", "k").as_deref(), Some("1"));
+        assert_eq!(
+            store.list("a"),
+            vec![
+                ("j".to_string(), "2".to_string()),
+                ("k".to_string(), "1".to_string())
+            ]
+        );
+
+        store.set("a", "k", "4", None);
+        assert_eq!(store.get("a", "k").as_deref(), Some("4"));
+
+        assert!(store.delete("a", "k"));
+        assert!(!store.delete("a", "k"));
+
+        store.set("a", "short", "5", Some(Duration::from_millis(30)));
+        assert_eq!(store.get("a", "short").as_deref(), Some("5"));
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(store.get("a", "short"), None);
+        assert!(!store.list("a").iter().any(|(k, _)| k == "short"));
+
+        store.clear("a");
+        assert!(store.list("a").is_empty());
+        assert_eq!(store.get("b", "k").as_deref(), Some("3"));
+
+        let window = Duration::from_millis(30);
+        assert_eq!(store.incr("c", "host", window), Some(1));
+        assert_eq!(store.incr("c", "host", window), Some(2));
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(store.incr("c", "host", window), Some(1));
+    }
+
+    #[test]
+    fn test_memory_store() {
+        exercise(&MemoryStore::default());
+    }
+
+    #[test]
+    fn test_memory_store_evicts_expired_entries() {
+        let store = MemoryStore::default();
+        store.set("a", "short", "1", Some(Duration::from_millis(10)));
+        store.set("a", "other", "2", Some(Duration::from_millis(10)));
+        std::thread::sleep(Duration::from_millis(30));
+
+        // 读取时移除过期条目
+        assert_eq!(store.get("a", "short"), None);
+        assert_eq!(store.entries().len(), 1);
+
+        // 写入在清理间隔内不扫描，到期后一并清理
+        store.set("a", "k", "3", None);
+        assert_eq!(store.entries().len(), 2);
+        store.last_sweep.store(0, Ordering::Relaxed);
+        store.set("a", "k", "4", None);
+        assert_eq!(store.entries().len(), 1);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store() {
+        exercise(&sqlite::SqliteStore::open_in_memory().unwrap());
+    }
+
+    /// 设置 REDIS_TEST_URL 时对真实 Redis 运行共用测试
+    #[cfg(feature = "redis")]
+    #[test]
+    fn test_redis_store() {
+        let Ok(url) = std::env::var("REDIS_TEST_URL") else {
+            return;
+        };
+        let store = redis::RedisStore::open(&url).unwrap();
+        for ns in ["a", "b", "c"] {
+            store.clear(ns);
+        }
+        exercise(&store);
+    }
+
+    #[cfg(feature = "redis")]
+    #[test]
+    fn test_redis_unavailable_degrades_to_miss() {
+        // 保留端口，连接会被拒绝
+        let store = redis::RedisStore::open("redis://127.0.0.1:1/").unwrap();
+        store.set("a", "k", "1", None);
+        assert_eq!(store.get("a", "k"), None);
+        assert!(!store.delete("a", "k"));
+        assert!(store.list("a").is_empty());
+        assert_eq!(store.incr("c", "host", Duration::from_secs(1)), None);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_migrations_are_idempotent() {
+        let path = std::env::temp_dir().join(format!("anime-search-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        {
+            let store = sqlite::SqliteStore::open(path).unwrap();
+            store.set("a", "k", "1", None);
+        }
+        let store = sqlite::SqliteStore::open(path).unwrap();
+        assert_eq!(store.get("a", "k").as_deref(), Some("1"));
+        drop(store);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+}
@@ -1,15 +1,22 @@
+#[cfg(feature = "scraper")]
+use crate::script::Script;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, LazyLock};
 
 /// Kazumi 风格的规则定义
-/// 完全兼容 Kazumi 规则格式: https://github.com/Predidit/KazumiRules
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 完全兼容 Kazumi 规则格式: <https://github.com/Predidit/KazumiRules>
+///
+/// JSON Schema 使用 Kazumi 风格的字段名 (规则文件中的写法)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Rule {
     /// API 版本
     #[serde(default = "default_api")]
     pub api: String,
 
-    /// 类型 (anime)
-    #[serde(rename = "type", default = "default_type")]
+    /// 类型 (anime；rss 表示 searchURL 返回 RSS 订阅，按条目解析，不需要选择器；
+    /// mock 表示按 `mock` 参数生成结果的模拟源，需 `ENABLE_MOCK_RULES=1` 才会加载)
+    #[serde(rename = "type", alias = "ruleType", default = "default_type")]
     pub rule_type: String,
 
     /// 平台名称
@@ -21,60 +28,79 @@ pub struct Rule {
 
     /// 是否支持多播放源
     #[serde(default, alias = "muliSources")]
+    #[schemars(rename = "muliSources")]
     pub muli_sources: bool,
 
     /// 是否使用 webview
     #[serde(default, alias = "useWebview")]
+    #[schemars(rename = "useWebview")]
     pub use_webview: bool,
 
     /// 是否使用原生播放器
     #[serde(default = "default_true", alias = "useNativePlayer")]
+    #[schemars(rename = "useNativePlayer")]
     pub use_native_player: bool,
 
     /// 是否使用 POST 请求
     #[serde(default, alias = "usePost")]
+    #[schemars(rename = "usePost")]
     pub use_post: bool,
 
     /// 是否使用旧版解析器
     #[serde(default, alias = "useLegacyParser")]
+    #[schemars(rename = "useLegacyParser")]
     pub use_legacy_parser: bool,
 
     /// 是否启用广告拦截
     #[serde(default, alias = "adBlocker")]
+    #[schemars(rename = "adBlocker")]
     pub ad_blocker: bool,
 
     /// 自定义 User-Agent
     #[serde(default, alias = "userAgent")]
+    #[schemars(rename = "userAgent")]
     pub user_agent: String,
 
     /// 基础 URL
     #[serde(alias = "baseURL")]
+    #[schemars(rename = "baseURL")]
     pub base_url: String,
 
     /// 搜索 URL (使用 @keyword 作为占位符)
     #[serde(alias = "searchURL")]
+    #[schemars(rename = "searchURL")]
     pub search_url: String,
 
     /// 搜索结果列表选择器 (CSS/XPath)
     #[serde(default, alias = "searchList")]
+    #[schemars(rename = "searchList")]
     pub search_list: String,
 
     /// 搜索结果名称选择器
     #[serde(default, alias = "searchName")]
+    #[schemars(rename = "searchName")]
     pub search_name: String,
 
     /// 搜索结果链接选择器
     #[serde(default, alias = "searchResult")]
+    #[schemars(rename = "searchResult")]
     pub search_result: String,
 
     /// 章节列表选择器
     #[serde(default, alias = "chapterRoads")]
+    #[schemars(rename = "chapterRoads")]
     pub chapter_roads: String,
 
     /// 章节结果选择器
     #[serde(default, alias = "chapterResult")]
+    #[schemars(rename = "chapterResult")]
     pub chapter_result: String,
 
+    /// 各播放源页面链接的 XPath (播放源需单独请求时配置，每个页面按章节选择器解析)
+    #[serde(default, alias = "chapterRoadLinks")]
+    #[schemars(rename = "chapterRoadLinks")]
+    pub chapter_road_links: String,
+
     /// Referer 头
     #[serde(default)]
     pub referer: String,
@@ -85,6 +111,15 @@ pub struct Rule {
     #[serde(default = "default_color")]
     pub color: String,
 
+    /// 颜色的色值 (规则集加载时由 [`assign_colors`] 分配，不出现在规则文件中)
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub color_hex: String,
+
+    /// 平台图标地址 (可为相对 baseURL 的路径，为空时使用站点的 /favicon.ico)
+    #[serde(default)]
+    pub icon: String,
+
     /// 平台标签 (如：在线, Magnet, BT 等)
     #[serde(default)]
     pub tags: Vec<String>,
@@ -92,6 +127,139 @@ pub struct Rule {
     /// 是否需要魔法
     #[serde(default)]
     pub magic: bool,
+
+    /// 搜索结果更新信息选择器 (如 "更新至第12集")
+    #[serde(default, alias = "searchUpdate")]
+    #[schemars(rename = "searchUpdate")]
+    pub search_update: String,
+
+    /// 搜索结果封面选择器 (匹配图片或其容器，以 `/@属性名` 结尾时优先取该属性)，
+    /// 依次尝试 data-src、data-original、srcset、src，取第一个像真实图片的地址
+    #[serde(default, alias = "searchCover")]
+    #[schemars(rename = "searchCover")]
+    pub search_cover: String,
+
+    /// 搜索结果别名选择器 (原名、外文名等副标题)，用于名称匹配并在界面中显示
+    #[serde(default, alias = "searchAltName")]
+    #[schemars(rename = "searchAltName")]
+    pub search_alt_name: String,
+
+    /// 章节选择器失效时，是否扫描详情页链接兜底解析章节
+    #[serde(default, alias = "episodeFallback")]
+    #[schemars(rename = "episodeFallback")]
+    pub episode_fallback: bool,
+
+    /// 兜底解析时章节链接 href 需匹配的正则 (为空时不限制)
+    #[serde(default, alias = "episodeHrefPattern")]
+    #[schemars(rename = "episodeHrefPattern")]
+    pub episode_href_pattern: String,
+
+    /// 搜索 token (CSRF/nonce) 选择器，设置后先请求 baseURL 提取 token 再搜索
+    /// (可用 `/@属性名` 结尾指定属性，默认依次取 value、content 属性和文本)
+    #[serde(default, alias = "tokenXpath", alias = "tokenXPath")]
+    #[schemars(rename = "tokenXpath")]
+    pub token_xpath: String,
+
+    /// 搜索 token 提交时的字段名 (为空时使用 "token")
+    #[serde(default, alias = "tokenField")]
+    #[schemars(rename = "tokenField")]
+    pub token_field: String,
+
+    /// 集数显示名模板 (`{n}` 为提取的集数，`{name}` 为原名，如 "第{n}集")
+    #[serde(default, alias = "episodeNameTemplate")]
+    #[schemars(rename = "episodeNameTemplate")]
+    pub episode_name_template: String,
+
+    /// 从集数名中提取集数的正则 (有捕获组时取第一个捕获组，默认取第一个数字)
+    #[serde(default, alias = "episodeNumberRegex")]
+    #[schemars(rename = "episodeNumberRegex")]
+    pub episode_number_regex: String,
+
+    /// 搜索结果页中「下一页」链接的选择器 (取 href，以 `/@属性名` 结尾时取该属性)
+    #[serde(default, alias = "searchNextPage")]
+    #[schemars(rename = "searchNextPage")]
+    pub search_next_page: String,
+
+    /// 合并链接相同的结果 (保留第一个，合并标签)，默认开启
+    #[serde(default = "default_true", alias = "dedupItems")]
+    #[schemars(rename = "dedupItems")]
+    pub dedup_items: bool,
+
+    /// 优先播放源的正则 (匹配播放源名称，匹配的播放源排在前面，其余保持原顺序，不丢弃)
+    #[serde(default, alias = "preferredRoadPattern")]
+    #[schemars(rename = "preferredRoadPattern")]
+    pub preferred_road_pattern: String,
+
+    /// 从结果链接中提取站点侧 ID 的正则 (有捕获组时取第一个捕获组)，用于填充 `source_id` 与去重
+    #[serde(default, alias = "idRegex")]
+    #[schemars(rename = "idRegex")]
+    pub id_regex: String,
+
+    /// searchURL 含 `@page` 时最多请求的页数 (默认 1，不翻页)
+    #[serde(default = "default_max_pages", alias = "maxPages")]
+    #[schemars(rename = "maxPages")]
+    pub max_pages: usize,
+
+    /// 该规则请求源站的超时秒数 (未设置或为 0 时使用全局 TIMEOUT_SECONDS)
+    #[serde(default, alias = "timeoutSecs")]
+    #[schemars(rename = "timeoutSecs")]
+    pub timeout_secs: Option<u64>,
+
+    /// 模拟结果 (测试与演示用): 设置后搜索直接返回这些条目，不发送任何请求
+    #[serde(default, alias = "mockResults")]
+    #[schemars(rename = "mockResults")]
+    pub mock_results: Option<Vec<SearchResultItem>>,
+
+    /// 模拟源参数 (`"type": "mock"` 时使用，缺省为 [`MockSource::default`])
+    #[serde(default)]
+    pub mock: Option<MockSource>,
+}
+
+/// 模拟源参数: 在引擎内按延迟、失败概率生成结果与集数，不发送任何请求 (用于测试流式搜索)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MockSource {
+    /// 每次搜索的固定延迟 (毫秒)
+    #[serde(default, alias = "latencyMs")]
+    #[schemars(rename = "latencyMs")]
+    pub latency_ms: u64,
+
+    /// 在固定延迟上随机增加 0 到该值的延迟 (毫秒)
+    #[serde(default, alias = "jitterMs")]
+    #[schemars(rename = "jitterMs")]
+    pub jitter_ms: u64,
+
+    /// 每次搜索返回的结果数
+    #[serde(default = "default_mock_items")]
+    pub items: usize,
+
+    /// 搜索失败的概率 (0 到 1)，失败时按源站返回 503 处理
+    #[serde(default, alias = "failureRate")]
+    #[schemars(rename = "failureRate")]
+    pub failure_rate: f64,
+
+    /// 每个结果的集数 (0 表示没有集数)
+    #[serde(default = "default_mock_episodes")]
+    pub episodes: usize,
+}
+
+fn default_mock_items() -> usize {
+    5
+}
+
+fn default_mock_episodes() -> usize {
+    12
+}
+
+impl Default for MockSource {
+    fn default() -> Self {
+        Self {
+            latency_ms: 0,
+            jitter_ms: 0,
+            items: default_mock_items(),
+            failure_rate: 0.0,
+            episodes: default_mock_episodes(),
+        }
+    }
 }
 
 fn default_api() -> String {
@@ -110,10 +278,107 @@ fn default_color() -> String {
     "white".to_string()
 }
 
+/// 前端已知的平台颜色名称及其色值
+const KNOWN_COLORS: &[(&str, &str)] = &[
+    ("red", "#f44336"),
+    ("pink", "#e91e63"),
+    ("purple", "#9c27b0"),
+    ("indigo", "#3f51b5"),
+    ("blue", "#2196f3"),
+    ("cyan", "#00bcd4"),
+    ("teal", "#009688"),
+    ("green", "#4caf50"),
+    ("lime", "#cddc39"),
+    ("yellow", "#ffeb3b"),
+    ("amber", "#ffc107"),
+    ("orange", "#ff9800"),
+    ("brown", "#795548"),
+    ("grey", "#9e9e9e"),
+    ("black", "#000000"),
+];
+
+/// 已知颜色名称的色值
+pub fn known_color_hex(color: &str) -> Option<&'static str> {
+    KNOWN_COLORS.iter().find(|(name, _)| *name == color).map(|(_, hex)| *hex)
+}
+
+/// 调色板的色相数
+const PALETTE_HUES: usize = 32;
+
+/// 调色板的 (饱和度, 亮度) 组合
+const PALETTE_SHADES: &[(f64, f64)] = &[(0.70, 0.45), (0.55, 0.32), (0.80, 0.62)];
+
+/// 未知颜色 (含默认的 white) 使用的调色板 (96 色)
+///
+/// 相邻位置的色相相隔 13/32 圈，顺延到下一个位置时颜色差异明显
+static COLOR_PALETTE: LazyLock<Vec<String>> = LazyLock::new(|| {
+    (0..PALETTE_HUES * PALETTE_SHADES.len())
+        .map(|slot| {
+            let hue = (slot * 13 % PALETTE_HUES) as f64 * 360.0 / PALETTE_HUES as f64;
+            let (saturation, lightness) = PALETTE_SHADES[slot / PALETTE_HUES];
+            hsl_to_hex(hue, saturation, lightness)
+        })
+        .collect()
+});
+
+/// HSL (色相 0~360，饱和度与亮度 0~1) 转为 `#rrggbb`
+fn hsl_to_hex(hue: f64, saturation: f64, lightness: f64) -> String {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |v: f64| ((v + m) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(r), channel(g), channel(b))
+}
+
+/// 为规则集中的规则分配颜色色值 (规则集加载时调用，规则按名称排序)
+///
+/// 已知颜色名称与 `#rrggbb` 直接换算；其他值 (含默认的 white) 从按规则名称散列的调色板位置开始，
+/// 顺延到第一个未被前面规则占用的颜色，规则数不超过调色板大小时同一规则集内颜色互不相同
+pub fn assign_colors(rules: &mut [Arc<Rule>]) {
+    let mut used = vec![false; COLOR_PALETTE.len()];
+    for rule in rules.iter_mut() {
+        let hex = match rule.fixed_color() {
+            Some(hex) => hex,
+            None => {
+                let start = rule.palette_slot();
+                let slot = (0..used.len())
+                    .map(|offset| (start + offset) % used.len())
+                    .find(|slot| !used[*slot])
+                    .unwrap_or(start);
+                used[slot] = true;
+                COLOR_PALETTE[slot].clone()
+            }
+        };
+        if rule.color_hex != hex {
+            Arc::make_mut(rule).color_hex = hex;
+        }
+    }
+}
+
+/// 规范化规则的颜色: 去除空白，颜色名称与色值统一为小写 (gray 视为 grey)
+pub fn normalize_color(color: &str) -> String {
+    match color.trim().to_ascii_lowercase().as_str() {
+        "gray" => "grey".to_string(),
+        color => color.to_string(),
+    }
+}
+
 fn default_true() -> bool {
     true
 }
 
+fn default_max_pages() -> usize {
+    1
+}
+
 impl Default for Rule {
     fn default() -> Self {
         Self {
@@ -135,50 +400,216 @@ impl Default for Rule {
             search_result: String::new(),
             chapter_roads: String::new(),
             chapter_result: String::new(),
+            chapter_road_links: String::new(),
             referer: String::new(),
             color: default_color(),
+            color_hex: String::new(),
+            icon: String::new(),
             tags: vec![],
             magic: false,
+            search_update: String::new(),
+            search_cover: String::new(),
+            search_alt_name: String::new(),
+            episode_fallback: false,
+            episode_href_pattern: String::new(),
+            token_xpath: String::new(),
+            token_field: String::new(),
+            episode_name_template: String::new(),
+            episode_number_regex: String::new(),
+            search_next_page: String::new(),
+            dedup_items: true,
+            preferred_road_pattern: String::new(),
+            id_regex: String::new(),
+            max_pages: 1,
+            timeout_secs: None,
+            mock_results: None,
+            mock: None,
+        }
+    }
+}
+
+impl Rule {
+    /// 是否为 RSS 规则 (`"type": "rss"`)
+    pub fn is_rss(&self) -> bool {
+        self.rule_type.eq_ignore_ascii_case("rss")
+    }
+
+    /// 是否为模拟源 (`"type": "mock"`)
+    pub fn is_mock(&self) -> bool {
+        self.rule_type.eq_ignore_ascii_case("mock")
+    }
+
+    /// 请求源站的超时 (规则未设置时为 None，使用客户端的全局超时)
+    pub fn request_timeout(&self) -> Option<std::time::Duration> {
+        self.timeout_secs
+            .filter(|secs| *secs > 0)
+            .map(std::time::Duration::from_secs)
+    }
+
+    /// 平台图标地址: 优先使用 `icon` (相对路径按 baseURL 补全)，否则为站点根目录的 /favicon.ico；
+    /// 只是拼接地址，不请求图标
+    pub fn icon_url(&self) -> Option<String> {
+        let base = url::Url::parse(&self.base_url).ok();
+        let icon = self.icon.trim();
+        if !icon.is_empty() {
+            return match url::Url::parse(icon) {
+                Ok(url) => Some(url.to_string()),
+                Err(_) => base?.join(icon).ok().map(String::from),
+            };
+        }
+        base?.join("/favicon.ico").ok().map(String::from)
+    }
+
+    /// 平台颜色的色值: 规则集加载时已分配 (见 [`assign_colors`])；
+    /// 未经规则集加载的规则 (如试运行) 取颜色对应的色值或名称散列到的调色板颜色
+    pub fn color_hex(&self) -> String {
+        if !self.color_hex.is_empty() {
+            return self.color_hex.clone();
+        }
+        self.fixed_color()
+            .unwrap_or_else(|| COLOR_PALETTE[self.palette_slot()].clone())
+    }
+
+    /// 颜色值直接对应的色值: 已知颜色名称取对应色值，`#rrggbb` 统一为小写，其他值为 None
+    fn fixed_color(&self) -> Option<String> {
+        let color = normalize_color(&self.color);
+        if let Some(hex) = known_color_hex(&color) {
+            return Some(hex.to_string());
         }
+        let is_hex = color.len() == 7 && color.starts_with('#') && color[1..].bytes().all(|b| b.is_ascii_hexdigit());
+        is_hex.then_some(color)
+    }
+
+    /// 规则名称散列到的调色板位置
+    /// (FNV-1a: 不依赖标准库散列的实现细节，跨版本与重启保持稳定)
+    fn palette_slot(&self) -> usize {
+        let hash = self
+            .name
+            .bytes()
+            .fold(0x811c_9dc5_u32, |hash, b| (hash ^ b as u32).wrapping_mul(0x0100_0193));
+        hash as usize % COLOR_PALETTE.len()
     }
 }
 
 /// 单个搜索结果
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResultItem {
     /// 动漫名称
     pub name: String,
+    /// 别名 (规则配置了 `searchAltName` 且与名称不同时)，如原名或外文名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt_name: Option<String>,
     /// 资源链接
     pub url: String,
     /// 可选标签 (如：集数、画质等)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
+    /// 更新信息 (如 "更新至第12集")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+    /// 封面地址 (规则配置了 `searchCover` 时)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
     /// 集数列表 (播放源 -> 集数列表)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub episodes: Option<Vec<EpisodeRoad>>,
+    /// 匹配到的列表节点 HTML (调试用，仅管理员 `include_raw=1` 时返回，已截断)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_html: Option<String>,
+    /// 站点侧的稳定 ID (规则配置了 `idRegex` 时从链接中提取)，可作为收藏、缓存的键
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<String>,
+    /// 来源规则名 (合并、导出、收藏时使用；在单个规则的 [`StreamResult`] 中不输出)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub rule: String,
+    /// 来源规则颜色
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_color: Option<String>,
+}
+
+impl SearchResultItem {
+    /// 来源规则名，条目未记录时使用 `fallback` (如所在 [`StreamResult`] 的平台名)
+    pub fn rule_or<'a>(&'a self, fallback: &'a str) -> &'a str {
+        if self.rule.is_empty() {
+            fallback
+        } else {
+            &self.rule
+        }
+    }
 }
 
 /// 播放源 (一个动漫可能有多个播放源)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EpisodeRoad {
     /// 播放源名称 (如: "线路1", "备用线路")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// 该播放源在详情页中的序号 (从 0 开始，没有集数而被丢弃的播放源不影响其余序号)
+    #[serde(default)]
+    pub index: usize,
+    /// 稳定 id (详情页链接 + 序号的哈希)，跨请求引用同一播放源，如 `/episodes?road_id=`
+    #[serde(default)]
+    pub id: String,
     /// 该播放源下的集数列表
     pub episodes: Vec<Episode>,
 }
 
+impl EpisodeRoad {
+    /// 详情页 `detail_url` 中第 `index` 个播放源
+    pub fn new(detail_url: &str, index: usize, name: Option<String>, episodes: Vec<Episode>) -> Self {
+        Self {
+            name,
+            index,
+            id: road_id(detail_url, index),
+            episodes,
+        }
+    }
+}
+
+/// 播放源 id: `详情页链接#序号` 的 FNV-1a 64 位哈希 (16 位十六进制，不随版本变化)
+pub fn road_id(detail_url: &str, index: usize) -> String {
+    let hash = format!("{}#{}", detail_url, index)
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{:016x}", hash)
+}
+
 /// 单集信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Episode {
     /// 集数名称 (如: "第1集", "01")
     pub name: String,
     /// 播放链接
     pub url: String,
+    /// 规范化后的显示名 (规则配置了集数模板且提取到集数时返回)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+}
+
+/// 规则搜索失败的类别 (供客户端按类别处理，如超时重试、忽略被屏蔽的来源)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// 请求超时 (含搜索时间预算耗尽)
+    Timeout,
+    /// 源站返回非 2xx 状态码 (403、429 除外)
+    BadStatus,
+    /// 源站拒绝访问 (403，多为 IP/UA 屏蔽)
+    Blocked,
+    /// 源站限流 (429，请求过于频繁)
+    RateLimited,
+    /// 页面中没有找到需要的内容 (如搜索 token)
+    ParseEmpty,
+    /// 规则因持续失败已被自动停用 (显式指定时仍会执行，失败归为此类)
+    CircuitOpen,
+    /// 其他错误 (网络、规则配置等)
+    Other,
 }
 
 /// 平台搜索的返回值
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PlatformSearchResult {
     /// 搜索结果列表
     pub items: Vec<SearchResultItem>,
@@ -187,39 +618,97 @@ pub struct PlatformSearchResult {
     /// 错误信息
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// 来源搜索结果页的下一页链接 (规则配置了 `searchNextPage` 且页面有下一页时)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_page_url: Option<String>,
+    /// 搜索耗时 (毫秒，含获取集数)
+    #[serde(default)]
+    pub elapsed_ms: u64,
+    /// 搜索页最后一次响应的状态码 (没有收到响应时为空)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    /// 搜索页的请求次数 (含反代重试，未发出请求时为 0)
+    #[serde(default)]
+    pub attempts: u8,
+    /// 错误类别
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+    /// 耗时分解 (请求 `debug_timing` 时)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
+}
+
+/// 单个规则搜索的耗时分解 (毫秒)，用于判断慢规则是慢在建连/等待还是慢在传输
+///
+/// reqwest 不单独报告 DNS 与建连耗时，两者计入 `ttfb_ms`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Timing {
+    /// 搜索页请求发出到收到响应头 (含 DNS、建连、TLS 与服务端处理，反代重试累计)
+    pub ttfb_ms: u64,
+    /// 读取搜索页响应体
+    pub body_ms: u64,
+    /// 规则总耗时 (含解析与获取集数)
+    pub total_ms: u64,
 }
 
 impl PlatformSearchResult {
-    pub fn with_error(message: String) -> Self {
+    pub fn with_error(kind: ErrorKind, message: String) -> Self {
         Self {
-            items: Vec::new(),
             count: -1,
             error: Some(message),
+            error_kind: Some(kind),
+            ..Default::default()
         }
     }
 
     pub fn with_items(items: Vec<SearchResultItem>) -> Self {
-        let count = items.len() as i32;
         Self {
+            count: items.len() as i32,
             items,
-            count,
-            error: None,
+            ..Default::default()
         }
     }
 }
 
-impl Default for PlatformSearchResult {
-    fn default() -> Self {
-        Self {
-            items: Vec::new(),
-            count: 0,
-            error: None,
-        }
-    }
+
+/// 单次搜索的可选参数 (由请求字段解析)
+#[cfg(feature = "scraper")]
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// 将结果名称转换为指定字形 (未指定时保持原文)
+    pub script: Option<Script>,
+    /// 覆盖本次搜索的并发数 (仅管理员请求，已截断到上限)
+    pub concurrency: Option<usize>,
+    /// 每个规则只返回第一个有效结果 (跳过其余结果的集数请求)
+    pub first_only: bool,
+    /// 附带每个结果匹配到的列表节点 HTML (调试用，仅管理员请求)
+    pub include_raw: bool,
+    /// 附带每个规则的耗时分解 (调试用，仅管理员请求)
+    pub debug_timing: bool,
+    /// 不请求详情页获取集数 (由调用方按需获取)
+    pub skip_episodes: bool,
+    /// 流式事件格式 (默认 v1)
+    pub event_schema: crate::events::EventSchema,
+    /// 流式事件的分帧方式 (默认每行一个 JSON)
+    pub framing: crate::events::Framing,
+    /// 通过元数据来源匹配规范名称并标注到结果上 (需要 bangumi 或 anilist feature)
+    pub enrich: bool,
+    /// 规范名称使用的元数据来源 (为空时使用 METADATA_PROVIDER)
+    pub metadata_provider: Option<String>,
+    /// 每个规则最多返回的结果数，同时替换 searchURL 中的 `@limit` (缺省时替换为 20，不截断)
+    pub limit: Option<usize>,
+    /// 替换 searchURL 中的 `@offset` (缺省时为 0)
+    pub offset: Option<usize>,
+    /// 本次搜索的截止时间 (覆盖 SEARCH_DEADLINE_SECONDS，到时未完成的规则以超时结果返回)
+    pub deadline: Option<std::time::Duration>,
+    /// 所有规则合计最多返回的结果数 (按事件发送顺序截断，达到后其余规则只发送进度)
+    pub total_limit: Option<usize>,
+    /// 为每个平台的第一个结果查询元数据来源的评分 (以单独的 rating 事件发送)
+    pub top_rating: bool,
 }
 
 /// SSE 流中的进度信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct StreamProgress {
     /// 已完成的平台数
     pub completed: usize,
@@ -227,35 +716,398 @@ pub struct StreamProgress {
     pub total: usize,
 }
 
+/// 元数据条目的规范名称 (关键词与条目名称完全匹配时得到)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CanonicalTitle {
+    /// 条目 ID (所属来源见 `provider`)
+    pub subject_id: i64,
+    /// 原名
+    pub name: String,
+    /// 中文名 (条目没有中文名时为空)
+    pub name_cn: String,
+    /// 元数据来源 (`bangumi` / `anilist`)
+    #[serde(default)]
+    pub provider: String,
+}
+
+/// 已自动停用 (熔断) 的规则及预计恢复时间
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RuleCooldown {
+    /// 规则名称
+    pub rule: String,
+    /// 停用时间 (RFC 3339)
+    pub since: String,
+    /// 停用原因
+    pub reason: String,
+    /// 预计恢复时间 (RFC 3339，按后台探测计划估算，探测尚未开始时没有)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_at: Option<String>,
+    /// 距预计恢复的秒数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
 /// SSE 流中的单个结果
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct StreamResult {
     /// 平台名称
     pub name: String,
     /// 平台颜色
     pub color: String,
+    /// 平台颜色的色值 (见 [`Rule::color_hex`]，出错时为红色)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub color_hex: String,
+    /// 平台图标地址 (见 [`Rule::icon_url`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
     /// 平台标签
     pub tags: Vec<String>,
-    /// 搜索结果
+    /// 搜索结果 (与平台重复的来源规则字段不输出)
+    #[serde(serialize_with = "serialize_items_without_rule")]
     pub items: Vec<SearchResultItem>,
     /// 错误信息
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// 错误类别 (与 `error` 同时出现)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+    /// 来源搜索结果页的下一页链接 (已补全为绝对地址)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_page_url: Option<String>,
+    /// 规范名称 (请求 `enrich=1` 且该平台第一个结果与条目名称匹配时)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<CanonicalTitle>,
+    /// 耗时分解 (管理员请求 `debug_timing=1` 且未命中结果缓存时)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
 }
 
-/// SSE 事件数据
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 平台第一个结果对应条目的评分 (按名称规范化后完全匹配)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TopRating {
+    /// 规则名
+    pub rule: String,
+    /// 对应结果的链接
+    pub url: String,
+    /// 条目 ID (所属来源见 `provider`)
+    pub subject_id: i64,
+    /// 元数据来源 (`bangumi` / `anilist`)
+    pub provider: String,
+    /// 评分
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// 排名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<i32>,
+    /// 封面地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+fn serialize_items_without_rule<S: serde::Serializer>(
+    items: &[SearchResultItem],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(items.iter().map(|item| SearchResultItem {
+        rule: String::new(),
+        rule_color: None,
+        ..item.clone()
+    }))
+}
+
+/// SSE 事件数据 (每行一个 JSON，按字段区分事件类型)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
+#[schemars(
+    example = StreamEvent::Init { total: 2 },
+    example = example_progress(),
+    example = example_result(),
+    example = example_rating(),
+    example = example_unavailable(),
+    example = StreamEvent::Done { done: true, timed_out: vec![] }
+)]
+// 事件构造后立即序列化，不值得为结果变体装箱
+#[allow(clippy::large_enum_variant)]
 pub enum StreamEvent {
     /// 初始事件，包含总数
     Init { total: usize },
-    /// 进度更新 (无结果)
-    Progress { progress: StreamProgress },
-    /// 进度更新 + 结果
+    /// 进度更新 + 结果 (反序列化按变体顺序尝试，须在 Progress 之前)
     Result {
         progress: StreamProgress,
         result: StreamResult,
+        /// 截至本事件 (含) 所有规则已发送的结果条数
+        #[serde(default)]
+        running_total: usize,
+    },
+    /// 进度更新 (无结果)
+    Progress { progress: StreamProgress },
+    /// 平台第一个结果在元数据来源中的评分 (请求 `top_rating=1` 且找到同名条目时，在该平台的结果之后发送)
+    Rating { rating: TopRating },
+    /// 所选规则全部处于自动停用状态且没有规则成功 (在完成信号前发送)，
+    /// 用于区分「没有结果」与「暂时没有可用的来源」
+    Unavailable {
+        all_rules_unavailable: bool,
+        /// 最早的预计恢复秒数
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after_secs: Option<u64>,
+        cooldowns: Vec<RuleCooldown>,
     },
-    /// 完成信号
-    Done { done: bool },
+    /// 完成信号 (`timed_out` 为超过搜索截止时间而被中止的规则，正常完成时不输出)
+    Done {
+        done: bool,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        timed_out: Vec<String>,
+    },
+}
+
+impl StreamEvent {
+    /// 事件类型名 (SSE 的 `event:` 字段)
+    pub fn name(&self) -> &'static str {
+        match self {
+            StreamEvent::Init { .. } => "init",
+            StreamEvent::Result { .. } => "result",
+            StreamEvent::Progress { .. } => "progress",
+            StreamEvent::Rating { .. } => "rating",
+            StreamEvent::Unavailable { .. } => "unavailable",
+            StreamEvent::Done { .. } => "done",
+        }
+    }
+}
+
+fn example_progress() -> StreamEvent {
+    StreamEvent::Progress {
+        progress: StreamProgress {
+            completed: 1,
+            total: 2,
+        },
+    }
+}
+
+fn example_rating() -> StreamEvent {
+    StreamEvent::Rating {
+        rating: TopRating {
+            rule: "AGE".to_string(),
+            url: "https://www.agedm.org/detail/20230096".to_string(),
+            subject_id: 400602,
+            provider: "bangumi".to_string(),
+            score: Some(9.1),
+            rank: Some(12),
+            image: None,
+        },
+    }
+}
+
+fn example_unavailable() -> StreamEvent {
+    StreamEvent::Unavailable {
+        all_rules_unavailable: true,
+        retry_after_secs: Some(1800),
+        cooldowns: vec![RuleCooldown {
+            rule: "AGE".to_string(),
+            since: "2024-03-22T08:00:00+00:00".to_string(),
+            reason: "连续失败 10 次".to_string(),
+            reset_at: Some("2024-03-22T09:30:00+00:00".to_string()),
+            retry_after_secs: Some(1800),
+        }],
+    }
+}
+
+fn example_result() -> StreamEvent {
+    StreamEvent::Result {
+        running_total: 1,
+        progress: StreamProgress {
+            completed: 2,
+            total: 2,
+        },
+        result: StreamResult {
+            name: "AGE".to_string(),
+            color: "orange".to_string(),
+            color_hex: "#ff9800".to_string(),
+            icon: Some("https://example.com/favicon.ico".to_string()),
+            tags: vec!["在线".to_string()],
+            items: vec![SearchResultItem {
+                name: "葬送的芙莉莲".to_string(),
+                alt_name: None,
+                url: "https://example.com/detail/1".to_string(),
+                tags: None,
+                latest: Some("更新至第28集".to_string()),
+                cover: None,
+                episodes: Some(vec![EpisodeRoad::new(
+                    "https://example.com/detail/1",
+                    0,
+                    Some("线路1".to_string()),
+                    vec![Episode {
+                        name: "第1集".to_string(),
+                        url: "https://example.com/play/1-1".to_string(),
+                        display_name: None,
+                    }],
+                )]),
+                raw_html: None,
+                source_id: None,
+                rule: String::new(),
+                rule_color: None,
+            }],
+            error: None,
+            error_kind: None,
+            next_page_url: None,
+            canonical: None,
+            timing: None,
+        },
+    }
+}
+
+/// `GET /rules` 中的规则信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleInfo {
+    pub name: String,
+    pub version: String,
+    #[serde(rename = "baseUrl")]
+    pub base_url: String,
+    pub color: String,
+    /// 解析后的颜色色值 (见 [`Rule::color_hex`])
+    pub color_hex: String,
+    /// 平台图标地址 (见 [`Rule::icon_url`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    pub tags: Vec<String>,
+    pub magic: bool,
+    /// 是否因连续失败被自动停用
+    pub auto_disabled: bool,
+}
+
+/// 规则更新结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateResult {
+    pub total: usize,
+    pub updated: usize,
+    pub added: usize,
+    pub failed: usize,
+    pub details: Vec<UpdateDetail>,
+}
+
+/// 单个规则的更新情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateDetail {
+    pub name: String,
+    pub action: String, // "added", "updated", "unchanged", "failed"
+    pub message: String,
+    /// 更新前的规则版本 (新增或失败时为空)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_version: Option<String>,
+    /// 更新后的规则版本
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_version: Option<String>,
+}
+
+/// `GET /update` 的响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateResponse {
+    pub success: bool,
+    #[serde(flatten)]
+    pub result: UpdateResult,
+}
+
+/// 规则文件的 JSON Schema (由 [`Rule`] 派生)
+pub fn rule_schema() -> schemars::Schema {
+    schemars::schema_for!(Rule)
+}
+
+/// 流式搜索事件的 JSON Schema (由 [`StreamEvent`] 派生，含示例)
+pub fn stream_event_schema() -> schemars::Schema {
+    schemars::schema_for!(StreamEvent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_event_schema_examples_match_variants() {
+        let schema = serde_json::to_value(stream_event_schema()).unwrap();
+        assert_eq!(schema["anyOf"].as_array().unwrap().len(), 6);
+
+        let examples = schema["examples"].as_array().unwrap();
+        assert_eq!(examples.len(), 6);
+        for example in examples {
+            let event = serde_json::from_value::<StreamEvent>(example.clone()).unwrap();
+            // 往返后不丢字段 (无标签枚举按变体顺序匹配)
+            assert_eq!(&serde_json::to_value(&event).unwrap(), example);
+        }
+    }
+
+    #[test]
+    fn test_icon_url_prefers_explicit_icon() {
+        let mut rule = Rule {
+            base_url: "https://www.agedm.org/search/".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(rule.icon_url().as_deref(), Some("https://www.agedm.org/favicon.ico"));
+
+        rule.icon = "/static/logo.png".to_string();
+        assert_eq!(rule.icon_url().as_deref(), Some("https://www.agedm.org/static/logo.png"));
+
+        rule.icon = "https://cdn.example.com/age.svg".to_string();
+        assert_eq!(rule.icon_url().as_deref(), Some("https://cdn.example.com/age.svg"));
+
+        rule.icon.clear();
+        rule.base_url = "not a url".to_string();
+        assert_eq!(rule.icon_url(), None);
+    }
+
+    #[test]
+    fn test_unknown_colors_get_stable_distinct_palette_slots() {
+        let rule = |name: &str, color: &str| Rule {
+            name: name.to_string(),
+            color: color.to_string(),
+            ..Default::default()
+        };
+        // 已知名称与色值保持不变
+        assert_eq!(rule("AGE", "orange").color_hex(), "#ff9800");
+        assert_eq!(rule("AGE", " Gray ").color_hex(), "#9e9e9e");
+        assert_eq!(rule("AGE", "#12AB9f").color_hex(), "#12ab9f");
+
+        // 未知颜色按规则名称分配，与颜色值无关且每次相同
+        let hexes: Vec<String> = ["AGE", "DM84", "BF", "7sefun"]
+            .iter()
+            .map(|name| rule(name, "white").color_hex())
+            .collect();
+        assert!(hexes.iter().all(|hex| COLOR_PALETTE.contains(hex)));
+        assert_eq!(rule("AGE", "chartreuse").color_hex(), hexes[0]);
+        assert_eq!(rule("AGE", "white").color_hex(), hexes[0]);
+        let distinct: std::collections::HashSet<_> = hexes.iter().collect();
+        assert_eq!(distinct.len(), hexes.len(), "{:?}", hexes);
+    }
+
+    #[test]
+    fn test_assign_colors_resolves_palette_collisions() {
+        let palette_rule = |name: String| {
+            Arc::new(Rule {
+                name,
+                ..Default::default()
+            })
+        };
+        // 规则数超过调色板一半，必然有散列冲突
+        let mut rules: Vec<Arc<Rule>> = (0..COLOR_PALETTE.len() / 2 + 10)
+            .map(|i| palette_rule(format!("源{:03}", i)))
+            .collect();
+        rules.push(Arc::new(Rule {
+            name: "固定".to_string(),
+            color: "orange".to_string(),
+            ..Default::default()
+        }));
+        assign_colors(&mut rules);
+
+        let palette: Vec<&str> = rules[..rules.len() - 1].iter().map(|r| r.color_hex.as_str()).collect();
+        let distinct: std::collections::HashSet<_> = palette.iter().collect();
+        assert_eq!(distinct.len(), palette.len(), "{:?}", palette);
+        assert_eq!(rules.last().unwrap().color_hex(), "#ff9800");
+
+        // 未经分配的规则回退到散列位置
+        let mut unassigned = (*rules[0]).clone();
+        unassigned.color_hex.clear();
+        assert_eq!(unassigned.color_hex(), COLOR_PALETTE[unassigned.palette_slot()]);
+
+        // 调色板颜色互不相同
+        let distinct: std::collections::HashSet<_> = COLOR_PALETTE.iter().collect();
+        assert_eq!(distinct.len(), COLOR_PALETTE.len());
+    }
 }
@@ -0,0 +1,238 @@
+//! 跨规则合并视图
+//! 把多个规则的搜索结果按规范化后的名称分组 (同一部动漫在不同站点的结果归为一组)；
+//! 可选地为每组获取各来源的集数，合并为「每个来源一个播放源」的结构。
+
+use crate::engine::fetch_episodes;
+use crate::types::{EpisodeRoad, Rule, StreamResult};
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+
+/// 单次合并最多请求的详情页数 (超出的来源不获取集数)
+pub const MAX_EPISODE_FETCHES: usize = 32;
+
+/// 分组中的一个来源
+#[derive(Debug, Clone, Serialize)]
+pub struct UnifiedSource {
+    /// 规则名
+    pub rule: String,
+    pub color: String,
+    /// 该来源中的原始名称
+    pub name: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+}
+
+/// 按名称合并后的一部动漫
+#[derive(Debug, Clone, Serialize)]
+pub struct UnifiedTitle {
+    /// 显示名称 (第一个来源中的名称)
+    pub title: String,
+    pub sources: Vec<UnifiedSource>,
+    /// 合并后的集数 (每个来源一个播放源，播放源名称为规则名)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episodes: Option<Vec<EpisodeRoad>>,
+}
+
+/// 分组用的名称键: 忽略大小写、空白与标点
+pub(crate) fn title_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 按名称分组 (保持首次出现的顺序；同一规则的重复结果只保留第一个)
+pub fn group_results(results: &[StreamResult]) -> Vec<UnifiedTitle> {
+    let mut titles: Vec<UnifiedTitle> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for result in results {
+        for item in &result.items {
+            let key = title_key(&item.name);
+            if key.is_empty() {
+                continue;
+            }
+            let source = UnifiedSource {
+                rule: item.rule_or(&result.name).to_string(),
+                color: item.rule_color.clone().unwrap_or_else(|| result.color.clone()),
+                name: item.name.clone(),
+                url: item.url.clone(),
+                latest: item.latest.clone(),
+            };
+            match index.get(&key) {
+                Some(&i) => {
+                    if !titles[i].sources.iter().any(|s| s.rule == source.rule) {
+                        titles[i].sources.push(source);
+                    }
+                }
+                None => {
+                    index.insert(key, titles.len());
+                    titles.push(UnifiedTitle {
+                        title: item.name.clone(),
+                        sources: vec![source],
+                        episodes: None,
+                    });
+                }
+            }
+        }
+    }
+    titles
+}
+
+/// 把各来源的播放源合并为每个来源一个播放源 (取集数最多的播放源，来源顺序不变)
+pub fn merge_roads(sources: Vec<(String, Vec<EpisodeRoad>)>) -> Vec<EpisodeRoad> {
+    sources
+        .into_iter()
+        .filter_map(|(rule, roads)| {
+            roads
+                .into_iter()
+                .filter(|road| !road.episodes.is_empty())
+                // 集数相同时取靠前的播放源
+                .min_by_key(|road| std::cmp::Reverse(road.episodes.len()))
+                // 保留来源播放源的序号与 id
+                .map(|road| EpisodeRoad {
+                    name: Some(rule),
+                    ..road
+                })
+        })
+        .collect()
+}
+
+/// 为每组获取各来源的集数并合并 (同时请求数不超过 `concurrency`，总数不超过 MAX_EPISODE_FETCHES)
+pub async fn attach_episodes(titles: &mut [UnifiedTitle], rules: &[Arc<Rule>], concurrency: usize) {
+    let jobs: Vec<(usize, usize, Arc<Rule>, String)> = titles
+        .iter()
+        .enumerate()
+        .flat_map(|(t, title)| {
+            title
+                .sources
+                .iter()
+                .enumerate()
+                .filter_map(move |(s, source)| {
+                    rules
+                        .iter()
+                        .find(|r| r.name == source.rule)
+                        .map(|rule| (t, s, rule.clone(), source.url.clone()))
+                })
+        })
+        .take(MAX_EPISODE_FETCHES)
+        .collect();
+
+    let fetched: Vec<(usize, usize, Vec<EpisodeRoad>)> = futures::stream::iter(jobs)
+        .map(|(t, s, rule, url)| async move {
+            let roads = fetch_episodes(&rule, &url).await.unwrap_or_else(|e| {
+                debug!("获取章节失败 {}: {}", url, e);
+                Vec::new()
+            });
+            (t, s, roads)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut per_title: HashMap<usize, Vec<(usize, Vec<EpisodeRoad>)>> = HashMap::new();
+    for (t, s, roads) in fetched {
+        per_title.entry(t).or_default().push((s, roads));
+    }
+    for (t, mut sources) in per_title {
+        sources.sort_by_key(|(s, _)| *s);
+        let title = &mut titles[t];
+        let sources = sources
+            .into_iter()
+            .map(|(s, roads)| (title.sources[s].rule.clone(), roads))
+            .collect();
+        title.episodes = Some(merge_roads(sources));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Episode, SearchResultItem};
+
+    fn item(name: &str, url: &str) -> SearchResultItem {
+        SearchResultItem {
+            name: name.to_string(),
+            alt_name: None,
+            url: url.to_string(),
+            tags: None,
+            latest: None,
+            cover: None,
+            episodes: None,
+            raw_html: None,
+            source_id: None,
+            rule: String::new(),
+            rule_color: None,
+        }
+    }
+
+    fn result(rule: &str, items: Vec<SearchResultItem>) -> StreamResult {
+        StreamResult {
+            name: rule.to_string(),
+            color: "blue".to_string(),
+            color_hex: String::new(),
+            icon: None,
+            tags: vec![],
+            items,
+            error: None,
+            error_kind: None,
+            next_page_url: None,
+            canonical: None,
+            timing: None,
+        }
+    }
+
+    fn road(name: &str, episodes: &[&str]) -> EpisodeRoad {
+        EpisodeRoad::new(
+            &format!("https://example.com/{}", name),
+            0,
+            Some(name.to_string()),
+            episodes
+                .iter()
+                .map(|n| Episode {
+                    name: n.to_string(),
+                    url: format!("https://example.com/{}/{}", name, n),
+                    display_name: None,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_same_title_from_two_sources_merges_episodes() {
+        let results = vec![
+            result(
+                "AGE",
+                vec![
+                    item("葬送的芙莉莲", "https://age.example/1"),
+                    item("芙莉莲 剧场版", "https://age.example/2"),
+                ],
+            ),
+            result("MX", vec![item(" 葬送的芙莉莲！", "https://mx.example/9")]),
+        ];
+        let titles = group_results(&results);
+        assert_eq!(titles.len(), 2);
+        assert_eq!(titles[0].title, "葬送的芙莉莲");
+        let rules: Vec<_> = titles[0].sources.iter().map(|s| s.rule.as_str()).collect();
+        assert_eq!(rules, vec!["AGE", "MX"]);
+
+        let merged = merge_roads(vec![
+            (
+                "AGE".to_string(),
+                vec![
+                    road("线路1", &["01", "02"]),
+                    road("线路2", &["01", "02", "03"]),
+                ],
+            ),
+            ("MX".to_string(), vec![road("主线", &["01"])]),
+        ]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].name.as_deref(), Some("AGE"));
+        assert_eq!(merged[0].episodes.len(), 3);
+        assert_eq!(merged[1].name.as_deref(), Some("MX"));
+        assert_eq!(merged[1].episodes[0].url, "https://example.com/主线/01");
+    }
+}
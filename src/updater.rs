@@ -3,15 +3,15 @@
 
 use crate::config::CONFIG;
 use crate::http_client::HTTP_CLIENT;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use std::fs;
 use std::path::Path;
 use tracing::{debug, info, warn};
 
-/// 规则目录
-const RULES_DIR: &str = "rules";
-/// 存储上次 commit SHA 的文件
-const LAST_COMMIT_FILE: &str = "rules/.last_commit";
+pub use crate::types::{UpdateDetail, UpdateResult};
+
+/// 存储上次 commit SHA 的文件 (位于规则目录)
+const LAST_COMMIT_FILE: &str = ".last_commit";
 
 /// 带代理重试的 GET 请求
 async fn get_with_retry(url: &str) -> anyhow::Result<reqwest::Response> {
@@ -72,26 +72,21 @@ struct GitHubContent {
     content_type: String,
 }
 
-/// 更新结果
-#[derive(Debug, Clone, Serialize)]
-pub struct UpdateResult {
-    pub total: usize,
-    pub updated: usize,
-    pub added: usize,
-    pub failed: usize,
-    pub details: Vec<UpdateDetail>,
-}
-
-#[derive(Debug, Clone, Serialize)]
-pub struct UpdateDetail {
-    pub name: String,
-    pub action: String, // "added", "updated", "failed"
-    pub message: String,
+impl UpdateDetail {
+    fn failed(name: &str, message: String) -> Self {
+        Self {
+            name: name.to_string(),
+            action: "failed".to_string(),
+            message,
+            old_version: None,
+            new_version: None,
+        }
+    }
 }
 
 /// 检查本地是否有规则文件
 pub fn has_local_rules() -> bool {
-    let rules_path = Path::new(RULES_DIR);
+    let rules_path = Path::new(&CONFIG.rules_dir);
     if !rules_path.exists() {
         return false;
     }
@@ -102,7 +97,7 @@ pub fn has_local_rules() -> bool {
             .any(|e| {
                 let name = e.file_name();
                 let name = name.to_string_lossy();
-                name.ends_with(".json") && name != "index.json"
+                name.ends_with(".json") && name != "index.json" && name != "groups.json"
             }),
         Err(_) => false,
     }
@@ -110,13 +105,13 @@ pub fn has_local_rules() -> bool {
 
 /// 读取上次的 commit SHA
 fn read_last_commit() -> Option<String> {
-    fs::read_to_string(LAST_COMMIT_FILE).ok().map(|s| s.trim().to_string())
+    fs::read_to_string(Path::new(&CONFIG.rules_dir).join(LAST_COMMIT_FILE)).ok().map(|s| s.trim().to_string())
 }
 
 /// 保存当前 commit SHA
 fn save_last_commit(sha: &str) -> anyhow::Result<()> {
-    let _ = fs::create_dir_all(RULES_DIR);
-    fs::write(LAST_COMMIT_FILE, sha)?;
+    let _ = fs::create_dir_all(&CONFIG.rules_dir);
+    fs::write(Path::new(&CONFIG.rules_dir).join(LAST_COMMIT_FILE), sha)?;
     Ok(())
 }
 
@@ -160,15 +155,26 @@ async fn download_rule(name: &str) -> anyhow::Result<String> {
 
 /// 保存规则到本地
 fn save_rule(name: &str, content: &str) -> anyhow::Result<()> {
-    let _ = fs::create_dir_all(RULES_DIR);
-    let path = Path::new(RULES_DIR).join(format!("{}.json", name));
+    let _ = fs::create_dir_all(&CONFIG.rules_dir);
+    let path = Path::new(&CONFIG.rules_dir).join(format!("{}.json", name));
     fs::write(path, content)?;
     Ok(())
 }
 
+/// 读取本地规则内容
+fn read_rule(name: &str) -> Option<String> {
+    fs::read_to_string(Path::new(&CONFIG.rules_dir).join(format!("{}.json", name))).ok()
+}
+
+/// 规则 JSON 中的 version 字段
+fn rule_version(content: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(content).ok()?;
+    value.get("version")?.as_str().map(|v| v.to_string())
+}
+
 /// 检查本地是否存在该规则
 fn rule_exists(name: &str) -> bool {
-    Path::new(RULES_DIR).join(format!("{}.json", name)).exists()
+    Path::new(&CONFIG.rules_dir).join(format!("{}.json", name)).exists()
 }
 
 /// 检测变动并更新规则
@@ -192,11 +198,9 @@ pub async fn update_rules() -> UpdateResult {
         Ok(sha) => sha,
         Err(e) => {
             warn!("获取最新 commit 失败: {}", e);
-            result.details.push(UpdateDetail {
-                name: "commit".to_string(),
-                action: "failed".to_string(),
-                message: format!("获取 commit 失败: {}", e),
-            });
+            result
+                .details
+                .push(UpdateDetail::failed("commit", format!("获取 commit 失败: {}", e)));
             return result;
         }
     };
@@ -223,11 +227,9 @@ pub async fn update_rules() -> UpdateResult {
         Ok(files) => files,
         Err(e) => {
             warn!("获取规则列表失败: {}", e);
-            result.details.push(UpdateDetail {
-                name: "contents".to_string(),
-                action: "failed".to_string(),
-                message: format!("获取文件列表失败: {}", e),
-            });
+            result
+                .details
+                .push(UpdateDetail::failed("contents", format!("获取文件列表失败: {}", e)));
             return result;
         }
     };
@@ -238,17 +240,26 @@ pub async fn update_rules() -> UpdateResult {
     // 下载并保存每个规则
     for name in rule_files {
         let is_new = !rule_exists(&name);
+        let old_content = read_rule(&name);
 
         match download_rule(&name).await {
+            Ok(content) if old_content.as_deref() == Some(content.as_str()) => {
+                // 内容未变，不重写文件，也不计入更新数
+                result.details.push(UpdateDetail {
+                    name: name.clone(),
+                    action: "unchanged".to_string(),
+                    message: "ok".to_string(),
+                    old_version: rule_version(&content),
+                    new_version: rule_version(&content),
+                });
+            }
             Ok(content) => {
                 if let Err(e) = save_rule(&name, &content) {
                     warn!("保存规则 {} 失败: {}", name, e);
                     result.failed += 1;
-                    result.details.push(UpdateDetail {
-                        name: name.clone(),
-                        action: "failed".to_string(),
-                        message: format!("保存失败: {}", e),
-                    });
+                    result
+                        .details
+                        .push(UpdateDetail::failed(&name, format!("保存失败: {}", e)));
                 } else {
                     if is_new {
                         result.added += 1;
@@ -261,17 +272,17 @@ pub async fn update_rules() -> UpdateResult {
                         name: name.clone(),
                         action: if is_new { "added" } else { "updated" }.to_string(),
                         message: "ok".to_string(),
+                        old_version: old_content.as_deref().and_then(rule_version),
+                        new_version: rule_version(&content),
                     });
                 }
             }
             Err(e) => {
                 warn!("下载规则 {} 失败: {}", name, e);
                 result.failed += 1;
-                result.details.push(UpdateDetail {
-                    name: name.clone(),
-                    action: "failed".to_string(),
-                    message: format!("下载失败: {}", e),
-                });
+                result
+                    .details
+                    .push(UpdateDetail::failed(&name, format!("下载失败: {}", e)));
             }
         }
     }
@@ -80,9 +80,7 @@ fn convert_xpath(xpath: &str) -> Result<(String, Option<PositionFilter>), String
     // 移除开头的 // 或 .// 或 /
     if xpath.starts_with(".//") {
         xpath = xpath[3..].to_string();
-    } else if xpath.starts_with("//") {
-        xpath = xpath[2..].to_string();
-    } else if xpath.starts_with("./") {
+    } else if xpath.starts_with("//") || xpath.starts_with("./") {
         xpath = xpath[2..].to_string();
     } else if xpath.starts_with("/") {
         xpath = xpath[1..].to_string();
@@ -0,0 +1,1108 @@
+//! 端到端集成测试
+//! 以 `build_app` 启动完整路由，规则目录与数据目录位于临时目录，
+//! 源站、GitHub (规则索引) 与 Bangumi API 均由同一个 wiremock 服务模拟。
+//!
+//! 配置与规则列表是进程级全局状态，因此所有用例共用一套环境 (首次调用 [`harness`] 时初始化)，
+//! 各用例使用互不重叠的规则名与路径。
+
+#![cfg(all(feature = "server", feature = "scraper", feature = "bangumi"))]
+
+use anime_search::config::{Config, CONFIG};
+use anime_search::server::build_app;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use wiremock::matchers::{header, method, path, path_regex, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const ADMIN_KEY: &str = "integration-admin";
+const RULES_REPO: &str = "fixture/rules";
+const COMMIT_SHA: &str = "0123456789abcdef0123456789abcdef01234567";
+
+/// 源站搜索页 (两条结果)
+const SEARCH_PAGE: &str = r#"<html><body>
+<div class="item"><a href="/v/1">葬送的芙莉莲</a></div>
+<div class="item"><a href="/v/2">葬送的芙莉莲 第二季</a></div>
+</body></html>"#;
+
+/// 共用的测试环境
+struct Harness {
+    rules_dir: PathBuf,
+}
+
+/// 规则 JSON (Kazumi 格式)
+fn rule_json(upstream: &str, name: &str, site: &str) -> String {
+    serde_json::to_string_pretty(&json!({
+        "api": "1",
+        "type": "anime",
+        "name": name,
+        "version": "1.0",
+        "baseURL": format!("{}/{}/", upstream, site),
+        "searchURL": format!("{}/{}/search?wd=@keyword", upstream, site),
+        "searchList": "//div[@class='item']",
+        "searchName": "//a",
+        "searchResult": "//a",
+        "chapterRoads": "//ul",
+        "chapterResult": "//li/a",
+    }))
+    .unwrap()
+}
+
+/// 注册模拟上游: 两个源站、GitHub 规则索引与 Bangumi API
+async fn mount_upstreams(server: &MockServer) {
+    let upstream = server.uri();
+
+    // 源站: site-a 正常返回结果，site-b 返回 404
+    Mock::given(method("GET"))
+        .and(path("/site-a/search"))
+        .and(query_param("wd", "芙莉莲"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(SEARCH_PAGE))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/site-b/search"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(server)
+        .await;
+    // 第一条结果的详情页 (第二条没有详情页，获取集数失败)
+    Mock::given(method("GET"))
+        .and(path("/site-a/v/1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"<ul><li><a href="/p/1-1">第1集</a></li><li><a href="/p/1-2">第2集</a></li></ul>"#,
+        ))
+        .mount(server)
+        .await;
+
+    // site-r: 详情页只有播放源链接，各播放源页面单独请求 (线路1 较慢，线路3 不存在)
+    Mock::given(method("GET"))
+        .and(path("/site-r/v/9"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"<div class="roads"><a href="/site-r/r/0">线路1</a><a href="../r/1">线路2</a><a href="/site-r/r/2">线路3</a></div>"#,
+        ))
+        .mount(server)
+        .await;
+    for (road, delay) in [(0, 600), (1, 0)] {
+        Mock::given(method("GET"))
+            .and(path(format!("/site-r/r/{}", road)))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(format!(
+                        r#"<ul><li><a href="/p/{road}-1">第1集</a></li><li><a href="/p/{road}-2">第2集</a></li></ul>"#
+                    ))
+                    .set_delay(std::time::Duration::from_millis(delay)),
+            )
+            .mount(server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/site-r/r/2"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(server)
+        .await;
+
+    // GitHub: 最新 commit、目录列表与规则文件
+    Mock::given(method("GET"))
+        .and(path(format!("/gh-api/repos/{}/commits/main", RULES_REPO)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"sha": COMMIT_SHA})))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/gh-api/repos/{}/contents", RULES_REPO)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"name": "ItSearchA.json", "type": "file"},
+            {"name": "ItFresh.json", "type": "file"},
+            {"name": "index.json", "type": "file"},
+            {"name": "docs", "type": "dir"},
+        ])))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/gh-raw/{}/main/ItSearchA.json", RULES_REPO)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(rule_json(
+            &upstream,
+            "ItSearchA",
+            "site-a",
+        )))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/gh-raw/{}/main/ItFresh.json", RULES_REPO)))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(rule_json(&upstream, "ItFresh", "site-a")),
+        )
+        .mount(server)
+        .await;
+
+    // Bangumi: 条目详情仅在携带约定 token 时返回
+    Mock::given(method("GET"))
+        .and(path("/bgm-api/v0/subjects/400602"))
+        .and(header("Authorization", "Bearer token-explicit"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(
+                json!({"id": 400602, "name": "葬送のフリーレン", "via": "explicit"}),
+            ),
+        )
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/bgm-api/v0/subjects/400602"))
+        .and(header("Authorization", "Bearer token-profile"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"id": 400602, "name": "葬送のフリーレン", "via": "profile"})),
+        )
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/bgm-api/v0/subjects/400602"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({"title": "Unauthorized"})))
+        .with_priority(10)
+        .mount(server)
+        .await;
+
+    // Bangumi 流式搜索: 旧版搜索接口与条目详情 (400603 详情请求失败)
+    let subject = |id: i64, name_cn: &str| {
+        json!({
+            "id": id,
+            "url": format!("http://bgm.tv/subject/{}", id),
+            "type": 2,
+            "name": "葬送のフリーレン",
+            "name_cn": name_cn,
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path_regex("^/bgm-api/search/subject/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": 2,
+            "list": [subject(400602, "葬送的芙莉莲"), subject(400603, "葬送的芙莉莲 特别篇")],
+        })))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/bgm-api/subject/400602"))
+        .respond_with(ResponseTemplate::new(200).set_body_json({
+            let mut detail = subject(400602, "葬送的芙莉莲");
+            detail["rating"] = json!({"score": 9.1, "total": 1000});
+            detail["rank"] = json!(12);
+            detail
+        }))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/bgm-api/subject/400603"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/bgm-api/v0/subjects/400603"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": 400603,
+            "type": 2,
+            "name": "葬送のフリーレン",
+            "name_cn": "葬送的芙莉莲 特别篇",
+            "eps": 28,
+        })))
+        .mount(server)
+        .await;
+}
+
+/// 初始化共用环境 (模拟上游、临时目录、环境变量)，必须在首次读取 CONFIG 之前调用
+fn harness() -> &'static Harness {
+    static HARNESS: OnceLock<Harness> = OnceLock::new();
+    HARNESS.get_or_init(|| {
+        // 模拟上游运行在独立线程的运行时中，不随单个用例的运行时结束
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let server = MockServer::start().await;
+                mount_upstreams(&server).await;
+                tx.send(server.uri()).unwrap();
+                std::future::pending::<()>().await;
+            });
+        });
+        let upstream = rx.recv().unwrap();
+
+        let root = std::env::temp_dir().join(format!("anime-search-it-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let rules_dir = root.join("rules");
+        let data_dir = root.join("data");
+        std::fs::create_dir_all(&rules_dir).unwrap();
+        for (name, site) in [
+            ("ItSearchA", "site-a"),
+            ("ItSearchB", "site-b"),
+            ("ItToggle", "site-a"),
+        ] {
+            std::fs::write(
+                rules_dir.join(format!("{}.json", name)),
+                rule_json(&upstream, name, site),
+            )
+            .unwrap();
+        }
+        // 播放源需单独请求的规则
+        let mut linked: Value = serde_json::from_str(&rule_json(&upstream, "ItRoads", "site-r")).unwrap();
+        linked["chapterRoadLinks"] = json!("//div[@class='roads']/a");
+        std::fs::write(rules_dir.join("ItRoads.json"), linked.to_string()).unwrap();
+        // 模拟规则: 不请求上游，固定延迟生成 3 个结果
+        std::fs::write(
+            rules_dir.join("ItMock.json"),
+            json!({
+                "name": "ItMock",
+                "type": "mock",
+                "baseURL": "mock://ItMock/",
+                "searchURL": "mock://ItMock/search?q=@keyword",
+                "mock": {"latencyMs": 30, "items": 3, "episodes": 2},
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        for (key, value) in [
+            ("RULES_DIR", rules_dir.to_string_lossy().into_owned()),
+            ("DATA_DIR", data_dir.to_string_lossy().into_owned()),
+            ("ADMIN_KEY", ADMIN_KEY.to_string()),
+            ("RULES_REPO", RULES_REPO.to_string()),
+            ("RULES_BRANCH", "main".to_string()),
+            ("GITHUB_API_BASE", format!("{}/gh-api", upstream)),
+            ("GITHUB_RAW_BASE", format!("{}/gh-raw", upstream)),
+            ("BANGUMI_API_BASE", format!("{}/bgm-api", upstream)),
+            ("CACHE_SEARCH_TTL_SECS", "0".to_string()),
+            ("ENABLE_MOCK_RULES", "1".to_string()),
+        ] {
+            std::env::set_var(key, value);
+        }
+
+        Harness { rules_dir }
+    })
+}
+
+/// 在随机端口启动服务，返回基础地址
+async fn spawn_app() -> String {
+    // 先初始化环境变量，再读取全局配置
+    harness();
+    spawn_app_with(&CONFIG).await
+}
+
+/// 以指定配置构建路由并启动服务 (处理函数仍读取全局配置)
+async fn spawn_app_with(config: &Config) -> String {
+    harness();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = build_app(config);
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+/// 手工构造 multipart/form-data 请求体 (字段均为文本)
+fn multipart_body(boundary: &str, fields: &[(&str, &str)]) -> String {
+    let mut body = String::new();
+    for (name, value) in fields {
+        body.push_str(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            boundary, name, value
+        ));
+    }
+    body.push_str(&format!("--{}--\r\n", boundary));
+    body
+}
+
+/// SSE 响应中各帧 `data:` 行的 JSON
+fn sse_data(text: &str) -> Vec<Value> {
+    text.lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_streaming_search_with_one_failing_rule() {
+    let base = spawn_app().await;
+    let boundary = "integration-boundary";
+    let response = reqwest::Client::new()
+        .post(format!("{}/api", base))
+        .header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(multipart_body(
+            boundary,
+            &[("anime", "芙莉莲"), ("rules", "ItSearchA,ItSearchB")],
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/event-stream"));
+
+    // SSE 帧: event / id / data，空行分隔，id 按发送顺序从 1 递增
+    let text = response.text().await.unwrap();
+    let frames: Vec<&str> = text.split("\n\n").filter(|f| !f.is_empty()).collect();
+    let mut events: Vec<Value> = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        let lines: Vec<&str> = frame.lines().collect();
+        assert!(lines[0].starts_with("event: "), "{}", frame);
+        assert_eq!(lines[1], format!("id: {}", i + 1));
+        events.push(serde_json::from_str(lines[2].strip_prefix("data: ").unwrap()).unwrap());
+    }
+    assert!(frames.first().unwrap().starts_with("event: init\n"));
+    assert!(frames.last().unwrap().starts_with("event: done\n"));
+    assert_eq!(events.first().unwrap()["total"], 2);
+    assert_eq!(events.last().unwrap()["done"], true);
+
+    let results: Vec<&Value> = events.iter().filter_map(|e| e.get("result")).collect();
+    assert_eq!(results.len(), 2);
+    let ok = results.iter().find(|r| r["name"] == "ItSearchA").unwrap();
+    assert!(ok["error"].is_null());
+    let names: Vec<&str> = ok["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["葬送的芙莉莲", "葬送的芙莉莲 第二季"]);
+    assert!(ok["items"][0]["url"].as_str().unwrap().ends_with("/v/1"));
+
+    let failed = results.iter().find(|r| r["name"] == "ItSearchB").unwrap();
+    assert!(failed["items"].as_array().unwrap().is_empty());
+    assert!(failed["error"].as_str().is_some());
+    assert_eq!(failed["error_kind"], "bad_status");
+}
+
+#[tokio::test]
+async fn test_streaming_search_ndjson_framing() {
+    let base = spawn_app().await;
+    let response = reqwest::get(format!(
+        "{}/api?anime=芙莉莲&rules=ItSearchA&framing=ndjson",
+        base
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("application/x-ndjson"));
+
+    let events: Vec<Value> = response
+        .text()
+        .await
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.first().unwrap()["total"], 1);
+    assert_eq!(events[1]["result"]["name"], "ItSearchA");
+    assert_eq!(events.last().unwrap()["done"], true);
+}
+
+#[tokio::test]
+async fn test_json_search_returns_all_results() {
+    let base = spawn_app().await;
+    let body: Value = reqwest::get(format!(
+        "{}/search?anime=芙莉莲&rules=ItSearchA,ItSearchB&episodes=0",
+        base
+    ))
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(body["keyword"], "芙莉莲");
+    assert!(body["elapsed_ms"].as_u64().is_some());
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["name"], "ItSearchA");
+    assert_eq!(results[0]["items"].as_array().unwrap().len(), 2);
+    assert!(results[0]["items"][0].get("episodes").is_none());
+    assert_eq!(results[1]["name"], "ItSearchB");
+    assert_eq!(results[1]["error_kind"], "bad_status");
+
+    let response = reqwest::get(format!("{}/search?rules=ItSearchA", base)).await.unwrap();
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn test_episode_stream_per_road() {
+    let base = spawn_app().await;
+    let rules: Vec<Value> = reqwest::get(format!("{}/rules", base))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let site = rules.iter().find(|r| r["name"] == "ItSearchA").unwrap()["baseUrl"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let stream = |detail: &str| {
+        let url = format!("{}/episodes/stream?rule=ItSearchA&url={}{}", base, site, detail);
+        async move {
+            let response = reqwest::get(url).await.unwrap();
+            assert!(response.headers()["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("application/x-ndjson"));
+            response
+                .text()
+                .await
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect::<Vec<Value>>()
+        }
+    };
+
+    let events = stream("v/1").await;
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["road"]["episodes"][1]["name"], "第2集");
+    assert_eq!(events[1], json!({"done": true, "roads": 1}));
+
+    // 详情页不存在: 错误事件后仍有完成信号
+    let events = stream("v/2").await;
+    assert!(events[0]["error"].as_str().is_some());
+    assert_eq!(events[1], json!({"done": true, "roads": 0}));
+
+    let response = reqwest::get(format!(
+        "{}/episodes/stream?rule=ItSearchA&url=https://evil.example/v/1",
+        base
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn test_episode_stream_yields_linked_roads_as_they_resolve() {
+    let base = spawn_app().await;
+    let rules: Vec<Value> = reqwest::get(format!("{}/rules", base))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let site = rules.iter().find(|r| r["name"] == "ItRoads").unwrap()["baseUrl"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let stream = |query: String| {
+        let url = format!("{}/episodes/stream?rule=ItRoads&url={}v/9{}", base, site, query);
+        async move {
+            reqwest::get(url)
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect::<Vec<Value>>()
+        }
+    };
+
+    // 各播放源页面并发请求，先完成的先返回；失败的播放源为错误事件，不影响其余播放源
+    let events = stream(String::new()).await;
+    let roads: Vec<&Value> = events.iter().filter_map(|e| e.get("road")).collect();
+    assert_eq!(roads.len(), 2);
+    assert_eq!(roads[0]["index"], 1);
+    assert_eq!(roads[0]["name"], "线路2");
+    assert_eq!(roads[1]["index"], 0);
+    assert_eq!(roads[1]["episodes"][1]["name"], "第2集");
+    assert_eq!(events.iter().filter(|e| e.get("error").is_some()).count(), 1);
+    assert_eq!(events.last().unwrap(), &json!({"done": true, "roads": 2}));
+
+    // road_id 过滤: 完成信号只计入实际返回的播放源
+    let id = roads[1]["id"].as_str().unwrap();
+    let events = stream(format!("&road_id={}", id)).await;
+    let filtered: Vec<&Value> = events.iter().filter_map(|e| e.get("road")).collect();
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0]["id"], id);
+    assert_eq!(events.last().unwrap(), &json!({"done": true, "roads": 1}));
+
+    // 非流式接口按页面顺序返回
+    let body: Value = reqwest::get(format!("{}/episodes?rule=ItRoads&url={}v/9", base, site))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let indexes: Vec<&Value> = body["roads"].as_array().unwrap().iter().map(|r| &r["index"]).collect();
+    assert_eq!(indexes, [0, 1]);
+}
+
+#[tokio::test]
+async fn test_result_cache_endpoints() {
+    let base = spawn_app().await;
+    let client = reqwest::Client::new();
+    // 测试环境 CACHE_SEARCH_TTL_SECS=0，不缓存
+    let stats: Value = client
+        .get(format!("{}/cache/stats", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(stats, json!({"enabled": false}));
+
+    let response = client.post(format!("{}/cache/clear", base)).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let body: Value = client
+        .post(format!("{}/cache/clear", base))
+        .header("X-Admin-Key", ADMIN_KEY)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body, json!({"success": true, "cleared": 0}));
+}
+
+#[tokio::test]
+async fn test_update_against_fake_index() {
+    let base = spawn_app().await;
+    let body: Value = reqwest::get(format!("{}/update", base))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(body["success"], true);
+    // index.json 与目录不计入规则
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["added"], 1);
+    assert_eq!(body["updated"], 0);
+    assert_eq!(body["failed"], 0);
+    let action = |name: &str| {
+        body["details"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|d| d["name"] == name)
+            .map(|d| d["action"].clone())
+    };
+    assert_eq!(action("ItFresh"), Some(json!("added")));
+    assert_eq!(action("ItSearchA"), Some(json!("unchanged")));
+
+    let rules_dir = &harness().rules_dir;
+    assert!(rules_dir.join("ItFresh.json").exists());
+    assert_eq!(
+        std::fs::read_to_string(rules_dir.join(".last_commit")).unwrap(),
+        COMMIT_SHA
+    );
+
+    // 再次更新 (经客户端): commit 未变化，不拉取规则
+    #[cfg(feature = "client")]
+    {
+        let update = anime_search::client::ApiClient::new(base).update().await.unwrap();
+        assert!(update.success);
+        assert_eq!(update.result.total, 0);
+        assert_eq!(update.result.added, 0);
+        assert!(update.result.details.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_bangumi_proxy_passes_tokens_through() {
+    let base = spawn_app().await;
+    let client = reqwest::Client::new();
+    let subject_url = format!("{}/bgm/v0/subjects/400602", base);
+
+    // 显式 Authorization
+    let response = client
+        .get(&subject_url)
+        .header("Authorization", "Bearer token-explicit")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["access-control-allow-origin"], "*");
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["via"], "explicit");
+
+    // 不带 token 时上游的 401 原样返回
+    let response = client.get(&subject_url).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+
+    // token 档案: 登记后通过 X-Token-Profile 使用
+    let response = client
+        .post(format!("{}/admin/token-profiles", base))
+        .header("X-Admin-Key", ADMIN_KEY)
+        .json(&json!({"name": "it-profile", "token": "token-profile"}))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let body: Value = client
+        .get(&subject_url)
+        .header("X-Token-Profile", "it-profile")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["via"], "profile");
+
+    let response = client
+        .get(&subject_url)
+        .header("X-Token-Profile", "it-missing")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn test_rule_and_favorite_endpoints() {
+    let base = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let rules: Value = client
+        .get(format!("{}/rules", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let toggle = rules
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["name"] == "ItToggle")
+        .unwrap();
+    assert_eq!(toggle["version"], "1.0");
+    assert_eq!(toggle["auto_disabled"], false);
+
+    // 启用规则: 需要管理密钥，规则必须存在
+    let enable = |name: &str, key: Option<&str>| {
+        let mut request = client.post(format!("{}/admin/rules/{}/enable", base, name));
+        if let Some(key) = key {
+            request = request.header("X-Admin-Key", key);
+        }
+        request.send()
+    };
+    assert_eq!(enable("ItToggle", None).await.unwrap().status(), 401);
+    assert_eq!(
+        enable("ItNoSuchRule", Some(ADMIN_KEY))
+            .await
+            .unwrap()
+            .status(),
+        404
+    );
+    let body: Value = enable("ItToggle", Some(ADMIN_KEY))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["success"], true);
+    assert_eq!(body["was_auto_disabled"], false);
+
+    // 收藏: 新增、重复新增、列出、删除
+    let favorite = json!({
+        "keyword": "芙莉莲",
+        "rule": "ItToggle",
+        "name": "葬送的芙莉莲",
+        "url": "https://example.com/v/1",
+    });
+    let response = client
+        .post(format!("{}/favorites", base))
+        .json(&favorite)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let created: Value = response.json().await.unwrap();
+    let id = created["id"].as_str().unwrap().to_string();
+    let response = client
+        .post(format!("{}/favorites", base))
+        .json(&favorite)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let listed: Value = client
+        .get(format!("{}/favorites?q=ItToggle", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed["total"], 1);
+    assert_eq!(listed["items"][0]["id"], id.as_str());
+
+    let delete = || client.delete(format!("{}/favorites/{}", base, id)).send();
+    assert_eq!(delete().await.unwrap().status(), 200);
+    assert_eq!(delete().await.unwrap().status(), 404);
+}
+
+#[tokio::test]
+async fn test_root_mode_api_and_redirect() {
+    harness();
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    let api = spawn_app_with(&Config {
+        root_mode: "api".to_string(),
+        ..CONFIG.clone()
+    })
+    .await;
+    let info: Value = client.get(format!("{}/", api)).send().await.unwrap().json().await.unwrap();
+    assert!(info["endpoints"].is_object(), "{}", info);
+
+    let redirect = spawn_app_with(&Config {
+        root_mode: "redirect".to_string(),
+        root_redirect_url: Some("https://dash.example.com/anime".to_string()),
+        ..CONFIG.clone()
+    })
+    .await;
+    let response = client.get(format!("{}/", redirect)).send().await.unwrap();
+    assert_eq!(response.status(), 302);
+    assert_eq!(response.headers()["location"], "https://dash.example.com/anime");
+
+    // 搜索接口不受首页模式影响
+    let boundary = "integration-boundary";
+    let response = client
+        .post(format!("{}/api", redirect))
+        .header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(multipart_body(boundary, &[("anime", "芙莉莲"), ("rules", "ItMock")]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.text().await.unwrap().contains("\"done\""));
+}
+
+#[tokio::test]
+async fn test_mock_rule_streaming_and_loadtest() {
+    let base = spawn_app().await;
+    let client = reqwest::Client::new();
+    let boundary = "integration-boundary";
+    let body = client
+        .post(format!("{}/api", base))
+        .header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(multipart_body(boundary, &[("anime", "芙莉莲"), ("rules", "ItMock")]))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let events = sse_data(&body);
+    let result = events.iter().find_map(|e| e.get("result")).unwrap();
+    assert_eq!(result["name"], "ItMock");
+    assert_eq!(result["items"][2]["name"], "芙莉莲 3");
+    assert_eq!(result["items"][0]["episodes"][0]["episodes"][1]["name"], "第2集");
+    assert_eq!(events.last().unwrap()["done"], true);
+
+    let loadtest = |query: &str| {
+        client
+            .get(format!("{}/dev/loadtest?{}", base, query))
+            .header("X-Admin-Key", ADMIN_KEY)
+            .send()
+    };
+    let anonymous = client
+        .get(format!("{}/dev/loadtest?rules=ItMock", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(anonymous.status(), 401);
+    let report: Value = loadtest("rules=ItMock&concurrency=5")
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(report["concurrency"], 5);
+    // 每次搜索: init + 结果 + done
+    assert_eq!(report["events"], 15);
+    assert!(report["event_max_ms"].as_f64().unwrap() >= 30.0);
+    assert!(report["search_p50_ms"].as_f64().unwrap() >= 30.0);
+
+    // 只允许模拟规则
+    assert_eq!(loadtest("rules=ItMock,ItSearchA").await.unwrap().status(), 400);
+    assert_eq!(loadtest("").await.unwrap().status(), 400);
+}
+
+#[tokio::test]
+async fn test_single_source_search_with_episodes() {
+    let base = spawn_app().await;
+    let client = reqwest::Client::new();
+    let search = |rule: &str, query: &str| {
+        client
+            .get(format!("{}/source/{}/search?{}", base, rule, query))
+            .send()
+    };
+
+    let body: Value = search("ItSearchA", "anime=芙莉莲")
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["rule"], "ItSearchA");
+    let items = body["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["name"], "葬送的芙莉莲");
+    let episodes = &items[0]["episodes"][0]["episodes"];
+    assert_eq!(episodes[1]["name"], "第2集");
+    assert!(episodes[1]["url"].as_str().unwrap().ends_with("/p/1-2"));
+    assert_eq!(items[1]["episodes"], json!([]));
+
+    let body: Value = search("ItSearchA", "anime=芙莉莲&episodes=0")
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["items"][0]["episodes"], json!([]));
+
+    assert_eq!(search("ItNoSuchRule", "anime=芙莉莲").await.unwrap().status(), 404);
+    assert_eq!(search("ItSearchA", "anime=").await.unwrap().status(), 400);
+    assert_eq!(search("ItSearchB", "anime=芙莉莲").await.unwrap().status(), 502);
+}
+
+#[cfg(feature = "client")]
+mod client {
+    use super::*;
+    use anime_search::bangumi::BangumiStreamEvent;
+    use anime_search::client::{ApiClient, ClientError, SearchParams};
+    use anime_search::StreamEvent;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_client_stream_search_and_rules() {
+        let client = ApiClient::new(spawn_app().await);
+
+        let params = SearchParams {
+            rules: vec!["ItSearchA".to_string(), "ItSearchB".to_string()],
+            ..SearchParams::new("芙莉莲")
+        };
+        let events: Vec<StreamEvent> = client
+            .stream_search(&params)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        assert!(matches!(events.first(), Some(StreamEvent::Init { total: 2 })));
+        assert!(matches!(events.last(), Some(StreamEvent::Done { done: true, .. })));
+        let results: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::Result { result, .. } => Some(result),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(results.len(), 2);
+        let ok = results.iter().find(|r| r.name == "ItSearchA").unwrap();
+        assert_eq!(ok.items.len(), 2);
+        assert_eq!(ok.items[0].name, "葬送的芙莉莲");
+        let failed = results.iter().find(|r| r.name == "ItSearchB").unwrap();
+        assert!(failed.error.is_some());
+
+        // 错误响应转换为带状态码与 error 字段的错误
+        let err = client
+            .stream_search(&SearchParams {
+                rules: vec!["ItNoSuchRule".to_string()],
+                ..SearchParams::new("芙莉莲")
+            })
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, ClientError::Status { status: 400, .. }), "{:?}", err);
+
+        let rules = client.rules().await.unwrap();
+        let toggle = rules.iter().find(|r| r.name == "ItToggle").unwrap();
+        assert_eq!(toggle.version, "1.0");
+        assert!(toggle.base_url.ends_with("/site-a/"));
+        assert!(!toggle.auto_disabled);
+    }
+
+    #[tokio::test]
+    async fn test_client_bangumi_subject_and_search() {
+        let client = ApiClient::new(spawn_app().await);
+
+        let subject = client.bangumi_subject(400603, None).await.unwrap();
+        assert_eq!(subject.name_cn, "葬送的芙莉莲 特别篇");
+        assert_eq!(subject.eps, Some(28));
+        let err = client.bangumi_subject(400602, None).await.unwrap_err();
+        assert!(matches!(err, ClientError::Status { status: 401, .. }), "{:?}", err);
+
+        let events: Vec<BangumiStreamEvent> = client
+            .bangumi_search("芙莉莲")
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        let Some(BangumiStreamEvent::Hits { total, hits }) = events.first() else {
+            panic!("{:?}", events);
+        };
+        assert_eq!(*total, 2);
+        assert_eq!(hits[0].name_cn, "葬送的芙莉莲");
+        assert!(events.iter().any(|e| matches!(
+            e,
+            BangumiStreamEvent::Subject { id: 400602, subject } if subject.name_cn == "葬送的芙莉莲"
+        )));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, BangumiStreamEvent::SubjectError { id: 400603, .. })));
+        assert!(matches!(events.last(), Some(BangumiStreamEvent::Done { done: true })));
+    }
+}
+
+#[tokio::test]
+async fn test_response_envelope() {
+    harness();
+    let client = reqwest::Client::new();
+    let base = spawn_app_with(&Config {
+        envelope: true,
+        ..CONFIG.clone()
+    })
+    .await;
+
+    let rules: Value = client.get(format!("{}/rules", base)).send().await.unwrap().json().await.unwrap();
+    assert!(rules["error"].is_null(), "{}", rules);
+    assert!(rules["data"].is_array(), "{}", rules);
+
+    let response = client.get(format!("{}/source/NoSuchRule/search?keyword=a", base)).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    let body: Value = response.json().await.unwrap();
+    assert!(body["data"].is_null(), "{}", body);
+    assert_eq!(body["error"]["status"], 404);
+    assert!(body["error"]["message"].is_string(), "{}", body);
+
+    // 单个请求可关闭，非 JSON 响应不受影响
+    let bare: Value = client.get(format!("{}/rules?envelope=0", base)).send().await.unwrap().json().await.unwrap();
+    assert!(bare.is_array(), "{}", bare);
+    let response = client.get(format!("{}/", base)).send().await.unwrap();
+    assert!(!response.text().await.unwrap().starts_with("{\"data\""));
+}
+
+/// 按请求头与额外表单字段搜索 ItMock，返回结果条数与 running_total
+async fn search_with_limits(base: &str, headers: &[(&str, &str)], fields: &[(&str, &str)]) -> (usize, Value) {
+    let boundary = "integration-boundary";
+    let mut request = reqwest::Client::new()
+        .post(format!("{}/api", base))
+        .header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={}", boundary),
+        );
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let mut form = vec![("anime", "芙莉莲"), ("rules", "ItMock")];
+    form.extend_from_slice(fields);
+    let text = request
+        .body(multipart_body(boundary, &form))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let event = sse_data(&text)
+        .into_iter()
+        .find(|e| e.get("result").is_some())
+        .unwrap();
+    (event["result"]["items"].as_array().unwrap().len(), event["running_total"].clone())
+}
+
+#[tokio::test]
+async fn test_search_limits_from_headers() {
+    let base = spawn_app().await;
+    let search = |headers, fields| search_with_limits(&base, headers, fields);
+
+    // ItMock 固定生成 3 个结果
+    assert_eq!(search(&[("X-Per-Rule-Limit", "2")], &[]).await.0, 2);
+    assert_eq!(
+        search(&[("X-Per-Rule-Limit", "3"), ("X-Total-Limit", "1")], &[]).await,
+        (1, json!(1))
+    );
+    // 表单字段优先于请求头，无效的请求头忽略
+    assert_eq!(search(&[("X-Per-Rule-Limit", "1")], &[("limit", "2")]).await.0, 2);
+    assert_eq!(search(&[("X-Total-Limit", "2")], &[("total_limit", "1")]).await.0, 1);
+    assert_eq!(search(&[("X-Per-Rule-Limit", "abc")], &[]).await.0, 3);
+}
+
+#[tokio::test]
+async fn test_top_rating_sent_after_result() {
+    let base = spawn_app().await;
+    let boundary = "integration-boundary";
+    let search = |fields: Vec<(&'static str, &'static str)>, query: &'static str| {
+        let base = base.clone();
+        async move {
+            let text = reqwest::Client::new()
+                .post(format!("{}/api{}", base, query))
+                .header(
+                    "Content-Type",
+                    format!("multipart/form-data; boundary={}", boundary),
+                )
+                .body(multipart_body(boundary, &fields))
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            sse_data(&text)
+        }
+    };
+
+    let events = search(
+        vec![("anime", "芙莉莲"), ("rules", "ItSearchA"), ("top_rating", "1")],
+        "",
+    )
+    .await;
+    let position = |key: &str| events.iter().position(|e| e.get(key).is_some()).unwrap();
+    assert!(position("result") < position("rating"));
+    assert!(position("rating") < position("done"));
+    let rating = &events[position("rating")]["rating"];
+    assert_eq!(rating["rule"], "ItSearchA");
+    assert_eq!(rating["subject_id"], 400602);
+    assert_eq!(rating["provider"], "bangumi");
+    assert_eq!(rating["score"], 9.1);
+    assert_eq!(rating["rank"], 12);
+    assert!(rating["url"].as_str().unwrap().ends_with("/v/1"));
+
+    // v2 事件使用 event 标签与 camelCase 字段
+    let events = search(
+        vec![("anime", "芙莉莲"), ("rules", "ItSearchA"), ("top_rating", "1")],
+        "?schema=2",
+    )
+    .await;
+    let rating = events.iter().find(|e| e["event"] == "rating").unwrap();
+    assert_eq!(rating["subjectId"], 400602);
+
+    // 未请求时不查询评分
+    let events = search(vec![("anime", "芙莉莲"), ("rules", "ItSearchA")], "").await;
+    assert!(events.iter().all(|e| e.get("rating").is_none()));
+}
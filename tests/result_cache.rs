@@ -0,0 +1,158 @@
+//! 搜索结果缓存的端到端测试
+//! 配置是进程级全局状态，共用环境的 `integration.rs` 关闭了结果缓存，
+//! 因此单独一个测试进程: 以别名 CACHE_TTL_SECS 开启缓存，验证未命中 → 命中 → 清空；
+//! 单源搜索接口与库接口 (`engine::search_with_options`、`Engine::search_rule`) 共用同一缓存。
+#![cfg(all(feature = "server", feature = "scraper"))]
+
+use anime_search::config::CONFIG;
+use anime_search::engine::search_with_options;
+use anime_search::rules::get_builtin_rules;
+use anime_search::server::build_app;
+use anime_search::{Engine, RuleSet, SearchOptions};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const ADMIN_KEY: &str = "cache-admin";
+
+/// 源站搜索页 (两条结果)
+const SEARCH_PAGE: &str = r#"<html><body>
+<div class="item"><a href="/v/1">葬送的芙莉莲</a></div>
+<div class="item"><a href="/v/2">葬送的芙莉莲 第二季</a></div>
+</body></html>"#;
+
+#[tokio::test]
+async fn test_result_cache_miss_hit_and_clear() {
+    // 源站只应被请求一次 (第二次搜索命中缓存)
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/site/search"))
+        .and(query_param("wd", "芙莉莲"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(SEARCH_PAGE))
+        .expect(1)
+        .mount(&upstream)
+        .await;
+
+    let root = std::env::temp_dir().join(format!("anime-search-cache-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let rules_dir = root.join("rules");
+    std::fs::create_dir_all(&rules_dir).unwrap();
+    let rule = json!({
+        "api": "1",
+        "type": "anime",
+        "name": "ItCached",
+        "version": "1.0",
+        "baseURL": format!("{}/site/", upstream.uri()),
+        "searchURL": format!("{}/site/search?wd=@keyword", upstream.uri()),
+        "searchList": "//div[@class='item']",
+        "searchName": "//a",
+        "searchResult": "//a",
+    });
+    std::fs::write(rules_dir.join("ItCached.json"), rule.to_string()).unwrap();
+    for (key, value) in [
+        ("RULES_DIR", rules_dir.to_string_lossy().into_owned()),
+        ("DATA_DIR", root.join("data").to_string_lossy().into_owned()),
+        ("ADMIN_KEY", ADMIN_KEY.to_string()),
+        // 别名: 未设置 CACHE_SEARCH_TTL_SECS 时生效
+        ("CACHE_TTL_SECS", "300".to_string()),
+    ] {
+        std::env::set_var(key, value);
+    }
+    std::env::remove_var("CACHE_SEARCH_TTL_SECS");
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let app = build_app(&CONFIG);
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let stats = || async {
+        client
+            .get(format!("{}/cache/stats", base))
+            .send()
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap()
+    };
+    let search = || async {
+        let body: Value = client
+            .get(format!("{}/search?anime=芙莉莲&rules=ItCached&episodes=0", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        body["results"][0]["items"].as_array().unwrap().len()
+    };
+
+    let initial = stats().await;
+    assert_eq!(initial["enabled"], true);
+    assert_eq!(initial["ttl_seconds"], 300);
+    assert_eq!((initial["hits"].as_u64(), initial["entries"].as_u64()), (Some(0), Some(0)));
+
+    // 未命中: 请求源站并写入缓存
+    assert_eq!(search().await, 2);
+    let after_miss = stats().await;
+    assert_eq!(after_miss["misses"], 1);
+    assert_eq!(after_miss["hits"], 0);
+    assert_eq!(after_miss["entries"], 1);
+
+    // 命中: 不再请求源站
+    assert_eq!(search().await, 2);
+    let after_hit = stats().await;
+    assert_eq!(after_hit["misses"], 1);
+    assert_eq!(after_hit["hits"], 1);
+    assert_eq!(after_hit["entries"], 1);
+
+    // 单源搜索接口: 同样命中
+    let single: Value = client
+        .get(format!("{}/source/ItCached/search?anime=芙莉莲&episodes=0", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(single["items"].as_array().unwrap().len(), 2);
+    assert_eq!(stats().await["hits"], 2);
+
+    // 库接口: 直接调用引擎与 Engine 也命中，不请求源站
+    let rule = get_builtin_rules().into_iter().find(|r| r.name == "ItCached").unwrap();
+    let options = SearchOptions {
+        skip_episodes: true,
+        ..Default::default()
+    };
+    let result = search_with_options(&rule, "芙莉莲", &options).await;
+    assert_eq!((result.count, result.attempts), (2, 0));
+    let engine = Engine::new(RuleSet::from_rules([(*rule).clone()])).with_options(options);
+    assert_eq!(engine.search_rule(&rule, "芙莉莲").await.count, 2);
+    let after_callers = stats().await;
+    assert_eq!(after_callers["hits"], 4);
+    assert_eq!(after_callers["misses"], 1);
+
+    // 清空: 报告清除的条目数
+    let cleared: Value = client
+        .post(format!("{}/cache/clear", base))
+        .header("X-Admin-Key", ADMIN_KEY)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(cleared, json!({"success": true, "cleared": 1}));
+    assert_eq!(stats().await["entries"], 0);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
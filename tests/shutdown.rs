@@ -0,0 +1,82 @@
+//! 停机排空的端到端测试
+//! 排空状态是进程级全局状态，因此单独一个测试进程: 源站一直不响应，流式搜索卡住，
+//! 停机信号后服务应在排空时间到达时退出，而不是等待卡住的连接关闭。
+#![cfg(all(feature = "server", feature = "scraper"))]
+
+use anime_search::config::CONFIG;
+use anime_search::server::build_app;
+use anime_search::shutdown;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_stuck_stream_does_not_block_shutdown_past_drain() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/site/search"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(60)))
+        .mount(&upstream)
+        .await;
+
+    let root = std::env::temp_dir().join(format!("anime-search-shutdown-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let rules_dir = root.join("rules");
+    std::fs::create_dir_all(&rules_dir).unwrap();
+    let rule = json!({
+        "api": "1",
+        "type": "anime",
+        "name": "ItStuck",
+        "version": "1.0",
+        "baseURL": format!("{}/site/", upstream.uri()),
+        "searchURL": format!("{}/site/search?wd=@keyword", upstream.uri()),
+        "searchList": "//div[@class='item']",
+        "searchName": "//a",
+        "searchResult": "//a",
+    });
+    std::fs::write(rules_dir.join("ItStuck.json"), rule.to_string()).unwrap();
+    for (key, value) in [
+        ("RULES_DIR", rules_dir.to_string_lossy().into_owned()),
+        ("DATA_DIR", root.join("data").to_string_lossy().into_owned()),
+        // 源站请求与搜索都不会在测试期间超时
+        ("TIMEOUT_SECONDS", "120".to_string()),
+        ("SEARCH_DEADLINE_SECONDS", "0".to_string()),
+        ("SEARCH_TIME_BUDGET_SECONDS", "0".to_string()),
+    ] {
+        std::env::set_var(key, value);
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let app = build_app(&CONFIG);
+    let server = tokio::spawn(async move {
+        let server = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown::wait_for_shutdown());
+        shutdown::serve_until_drained(server).await
+    });
+
+    // 流式搜索开始输出后卡在源站请求上
+    let mut response = reqwest::get(format!("{}/api?anime=芙莉莲&rules=ItStuck", base))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.chunk().await.unwrap().is_some());
+
+    let started = Instant::now();
+    shutdown::trigger(Some(1));
+    let result = tokio::time::timeout(Duration::from_secs(10), server)
+        .await
+        .expect("shutdown blocked by the stuck stream");
+    assert!(result.unwrap().is_ok());
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(900), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(5), "{:?}", elapsed);
+    assert!(shutdown::is_draining());
+
+    let _ = std::fs::remove_dir_all(&root);
+}